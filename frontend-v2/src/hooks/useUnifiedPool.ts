@@ -7,7 +7,7 @@
 import { useState, useEffect, useCallback } from 'react';
 import { Connection, PublicKey } from '@solana/web3.js';
 import { useV2AmmPool } from './useV2AmmPool';
-import { derivePoolState, getPoolState, getNativePoolReserves, derivePoolPda, deriveTokenVault } from '../utils/nativePool';
+import { findNativePoolState, getPoolState, getNativePoolReserves, derivePoolPda, deriveTokenVault } from '../utils/nativePool';
 import { isNativeXNT, NATIVE_XNT_MARKER } from '../config/x1-native';
 import { useNetworkStore } from '../store/useNetworkStore';
 
@@ -43,7 +43,7 @@ export function useUnifiedPool(mint0: string, mint1: string, connection?: Connec
       const networkConfig = useNetworkStore.getState().config;
       const programId = new PublicKey(networkConfig.ammProgramId);
       
-      const [poolState] = derivePoolState(new PublicKey(tokenMint), programId);
+      const poolState = await findNativePoolState(conn, new PublicKey(tokenMint), programId);
       const poolStateData = await getPoolState(conn, poolState);
       
       if (!poolStateData || !poolStateData.isNativePool) {
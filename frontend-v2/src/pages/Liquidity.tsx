@@ -169,7 +169,7 @@ export default function Liquidity() {
 
         // Try to match this LP mint against known token pairs
         // Check NATIVE XNT + other token pairs first (native pools)
-        const { derivePoolState: deriveNativePoolState, deriveLpMint } = await import('../utils/nativePool');
+        const { findNativePoolState, deriveLpMint } = await import('../utils/nativePool');
         const { useNetworkStore } = await import('../store/useNetworkStore');
         
         // Get dynamic program ID from network store
@@ -180,7 +180,7 @@ export default function Liquidity() {
           try {
             // Check for native pool
             const tokenMintPubkey = new PublicKey(token.address);
-            const [nativePoolState] = deriveNativePoolState(tokenMintPubkey, programId);
+            const nativePoolState = await findNativePoolState(connection, tokenMintPubkey, programId);
             const [nativeLpMint] = deriveLpMint(nativePoolState, programId);
 
             if (nativeLpMint.toBase58() === lpMint) {
@@ -293,7 +293,7 @@ export default function Liquidity() {
       // Handle native XNT pools
       if (isNative0 || isNative1) {
         console.log('🔵 Native XNT pool detected - checking for native pool');
-        const { derivePoolState: deriveNativePoolState, deriveLpMint } = await import('../utils/nativePool');
+        const { findNativePoolState, deriveLpMint } = await import('../utils/nativePool');
         const { useNetworkStore } = await import('../store/useNetworkStore');
         
         // Get dynamic program ID from network store
@@ -305,7 +305,7 @@ export default function Liquidity() {
         const tokenMintPubkey = new PublicKey(tokenMint);
         
         // Derive native pool addresses
-        const [nativePoolState] = deriveNativePoolState(tokenMintPubkey, programId);
+        const nativePoolState = await findNativePoolState(connection, tokenMintPubkey, programId);
         const [nativeLpMint] = deriveLpMint(nativePoolState, programId);
         
         console.log('🔍 Checking native pool:', {
@@ -88,7 +88,7 @@ async function getPoolBasedPrice(
     const tokenMintPubkey = new PublicKey(tokenMint);
     
     // Check if this is a native XNT pool first
-    const { derivePoolState: deriveNativePoolState, getPoolState, getNativePoolReserves, derivePoolPda, deriveTokenVault } = await import('../utils/nativePool');
+    const { findNativePoolState, getPoolState, getNativePoolReserves, derivePoolPda, deriveTokenVault } = await import('../utils/nativePool');
     const { isNativeXNT } = await import('../config/x1-native');
     const { useNetworkStore } = await import('../store/useNetworkStore');
     
@@ -98,7 +98,7 @@ async function getPoolBasedPrice(
       const programId = new PublicKey(networkConfig.ammProgramId);
       
       // Try native pool first
-      const [nativePoolState] = deriveNativePoolState(tokenMintPubkey, programId);
+      const nativePoolState = await findNativePoolState(connection, tokenMintPubkey, programId);
       const nativePoolStateData = await getPoolState(connection, nativePoolState);
       
       if (nativePoolStateData?.isNativePool) {
@@ -16,13 +16,17 @@ import {
   TOKEN_2022_PROGRAM_ID,
   getAssociatedTokenAddressSync,
   createAssociatedTokenAccountInstruction,
+  createAssociatedTokenAccountIdempotentInstruction,
 } from '@solana/spl-token';
 import {
-  derivePoolState,
+  findNativePoolState,
+  deriveLegacyPoolState,
   derivePoolPda,
   deriveTokenVault,
   derivePoolAuthority,
   deriveLpMint,
+  deriveLockedLiquidityOwner,
+  deriveGlobalConfig,
   getPoolState,
   getNativePoolReserves,
 } from './nativePool';
@@ -41,12 +45,22 @@ function getInstructionDiscriminator(name: string): Buffer {
 
 /**
  * Serialize remove_native_liquidity instruction data
+ * With closeLpAccount, remove_native_liquidity_and_close (also closes the emptied LP account)
  */
-function serializeRemoveNativeLiquidity(lpAmount: bigint): Buffer {
-  const data = Buffer.alloc(8 + 8);
-  const discriminator = getInstructionDiscriminator('remove_native_liquidity');
+function serializeRemoveNativeLiquidity(
+  lpAmount: bigint,
+  minXntOut: bigint,
+  minTokenOut: bigint,
+  closeLpAccount = false
+): Buffer {
+  const data = Buffer.alloc(8 + 8 + 8 + 8);
+  const discriminator = getInstructionDiscriminator(
+    closeLpAccount ? 'remove_native_liquidity_and_close' : 'remove_native_liquidity'
+  );
   discriminator.copy(data, 0);
   data.writeBigUInt64LE(lpAmount, 8);
+  data.writeBigUInt64LE(minXntOut, 16);
+  data.writeBigUInt64LE(minTokenOut, 24);
   return data;
 }
 
@@ -84,7 +98,7 @@ function serializeInitializeNativePool(
   data.writeUInt16LE(protocolFeeBps, offset);
   offset += 2;
   
-  // native_mint_index (u8) - 0 = XNT is token0
+  // native_mint_index (u8) - 0 = XNT is token0, 1 = XNT is token1
   data.writeUInt8(nativeMintIndex, offset);
   
   return data;
@@ -134,7 +148,7 @@ export async function buildAddNativeLiquidityTransaction(
   slippageBps: number = 50 // 0.5% default
 ): Promise<Transaction> {
   // Derive PDAs
-  const [poolState] = derivePoolState(tokenMint, programId);
+  const poolState = await findNativePoolState(connection, tokenMint, programId);
   const [poolPda] = derivePoolPda(poolState, programId);
   const [tokenVault] = deriveTokenVault(poolState, programId);
   const [lpMint] = deriveLpMint(poolState, programId);
@@ -197,25 +211,27 @@ export async function buildAddNativeLiquidityTransaction(
     // 1. payer
     // 2. pool_state (Anchor derives from token_mint seeds - needs token_mint in position 3!)
     // 3. token_mint (MUST be position 3 for Anchor to derive pool_state correctly!)
-    // 4. token_vault
-    // 5. lp_mint
-    // 6. pool_authority
-    // 7. token_program
-    // 8. token_2022_program
-    // 9. system_program
-    // 10. rent
+    // 4. legacy_pool_state (pre-index pool address, init fails if it holds a pool on this side)
+    // 5. token_vault
+    // 6. lp_mint
+    // 7. pool_authority
+    // 8. token_program
+    // 9. token_2022_program
+    // 10. system_program
+    // 11. rent
     const initPoolIx = new TransactionInstruction({
       keys: [
         { pubkey: user, isSigner: true, isWritable: true }, // 1. payer
         { pubkey: poolState, isSigner: false, isWritable: true }, // 2. pool_state
         { pubkey: tokenMint, isSigner: false, isWritable: false }, // 3. token_mint (MOVED FROM POSITION 5!)
-        { pubkey: tokenVault, isSigner: false, isWritable: true }, // 4. token_vault
-        { pubkey: lpMint, isSigner: false, isWritable: true }, // 5. lp_mint
-        { pubkey: poolAuthority, isSigner: false, isWritable: false }, // 6. pool_authority
-        { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false }, // 7. token_program
-        { pubkey: TOKEN_2022_PROGRAM_ID, isSigner: false, isWritable: false }, // 8. token_2022_program
-        { pubkey: SystemProgram.programId, isSigner: false, isWritable: false }, // 9. system_program
-        { pubkey: new PublicKey('SysvarRent111111111111111111111111111111111'), isSigner: false, isWritable: false }, // 10. rent
+        { pubkey: deriveLegacyPoolState(tokenMint, programId)[0], isSigner: false, isWritable: false }, // 4. legacy_pool_state
+        { pubkey: tokenVault, isSigner: false, isWritable: true }, // 5. token_vault
+        { pubkey: lpMint, isSigner: false, isWritable: true }, // 6. lp_mint
+        { pubkey: poolAuthority, isSigner: false, isWritable: false }, // 7. pool_authority
+        { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false }, // 8. token_program
+        { pubkey: TOKEN_2022_PROGRAM_ID, isSigner: false, isWritable: false }, // 9. token_2022_program
+        { pubkey: SystemProgram.programId, isSigner: false, isWritable: false }, // 10. system_program
+        { pubkey: new PublicKey('SysvarRent111111111111111111111111111111111'), isSigner: false, isWritable: false }, // 11. rent
       ],
       programId,
       data: initPoolData,
@@ -254,6 +270,28 @@ export async function buildAddNativeLiquidityTransaction(
     transaction.add(createLpAtaIx);
   }
   
+  // The first deposit mints the locked minimum liquidity to an LP account
+  // owned by a PDA the program never signs for
+  const [lockedLiquidityOwner] = deriveLockedLiquidityOwner(poolState, programId);
+  const lockedLpAccount = getAssociatedTokenAddressSync(
+    lpMint,
+    lockedLiquidityOwner,
+    true, // PDA owner
+    TOKEN_PROGRAM_ID
+  );
+  const isFirstDeposit = !poolStateData || poolStateData.totalAmountMinted === 0n;
+  if (isFirstDeposit) {
+    transaction.add(
+      createAssociatedTokenAccountIdempotentInstruction(
+        user,
+        lockedLpAccount,
+        lockedLiquidityOwner,
+        lpMint,
+        TOKEN_PROGRAM_ID
+      )
+    );
+  }
+  
   // Calculate min LP out with slippage
   const minLpOut = xntAmount - (xntAmount * BigInt(slippageBps)) / 10000n;
   
@@ -285,6 +323,8 @@ export async function buildAddNativeLiquidityTransaction(
     { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false }, // Standard Token Program (Anchor requires this)
     { pubkey: TOKEN_2022_PROGRAM_ID, isSigner: false, isWritable: false }, // Token2022 Program for dynamic use
     { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+    { pubkey: deriveGlobalConfig(programId)[0], isSigner: false, isWritable: false }, // Kill-switch
+    { pubkey: lockedLpAccount, isSigner: false, isWritable: true }, // locked_lp_account
   ];
   
   const addLiqIx = new TransactionInstruction({
@@ -323,7 +363,7 @@ export async function calculateOptimalLiquidityAmounts(
   shareOfPool: number;
 } | null> {
   try {
-    const [poolState] = derivePoolState(tokenMint, programId);
+    const poolState = await findNativePoolState(connection, tokenMint, programId);
     const [poolPda] = derivePoolPda(poolState, programId);
     const [tokenVault] = deriveTokenVault(poolState, programId);
     
@@ -415,7 +455,9 @@ export async function buildRemoveNativeLiquidityTransaction(
   user: PublicKey,
   tokenMint: string,
   lpAmount: bigint,
-  ammProgramId: PublicKey
+  ammProgramId: PublicKey,
+  minXntOut: bigint = 0n,
+  minTokenOut: bigint = 0n
 ): Promise<Transaction | null> {
   console.log("🔴 REMOVE NATIVE LIQUIDITY TRANSACTION BUILDER");
   console.log(`  user: ${user.toBase58()}`);
@@ -424,7 +466,7 @@ export async function buildRemoveNativeLiquidityTransaction(
 
   try {
     const tokenMintPubkey = new PublicKey(tokenMint);
-    const [poolState] = derivePoolState(tokenMintPubkey, ammProgramId);
+    const poolState = await findNativePoolState(connection, tokenMintPubkey, ammProgramId);
     const [poolPda] = derivePoolPda(poolState, ammProgramId);
     const [tokenVault] = deriveTokenVault(poolState, ammProgramId);
     const [poolAuthority] = derivePoolAuthority(poolState, ammProgramId);
@@ -440,10 +482,7 @@ export async function buildRemoveNativeLiquidityTransaction(
     const userTokenAccount = getAssociatedTokenAddressSync(tokenMintPubkey, user, false, tokenProgramId);
     const userLpAccount = getAssociatedTokenAddressSync(lpMint, user, false, TOKEN_PROGRAM_ID);
 
-    const discriminator = getInstructionDiscriminator('remove_native_liquidity');
-    const data = Buffer.alloc(16);
-    discriminator.copy(data, 0);
-    data.writeBigUInt64LE(lpAmount, 8);
+    const data = serializeRemoveNativeLiquidity(lpAmount, minXntOut, minTokenOut);
 
     const instruction = new TransactionInstruction({
       keys: [
@@ -458,6 +497,7 @@ export async function buildRemoveNativeLiquidityTransaction(
         { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
         { pubkey: TOKEN_2022_PROGRAM_ID, isSigner: false, isWritable: false },
         { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+        { pubkey: deriveGlobalConfig(ammProgramId)[0], isSigner: false, isWritable: false }, // Kill-switch
       ],
       programId: ammProgramId,
       data,
@@ -127,9 +127,25 @@ export function derivePoolPda(
 }
 
 /**
- * Derive pool state PDA from token mint
+ * Derive pool state PDA from token mint and XNT's side of the pair
+ * Seeds: ['pool', token_mint, native_mint_index]
  */
 export function derivePoolState(
+  tokenMint: PublicKey,
+  programId: PublicKey,
+  nativeMintIndex: number = 0
+): [PublicKey, number] {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from('pool'), tokenMint.toBuffer(), Buffer.from([nativeMintIndex])],
+    programId
+  );
+}
+
+/**
+ * Derive pool state PDA of a native pool created before native_mint_index
+ * was part of the seed (seeds: ['pool', token_mint])
+ */
+export function deriveLegacyPoolState(
   tokenMint: PublicKey,
   programId: PublicKey
 ): [PublicKey, number] {
@@ -139,6 +155,26 @@ export function derivePoolState(
   );
 }
 
+/**
+ * Find the native pool state for a token mint: the legacy address if a pool
+ * with this native_mint_index exists there, otherwise the current derivation
+ */
+export async function findNativePoolState(
+  connection: Connection,
+  tokenMint: PublicKey,
+  programId: PublicKey,
+  nativeMintIndex: number = 0
+): Promise<PublicKey> {
+  const [legacyPoolState] = deriveLegacyPoolState(tokenMint, programId);
+  const legacyPool = await getPoolState(connection, legacyPoolState);
+  if (legacyPool?.isNativePool && legacyPool.nativeMintIndex === nativeMintIndex) {
+    return legacyPoolState;
+  }
+  
+  const [poolState] = derivePoolState(tokenMint, programId, nativeMintIndex);
+  return poolState;
+}
+
 /**
  * Derive token vault PDA
  */
@@ -165,6 +201,66 @@ export function deriveLpMint(
   );
 }
 
+/**
+ * Derive the PDA owning a pool's locked minimum liquidity
+ * The program never signs for it, so LP held by it can never move
+ */
+export function deriveLockedLiquidityOwner(
+  poolStateAddress: PublicKey,
+  programId: PublicKey
+): [PublicKey, number] {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from('locked_liquidity'), poolStateAddress.toBuffer()],
+    programId
+  );
+}
+
+/**
+ * Derive the program-wide GlobalConfig PDA holding the kill-switch
+ * Swaps and liquidity instructions take it to check whether the program is paused
+ */
+export function deriveGlobalConfig(programId: PublicKey): [PublicKey, number] {
+  return PublicKey.findProgramAddressSync([Buffer.from('global_config')], programId);
+}
+
+/**
+ * Derive a pool's FeeSplit PDA - swaps always pass it
+ */
+export function deriveFeeSplit(
+  poolStateAddress: PublicKey,
+  programId: PublicKey
+): [PublicKey, number] {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from('fee_split'), poolStateAddress.toBuffer()],
+    programId
+  );
+}
+
+/**
+ * Fetch the recipients of a pool's FeeSplit, in recipient order
+ * Empty while the pool has no FeeSplit (the protocol fee goes to the treasury)
+ */
+export async function fetchFeeSplitRecipients(
+  connection: Connection,
+  poolStateAddress: PublicKey,
+  programId: PublicKey
+): Promise<PublicKey[]> {
+  const [feeSplit] = deriveFeeSplit(poolStateAddress, programId);
+  const accountInfo = await connection.getAccountInfo(feeSplit);
+  if (!accountInfo || accountInfo.data.length === 0) {
+    return [];
+  }
+  
+  // Layout: discriminator (8) + pool (32) + count (1) + recipients (32 each)
+  const count = accountInfo.data[8 + 32];
+  const recipients: PublicKey[] = [];
+  for (let i = 0; i < count; i++) {
+    const offset = 8 + 32 + 1 + i * 32;
+    recipients.push(new PublicKey(accountInfo.data.slice(offset, offset + 32)));
+  }
+  return recipients;
+}
+
 /**
  * Derive pool authority PDA
  */
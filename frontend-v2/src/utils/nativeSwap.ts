@@ -18,10 +18,13 @@ import {
   createAssociatedTokenAccountInstruction,
 } from '@solana/spl-token';
 import {
-  derivePoolState,
+  findNativePoolState,
   derivePoolPda,
   deriveTokenVault,
   derivePoolAuthority,
+  deriveGlobalConfig,
+  deriveFeeSplit,
+  fetchFeeSplitRecipients,
   getPoolState,
   getNativePoolReserves,
   calculateNativeSwapOutput,
@@ -71,6 +74,12 @@ function serializeSwapNative(
   return data;
 }
 
+/**
+ * Optional accounts swap_native declares after fee_split
+ * (user_lp_account ... lp_balance_snapshot) - remaining accounts only start after them
+ */
+const SWAP_NATIVE_OPTIONAL_ACCOUNTS = 4;
+
 /**
  * Build native swap transaction
  */
@@ -85,7 +94,7 @@ export async function buildNativeSwapTransaction(
   slippageBps: number = 50 // 0.5% default
 ): Promise<Transaction> {
   // Derive PDAs
-  const [poolState] = derivePoolState(tokenMint, programId);
+  const poolState = await findNativePoolState(connection, tokenMint, programId);
   const [poolPda] = derivePoolPda(poolState, programId);
   const [tokenVault] = deriveTokenVault(poolState, programId);
   const [poolAuthority] = derivePoolAuthority(poolState, programId);
@@ -165,8 +174,22 @@ export async function buildNativeSwapTransaction(
     { pubkey: TOKEN_2022_PROGRAM_ID, isSigner: false, isWritable: false }, // For Token2022 tokens
     { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
     { pubkey: treasuryPubkey, isSigner: false, isWritable: true }, // Protocol treasury (required in context)
+    { pubkey: deriveGlobalConfig(programId)[0], isSigner: false, isWritable: false }, // Kill-switch
+    { pubkey: deriveFeeSplit(poolState, programId)[0], isSigner: false, isWritable: false }, // FeeSplit PDA
   ];
   
+  // A pool's FeeSplit, once created, receives the protocol fee instead of the treasury -
+  // its recipients' wallets follow the optional accounts (program ID = None) as remaining accounts
+  const feeSplitRecipients = await fetchFeeSplitRecipients(connection, poolState, programId);
+  if (feeSplitRecipients.length > 0) {
+    for (let i = 0; i < SWAP_NATIVE_OPTIONAL_ACCOUNTS; i++) {
+      keys.push({ pubkey: programId, isSigner: false, isWritable: false });
+    }
+    for (const recipient of feeSplitRecipients) {
+      keys.push({ pubkey: recipient, isSigner: false, isWritable: true });
+    }
+  }
+  
   console.log('  ✅ Protocol treasury account added:', treasuryPubkey.toString());
   
   console.log(`📋 Total accounts in instruction: ${keys.length}`);
@@ -204,7 +227,7 @@ export async function getNativeSwapQuote(
   reserveOut: bigint;
 } | null> {
   try {
-    const [poolState] = derivePoolState(tokenMint, programId);
+    const poolState = await findNativePoolState(connection, tokenMint, programId);
     const [poolPda] = derivePoolPda(poolState, programId);
     const [tokenVault] = deriveTokenVault(poolState, programId);
     
@@ -266,7 +289,7 @@ export async function getNativeSwapReverseQuote(
   priceImpact: number;
 } | null> {
   try {
-    const [poolState] = derivePoolState(tokenMint, programId);
+    const poolState = await findNativePoolState(connection, tokenMint, programId);
     const [poolPda] = derivePoolPda(poolState, programId);
     const [tokenVault] = deriveTokenVault(poolState, programId);
     
@@ -12,7 +12,7 @@ import {
 import BN from 'bn.js';
 import { buildAddLiquidityTransaction, buildRemoveLiquidityTransaction } from './v2AmmLiquidity';
 import { buildAddNativeLiquidityTransaction, buildRemoveNativeLiquidityTransaction, calculateOptimalLiquidityAmounts } from './nativeLiquidity';
-import { derivePoolState, isNativePool } from './nativePool';
+import { findNativePoolState, isNativePool } from './nativePool';
 import { isNativeXNT, NATIVE_XNT_MARKER, WRAPPED_XNT_MINT_TESTNET } from '../config/x1-native';
 import { useNetworkStore } from '../store/useNetworkStore';
 
@@ -85,7 +85,7 @@ export async function buildUnifiedAddLiquidityTransaction(
       const programId = new PublicKey(networkConfig.ammProgramId);
       
       // Check if native pool exists
-      const [poolState] = derivePoolState(tokenMint, programId);
+      const poolState = await findNativePoolState(connection, tokenMint, programId);
       console.log('   poolState:', poolState.toString());
       
       const isNative = await isNativePool(connection, poolState);
@@ -187,7 +187,7 @@ export async function buildUnifiedRemoveLiquidityTransaction(
       const programId = new PublicKey(networkConfig.ammProgramId);
       
       // Check if native pool exists
-      const [poolState] = derivePoolState(tokenMint, programId);
+      const poolState = await findNativePoolState(connection, tokenMint, programId);
       const isNative = await isNativePool(connection, poolState);
       
       if (isNative) {
@@ -279,7 +279,7 @@ export async function calculateUnifiedLiquidityAmounts(
       const programId = new PublicKey(networkConfig.ammProgramId);
       
       // Check if native pool exists
-      const [poolState] = derivePoolState(tokenMint, programId);
+      const poolState = await findNativePoolState(connection, tokenMint, programId);
       const isNative = await isNativePool(connection, poolState);
       
       if (isNative) {
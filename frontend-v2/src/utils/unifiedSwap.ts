@@ -12,7 +12,7 @@ import {
 import BN from 'bn.js';
 import { buildV2AmmSwapTransaction } from './v2AmmSwap';
 import { buildNativeSwapTransaction, getNativeSwapQuote } from './nativeSwap';
-import { derivePoolState, isNativePool } from './nativePool';
+import { findNativePoolState, isNativePool } from './nativePool';
 import { isNativeXNT, NATIVE_XNT_MARKER } from '../config/x1-native';
 import { useNetworkStore } from '../store/useNetworkStore';
 
@@ -74,7 +74,7 @@ export async function buildUnifiedSwapTransaction(
       const programId = new PublicKey(networkConfig.ammProgramId);
       
       // Check if native pool exists
-      const [poolState] = derivePoolState(tokenMint, programId);
+      const poolState = await findNativePoolState(connection, tokenMint, programId);
       const isNative = await isNativePool(connection, poolState);
       
       console.log('  isNative:', isNative);
@@ -155,7 +155,7 @@ export async function getUnifiedSwapQuote(
       const programId = new PublicKey(networkConfig.ammProgramId);
       
       // Check if native pool exists
-      const [poolState] = derivePoolState(tokenMint, programId);
+      const poolState = await findNativePoolState(connection, tokenMint, programId);
       const isNative = await isNativePool(connection, poolState);
       
       if (isNative) {
@@ -37,6 +37,7 @@ import {
   getVaultsForMints,
 } from './v2AmmPool';
 import { getAmmProgramId, XNT_MINT } from './v2AmmSwap';
+import { deriveLockedLiquidityOwner, deriveGlobalConfig } from './nativePool';
 
 /**
  * Build initialize_pool instruction
@@ -137,6 +138,8 @@ async function buildInitPoolInstruction(
  * @param owner - User wallet (signer)
  * @param amountLiq0 - Amount of token0 to deposit
  * @param amountLiq1 - Amount of token1 to deposit
+ * @param lockedLpAccount - LP account of the locked-liquidity PDA, required on the first deposit
+ * @param mints - mint0 and mint1 - required for Token 2022 mints with a transfer fee or hook
  */
 export function buildAddLiquidityInstruction(
   poolState: PublicKey,
@@ -149,7 +152,9 @@ export function buildAddLiquidityInstruction(
   userPoolAta: PublicKey,
   owner: PublicKey,
   amountLiq0: BN,
-  amountLiq1: BN
+  amountLiq1: BN,
+  lockedLpAccount?: PublicKey,
+  mints?: [PublicKey, PublicKey]
 ): TransactionInstruction {
   // Anchor instruction discriminator: sha256("global:add_liquidity")[:8]
   const encoder = new TextEncoder();
@@ -165,21 +170,32 @@ export function buildAddLiquidityInstruction(
 
   const data = Buffer.concat([discriminator, amountLiq0Buffer, amountLiq1Buffer]);
 
+  const keys = [
+    { pubkey: poolState, isSigner: false, isWritable: true },
+    { pubkey: poolAuthority, isSigner: false, isWritable: false }, // Script shows false!
+    { pubkey: vault0, isSigner: false, isWritable: true },
+    { pubkey: vault1, isSigner: false, isWritable: true },
+    { pubkey: poolMint, isSigner: false, isWritable: true },
+    { pubkey: user0, isSigner: false, isWritable: true },
+    { pubkey: user1, isSigner: false, isWritable: true },
+    { pubkey: userPoolAta, isSigner: false, isWritable: true },
+    { pubkey: owner, isSigner: true, isWritable: false },
+    { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+    { pubkey: TOKEN_2022_PROGRAM_ID, isSigner: false, isWritable: false }, // Token 2022 program
+    { pubkey: deriveGlobalConfig(getAmmProgramId())[0], isSigner: false, isWritable: false }, // Kill-switch
+  ];
+  if (lockedLpAccount || mints) {
+    // Optional accounts are positional - the program ID stands in for a missing one
+    keys.push({ pubkey: lockedLpAccount ?? getAmmProgramId(), isSigner: false, isWritable: !!lockedLpAccount });
+  }
+  if (mints) {
+    keys.push({ pubkey: mints[0], isSigner: false, isWritable: false });
+    keys.push({ pubkey: mints[1], isSigner: false, isWritable: false });
+  }
+
   return new TransactionInstruction({
     programId: getAmmProgramId(),
-    keys: [
-      { pubkey: poolState, isSigner: false, isWritable: true },
-      { pubkey: poolAuthority, isSigner: false, isWritable: false }, // Script shows false!
-      { pubkey: vault0, isSigner: false, isWritable: true },
-      { pubkey: vault1, isSigner: false, isWritable: true },
-      { pubkey: poolMint, isSigner: false, isWritable: true },
-      { pubkey: user0, isSigner: false, isWritable: true },
-      { pubkey: user1, isSigner: false, isWritable: true },
-      { pubkey: userPoolAta, isSigner: false, isWritable: true },
-      { pubkey: owner, isSigner: true, isWritable: false },
-      { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
-      { pubkey: TOKEN_2022_PROGRAM_ID, isSigner: false, isWritable: false }, // Token 2022 program
-    ],
+    keys,
     data,
   });
 }
@@ -197,6 +213,8 @@ export function buildAddLiquidityInstruction(
  * @param userPoolAta - User's LP token account (burns tokens)
  * @param owner - User wallet (signer)
  * @param burnAmount - Amount of LP tokens to burn
+ * @param closeLpAccount - Use remove_liquidity_and_close, closing userPoolAta (rent back to owner) once the burn empties it
+ * @param mints - mint0 and mint1 - required for Token 2022 mints with a transfer fee or hook
  */
 export function buildRemoveLiquidityInstruction(
   poolState: PublicKey,
@@ -208,11 +226,13 @@ export function buildRemoveLiquidityInstruction(
   user1: PublicKey,
   userPoolAta: PublicKey,
   owner: PublicKey,
-  burnAmount: BN
+  burnAmount: BN,
+  closeLpAccount = false,
+  mints?: [PublicKey, PublicKey]
 ): TransactionInstruction {
-  // Anchor instruction discriminator: sha256("global:remove_liquidity")[:8]
+  // Anchor instruction discriminator: sha256("global:remove_liquidity")[:8] (or "global:remove_liquidity_and_close")
   const encoder = new TextEncoder();
-  const hash = sha256(encoder.encode('global:remove_liquidity'));
+  const hash = sha256(encoder.encode(closeLpAccount ? 'global:remove_liquidity_and_close' : 'global:remove_liquidity'));
   const discriminator = Buffer.from(hash.slice(0, 8));
 
   // Encode arguments: burn_amount (u64)
@@ -221,21 +241,30 @@ export function buildRemoveLiquidityInstruction(
 
   const data = Buffer.concat([discriminator, burnAmountBuffer]);
 
+  const keys = [
+    { pubkey: poolState, isSigner: false, isWritable: true },
+    { pubkey: poolAuthority, isSigner: false, isWritable: false }, // Script shows false!
+    { pubkey: vault0, isSigner: false, isWritable: true },
+    { pubkey: vault1, isSigner: false, isWritable: true },
+    { pubkey: poolMint, isSigner: false, isWritable: true },
+    { pubkey: user0, isSigner: false, isWritable: true },
+    { pubkey: user1, isSigner: false, isWritable: true },
+    { pubkey: userPoolAta, isSigner: false, isWritable: true },
+    { pubkey: owner, isSigner: true, isWritable: false },
+    { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+    { pubkey: TOKEN_2022_PROGRAM_ID, isSigner: false, isWritable: false }, // Token 2022 program
+    { pubkey: deriveGlobalConfig(getAmmProgramId())[0], isSigner: false, isWritable: false }, // Kill-switch
+  ];
+  if (mints) {
+    // locked_lp_account (None) precedes the optional mints
+    keys.push({ pubkey: getAmmProgramId(), isSigner: false, isWritable: false });
+    keys.push({ pubkey: mints[0], isSigner: false, isWritable: false });
+    keys.push({ pubkey: mints[1], isSigner: false, isWritable: false });
+  }
+
   return new TransactionInstruction({
     programId: getAmmProgramId(),
-    keys: [
-      { pubkey: poolState, isSigner: false, isWritable: true },
-      { pubkey: poolAuthority, isSigner: false, isWritable: false }, // Script shows false!
-      { pubkey: vault0, isSigner: false, isWritable: true },
-      { pubkey: vault1, isSigner: false, isWritable: true },
-      { pubkey: poolMint, isSigner: false, isWritable: true },
-      { pubkey: user0, isSigner: false, isWritable: true },
-      { pubkey: user1, isSigner: false, isWritable: true },
-      { pubkey: userPoolAta, isSigner: false, isWritable: true },
-      { pubkey: owner, isSigner: true, isWritable: false },
-      { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
-      { pubkey: TOKEN_2022_PROGRAM_ID, isSigner: false, isWritable: false }, // Token 2022 program
-    ],
+    keys,
     data,
   });
 }
@@ -434,6 +463,30 @@ export async function buildAddLiquidityTransaction(
     );
   }
 
+  // The first deposit mints the locked minimum liquidity to an LP account
+  // owned by a PDA the program never signs for
+  // total_amount_minted is the first field after the 8-byte discriminator
+  const isFirstDeposit = !poolStateAccount || poolStateAccount.data.readBigUInt64LE(8) === 0n;
+  let lockedLpAccount: PublicKey | undefined;
+  if (isFirstDeposit) {
+    const [lockedLiquidityOwner] = deriveLockedLiquidityOwner(poolState, getAmmProgramId());
+    lockedLpAccount = await getAssociatedTokenAddress(
+      poolMint,
+      lockedLiquidityOwner,
+      true, // PDA owner
+      TOKEN_PROGRAM_ID
+    );
+    transaction.add(
+      createAssociatedTokenAccountIdempotentInstruction(
+        owner,
+        lockedLpAccount,
+        lockedLiquidityOwner,
+        poolMint,
+        TOKEN_PROGRAM_ID
+      )
+    );
+  }
+
   // 7. Handle native XNT wrapping (matching script approach)
   // Script wraps XNT BEFORE add_liquidity instruction
   // We do it in the same transaction but BEFORE the add_liquidity instruction
@@ -527,7 +580,10 @@ export async function buildAddLiquidityTransaction(
     userPoolAta,
     owner,
     amountA,     // Amount of mintA
-    amountB      // Amount of mintB
+    amountB,     // Amount of mintB
+    lockedLpAccount,
+    // Token 2022 sides may carry a transfer fee or hook, which need the mint
+    mintAIs2022 || mintBIs2022 ? [mintA, mintB] : undefined
   );
 
   transaction.add(addLiquidityIx);
@@ -678,7 +734,9 @@ export async function buildRemoveLiquidityTransaction(
     userAtaB,    // User's account for mintB (receives tokens)
     userPoolAta, // User's LP account (burns tokens)
     owner,
-    burnAmount
+    burnAmount,
+    false,
+    mintAIs2022 || mintBIs2022 ? [mintA, mintB] : undefined
   );
 
   transaction.add(removeLiquidityIx);
@@ -16,6 +16,7 @@ import {
   TOKEN_PROGRAM_ID,
   TOKEN_2022_PROGRAM_ID,
   getAssociatedTokenAddress,
+  getAssociatedTokenAddressSync,
   createAssociatedTokenAccountInstruction,
   createSyncNativeInstruction,
   getAccount,
@@ -83,6 +84,8 @@ import {
   type V2AmmPoolStateData,
 } from './v2AmmPoolState';
 
+import { deriveGlobalConfig, deriveFeeSplit, fetchFeeSplitRecipients } from './nativePool';
+
 // Re-export for convenience
 export {
   derivePoolStateUtil as derivePoolState,
@@ -146,8 +149,20 @@ export function calculateSwapOutput(
   return amountOut;
 }
 
+/**
+ * Optional accounts the swap instruction declares after fee_split
+ * (protocol_treasury ... lp_balance_snapshot) - remaining accounts only start after them
+ */
+const SWAP_OPTIONAL_ACCOUNTS = 11;
+/** Position of mint_src (mint_dst follows) among those optional accounts */
+const SWAP_MINT_SRC_INDEX = 6;
+
 /**
  * Build V2 AMM swap instruction
+ * @param verifyExecution - Use swap_verified, which re-reads every balance moved and fails on any mismatch
+ * @param feeSplitRecipientAtas - XNT token accounts of the pool's FeeSplit recipients, in recipient order
+ * @param mints - Input and output mints - required for Token 2022 mints with a transfer fee or hook,
+ *   which the program must move with TransferChecked
  */
 export function buildV2AmmSwapInstruction(
   poolState: PublicKey,
@@ -159,12 +174,15 @@ export function buildV2AmmSwapInstruction(
   owner: PublicKey,
   protocolTreasuryAta: PublicKey, // Always required (can be a dummy account if no treasury)
   amountIn: BN,
-  minAmountOut: BN
+  minAmountOut: BN,
+  verifyExecution = false,
+  feeSplitRecipientAtas: PublicKey[] = [],
+  mints?: { src: PublicKey; dst: PublicKey }
 ): TransactionInstruction {
-  // Anchor instruction discriminator: sha256("global:swap")[:8]
+  // Anchor instruction discriminator: sha256("global:swap")[:8] (or "global:swap_verified")
   // Use browser-compatible crypto from @noble/hashes
   const encoder = new TextEncoder();
-  const hash = sha256(encoder.encode('global:swap'));
+  const hash = sha256(encoder.encode(verifyExecution ? 'global:swap_verified' : 'global:swap'));
   const discriminator = Buffer.from(hash.slice(0, 8));
 
   // Encode arguments: amount_in (u64) + min_amount_out (u64)
@@ -175,20 +193,39 @@ export function buildV2AmmSwapInstruction(
 
   const data = Buffer.concat([discriminator, amountInBuffer, minAmountOutBuffer]);
 
+  const programId = getAmmProgramId();
+  const keys = [
+    { pubkey: poolState, isSigner: false, isWritable: true },
+    { pubkey: poolAuthority, isSigner: false, isWritable: true }, // Must be writable for PDA signing
+    { pubkey: vaultSrc, isSigner: false, isWritable: true },
+    { pubkey: vaultDst, isSigner: false, isWritable: true },
+    { pubkey: userSrc, isSigner: false, isWritable: true },
+    { pubkey: userDst, isSigner: false, isWritable: true },
+    { pubkey: owner, isSigner: true, isWritable: false },
+    { pubkey: protocolTreasuryAta, isSigner: false, isWritable: true }, // Always writable (even if default/unused)
+    { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+    { pubkey: TOKEN_2022_PROGRAM_ID, isSigner: false, isWritable: false }, // Token 2022 program
+    { pubkey: deriveGlobalConfig(programId)[0], isSigner: false, isWritable: false }, // Kill-switch
+    { pubkey: deriveFeeSplit(poolState, programId)[0], isSigner: false, isWritable: false }, // FeeSplit PDA
+  ];
+  if (mints || feeSplitRecipientAtas.length > 0) {
+    // Optional accounts are positional (program ID = None), recipients follow in remaining accounts
+    const optionalAccounts: PublicKey[] = Array(SWAP_OPTIONAL_ACCOUNTS).fill(programId);
+    if (mints) {
+      optionalAccounts[SWAP_MINT_SRC_INDEX] = mints.src;
+      optionalAccounts[SWAP_MINT_SRC_INDEX + 1] = mints.dst;
+    }
+    for (const account of optionalAccounts) {
+      keys.push({ pubkey: account, isSigner: false, isWritable: false });
+    }
+    for (const recipientAta of feeSplitRecipientAtas) {
+      keys.push({ pubkey: recipientAta, isSigner: false, isWritable: true });
+    }
+  }
+
   return new TransactionInstruction({
-    programId: getAmmProgramId(),
-    keys: [
-      { pubkey: poolState, isSigner: false, isWritable: true },
-      { pubkey: poolAuthority, isSigner: false, isWritable: true }, // Must be writable for PDA signing
-      { pubkey: vaultSrc, isSigner: false, isWritable: true },
-      { pubkey: vaultDst, isSigner: false, isWritable: true },
-      { pubkey: userSrc, isSigner: false, isWritable: true },
-      { pubkey: userDst, isSigner: false, isWritable: true },
-      { pubkey: owner, isSigner: true, isWritable: false },
-      { pubkey: protocolTreasuryAta, isSigner: false, isWritable: true }, // Always writable (even if default/unused)
-      { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
-      { pubkey: TOKEN_2022_PROGRAM_ID, isSigner: false, isWritable: false }, // Token 2022 program
-    ],
+    programId,
+    keys,
     data,
   });
 }
@@ -368,6 +405,14 @@ export async function buildV2AmmSwapTransaction(
     );
   }
 
+  // A pool's FeeSplit, once created, receives the protocol fee instead of the treasury
+  const feeSplitRecipients = hasXNT
+    ? await fetchFeeSplitRecipients(connection, poolState, getAmmProgramId())
+    : [];
+  const feeSplitRecipientAtas = feeSplitRecipients.map((recipient) =>
+    getAssociatedTokenAddressSync(XNT_MINT, recipient, true, TOKEN_PROGRAM_ID)
+  );
+
   // Build swap instruction
   const swapIx = buildV2AmmSwapInstruction(
     poolState,
@@ -379,7 +424,11 @@ export async function buildV2AmmSwapTransaction(
     owner,
     protocolTreasuryAta,
     amountIn,
-    minAmountOut
+    minAmountOut,
+    false,
+    feeSplitRecipientAtas,
+    // Token 2022 sides may carry a transfer fee or hook, which need the mint
+    inputIs2022 || outputIs2022 ? { src: inputMint, dst: outputMint } : undefined
   );
 
   transaction.add(swapIx);
@@ -30,4 +30,65 @@ pub enum ErrorCode {
     InsufficientRentReserve,
     #[msg("Invalid account data - failed to deserialize")]
     InvalidAccountData,
+    #[msg("Signer is not the pool admin")]
+    Unauthorized,
+    #[msg("min_amount_out implies more slippage than the pool allows")]
+    SlippageToleranceTooHigh,
+    #[msg("Pool is already mid-instruction (reentrancy)")]
+    Reentrancy,
+    #[msg("A pool is already registered for this mint pair")]
+    PoolAlreadyRegistered,
+    #[msg("Deposit would take the pool's XNT reserve above its TVL cap")]
+    TvlCapExceeded,
+    #[msg("Swap decreased the pool's constant-product invariant")]
+    InvariantViolated,
+    #[msg("The global kill-switch is not engaged")]
+    KillSwitchNotEngaged,
+    #[msg("Pool has no emergency recovery address")]
+    EmergencyRecoveryNotSet,
+    #[msg("Swaps are disabled on this pool")]
+    SwapsDisabled,
+    #[msg("Deposits are disabled on this pool")]
+    DepositsDisabled,
+    #[msg("Protocol fee would exceed the LP fee")]
+    ProtocolFeeExceedsLpFee,
+    #[msg("Pool is not closed or still has LP holders")]
+    PoolNotWoundDown,
+    #[msg("Pool has a withdrawal delay - use request_remove_liquidity")]
+    WithdrawalDelayActive,
+    #[msg("Withdrawal request is still within its delay")]
+    WithdrawalNotReady,
+    #[msg("Swap output exceeds the pool's per-swap share of the output reserve")]
+    SwapTooLarge,
+    #[msg("Pool has a fee timelock - use propose_fee_change")]
+    FeeChangeTimelocked,
+    #[msg("Pending fee change is still within its timelock")]
+    FeeChangeNotReady,
+    #[msg("Token 2022 mint has an extension the pool does not support")]
+    UnsupportedMintExtension,
+    #[msg("Burn would leave LP supply below the locked minimum liquidity")]
+    BurnBelowMinimumLiquidity,
+    #[msg("Pool has no liquidity on one side")]
+    PoolReserveEmpty,
+    #[msg("Swap output rounds to zero - amount_in is too small for this pool")]
+    SwapOutputZero,
+    #[msg("Token account mint does not match the pool vault or LP mint it pairs with")]
+    MintMismatch,
+    #[msg("Pool LP is soulbound and can't be moved out of the holder's account")]
+    LpSoulbound,
+    #[msg("Balances after the swap don't match the computed transfer amounts")]
+    ExecutionMismatch,
+    #[msg("LP account still holds LP tokens and can't be closed")]
+    LpAccountNotEmpty,
+    #[msg("First deposit too small - sqrt(amount0 * amount1) must be at least MIN_INITIAL_LIQUIDITY")]
+    InsufficientInitialLiquidity,
+    // Appended after the existing variants so their error codes don't shift
+    #[msg("This operation is only for SPL pools - use the native pool instruction")]
+    IsNativePool,
+    #[msg("The global kill-switch is engaged")]
+    ProgramPaused,
+    #[msg("No input delivers that output - the pool can't release enough to cover the protocol fee")]
+    OutputUnreachable,
+    #[msg("Swap math took more compute units than SWAP_MATH_CU_BUDGET (bench builds only)")]
+    SwapMathOverBudget,
 }
@@ -0,0 +1,203 @@
+use anchor_lang::prelude::*;
+
+// Audit trail of admin actions - every event names the pool and the signer
+
+#[event]
+pub struct AdminChanged {
+    pub pool: Pubkey,
+    pub actor: Pubkey,
+    pub old_admin: Pubkey,
+    pub new_admin: Pubkey,
+}
+
+// Emitted by set_protocol_fee, set_protocol_fee_mode and apply_fee_change
+#[event]
+pub struct FeeChanged {
+    pub pool: Pubkey,
+    pub actor: Pubkey,
+    pub old_fee_numerator: u64,
+    pub new_fee_numerator: u64,
+    pub old_protocol_fee_bps: u16,
+    pub new_protocol_fee_bps: u16,
+    pub old_protocol_fee_mode: u8,
+    pub new_protocol_fee_mode: u8,
+}
+
+// Emitted by propose_fee_change - the change applies from effective_at
+#[event]
+pub struct FeeChangeProposed {
+    pub pool: Pubkey,
+    pub actor: Pubkey,
+    pub pending_fee_numerator: u64,
+    pub pending_protocol_fee_bps: u16,
+    pub effective_at: i64,
+}
+
+// Emitted by cancel_fee_change, and by set_fee_timelock when it drops a pending change
+#[event]
+pub struct FeeChangeCancelled {
+    pub pool: Pubkey,
+    pub actor: Pubkey,
+    pub pending_fee_numerator: u64,
+    pub pending_protocol_fee_bps: u16,
+}
+
+#[event]
+pub struct TreasuryChanged {
+    pub pool: Pubkey,
+    pub actor: Pubkey,
+    pub old_treasury: Pubkey,
+    pub new_treasury: Pubkey,
+}
+
+// Emitted when set_swaps_enabled / set_deposits_enabled turns a switch off
+#[event]
+pub struct PoolPaused {
+    pub pool: Pubkey,
+    pub actor: Pubkey,
+    pub old_swaps_enabled: bool,
+    pub new_swaps_enabled: bool,
+    pub old_deposits_enabled: bool,
+    pub new_deposits_enabled: bool,
+}
+
+// Emitted when set_swaps_enabled / set_deposits_enabled turns a switch back on
+#[event]
+pub struct PoolUnpaused {
+    pub pool: Pubkey,
+    pub actor: Pubkey,
+    pub old_swaps_enabled: bool,
+    pub new_swaps_enabled: bool,
+    pub old_deposits_enabled: bool,
+    pub new_deposits_enabled: bool,
+}
+
+// Emitted by snapshot_reserves - anyone can record a pool's reserves at a slot
+// reserve0 is vault0 (SPL) or the token vault (native), reserve1_or_native is
+// vault1 (SPL) or native_reserve (native)
+#[event]
+pub struct ReserveSnapshot {
+    pub pool: Pubkey,
+    pub reserve0: u64,
+    pub reserve1_or_native: u64,
+    pub total_lp: u64,
+    pub slot: u64,
+    pub timestamp: i64,
+}
+
+// Emitted by set_protocol_fee_compounding - Pubkey::default() means compounding is off
+#[event]
+pub struct ProtocolFeeCompoundingChanged {
+    pub pool: Pubkey,
+    pub actor: Pubkey,
+    pub old_protocol_lp_account: Pubkey,
+    pub new_protocol_lp_account: Pubkey,
+}
+
+// Emitted by rebalance - the admin's side of the internal swap
+#[event]
+pub struct Rebalanced {
+    pub pool: Pubkey,
+    pub actor: Pubkey,
+    pub target_price_x64: u128,
+    pub token0_in: bool,
+    pub amount_in: u64,
+    pub amount_out: u64,
+}
+
+// Emitted by set_max_slippage, set_max_swap_out_bps and set_max_tvl_native
+#[event]
+pub struct PoolLimitsChanged {
+    pub pool: Pubkey,
+    pub actor: Pubkey,
+    pub old_max_allowed_slippage_bps: u16,
+    pub new_max_allowed_slippage_bps: u16,
+    pub old_max_swap_out_bps_of_reserve: u16,
+    pub new_max_swap_out_bps_of_reserve: u16,
+    pub old_max_tvl_native: u64,
+    pub new_max_tvl_native: u64,
+}
+
+#[event]
+pub struct LpFeeDiscountChanged {
+    pub pool: Pubkey,
+    pub actor: Pubkey,
+    pub old_threshold: u64,
+    pub new_threshold: u64,
+    pub old_discount_bps: u16,
+    pub new_discount_bps: u16,
+}
+
+#[event]
+pub struct FeeTimelockChanged {
+    pub pool: Pubkey,
+    pub actor: Pubkey,
+    pub old_fee_timelock_secs: u64,
+    pub new_fee_timelock_secs: u64,
+}
+
+#[event]
+pub struct CreateTreasuryAtaChanged {
+    pub pool: Pubkey,
+    pub actor: Pubkey,
+    pub old_create_treasury_ata: bool,
+    pub new_create_treasury_ata: bool,
+}
+
+#[event]
+pub struct NativeMintChanged {
+    pub pool: Pubkey,
+    pub actor: Pubkey,
+    pub old_native_mint: Pubkey,
+    pub new_native_mint: Pubkey,
+}
+
+#[event]
+pub struct WithdrawalDelayChanged {
+    pub pool: Pubkey,
+    pub actor: Pubkey,
+    pub old_withdrawal_delay_secs: u64,
+    pub new_withdrawal_delay_secs: u64,
+}
+
+#[event]
+pub struct BootstrapWindowChanged {
+    pub pool: Pubkey,
+    pub actor: Pubkey,
+    pub old_bootstrap_secs: u64,
+    pub new_bootstrap_secs: u64,
+}
+
+#[event]
+pub struct LpRebateChanged {
+    pub pool: Pubkey,
+    pub actor: Pubkey,
+    pub old_lp_rebate_bps: u16,
+    pub new_lp_rebate_bps: u16,
+}
+
+// Emitted by initialize_fee_split, set_fee_split, and close_fee_split with no recipients
+#[event]
+pub struct FeeSplitChanged {
+    pub pool: Pubkey,
+    pub actor: Pubkey,
+    pub recipients: Vec<Pubkey>,
+    pub weights_bps: Vec<u16>,
+}
+
+// Emitted by set_global_pause - the kill-switch covers every pool, so there is no pool key
+#[event]
+pub struct GlobalPauseChanged {
+    pub actor: Pubkey,
+    pub old_paused: bool,
+    pub new_paused: bool,
+}
+
+// Emitted by sync_lp_supply when total_amount_minted is corrected to the LP mint's supply
+#[event]
+pub struct LpSupplySynced {
+    pub pool: Pubkey,
+    pub actor: Pubkey,
+    pub old_total_amount_minted: u64,
+    pub new_total_amount_minted: u64,
+}
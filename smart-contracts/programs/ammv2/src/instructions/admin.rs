@@ -0,0 +1,959 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program::invoke_signed;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+use anchor_spl::token_interface::{Mint as MintInterface, TokenAccount as TokenAccountInterface};
+use crate::state::{PoolState, MAX_BOOTSTRAP_SECS, MAX_FEE_TIMELOCK_SECS, MAX_WITHDRAWAL_DELAY_SECS, PROTOCOL_FEE_MODE_NOTIONAL, PROTOCOL_FEE_MODE_LP_FEE};
+use crate::error::ErrorCode;
+use crate::events::{
+    AdminChanged, BootstrapWindowChanged, CreateTreasuryAtaChanged, FeeChangeCancelled, FeeChangeProposed, FeeChanged,
+    FeeTimelockChanged, LpFeeDiscountChanged, LpRebateChanged, LpSupplySynced, NativeMintChanged, PoolLimitsChanged,
+    PoolPaused, PoolUnpaused, ProtocolFeeCompoundingChanged, TreasuryChanged, WithdrawalDelayChanged,
+};
+use crate::utils::{is_token_2022, token_program_for};
+
+/// Set the maximum slippage a swap's min_amount_out may imply (admin only)
+pub fn set_max_slippage(ctx: Context<UpdatePoolConfig>, max_allowed_slippage_bps: u16) -> Result<()> {
+    let pool_state = &mut ctx.accounts.pool_state;
+    
+    require!(pool_state.admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+    require!(max_allowed_slippage_bps <= 10000, ErrorCode::InvalidInput);
+    
+    let old_limits = PoolLimits::of(pool_state);
+    pool_state.max_allowed_slippage_bps = max_allowed_slippage_bps;
+    
+    emit_limits_change(pool_state, ctx.accounts.admin.key(), old_limits);
+    
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct UpdatePoolConfig<'info> {
+    pub admin: Signer<'info>,
+    
+    // SPL and native pools share the PoolState layout, so every setter taking this
+    // context serves both. Pools in a pre-admin layout must go through migrate_pool first
+    #[account(mut)]
+    pub pool_state: Account<'info, PoolState>,
+}
+
+/// Cap the share of the output reserve a single swap may take (admin only, 10000 = unrestricted)
+pub fn set_max_swap_out_bps(ctx: Context<UpdatePoolConfig>, max_swap_out_bps_of_reserve: u16) -> Result<()> {
+    let pool_state = &mut ctx.accounts.pool_state;
+    
+    require!(pool_state.admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+    require!(
+        max_swap_out_bps_of_reserve > 0 && max_swap_out_bps_of_reserve <= 10000,
+        ErrorCode::InvalidInput
+    );
+    
+    let old_limits = PoolLimits::of(pool_state);
+    pool_state.max_swap_out_bps_of_reserve = max_swap_out_bps_of_reserve;
+    
+    emit_limits_change(pool_state, ctx.accounts.admin.key(), old_limits);
+    
+    Ok(())
+}
+
+/// Cap the XNT reserve deposits may bring the pool to (admin only, 0 = unlimited)
+/// Lowering it below the current reserve only blocks further deposits
+pub fn set_max_tvl_native(ctx: Context<UpdatePoolConfig>, max_tvl_native: u64) -> Result<()> {
+    let pool_state = &mut ctx.accounts.pool_state;
+    
+    require!(pool_state.admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+    
+    let old_limits = PoolLimits::of(pool_state);
+    pool_state.max_tvl_native = max_tvl_native;
+    
+    emit_limits_change(pool_state, ctx.accounts.admin.key(), old_limits);
+    
+    Ok(())
+}
+
+// set_max_slippage, set_max_swap_out_bps and set_max_tvl_native report all three limits
+struct PoolLimits {
+    max_allowed_slippage_bps: u16,
+    max_swap_out_bps_of_reserve: u16,
+    max_tvl_native: u64,
+}
+
+impl PoolLimits {
+    fn of(pool_state: &PoolState) -> Self {
+        Self {
+            max_allowed_slippage_bps: pool_state.max_allowed_slippage_bps,
+            max_swap_out_bps_of_reserve: pool_state.max_swap_out_bps_of_reserve,
+            max_tvl_native: pool_state.max_tvl_native,
+        }
+    }
+}
+
+fn emit_limits_change(pool_state: &Account<PoolState>, actor: Pubkey, old: PoolLimits) {
+    emit!(PoolLimitsChanged {
+        pool: pool_state.key(),
+        actor,
+        old_max_allowed_slippage_bps: old.max_allowed_slippage_bps,
+        new_max_allowed_slippage_bps: pool_state.max_allowed_slippage_bps,
+        old_max_swap_out_bps_of_reserve: old.max_swap_out_bps_of_reserve,
+        new_max_swap_out_bps_of_reserve: pool_state.max_swap_out_bps_of_reserve,
+        old_max_tvl_native: old.max_tvl_native,
+        new_max_tvl_native: pool_state.max_tvl_native,
+    });
+}
+
+/// Discount the LP fee for swappers holding LP tokens (admin only)
+/// Holders of at least `threshold` LP tokens pay `discount_bps` less of fee_numerator
+/// threshold = 0 disables the discount
+pub fn set_lp_fee_discount(ctx: Context<UpdatePoolConfig>, threshold: u64, discount_bps: u16) -> Result<()> {
+    let pool_state = &mut ctx.accounts.pool_state;
+    
+    require!(pool_state.admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+    require!(discount_bps <= 10000, ErrorCode::InvalidInput);
+    
+    let old_threshold = pool_state.lp_discount_threshold;
+    let old_discount_bps = pool_state.lp_discount_bps;
+    pool_state.lp_discount_threshold = threshold;
+    pool_state.lp_discount_bps = discount_bps;
+    
+    emit!(LpFeeDiscountChanged {
+        pool: pool_state.key(),
+        actor: ctx.accounts.admin.key(),
+        old_threshold,
+        new_threshold: threshold,
+        old_discount_bps,
+        new_discount_bps: discount_bps,
+    });
+    
+    Ok(())
+}
+
+/// Change the pool's protocol fee (admin only)
+/// Rejects a protocol cut above the LP fee unless `allow_high_protocol_fee` is set
+pub fn set_protocol_fee(
+    ctx: Context<UpdatePoolConfig>,
+    protocol_fee_bps: u16,
+    allow_high_protocol_fee: bool,
+) -> Result<()> {
+    let pool_state = &mut ctx.accounts.pool_state;
+    
+    require!(pool_state.admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+    require!(protocol_fee_bps <= 10000, ErrorCode::InvalidProtocolFee);
+    // Under a fee timelock only decreases apply immediately
+    require!(
+        pool_state.fee_timelock_secs == 0 || protocol_fee_bps <= pool_state.protocol_fee_bps,
+        ErrorCode::FeeChangeTimelocked
+    );
+    if !allow_high_protocol_fee {
+        crate::utils::check_protocol_fee_within_lp_fee(
+            protocol_fee_bps,
+            pool_state.protocol_fee_mode,
+            pool_state.fee_numerator,
+            pool_state.fee_denominator,
+        )?;
+    }
+    
+    let old_protocol_fee_bps = pool_state.protocol_fee_bps;
+    pool_state.protocol_fee_bps = protocol_fee_bps;
+    
+    emit!(FeeChanged {
+        pool: pool_state.key(),
+        actor: ctx.accounts.admin.key(),
+        old_fee_numerator: pool_state.fee_numerator,
+        new_fee_numerator: pool_state.fee_numerator,
+        old_protocol_fee_bps,
+        new_protocol_fee_bps: protocol_fee_bps,
+        old_protocol_fee_mode: pool_state.protocol_fee_mode,
+        new_protocol_fee_mode: pool_state.protocol_fee_mode,
+    });
+    
+    Ok(())
+}
+
+/// Choose how protocol_fee_bps is applied (admin only)
+/// See utils::protocol_fee_amount for the economic difference between modes
+pub fn set_protocol_fee_mode(ctx: Context<UpdatePoolConfig>, protocol_fee_mode: u8) -> Result<()> {
+    let pool_state = &mut ctx.accounts.pool_state;
+    
+    require!(pool_state.admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+    require!(
+        protocol_fee_mode == PROTOCOL_FEE_MODE_NOTIONAL || protocol_fee_mode == PROTOCOL_FEE_MODE_LP_FEE,
+        ErrorCode::InvalidInput
+    );
+    // Switching modes can raise the effective fee, which a timelock rules out
+    require!(pool_state.fee_timelock_secs == 0, ErrorCode::FeeChangeTimelocked);
+    // The current protocol_fee_bps must stay within the LP fee under the new mode
+    crate::utils::check_protocol_fee_within_lp_fee(
+        pool_state.protocol_fee_bps,
+        protocol_fee_mode,
+        pool_state.fee_numerator,
+        pool_state.fee_denominator,
+    )?;
+    
+    let old_protocol_fee_mode = pool_state.protocol_fee_mode;
+    pool_state.protocol_fee_mode = protocol_fee_mode;
+    
+    emit!(FeeChanged {
+        pool: pool_state.key(),
+        actor: ctx.accounts.admin.key(),
+        old_fee_numerator: pool_state.fee_numerator,
+        new_fee_numerator: pool_state.fee_numerator,
+        old_protocol_fee_bps: pool_state.protocol_fee_bps,
+        new_protocol_fee_bps: pool_state.protocol_fee_bps,
+        old_protocol_fee_mode,
+        new_protocol_fee_mode: protocol_fee_mode,
+    });
+    
+    Ok(())
+}
+
+/// Set how long a proposed fee change must wait before apply_fee_change (admin only)
+/// The timelock can only grow, or an admin could drop it and raise fees at once.
+/// Growing it cancels a pending change, which was stamped with the old timelock
+pub fn set_fee_timelock(ctx: Context<UpdatePoolConfig>, fee_timelock_secs: u64) -> Result<()> {
+    let pool_state = &mut ctx.accounts.pool_state;
+    
+    require!(pool_state.admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+    require!(
+        fee_timelock_secs >= pool_state.fee_timelock_secs && fee_timelock_secs <= MAX_FEE_TIMELOCK_SECS,
+        ErrorCode::InvalidInput
+    );
+    
+    if fee_timelock_secs != pool_state.fee_timelock_secs && pool_state.fee_change_effective_at != 0 {
+        emit!(FeeChangeCancelled {
+            pool: pool_state.key(),
+            actor: ctx.accounts.admin.key(),
+            pending_fee_numerator: pool_state.pending_fee_numerator,
+            pending_protocol_fee_bps: pool_state.pending_protocol_fee_bps,
+        });
+        pool_state.clear_pending_fee_change();
+    }
+    let old_fee_timelock_secs = pool_state.fee_timelock_secs;
+    pool_state.fee_timelock_secs = fee_timelock_secs;
+    
+    emit!(FeeTimelockChanged {
+        pool: pool_state.key(),
+        actor: ctx.accounts.admin.key(),
+        old_fee_timelock_secs,
+        new_fee_timelock_secs: fee_timelock_secs,
+    });
+    
+    Ok(())
+}
+
+/// Schedule a new LP fee numerator and protocol fee, applicable after fee_timelock_secs (admin only)
+/// Replaces any change already pending; swaps keep the current fees until apply_fee_change
+pub fn propose_fee_change(
+    ctx: Context<UpdatePoolConfig>,
+    fee_numerator: u64,
+    protocol_fee_bps: u16,
+    allow_high_protocol_fee: bool,
+) -> Result<()> {
+    let pool_state = &mut ctx.accounts.pool_state;
+    
+    require!(pool_state.admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+    require!(fee_numerator < pool_state.fee_denominator, ErrorCode::InvalidInput);
+    require!(protocol_fee_bps <= 10000, ErrorCode::InvalidProtocolFee);
+    if !allow_high_protocol_fee {
+        crate::utils::check_protocol_fee_within_lp_fee(
+            protocol_fee_bps,
+            pool_state.protocol_fee_mode,
+            fee_numerator,
+            pool_state.fee_denominator,
+        )?;
+    }
+    
+    pool_state.schedule_fee_change(fee_numerator, protocol_fee_bps, Clock::get()?.unix_timestamp)?;
+    
+    emit!(FeeChangeProposed {
+        pool: pool_state.key(),
+        actor: ctx.accounts.admin.key(),
+        pending_fee_numerator: fee_numerator,
+        pending_protocol_fee_bps: protocol_fee_bps,
+        effective_at: pool_state.fee_change_effective_at,
+    });
+    
+    Ok(())
+}
+
+/// Drop the pending fee change before it is applied (admin only)
+pub fn cancel_fee_change(ctx: Context<UpdatePoolConfig>) -> Result<()> {
+    let pool_state = &mut ctx.accounts.pool_state;
+    
+    require!(pool_state.admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+    require!(pool_state.fee_change_effective_at != 0, ErrorCode::InvalidInput);
+    
+    emit!(FeeChangeCancelled {
+        pool: pool_state.key(),
+        actor: ctx.accounts.admin.key(),
+        pending_fee_numerator: pool_state.pending_fee_numerator,
+        pending_protocol_fee_bps: pool_state.pending_protocol_fee_bps,
+    });
+    pool_state.clear_pending_fee_change();
+    
+    Ok(())
+}
+
+/// Activate the pending fee change once its timelock has passed (admin only)
+pub fn apply_fee_change(ctx: Context<UpdatePoolConfig>) -> Result<()> {
+    let pool_state = &mut ctx.accounts.pool_state;
+    
+    require!(pool_state.admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+    
+    let old_fee_numerator = pool_state.fee_numerator;
+    let old_protocol_fee_bps = pool_state.protocol_fee_bps;
+    pool_state.apply_pending_fee_change(Clock::get()?.unix_timestamp)?;
+    
+    emit!(FeeChanged {
+        pool: pool_state.key(),
+        actor: ctx.accounts.admin.key(),
+        old_fee_numerator,
+        new_fee_numerator: pool_state.fee_numerator,
+        old_protocol_fee_bps,
+        new_protocol_fee_bps: pool_state.protocol_fee_bps,
+        old_protocol_fee_mode: pool_state.protocol_fee_mode,
+        new_protocol_fee_mode: pool_state.protocol_fee_mode,
+    });
+    
+    Ok(())
+}
+
+/// Opt the pool into creating a missing treasury ATA during swaps (admin only)
+pub fn set_create_treasury_ata(ctx: Context<UpdatePoolConfig>, enabled: bool) -> Result<()> {
+    let pool_state = &mut ctx.accounts.pool_state;
+    
+    require!(pool_state.admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+    
+    let old_create_treasury_ata = pool_state.create_treasury_ata;
+    pool_state.create_treasury_ata = enabled;
+    
+    emit!(CreateTreasuryAtaChanged {
+        pool: pool_state.key(),
+        actor: ctx.accounts.admin.key(),
+        old_create_treasury_ata,
+        new_create_treasury_ata: enabled,
+    });
+    
+    Ok(())
+}
+
+/// Set the mint SPL swaps treat as XNT for the protocol fee (admin only)
+/// Pubkey::default() restores the classic SPL native mint
+pub fn set_native_mint(ctx: Context<UpdatePoolConfig>, native_mint: Pubkey) -> Result<()> {
+    let pool_state = &mut ctx.accounts.pool_state;
+    
+    require!(pool_state.admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+    
+    let old_native_mint = pool_state.native_mint;
+    pool_state.native_mint = native_mint;
+    
+    emit!(NativeMintChanged {
+        pool: pool_state.key(),
+        actor: ctx.accounts.admin.key(),
+        old_native_mint,
+        new_native_mint: native_mint,
+    });
+    
+    Ok(())
+}
+
+/// Enable or disable swaps on the pool (admin only)
+pub fn set_swaps_enabled(ctx: Context<UpdatePoolConfig>, enabled: bool) -> Result<()> {
+    let pool_state = &mut ctx.accounts.pool_state;
+    
+    require!(pool_state.admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+    
+    let old_swaps_enabled = pool_state.swaps_enabled;
+    pool_state.swaps_enabled = enabled;
+    
+    emit_pause_change(pool_state, ctx.accounts.admin.key(), old_swaps_enabled, pool_state.deposits_enabled);
+    
+    Ok(())
+}
+
+/// Enable or disable deposits on the pool (admin only)
+/// Withdrawals stay open either way
+pub fn set_deposits_enabled(ctx: Context<UpdatePoolConfig>, enabled: bool) -> Result<()> {
+    let pool_state = &mut ctx.accounts.pool_state;
+    
+    require!(pool_state.admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+    
+    let old_deposits_enabled = pool_state.deposits_enabled;
+    pool_state.deposits_enabled = enabled;
+    
+    emit_pause_change(pool_state, ctx.accounts.admin.key(), pool_state.swaps_enabled, old_deposits_enabled);
+    
+    Ok(())
+}
+
+// PoolPaused when a switch went off, PoolUnpaused when one came back on, nothing if unchanged
+pub(crate) fn emit_pause_change(pool_state: &Account<PoolState>, actor: Pubkey, old_swaps_enabled: bool, old_deposits_enabled: bool) {
+    let new_swaps_enabled = pool_state.swaps_enabled;
+    let new_deposits_enabled = pool_state.deposits_enabled;
+    if (old_swaps_enabled && !new_swaps_enabled) || (old_deposits_enabled && !new_deposits_enabled) {
+        emit!(PoolPaused {
+            pool: pool_state.key(),
+            actor,
+            old_swaps_enabled,
+            new_swaps_enabled,
+            old_deposits_enabled,
+            new_deposits_enabled,
+        });
+    } else if (!old_swaps_enabled && new_swaps_enabled) || (!old_deposits_enabled && new_deposits_enabled) {
+        emit!(PoolUnpaused {
+            pool: pool_state.key(),
+            actor,
+            old_swaps_enabled,
+            new_swaps_enabled,
+            old_deposits_enabled,
+            new_deposits_enabled,
+        });
+    }
+}
+
+/// Require LPs to exit through request_remove_liquidity, waiting `withdrawal_delay_secs` (admin only)
+/// 0 restores immediate withdrawals; requests already pending keep their unlock time
+pub fn set_withdrawal_delay(ctx: Context<UpdatePoolConfig>, withdrawal_delay_secs: u64) -> Result<()> {
+    let pool_state = &mut ctx.accounts.pool_state;
+    
+    require!(pool_state.admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+    require!(withdrawal_delay_secs <= MAX_WITHDRAWAL_DELAY_SECS, ErrorCode::InvalidInput);
+    // A delay forces withdrawals through the LP escrow, which soulbound pools can't use
+    require!(!pool_state.lp_soulbound || withdrawal_delay_secs == 0, ErrorCode::LpSoulbound);
+    
+    let old_withdrawal_delay_secs = pool_state.withdrawal_delay_secs;
+    pool_state.withdrawal_delay_secs = withdrawal_delay_secs;
+    
+    emit!(WithdrawalDelayChanged {
+        pool: pool_state.key(),
+        actor: ctx.accounts.admin.key(),
+        old_withdrawal_delay_secs,
+        new_withdrawal_delay_secs: withdrawal_delay_secs,
+    });
+    
+    Ok(())
+}
+
+/// Waive the protocol fee on swaps for `bootstrap_secs` after pool creation (admin only)
+/// Measured from created_at, so the window can't be pushed past MAX_BOOTSTRAP_SECS
+/// of the pool's life. Pools created before created_at existed have no window
+pub fn set_bootstrap_window(ctx: Context<UpdatePoolConfig>, bootstrap_secs: u64) -> Result<()> {
+    let pool_state = &mut ctx.accounts.pool_state;
+    
+    require!(pool_state.admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+    require!(bootstrap_secs <= MAX_BOOTSTRAP_SECS, ErrorCode::InvalidInput);
+    
+    let old_bootstrap_secs = pool_state.bootstrap_secs;
+    pool_state.bootstrap_secs = bootstrap_secs;
+    
+    emit!(BootstrapWindowChanged {
+        pool: pool_state.key(),
+        actor: ctx.accounts.admin.key(),
+        old_bootstrap_secs,
+        new_bootstrap_secs: bootstrap_secs,
+    });
+    
+    Ok(())
+}
+
+/// Leave `lp_rebate_bps` of each swap's protocol fee in the pool for LPs (admin only)
+/// Only the protocol_fee_bps fee is rebated - an LP fee routed to the treasury is not
+pub fn set_lp_rebate(ctx: Context<UpdatePoolConfig>, lp_rebate_bps: u16) -> Result<()> {
+    let pool_state = &mut ctx.accounts.pool_state;
+    
+    require!(pool_state.admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+    require!(lp_rebate_bps <= 10000, ErrorCode::InvalidInput);
+    
+    let old_lp_rebate_bps = pool_state.lp_rebate_bps;
+    pool_state.lp_rebate_bps = lp_rebate_bps;
+    
+    emit!(LpRebateChanged {
+        pool: pool_state.key(),
+        actor: ctx.accounts.admin.key(),
+        old_lp_rebate_bps,
+        new_lp_rebate_bps: lp_rebate_bps,
+    });
+    
+    Ok(())
+}
+
+/// Compound swaps' protocol fee into LP minted to `protocol_lp_account`, an LP token
+/// account of this pool, instead of paying it out (admin only). Pubkey::default() turns
+/// it off. Swaps must then pass the LP mint and that account
+pub fn set_protocol_fee_compounding(ctx: Context<SetProtocolFeeCompounding>, protocol_lp_account: Pubkey) -> Result<()> {
+    let pool_state = &mut ctx.accounts.pool_state;
+    
+    require!(pool_state.admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+    let pool_state_key = pool_state.key();
+    if protocol_lp_account != Pubkey::default() {
+        // Every swap would otherwise fail minting to it, or mint LP of another pool's mint
+        let lp_account = ctx.accounts.protocol_lp_account.as_ref().ok_or(ErrorCode::InvalidTreasury)?;
+        require!(lp_account.key() == protocol_lp_account, ErrorCode::InvalidTreasury);
+        require!(
+            lp_account.mint == pool_state.lp_mint_address(&pool_state_key, ctx.program_id),
+            ErrorCode::MintMismatch
+        );
+    }
+    
+    let old_protocol_lp_account = pool_state.protocol_lp_account;
+    pool_state.protocol_lp_account = protocol_lp_account;
+    
+    emit!(ProtocolFeeCompoundingChanged {
+        pool: pool_state_key,
+        actor: ctx.accounts.admin.key(),
+        old_protocol_lp_account,
+        new_protocol_lp_account: protocol_lp_account,
+    });
+    
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetProtocolFeeCompounding<'info> {
+    pub admin: Signer<'info>,
+    
+    #[account(mut)]
+    pub pool_state: Account<'info, PoolState>,
+    
+    /// The new protocol_lp_account - required unless compounding is being turned off
+    /// LP mints always use the standard Token program
+    pub protocol_lp_account: Option<Box<Account<'info, TokenAccount>>>,
+}
+
+/// Hand the pool's admin role to `new_admin` (admin only)
+pub fn set_admin(ctx: Context<UpdatePoolConfig>, new_admin: Pubkey) -> Result<()> {
+    let pool_state = &mut ctx.accounts.pool_state;
+    
+    require!(pool_state.admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+    // Pubkey::default() would leave the pool without an admin for good
+    require!(new_admin != Pubkey::default(), ErrorCode::InvalidInput);
+    
+    let old_admin = pool_state.admin;
+    pool_state.admin = new_admin;
+    
+    emit!(AdminChanged {
+        pool: pool_state.key(),
+        actor: ctx.accounts.admin.key(),
+        old_admin,
+        new_admin,
+    });
+    
+    Ok(())
+}
+
+/// Change where the protocol fee goes (admin only)
+/// Pubkey::default() stops collecting it, leaving the whole fee to LPs. SPL pools pay
+/// the treasury's XNT token account, native pools pay the treasury wallet in lamports
+pub fn set_protocol_treasury(ctx: Context<UpdatePoolConfig>, protocol_treasury: Pubkey) -> Result<()> {
+    let pool_state = &mut ctx.accounts.pool_state;
+    
+    require!(pool_state.admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+    // Protocol-owned pools route every fee to the treasury
+    require!(
+        !pool_state.protocol_owned || protocol_treasury != Pubkey::default(),
+        ErrorCode::InvalidTreasury
+    );
+    
+    let old_treasury = pool_state.protocol_treasury;
+    pool_state.protocol_treasury = protocol_treasury;
+    
+    emit!(TreasuryChanged {
+        pool: pool_state.key(),
+        actor: ctx.accounts.admin.key(),
+        old_treasury,
+        new_treasury: protocol_treasury,
+    });
+    
+    Ok(())
+}
+
+/// Recover tokens sent directly to an SPL pool vault (admin only)
+/// Only allowed while the pool has no LP supply, so active LP funds are never touched
+/// Passing the vault's mint moves the tokens with TransferChecked (hook accounts follow
+/// as remaining accounts), which Token 2022 mints with a transfer fee or hook require
+pub fn recover_stuck_tokens<'info>(
+    ctx: Context<'_, '_, '_, 'info, RecoverStuckTokens<'info>>,
+    amount: u64,
+) -> Result<()> {
+    let pool_state = &ctx.accounts.pool_state;
+    
+    require!(pool_state.admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+    require!(pool_state.total_amount_minted == 0, ErrorCode::InvalidInput);
+    require!(amount > 0, ErrorCode::InvalidInput);
+    
+    // Vault must be one of this pool's vault PDAs
+    let pool_state_key = pool_state.key();
+    let (vault0_pda, _) = Pubkey::find_program_address(&[b"vault0", pool_state_key.as_ref()], ctx.program_id);
+    let (vault1_pda, _) = Pubkey::find_program_address(&[b"vault1", pool_state_key.as_ref()], ctx.program_id);
+    let vault_key = ctx.accounts.vault.key();
+    require!(vault_key == vault0_pda || vault_key == vault1_pda, ErrorCode::InvalidTreasury);
+    let token_index = if vault_key == vault0_pda { 0 } else { 1 };
+    
+    // The optional mint must be the vault's
+    if let Some(mint) = ctx.accounts.mint.as_ref() {
+        let vault = TokenAccountInterface::try_deserialize(&mut &ctx.accounts.vault.try_borrow_data()?[..])?;
+        require!(mint.key() == vault.mint, ErrorCode::MintMismatch);
+    }
+    
+    // Vault is owned by the token program that created its mint
+    let token_program = token_program_for(
+        ctx.accounts.vault.to_account_info().owner,
+        &ctx.accounts.token_program.to_account_info(),
+        &ctx.accounts.token_2022_program.to_account_info(),
+    )?;
+    
+    let bump = ctx.bumps.pool_authority;
+    let pda_sign = &[b"authority", pool_state_key.as_ref(), &[bump]];
+    crate::utils::transfer_tokens_checked(
+        ctx.accounts.vault.to_account_info(),
+        ctx.accounts.recovery_token_account.to_account_info(),
+        ctx.accounts.pool_authority.to_account_info(),
+        token_program,
+        ctx.accounts.mint.as_ref().map(|mint| mint.to_account_info()),
+        pool_state.mint_decimals(token_index),
+        ctx.remaining_accounts,
+        amount,
+        &[pda_sign],
+    )?;
+    
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct RecoverStuckTokens<'info> {
+    pub admin: Signer<'info>,
+    
+    pub pool_state: Account<'info, PoolState>,
+    
+    /// CHECK: This is a PDA used for signing
+    #[account(seeds=[b"authority", pool_state.key().as_ref()], bump)]
+    pub pool_authority: UncheckedAccount<'info>,
+    
+    /// CHECK: Vault can be Token or Token2022, validated in handler
+    #[account(mut)]
+    pub vault: UncheckedAccount<'info>,
+    
+    /// Token account receiving the recovered tokens
+    /// CHECK: Validated by the token program during transfer
+    #[account(mut)]
+    pub recovery_token_account: UncheckedAccount<'info>,
+    
+    pub token_program: Program<'info, Token>,
+    /// CHECK: Token 2022 program - verified in handler
+    pub token_2022_program: UncheckedAccount<'info>,
+    
+    // Optional mint of the vault - passing it moves the tokens with TransferChecked
+    pub mint: Option<Box<InterfaceAccount<'info, MintInterface>>>,
+}
+
+/// Move lamports sent directly to an SPL pool vault, above its rent-exempt minimum, to
+/// `recovery` (admin only). The token balance is untouched. Only Token 2022 vaults can be
+/// swept - the classic Token program has no instruction releasing a token account's lamports
+pub fn sweep_vault_lamports(ctx: Context<SweepVaultLamports>) -> Result<()> {
+    let pool_state = &ctx.accounts.pool_state;
+    
+    require!(pool_state.admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+    
+    // Vault must be one of this pool's vault PDAs
+    let pool_state_key = pool_state.key();
+    let (vault0_pda, _) = Pubkey::find_program_address(&[b"vault0", pool_state_key.as_ref()], ctx.program_id);
+    let (vault1_pda, _) = Pubkey::find_program_address(&[b"vault1", pool_state_key.as_ref()], ctx.program_id);
+    let vault = ctx.accounts.vault.to_account_info();
+    require!(*vault.key == vault0_pda || *vault.key == vault1_pda, ErrorCode::InvalidTreasury);
+    require!(is_token_2022(vault.owner), ErrorCode::InvalidInput);
+    require!(is_token_2022(ctx.accounts.token_2022_program.key), ErrorCode::InvalidTreasury);
+    
+    // Vaults with extensions are larger than 165 bytes, so size the minimum from the account
+    let rent_minimum = Rent::get()?.minimum_balance(vault.data_len());
+    require!(vault.lamports() > rent_minimum, ErrorCode::InvalidInput);
+    
+    let withdraw_ix = spl_token_2022::instruction::withdraw_excess_lamports(
+        ctx.accounts.token_2022_program.key,
+        vault.key,
+        ctx.accounts.recovery.key,
+        ctx.accounts.pool_authority.key,
+        &[],
+    )?;
+    
+    let bump = ctx.bumps.pool_authority;
+    let pda_sign = &[b"authority", pool_state_key.as_ref(), &[bump]];
+    invoke_signed(
+        &withdraw_ix,
+        &[
+            vault,
+            ctx.accounts.recovery.to_account_info(),
+            ctx.accounts.pool_authority.to_account_info(),
+            ctx.accounts.token_2022_program.to_account_info(),
+        ],
+        &[pda_sign],
+    )?;
+    
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SweepVaultLamports<'info> {
+    pub admin: Signer<'info>,
+    
+    pub pool_state: Account<'info, PoolState>,
+    
+    /// CHECK: This is a PDA used for signing
+    #[account(seeds=[b"authority", pool_state.key().as_ref()], bump)]
+    pub pool_authority: UncheckedAccount<'info>,
+    
+    /// CHECK: Must be a Token 2022 vault of this pool, validated in handler
+    #[account(mut)]
+    pub vault: UncheckedAccount<'info>,
+    
+    /// CHECK: Any account may receive the swept lamports
+    #[account(mut)]
+    pub recovery: UncheckedAccount<'info>,
+    
+    /// CHECK: Token 2022 program - verified in handler
+    pub token_2022_program: UncheckedAccount<'info>,
+}
+
+/// Overwrite total_amount_minted with the LP mint's supply (admin only)
+/// A recovery tool like reconcile_native_reserve, for a supply that drifted through a
+/// manual-serialization bug. The supply includes the locked MINIMUM_LIQUIDITY, as
+/// total_amount_minted does
+pub fn sync_lp_supply(ctx: Context<SyncLpSupply>) -> Result<()> {
+    let pool_state = &mut ctx.accounts.pool_state;
+    
+    require!(pool_state.admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+    let pool_state_key = pool_state.key();
+    require!(
+        ctx.accounts.lp_mint.key() == pool_state.lp_mint_address(&pool_state_key, ctx.program_id),
+        ErrorCode::MintMismatch
+    );
+    
+    let old_total_amount_minted = pool_state.total_amount_minted;
+    let new_total_amount_minted = ctx.accounts.lp_mint.supply;
+    if old_total_amount_minted == new_total_amount_minted {
+        return Ok(());
+    }
+    pool_state.total_amount_minted = new_total_amount_minted;
+    
+    emit!(LpSupplySynced {
+        pool: pool_state_key,
+        actor: ctx.accounts.admin.key(),
+        old_total_amount_minted,
+        new_total_amount_minted,
+    });
+    
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SyncLpSupply<'info> {
+    pub admin: Signer<'info>,
+    
+    #[account(mut)]
+    pub pool_state: Account<'info, PoolState>,
+    
+    /// The pool's LP mint - validated in handler (SPL and native pools seed it differently)
+    pub lp_mint: Account<'info, Mint>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{
+        empty_account, install_stubs, mint_account, pda, program, program_account, signer, take_events, token_2022_account,
+        token_account, Info, TestAccount,
+    };
+
+    /// recover_stuck_tokens of 1_000 out of `vault`
+    fn recover(admin: Info, pool_state: &PoolState, pool_key: Pubkey, vault: Info) -> Result<()> {
+        let (pool_authority, bump) = Pubkey::find_program_address(&[b"authority", pool_key.as_ref()], &crate::ID);
+        let mut accounts = RecoverStuckTokens {
+            admin: Signer::try_from(admin).unwrap(),
+            pool_state: Account::try_from(program_account(pool_key, pool_state).info()).unwrap(),
+            pool_authority: UncheckedAccount::try_from(empty_account(pool_authority).info()),
+            vault: UncheckedAccount::try_from(vault),
+            recovery_token_account: UncheckedAccount::try_from(empty_account(Pubkey::new_unique()).info()),
+            token_program: Program::try_from(program(Token::id()).info()).unwrap(),
+            token_2022_program: UncheckedAccount::try_from(program(spl_token_2022::ID).info()),
+            mint: None,
+        };
+        recover_stuck_tokens(
+            Context::new(&crate::ID, &mut accounts, &[], RecoverStuckTokensBumps { pool_authority: bump }),
+            1_000,
+        )
+    }
+
+    #[test]
+    fn stuck_tokens_are_recovered_only_from_an_empty_pools_vaults() {
+        let admin = signer().info();
+        let pool_key = Pubkey::new_unique();
+        let pool_authority = pda(&[b"authority", pool_key.as_ref()]);
+        let vault = |seed: &[u8]| token_account(pda(&[seed, pool_key.as_ref()]), Pubkey::new_unique(), pool_authority, 1_000).info();
+        let empty_pool = PoolState { admin: *admin.key, ..Default::default() };
+        
+        assert!(recover(admin, &empty_pool, pool_key, vault(b"vault0")).is_ok());
+        assert!(recover(admin, &empty_pool, pool_key, vault(b"vault1")).is_ok());
+        assert_eq!(
+            recover(signer().info(), &empty_pool, pool_key, vault(b"vault0")).unwrap_err(),
+            ErrorCode::Unauthorized.into()
+        );
+        // Any other token account the pool authority holds
+        let other = token_account(Pubkey::new_unique(), Pubkey::new_unique(), pool_authority, 1_000).info();
+        assert_eq!(recover(admin, &empty_pool, pool_key, other).unwrap_err(), ErrorCode::InvalidTreasury.into());
+        // LPs still have a claim on the vaults
+        let live_pool = PoolState { total_amount_minted: 1, ..empty_pool };
+        assert_eq!(recover(admin, &live_pool, pool_key, vault(b"vault0")).unwrap_err(), ErrorCode::InvalidInput.into());
+    }
+
+    #[test]
+    fn admin_handover_is_logged_with_both_admins() {
+        install_stubs();
+        let admin = signer().info();
+        let new_admin = Pubkey::new_unique();
+        let pool_state = program_account(Pubkey::new_unique(), &PoolState { admin: *admin.key, ..Default::default() }).info();
+        let set = |signer: Info, new_admin: Pubkey| {
+            let mut accounts = UpdatePoolConfig {
+                admin: Signer::try_from(signer).unwrap(),
+                pool_state: Account::try_from(pool_state).unwrap(),
+            };
+            set_admin(Context::new(&crate::ID, &mut accounts, &[], UpdatePoolConfigBumps::default()), new_admin)?;
+            accounts.exit(&crate::ID)
+        };
+        
+        take_events::<AdminChanged>();
+        assert_eq!(set(signer().info(), new_admin).unwrap_err(), ErrorCode::Unauthorized.into());
+        assert_eq!(set(admin, Pubkey::default()).unwrap_err(), ErrorCode::InvalidInput.into());
+        assert!(take_events::<AdminChanged>().is_empty());
+        
+        set(admin, new_admin).unwrap();
+        let events = take_events::<AdminChanged>();
+        assert_eq!(events.len(), 1);
+        assert_eq!(
+            (events[0].pool, events[0].actor, events[0].old_admin, events[0].new_admin),
+            (*pool_state.key, *admin.key, *admin.key, new_admin)
+        );
+        // The old admin is locked out from then on
+        assert_eq!(set(admin, *admin.key).unwrap_err(), ErrorCode::Unauthorized.into());
+    }
+
+    #[test]
+    fn only_lamports_above_a_token_2022_vaults_rent_are_swept() {
+        install_stubs();
+        let admin = signer().info();
+        let pool_key = Pubkey::new_unique();
+        let pool_state = program_account(pool_key, &PoolState { admin: *admin.key, ..Default::default() }).info();
+        let (pool_authority, bump) = Pubkey::find_program_address(&[b"authority", pool_key.as_ref()], &crate::ID);
+        let sweep = |admin: Info, vault: TestAccount| {
+            let mut accounts = SweepVaultLamports {
+                admin: Signer::try_from(admin).unwrap(),
+                pool_state: Account::try_from(pool_state).unwrap(),
+                pool_authority: UncheckedAccount::try_from(empty_account(pool_authority).info()),
+                vault: UncheckedAccount::try_from(vault.info()),
+                recovery: UncheckedAccount::try_from(empty_account(Pubkey::new_unique()).info()),
+                token_2022_program: UncheckedAccount::try_from(program(spl_token_2022::ID).info()),
+            };
+            sweep_vault_lamports(Context::new(&crate::ID, &mut accounts, &[], SweepVaultLamportsBumps { pool_authority: bump }))
+        };
+        let vault0 = || token_2022_account(pda(&[b"vault0", pool_key.as_ref()]), Pubkey::new_unique(), pool_authority, 1_000);
+        
+        assert!(sweep(admin, vault0()).is_ok());
+        assert_eq!(sweep(signer().info(), vault0()).unwrap_err(), ErrorCode::Unauthorized.into());
+        let not_a_vault = token_2022_account(Pubkey::new_unique(), Pubkey::new_unique(), pool_authority, 1_000);
+        assert_eq!(sweep(admin, not_a_vault).unwrap_err(), ErrorCode::InvalidTreasury.into());
+        // The classic Token program can't release a token account's lamports
+        let classic = token_account(pda(&[b"vault1", pool_key.as_ref()]), Pubkey::new_unique(), pool_authority, 1_000);
+        assert_eq!(sweep(admin, classic).unwrap_err(), ErrorCode::InvalidInput.into());
+        // Nothing above the rent-exempt minimum of the vault's extended size
+        let vault = vault0();
+        let rent_exempt = TestAccount { lamports: Rent::default().minimum_balance(vault.data.len()), ..vault };
+        assert_eq!(sweep(admin, rent_exempt).unwrap_err(), ErrorCode::InvalidInput.into());
+    }
+
+    #[test]
+    fn fee_and_treasury_setters_serve_native_pools() {
+        let admin = signer().info();
+        let pool_state = program_account(Pubkey::new_unique(), &PoolState {
+            is_native_pool: true,
+            pool_kind: crate::state::POOL_KIND_NATIVE,
+            admin: *admin.key,
+            native_reserve: 1_000_000_000,
+            fee_numerator: 30,
+            fee_denominator: 10000,
+            ..Default::default()
+        }).info();
+        let update = |set: &dyn Fn(Context<UpdatePoolConfig<'static>>) -> Result<()>| {
+            let mut accounts = UpdatePoolConfig {
+                admin: Signer::try_from(admin).unwrap(),
+                pool_state: Account::try_from(pool_state).unwrap(),
+            };
+            set(Context::new(&crate::ID, &mut accounts, &[], UpdatePoolConfigBumps::default())).unwrap();
+            accounts.exit(&crate::ID).unwrap();
+        };
+        let treasury = Pubkey::new_unique();
+        
+        update(&|ctx| set_protocol_fee(ctx, 20, false));
+        update(&|ctx| set_protocol_treasury(ctx, treasury));
+        
+        // Written back over the same fields swap_native reads, the hand-written offsets untouched
+        let data = pool_state.try_borrow_data().unwrap();
+        let pool = PoolState::try_deserialize(&mut &data[..]).unwrap();
+        assert!(pool.is_native_pool);
+        assert_eq!((pool.protocol_fee_bps, pool.protocol_treasury), (20, treasury));
+        let offset = PoolState::NATIVE_RESERVE_OFFSET;
+        assert_eq!(u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap()), 1_000_000_000);
+        assert_eq!(data[PoolState::IN_PROGRESS_OFFSET], 0);
+    }
+
+    #[test]
+    fn soulbound_pools_keep_immediate_withdrawals() {
+        let admin = signer().info();
+        let delay = |lp_soulbound: bool, withdrawal_delay_secs: u64| {
+            let pool_state = PoolState { admin: *admin.key, lp_soulbound, ..Default::default() };
+            let mut accounts = UpdatePoolConfig {
+                admin: Signer::try_from(admin).unwrap(),
+                pool_state: Account::try_from(program_account(Pubkey::new_unique(), &pool_state).info()).unwrap(),
+            };
+            set_withdrawal_delay(
+                Context::new(&crate::ID, &mut accounts, &[], UpdatePoolConfigBumps::default()),
+                withdrawal_delay_secs,
+            )
+        };
+        
+        // A delay would move LP into the escrow, out of the holder's frozen account
+        assert_eq!(delay(true, 3600).unwrap_err(), ErrorCode::LpSoulbound.into());
+        assert!(delay(true, 0).is_ok());
+        assert!(delay(false, 3600).is_ok());
+    }
+
+    #[test]
+    fn lp_supply_is_synced_only_from_the_pools_own_mint() {
+        install_stubs();
+        let admin = signer().info();
+        let pool_key = Pubkey::new_unique();
+        let pool = PoolState {
+            admin: *admin.key,
+            pool_kind: crate::state::POOL_KIND_SPL,
+            total_amount_minted: 1_000,
+            ..Default::default()
+        };
+        let pool_state = program_account(pool_key, &pool).info();
+        let lp_mint = pool.lp_mint_address(&pool_key, &crate::ID);
+        let sync = |admin: Info, lp_mint: Pubkey| {
+            let mut accounts = SyncLpSupply {
+                admin: Signer::try_from(admin).unwrap(),
+                pool_state: Account::try_from(pool_state).unwrap(),
+                lp_mint: Account::try_from(mint_account(lp_mint, Pubkey::new_unique(), 1_500, 9).info()).unwrap(),
+            };
+            sync_lp_supply(Context::new(&crate::ID, &mut accounts, &[], SyncLpSupplyBumps::default()))?;
+            accounts.exit(&crate::ID)?;
+            Ok::<_, Error>(accounts.pool_state.total_amount_minted)
+        };
+        
+        assert_eq!(sync(signer().info(), lp_mint).unwrap_err(), ErrorCode::Unauthorized.into());
+        assert_eq!(sync(admin, Pubkey::new_unique()).unwrap_err(), ErrorCode::MintMismatch.into());
+        take_events::<LpSupplySynced>();
+        assert_eq!(sync(admin, lp_mint).unwrap(), 1_500);
+        let events = take_events::<LpSupplySynced>();
+        assert_eq!(events.len(), 1);
+        assert_eq!((events[0].old_total_amount_minted, events[0].new_total_amount_minted), (1_000, 1_500));
+        // Already in sync - nothing to log
+        assert_eq!(sync(admin, lp_mint).unwrap(), 1_500);
+        assert!(take_events::<LpSupplySynced>().is_empty());
+    }
+}
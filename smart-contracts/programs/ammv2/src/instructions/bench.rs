@@ -0,0 +1,142 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::compute_units::sol_remaining_compute_units;
+
+use crate::state::PoolState;
+use crate::error::ErrorCode;
+
+/// Compute units bench_swap_math allows the swap math - it fails above this, so a
+/// regression breaks the bench run instead of only moving a logged number
+pub const SWAP_MATH_CU_BUDGET: u64 = 10_000;
+
+/// Run a swap's pricing and fee math with the pool's fee config, without any transfer,
+/// logging the compute units it took and failing above SWAP_MATH_CU_BUDGET
+/// Only built with the `bench` feature. Reserves are arguments, so any pool state
+/// can be benchmarked against any depth
+pub fn bench_swap_math(
+    ctx: Context<BenchSwapMath>,
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+) -> Result<()> {
+    let pool_state = &ctx.accounts.pool_state;
+    require!(amount_in > 0, ErrorCode::InvalidInput);
+    
+    let (math, compute_units) = metered(sol_remaining_compute_units, || {
+        swap_math(pool_state, amount_in, reserve_in, reserve_out)
+    })?;
+    
+    msg!(
+        "compute_units: {} (budget {}), amount_out: {}, lp_fee: {}, protocol_fee: {}, lp_rebate: {}",
+        compute_units,
+        SWAP_MATH_CU_BUDGET,
+        math.amount_out,
+        math.lp_fee,
+        math.protocol_fee,
+        math.lp_rebate,
+    );
+    check_swap_math_budget(compute_units)
+}
+
+fn check_swap_math_budget(compute_units: u64) -> Result<()> {
+    require!(compute_units <= SWAP_MATH_CU_BUDGET, ErrorCode::SwapMathOverBudget);
+    Ok(())
+}
+
+struct SwapMath {
+    amount_out: u64,
+    lp_fee: u64,
+    protocol_fee: u128,
+    lp_rebate: u128,
+}
+
+// The math a swap runs before any transfer
+fn swap_math(pool_state: &PoolState, amount_in: u64, reserve_in: u64, reserve_out: u64) -> Result<SwapMath> {
+    let amount_out = crate::utils::calculate_swap_output(
+        amount_in,
+        reserve_in,
+        reserve_out,
+        pool_state.fee_numerator,
+        pool_state.fee_denominator,
+    )?;
+    let lp_fee = crate::utils::lp_fee_amount(amount_in, pool_state.fee_numerator, pool_state.fee_denominator)?;
+    let protocol_fee = crate::utils::protocol_fee_amount(
+        amount_in as u128,
+        pool_state.protocol_fee_bps,
+        pool_state.protocol_fee_mode,
+        pool_state.fee_numerator,
+        pool_state.fee_denominator,
+    ).ok_or(ErrorCode::MathOverflow)?;
+    let lp_rebate = pool_state.lp_rebate(protocol_fee);
+    
+    Ok(SwapMath { amount_out, lp_fee, protocol_fee, lp_rebate })
+}
+
+// `f`'s result and the compute units it took, read from `remaining_compute_units`
+// before and after
+fn metered<T>(remaining_compute_units: impl Fn() -> u64, f: impl FnOnce() -> Result<T>) -> Result<(T, u64)> {
+    let before = remaining_compute_units();
+    let result = f()?;
+    Ok((result, before.saturating_sub(remaining_compute_units())))
+}
+
+#[derive(Accounts)]
+pub struct BenchSwapMath<'info> {
+    pub pool_state: Account<'info, PoolState>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use crate::state::{PROTOCOL_FEE_MODE_LP_FEE, PROTOCOL_FEE_MODE_NOTIONAL};
+
+    #[test]
+    fn swap_math_runs_across_fee_configs_and_depths() {
+        for protocol_fee_mode in [PROTOCOL_FEE_MODE_NOTIONAL, PROTOCOL_FEE_MODE_LP_FEE] {
+            let pool_state = PoolState {
+                fee_numerator: 30,
+                fee_denominator: 10000,
+                protocol_fee_bps: 10000,
+                protocol_fee_mode,
+                lp_rebate_bps: 10000,
+                ..Default::default()
+            };
+            for (amount_in, reserve) in [(1, 1), (1_000_000, 1_000_000_000), (u64::MAX, u64::MAX)] {
+                let math = swap_math(&pool_state, amount_in, reserve, reserve).unwrap();
+                assert!(math.amount_out < reserve);
+                assert_eq!(math.lp_rebate, math.protocol_fee);
+            }
+        }
+    }
+
+    #[test]
+    fn swap_math_over_budget_fails_the_bench() {
+        // Off-chain there is no compute meter - count down a fake one instead
+        let meter = |cost: u64| {
+            let remaining = Cell::new(200_000u64);
+            move || {
+                let left = remaining.get();
+                remaining.set(left - cost);
+                left
+            }
+        };
+        let pool_state = PoolState { fee_numerator: 30, fee_denominator: 10000, ..Default::default() };
+        let run = || swap_math(&pool_state, 1_000_000, 1_000_000_000, 1_000_000_000);
+
+        let (_, compute_units) = metered(meter(SWAP_MATH_CU_BUDGET), run).unwrap();
+        assert_eq!(compute_units, SWAP_MATH_CU_BUDGET);
+        assert!(check_swap_math_budget(compute_units).is_ok());
+        let (_, compute_units) = metered(meter(SWAP_MATH_CU_BUDGET + 1), run).unwrap();
+        assert_eq!(
+            check_swap_math_budget(compute_units).unwrap_err(),
+            ErrorCode::SwapMathOverBudget.into()
+        );
+
+        // Math errors surface before any budget check
+        let empty_pool = || swap_math(&pool_state, 1_000_000, 0, 1_000_000_000);
+        assert_eq!(
+            metered(meter(1), empty_pool).err().unwrap(),
+            ErrorCode::InsufficientLiquidity.into()
+        );
+    }
+}
@@ -0,0 +1,252 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{token::Token, token_interface::{Mint, TokenAccount}};
+
+use crate::state::PoolState;
+use crate::error::ErrorCode;
+use crate::utils::token_program_for;
+
+/// Donate tokens to an SPL pool without minting LP
+/// Reserves are the vault balances, so the donation raises every LP token's
+/// redeemable value pro-rata
+/// A side whose mint is passed moves with TransferChecked (hook accounts follow as
+/// remaining accounts), which Token 2022 mints with a transfer fee or hook require
+pub fn donate_liquidity<'info>(
+    ctx: Context<'_, '_, '_, 'info, DonateLiquidity<'info>>,
+    amount0: u64,
+    amount1: u64,
+) -> Result<()> {
+    require!(!ctx.accounts.pool_state.is_native_pool, ErrorCode::InvalidInput);
+    require!(amount0 > 0 || amount1 > 0, ErrorCode::InvalidInput);
+    // With no LP supply nobody would receive the donation
+    require!(ctx.accounts.pool_state.total_amount_minted > 0, ErrorCode::InsufficientLiquidity);
+
+    // Guard against reentrant calls on this pool
+    crate::utils::begin_pool_operation(&ctx.accounts.pool_state.to_account_info())?;
+
+    // The token program checks the source mint matches the vault mint
+    for (token_index, user, vault, mint, amount) in [
+        (0, &ctx.accounts.user0, &ctx.accounts.vault0, &ctx.accounts.mint0, amount0),
+        (1, &ctx.accounts.user1, &ctx.accounts.vault1, &ctx.accounts.mint1, amount1),
+    ] {
+        if let Some(mint) = mint.as_ref() {
+            require!(mint.key() == vault.mint, ErrorCode::MintMismatch);
+        }
+        if amount == 0 {
+            continue;
+        }
+
+        let token_program = token_program_for(
+            user.to_account_info().owner,
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.token_2022_program.to_account_info(),
+        )?;
+        crate::utils::transfer_tokens_checked(
+            user.to_account_info(),
+            vault.to_account_info(),
+            ctx.accounts.owner.to_account_info(),
+            token_program,
+            mint.as_ref().map(|mint| mint.to_account_info()),
+            ctx.accounts.pool_state.mint_decimals(token_index),
+            ctx.remaining_accounts,
+            amount,
+            &[],
+        )?;
+    }
+
+    crate::utils::end_pool_operation(&ctx.accounts.pool_state.to_account_info())?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct DonateLiquidity<'info> {
+    #[account(mut)]
+    pub pool_state: Box<Account<'info, PoolState>>,
+
+    // vaults and user token accounts can be Token or Token2022
+    #[account(mut, seeds=[b"vault0", pool_state.key().as_ref()], bump)]
+    pub vault0: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, seeds=[b"vault1", pool_state.key().as_ref()], bump)]
+    pub vault1: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub user0: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut)]
+    pub user1: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub owner: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    /// CHECK: Token 2022 program - verified in handler
+    pub token_2022_program: UncheckedAccount<'info>,
+
+    // Optional mints of vault0 / vault1 - passing one moves that side with TransferChecked
+    pub mint0: Option<Box<InterfaceAccount<'info, Mint>>>,
+    pub mint1: Option<Box<InterfaceAccount<'info, Mint>>>,
+}
+
+/// Donate XNT and/or tokens to a native pool without minting LP
+/// Unlike a raw lamport transfer to pool_pda, this bumps native_reserve so
+/// the XNT is priced into swaps and redeemable by LPs
+pub fn donate_native_liquidity(
+    ctx: Context<DonateNativeLiquidity>,
+    xnt_amount: u64,
+    token_amount: u64,
+) -> Result<()> {
+    require!(ctx.accounts.pool_state.is_native_pool, ErrorCode::NotNativePool);
+    require!(xnt_amount > 0 || token_amount > 0, ErrorCode::InvalidInput);
+    // With no LP supply nobody would receive the donation
+    require!(ctx.accounts.pool_state.total_amount_minted > 0, ErrorCode::InsufficientLiquidity);
+
+    // Guard against reentrant calls on this pool
+    crate::utils::begin_pool_operation(&ctx.accounts.pool_state.to_account_info())?;
+
+    if xnt_amount > 0 {
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: ctx.accounts.user.to_account_info(),
+                to: ctx.accounts.pool_pda.to_account_info(),
+            },
+        );
+        anchor_lang::system_program::transfer(cpi_context, xnt_amount)?;
+
+        let new_native_reserve = ctx.accounts.pool_state.native_reserve
+            .checked_add(xnt_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        {
+            let pool_state_info = ctx.accounts.pool_state.to_account_info();
+            let mut data = pool_state_info.try_borrow_mut_data()?;
+            PoolState::write_native_reserve(&mut data, new_native_reserve)?;
+        }
+        ctx.accounts.pool_state.native_reserve = new_native_reserve;
+    }
+
+    if token_amount > 0 {
+        let token_program = token_program_for(
+            ctx.accounts.token_vault.to_account_info().owner,
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.token_2022_program.to_account_info(),
+        )?;
+        crate::utils::transfer_tokens(
+            ctx.accounts.user_token_account.to_account_info(),
+            ctx.accounts.token_vault.to_account_info(),
+            ctx.accounts.user.to_account_info(),
+            token_program,
+            token_amount,
+        )?;
+    }
+
+    crate::utils::end_pool_operation(&ctx.accounts.pool_state.to_account_info())?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct DonateNativeLiquidity<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(mut)]
+    pub pool_state: Account<'info, PoolState>,
+
+    /// Pool PDA that holds native XNT
+    /// CHECK: This is a PDA
+    #[account(
+        mut,
+        seeds = [b"pool_pda", pool_state.key().as_ref()],
+        bump
+    )]
+    pub pool_pda: UncheckedAccount<'info>,
+
+    /// Token vault - can be Token or Token2022
+    #[account(mut, seeds = [b"vault", pool_state.key().as_ref()], bump)]
+    pub token_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// User's token account - can be Token or Token2022
+    #[account(mut)]
+    pub user_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+    /// CHECK: Token-2022 program (optional, used for Token2022 tokens)
+    pub token_2022_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{empty_account, mint_account, pda, program, program_account, signer, token_account, Info};
+
+    /// donate_liquidity of 1_000 of each token to a pool of `mint0` / `mint1`, passing `mints`
+    fn donate(pool_state: &PoolState, (mint0, mint1): (Pubkey, Pubkey), mints: [Option<Info>; 2]) -> Result<()> {
+        let owner = signer().info();
+        let pool_key = Pubkey::new_unique();
+        let pool_authority = pda(&[b"authority", pool_key.as_ref()]);
+        let [mint0_account, mint1_account] = mints.map(|mint| mint.map(|mint| Box::new(InterfaceAccount::try_from(mint).unwrap())));
+        let mut accounts = DonateLiquidity {
+            pool_state: Box::new(Account::try_from(program_account(pool_key, pool_state).info()).unwrap()),
+            vault0: Box::new(InterfaceAccount::try_from(
+                token_account(pda(&[b"vault0", pool_key.as_ref()]), mint0, pool_authority, 1_000_000).info()
+            ).unwrap()),
+            vault1: Box::new(InterfaceAccount::try_from(
+                token_account(pda(&[b"vault1", pool_key.as_ref()]), mint1, pool_authority, 1_000_000).info()
+            ).unwrap()),
+            user0: Box::new(InterfaceAccount::try_from(token_account(Pubkey::new_unique(), mint0, *owner.key, 1_000).info()).unwrap()),
+            user1: Box::new(InterfaceAccount::try_from(token_account(Pubkey::new_unique(), mint1, *owner.key, 1_000).info()).unwrap()),
+            owner: Signer::try_from(owner).unwrap(),
+            token_program: Program::try_from(program(Token::id()).info()).unwrap(),
+            token_2022_program: UncheckedAccount::try_from(program(spl_token_2022::ID).info()),
+            mint0: mint0_account,
+            mint1: mint1_account,
+        };
+        donate_liquidity(Context::new(&crate::ID, &mut accounts, &[], DonateLiquidityBumps::default()), 1_000, 1_000)
+    }
+
+    #[test]
+    fn donation_moves_each_side_with_its_vault_mint() {
+        let pool_state = PoolState { total_amount_minted: 1_000_000, ..Default::default() };
+        let mints = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mint = |key| Some(mint_account(key, Pubkey::new_unique(), 1_000_000, 6).info());
+        assert!(donate(&pool_state, mints, [mint(mints.0), mint(mints.1)]).is_ok());
+        assert!(donate(&pool_state, mints, [None, mint(mints.1)]).is_ok());
+        assert_eq!(
+            donate(&pool_state, mints, [mint(mints.1), None]).unwrap_err(),
+            ErrorCode::MintMismatch.into()
+        );
+    }
+
+    #[test]
+    fn donations_go_to_existing_lps_only() {
+        let mints = (Pubkey::new_unique(), Pubkey::new_unique());
+        // Nobody holds LP yet
+        assert_eq!(
+            donate(&PoolState::default(), mints, [None, None]).unwrap_err(),
+            ErrorCode::InsufficientLiquidity.into()
+        );
+        
+        // A native donation is priced in through native_reserve
+        let owner = signer().info();
+        let pool_state = PoolState { is_native_pool: true, total_amount_minted: 1_000_000, native_reserve: 5_000, ..Default::default() };
+        let pool_key = Pubkey::new_unique();
+        let token_mint = Pubkey::new_unique();
+        let mut accounts = DonateNativeLiquidity {
+            user: Signer::try_from(owner).unwrap(),
+            pool_state: Account::try_from(program_account(pool_key, &pool_state).info()).unwrap(),
+            pool_pda: UncheckedAccount::try_from(empty_account(pda(&[b"pool_pda", pool_key.as_ref()])).info()),
+            token_vault: Box::new(InterfaceAccount::try_from(
+                token_account(pda(&[b"vault", pool_key.as_ref()]), token_mint, pda(&[b"authority", pool_key.as_ref()]), 0).info()
+            ).unwrap()),
+            user_token_account: Box::new(InterfaceAccount::try_from(
+                token_account(Pubkey::new_unique(), token_mint, *owner.key, 1_000).info()
+            ).unwrap()),
+            token_program: Program::try_from(program(Token::id()).info()).unwrap(),
+            token_2022_program: UncheckedAccount::try_from(program(spl_token_2022::ID).info()),
+            system_program: Program::try_from(program(System::id()).info()).unwrap(),
+        };
+        donate_native_liquidity(Context::new(&crate::ID, &mut accounts, &[], DonateNativeLiquidityBumps::default()), 1_000, 1_000).unwrap();
+        let pool_state = PoolState::try_deserialize(&mut &accounts.pool_state.to_account_info().try_borrow_data().unwrap()[..]).unwrap();
+        assert_eq!(pool_state.native_reserve, 6_000);
+        assert_eq!(pool_state.total_amount_minted, 1_000_000);
+    }
+}
@@ -0,0 +1,250 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{token::Token, token_interface::{Mint, TokenAccount}};
+
+use crate::state::{GlobalConfig, PoolState};
+use crate::error::ErrorCode;
+use crate::utils::token_program_for;
+
+/// Common gate for emergency withdrawals: pool admin, kill-switch engaged,
+/// and a recovery address fixed at pool creation
+fn check_emergency_withdraw(
+    pool_state: &PoolState,
+    global_config: &GlobalConfig,
+    admin: &Pubkey,
+    recovery: &Pubkey,
+) -> Result<()> {
+    require!(pool_state.admin == *admin, ErrorCode::Unauthorized);
+    require!(global_config.paused, ErrorCode::KillSwitchNotEngaged);
+    require!(pool_state.emergency_recovery != Pubkey::default(), ErrorCode::EmergencyRecoveryNotSet);
+    require!(pool_state.emergency_recovery == *recovery, ErrorCode::Unauthorized);
+    Ok(())
+}
+
+/// Move all SPL pool reserves to the pool's emergency_recovery wallet
+/// Only while the global kill-switch is engaged (admin only)
+/// A side whose mint is passed moves with TransferChecked (hook accounts follow as
+/// remaining accounts), which Token 2022 mints with a transfer fee or hook require
+pub fn emergency_withdraw<'info>(ctx: Context<'_, '_, '_, 'info, EmergencyWithdraw<'info>>) -> Result<()> {
+    require!(!ctx.accounts.pool_state.is_native_pool, ErrorCode::InvalidInput);
+    check_emergency_withdraw(
+        &ctx.accounts.pool_state,
+        &ctx.accounts.global_config,
+        &ctx.accounts.admin.key(),
+        &ctx.accounts.emergency_recovery.key(),
+    )?;
+
+    // Guard against reentrant calls on this pool
+    crate::utils::begin_pool_operation(&ctx.accounts.pool_state.to_account_info())?;
+
+    let pool_state_key = ctx.accounts.pool_state.key();
+    let pda_sign = &[b"authority", pool_state_key.as_ref(), &[ctx.bumps.pool_authority]];
+
+    for (token_index, vault, recovery_token_account, mint) in [
+        (0, &ctx.accounts.vault0, &ctx.accounts.recovery_token0, &ctx.accounts.mint0),
+        (1, &ctx.accounts.vault1, &ctx.accounts.recovery_token1, &ctx.accounts.mint1),
+    ] {
+        // Only token accounts owned by the fixed recovery wallet may receive funds
+        require!(recovery_token_account.owner == ctx.accounts.emergency_recovery.key(), ErrorCode::Unauthorized);
+        if let Some(mint) = mint.as_ref() {
+            require!(mint.key() == vault.mint, ErrorCode::MintMismatch);
+        }
+        if vault.amount == 0 {
+            continue;
+        }
+
+        let token_program = token_program_for(
+            vault.to_account_info().owner,
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.token_2022_program.to_account_info(),
+        )?;
+        crate::utils::transfer_tokens_checked(
+            vault.to_account_info(),
+            recovery_token_account.to_account_info(),
+            ctx.accounts.pool_authority.to_account_info(),
+            token_program,
+            mint.as_ref().map(|mint| mint.to_account_info()),
+            ctx.accounts.pool_state.mint_decimals(token_index),
+            ctx.remaining_accounts,
+            vault.amount,
+            &[pda_sign],
+        )?;
+    }
+
+    crate::utils::end_pool_operation(&ctx.accounts.pool_state.to_account_info())?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct EmergencyWithdraw<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(mut)]
+    pub pool_state: Box<Account<'info, PoolState>>,
+
+    #[account(seeds = [b"global_config"], bump)]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    /// CHECK: This is a PDA used for signing
+    #[account(seeds = [b"authority", pool_state.key().as_ref()], bump)]
+    pub pool_authority: UncheckedAccount<'info>,
+
+    // vaults and recovery token accounts can be Token or Token2022
+    #[account(mut, seeds = [b"vault0", pool_state.key().as_ref()], bump)]
+    pub vault0: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, seeds = [b"vault1", pool_state.key().as_ref()], bump)]
+    pub vault1: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Must equal pool_state.emergency_recovery, verified in handler
+    pub emergency_recovery: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub recovery_token0: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut)]
+    pub recovery_token1: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+    /// CHECK: Token 2022 program - verified in handler
+    pub token_2022_program: UncheckedAccount<'info>,
+
+    // Optional mints of vault0 / vault1 - passing one moves that side with TransferChecked
+    pub mint0: Option<Box<InterfaceAccount<'info, Mint>>>,
+    pub mint1: Option<Box<InterfaceAccount<'info, Mint>>>,
+}
+
+/// Move a native pool's XNT reserve and token vault to its emergency_recovery wallet
+/// Only while the global kill-switch is engaged (admin only)
+/// Passing the token mint moves the tokens with TransferChecked, as emergency_withdraw does
+pub fn emergency_withdraw_native<'info>(ctx: Context<'_, '_, '_, 'info, EmergencyWithdrawNative<'info>>) -> Result<()> {
+    require!(ctx.accounts.pool_state.is_native_pool, ErrorCode::NotNativePool);
+    check_emergency_withdraw(
+        &ctx.accounts.pool_state,
+        &ctx.accounts.global_config,
+        &ctx.accounts.admin.key(),
+        &ctx.accounts.emergency_recovery.key(),
+    )?;
+    require!(
+        ctx.accounts.recovery_token_account.owner == ctx.accounts.emergency_recovery.key(),
+        ErrorCode::Unauthorized
+    );
+    if let Some(token_mint) = ctx.accounts.token_mint.as_ref() {
+        require!(token_mint.key() == ctx.accounts.token_vault.mint, ErrorCode::MintMismatch);
+    }
+
+    // Guard against reentrant calls on this pool
+    crate::utils::begin_pool_operation(&ctx.accounts.pool_state.to_account_info())?;
+
+    let pool_state_key = ctx.accounts.pool_state.key();
+    let xnt_amount = ctx.accounts.pool_state.native_reserve;
+
+    // native_reserve excludes pool_pda's rent reserve, so the PDA stays rent-exempt
+    if xnt_amount > 0 {
+        let pool_pda_seeds = &[b"pool_pda", pool_state_key.as_ref(), &[ctx.bumps.pool_pda]];
+        let transfer_ix = anchor_lang::solana_program::system_instruction::transfer(
+            ctx.accounts.pool_pda.key,
+            ctx.accounts.emergency_recovery.key,
+            xnt_amount,
+        );
+        anchor_lang::solana_program::program::invoke_signed(
+            &transfer_ix,
+            &[
+                ctx.accounts.pool_pda.to_account_info(),
+                ctx.accounts.emergency_recovery.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+            &[pool_pda_seeds],
+        )?;
+    }
+
+    let token_amount = ctx.accounts.token_vault.amount;
+    if token_amount > 0 {
+        let token_program = token_program_for(
+            ctx.accounts.token_vault.to_account_info().owner,
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.token_2022_program.to_account_info(),
+        )?;
+        let pda_sign = &[b"authority", pool_state_key.as_ref(), &[ctx.bumps.pool_authority]];
+        crate::utils::transfer_tokens_checked(
+            ctx.accounts.token_vault.to_account_info(),
+            ctx.accounts.recovery_token_account.to_account_info(),
+            ctx.accounts.pool_authority.to_account_info(),
+            token_program,
+            ctx.accounts.token_mint.as_ref().map(|mint| mint.to_account_info()),
+            ctx.accounts.pool_state.token_decimals,
+            ctx.remaining_accounts,
+            token_amount,
+            &[pda_sign],
+        )?;
+    }
+
+    {
+        let pool_state_info = ctx.accounts.pool_state.to_account_info();
+        let mut data = pool_state_info.try_borrow_mut_data()?;
+        PoolState::write_native_reserve(&mut data, 0)?;
+    }
+    ctx.accounts.pool_state.native_reserve = 0;
+
+    crate::utils::end_pool_operation(&ctx.accounts.pool_state.to_account_info())?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct EmergencyWithdrawNative<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(mut)]
+    pub pool_state: Account<'info, PoolState>,
+
+    #[account(seeds = [b"global_config"], bump)]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    /// Pool PDA that holds native XNT
+    /// CHECK: This is a PDA
+    #[account(mut, seeds = [b"pool_pda", pool_state.key().as_ref()], bump)]
+    pub pool_pda: UncheckedAccount<'info>,
+
+    /// CHECK: This is a PDA used for signing
+    #[account(seeds = [b"authority", pool_state.key().as_ref()], bump)]
+    pub pool_authority: UncheckedAccount<'info>,
+
+    /// Token vault - can be Token or Token2022
+    #[account(mut, seeds = [b"vault", pool_state.key().as_ref()], bump)]
+    pub token_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Must equal pool_state.emergency_recovery, verified in handler
+    #[account(mut)]
+    pub emergency_recovery: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub recovery_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+    /// CHECK: Token-2022 program
+    pub token_2022_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+
+    // Optional mint of token_vault - passing it moves the tokens with TransferChecked
+    pub token_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emergency_withdraw_only_while_paused() {
+        let admin = Pubkey::new_unique();
+        let recovery = Pubkey::new_unique();
+        let pool_state = PoolState { admin, emergency_recovery: recovery, ..Default::default() };
+        let mut global_config = GlobalConfig { authority: Pubkey::new_unique(), paused: false };
+
+        assert_eq!(
+            check_emergency_withdraw(&pool_state, &global_config, &admin, &recovery).unwrap_err(),
+            ErrorCode::KillSwitchNotEngaged.into()
+        );
+        global_config.paused = true;
+        assert!(check_emergency_withdraw(&pool_state, &global_config, &admin, &recovery).is_ok());
+        // Still admin only, and only to the fixed recovery wallet
+        assert!(check_emergency_withdraw(&pool_state, &global_config, &recovery, &recovery).is_err());
+        assert!(check_emergency_withdraw(&pool_state, &global_config, &admin, &admin).is_err());
+    }
+}
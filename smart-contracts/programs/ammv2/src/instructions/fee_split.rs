@@ -0,0 +1,119 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{FeeSplit, PoolState, MAX_FEE_RECIPIENTS};
+use crate::error::ErrorCode;
+use crate::events::FeeSplitChanged;
+
+/// Create the pool's FeeSplit table (admin only)
+/// Swaps passing it split the protocol fee across `recipients` by `weights_bps`
+pub fn initialize_fee_split(
+    ctx: Context<InitializeFeeSplit>,
+    recipients: Vec<Pubkey>,
+    weights_bps: Vec<u16>,
+) -> Result<()> {
+    require!(ctx.accounts.pool_state.admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+
+    let fee_split = &mut ctx.accounts.fee_split;
+    fee_split.pool = ctx.accounts.pool_state.key();
+    write_fee_split(fee_split, &recipients, &weights_bps)?;
+
+    emit!(FeeSplitChanged {
+        pool: ctx.accounts.pool_state.key(),
+        actor: ctx.accounts.admin.key(),
+        recipients,
+        weights_bps,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializeFeeSplit<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub pool_state: Account<'info, PoolState>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + FeeSplit::LEN,
+        seeds = [b"fee_split", pool_state.key().as_ref()],
+        bump
+    )]
+    pub fee_split: Account<'info, FeeSplit>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Replace the recipients and weights of the pool's FeeSplit table (admin only)
+pub fn set_fee_split(
+    ctx: Context<UpdateFeeSplit>,
+    recipients: Vec<Pubkey>,
+    weights_bps: Vec<u16>,
+) -> Result<()> {
+    require!(ctx.accounts.pool_state.admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+
+    write_fee_split(&mut ctx.accounts.fee_split, &recipients, &weights_bps)?;
+
+    emit!(FeeSplitChanged {
+        pool: ctx.accounts.pool_state.key(),
+        actor: ctx.accounts.admin.key(),
+        recipients,
+        weights_bps,
+    });
+
+    Ok(())
+}
+
+/// Close the pool's FeeSplit table, fees go back to protocol_treasury (admin only)
+pub fn close_fee_split(ctx: Context<UpdateFeeSplit>) -> Result<()> {
+    require!(ctx.accounts.pool_state.admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+
+    ctx.accounts.fee_split.close(ctx.accounts.admin.to_account_info())?;
+
+    emit!(FeeSplitChanged {
+        pool: ctx.accounts.pool_state.key(),
+        actor: ctx.accounts.admin.key(),
+        recipients: Vec::new(),
+        weights_bps: Vec::new(),
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct UpdateFeeSplit<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub pool_state: Account<'info, PoolState>,
+
+    #[account(mut, seeds = [b"fee_split", pool_state.key().as_ref()], bump)]
+    pub fee_split: Account<'info, FeeSplit>,
+}
+
+// 1..=MAX_FEE_RECIPIENTS distinct recipients, weights summing to 10000
+fn write_fee_split(fee_split: &mut FeeSplit, recipients: &[Pubkey], weights_bps: &[u16]) -> Result<()> {
+    require!(
+        !recipients.is_empty() && recipients.len() <= MAX_FEE_RECIPIENTS,
+        ErrorCode::InvalidInput
+    );
+    require!(recipients.len() == weights_bps.len(), ErrorCode::InvalidInput);
+    require!(
+        weights_bps.iter().map(|weight| *weight as u32).sum::<u32>() == 10000,
+        ErrorCode::InvalidInput
+    );
+    for (i, recipient) in recipients.iter().enumerate() {
+        require!(*recipient != Pubkey::default(), ErrorCode::InvalidTreasury);
+        require!(!recipients[..i].contains(recipient), ErrorCode::InvalidInput);
+    }
+
+    fee_split.count = recipients.len() as u8;
+    fee_split.recipients = [Pubkey::default(); MAX_FEE_RECIPIENTS];
+    fee_split.weights_bps = [0; MAX_FEE_RECIPIENTS];
+    fee_split.recipients[..recipients.len()].copy_from_slice(recipients);
+    fee_split.weights_bps[..weights_bps.len()].copy_from_slice(weights_bps);
+
+    Ok(())
+}
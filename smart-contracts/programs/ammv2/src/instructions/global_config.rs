@@ -0,0 +1,65 @@
+use anchor_lang::prelude::*;
+
+use crate::state::GlobalConfig;
+use crate::error::ErrorCode;
+use crate::events::GlobalPauseChanged;
+
+/// Create the program-wide GlobalConfig (upgrade authority only)
+/// The upgrade authority becomes the config authority
+pub fn initialize_global_config(ctx: Context<InitializeGlobalConfig>) -> Result<()> {
+    let global_config = &mut ctx.accounts.global_config;
+    
+    global_config.authority = ctx.accounts.authority.key();
+    global_config.paused = false;
+    
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializeGlobalConfig<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + GlobalConfig::LEN,
+        seeds = [b"global_config"],
+        bump
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+    
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()) @ ErrorCode::Unauthorized)]
+    pub program: Program<'info, crate::program::Ammv2>,
+    
+    #[account(constraint = program_data.upgrade_authority_address == Some(authority.key()) @ ErrorCode::Unauthorized)]
+    pub program_data: Account<'info, ProgramData>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+/// Engage or release the global kill-switch (config authority only)
+pub fn set_global_pause(ctx: Context<UpdateGlobalConfig>, paused: bool) -> Result<()> {
+    let global_config = &mut ctx.accounts.global_config;
+    
+    require!(global_config.authority == ctx.accounts.authority.key(), ErrorCode::Unauthorized);
+    
+    let old_paused = global_config.paused;
+    global_config.paused = paused;
+    
+    emit!(GlobalPauseChanged {
+        actor: ctx.accounts.authority.key(),
+        old_paused,
+        new_paused: paused,
+    });
+    
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct UpdateGlobalConfig<'info> {
+    pub authority: Signer<'info>,
+    
+    #[account(mut, seeds = [b"global_config"], bump)]
+    pub global_config: Account<'info, GlobalConfig>,
+}
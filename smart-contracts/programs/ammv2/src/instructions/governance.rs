@@ -0,0 +1,135 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{self, Burn, Mint, Token},
+    token_interface::TokenAccount,
+};
+
+use crate::state::{GlobalConfig, PoolState, GOVERNANCE_SEED};
+use crate::error::ErrorCode;
+use crate::instructions::liquidity::pro_rata_share;
+use crate::utils::token_program_for;
+
+/// Burn LP held by the governance PDA of a protocol-owned pool and pay out its
+/// share of both vaults to the given accounts (GlobalConfig authority only)
+///
+/// Protocol-owned pools only mint LP to the governance PDA, which no wallet can
+/// sign for - this is the only way their liquidity leaves the pool
+pub fn withdraw_governance_liquidity(ctx: Context<WithdrawGovernanceLiquidity>, burn_amount: u64) -> Result<()> {
+    require!(!ctx.accounts.global_config.paused, ErrorCode::ProgramPaused);
+    require!(ctx.accounts.pool_state.protocol_owned, ErrorCode::InvalidInput);
+    ctx.accounts.pool_state.check_spl_pool()?;
+
+    let governance_lp_account = &ctx.accounts.governance_lp_account;
+    require!(governance_lp_account.owner == ctx.accounts.governance.key(), ErrorCode::Unauthorized);
+    require!(governance_lp_account.mint == ctx.accounts.pool_mint.key(), ErrorCode::MintMismatch);
+    require!(burn_amount <= governance_lp_account.amount, ErrorCode::NotEnoughBalance);
+    require!(ctx.accounts.destination0.mint == ctx.accounts.vault0.mint, ErrorCode::MintMismatch);
+    require!(ctx.accounts.destination1.mint == ctx.accounts.vault1.mint, ErrorCode::MintMismatch);
+
+    // Guard against reentrant calls on this pool
+    crate::utils::begin_pool_operation(&ctx.accounts.pool_state.to_account_info())?;
+
+    let pool_key = ctx.accounts.pool_state.key();
+    let state = &mut ctx.accounts.pool_state;
+    require!(state.total_amount_minted >= burn_amount, ErrorCode::BurnTooMuch);
+    crate::utils::check_burn_leaves_minimum_liquidity(state.total_amount_minted, burn_amount)?;
+
+    let amount0 = pro_rata_share(burn_amount, ctx.accounts.vault0.amount, state.total_amount_minted)?;
+    let amount1 = pro_rata_share(burn_amount, ctx.accounts.vault1.amount, state.total_amount_minted)?;
+
+    let bump = ctx.bumps.pool_authority;
+    let pda_sign = &[b"authority", pool_key.as_ref(), &[bump]];
+    for (vault, destination, amount) in [
+        (ctx.accounts.vault0.to_account_info(), ctx.accounts.destination0.to_account_info(), amount0),
+        (ctx.accounts.vault1.to_account_info(), ctx.accounts.destination1.to_account_info(), amount1),
+    ] {
+        let token_program = token_program_for(
+            vault.owner,
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.token_2022_program.to_account_info(),
+        )?;
+        crate::utils::transfer_tokens_signed(
+            vault,
+            destination,
+            ctx.accounts.pool_authority.to_account_info(),
+            token_program,
+            amount,
+            &[pda_sign],
+        )?;
+    }
+
+    // burn the governance LP (pool mint always uses standard Token program)
+    if state.lp_soulbound {
+        crate::utils::thaw_lp_account(
+            ctx.accounts.governance_lp_account.to_account_info(),
+            ctx.accounts.pool_mint.to_account_info(),
+            ctx.accounts.pool_authority.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            &[pda_sign],
+        )?;
+    }
+    let governance_sign: &[&[u8]] = &[GOVERNANCE_SEED, &[ctx.bumps.governance]];
+    token::burn(CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        Burn {
+            mint: ctx.accounts.pool_mint.to_account_info(),
+            from: ctx.accounts.governance_lp_account.to_account_info(),
+            authority: ctx.accounts.governance.to_account_info(),
+        },
+        &[governance_sign],
+    ), burn_amount)?;
+    if state.lp_soulbound {
+        crate::utils::freeze_lp_account(
+            ctx.accounts.governance_lp_account.to_account_info(),
+            ctx.accounts.pool_mint.to_account_info(),
+            ctx.accounts.pool_authority.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            &[pda_sign],
+        )?;
+    }
+
+    state.total_amount_minted -= burn_amount;
+
+    crate::utils::end_pool_operation(&ctx.accounts.pool_state.to_account_info())?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct WithdrawGovernanceLiquidity<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(seeds = [b"global_config"], bump, has_one = authority @ ErrorCode::Unauthorized)]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    /// CHECK: PDA owning protocol-owned pools' LP, signs the burn
+    #[account(seeds = [GOVERNANCE_SEED], bump)]
+    pub governance: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub pool_state: Box<Account<'info, PoolState>>,
+
+    /// CHECK: This is a PDA used for signing
+    #[account(seeds = [b"authority", pool_state.key().as_ref()], bump)]
+    pub pool_authority: UncheckedAccount<'info>,
+
+    // vaults and destination token accounts can be Token or Token2022
+    #[account(mut, seeds = [b"vault0", pool_state.key().as_ref()], bump)]
+    pub vault0: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, seeds = [b"vault1", pool_state.key().as_ref()], bump)]
+    pub vault1: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, address = pool_state.lp_mint_address(&pool_state.key(), &crate::ID) @ ErrorCode::InvalidInput)]
+    pub pool_mint: Box<Account<'info, Mint>>,
+
+    // LP account owned by the governance PDA
+    #[account(mut)]
+    pub governance_lp_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut)]
+    pub destination0: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut)]
+    pub destination1: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+    /// CHECK: Token 2022 program - verified in handler
+    pub token_2022_program: UncheckedAccount<'info>,
+}
@@ -1,15 +1,17 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{Mint, Token},
+    token::{self, Mint, MintTo, SetAuthority, Token},
+    token_interface::TokenAccount,
 };
+use anchor_spl::token::spl_token::instruction::AuthorityType;
 use anchor_spl::token::spl_token::instruction::initialize_account3 as initialize_account3_token;
 use spl_token_2022::instruction::initialize_account3 as initialize_account3_token2022;
 use anchor_lang::solana_program::system_instruction;
 use anchor_lang::solana_program::program::{invoke, invoke_signed};
-use crate::state::PoolState;
+use crate::state::{GlobalConfig, PoolState, DEFAULT_LP_DECIMALS, POOL_KIND_SPL, PROTOCOL_FEE_MODE_NOTIONAL};
 use crate::error::ErrorCode;
-use crate::utils::{is_token_2022, is_token};
+use crate::utils::{check_mint_extensions_supported, is_token_2022, is_token, token_account_len, LOCKED_LIQUIDITY_SEED, MINIMUM_LIQUIDITY};
 
 pub fn handler(
     ctx: Context<InitializePool>, 
@@ -17,6 +19,194 @@ pub fn handler(
     fee_denominator: u64,
     protocol_treasury: Option<Pubkey>,
     protocol_fee_bps: Option<u16>,
+    lp_decimals: Option<u8>,
+    lp_soulbound: Option<bool>,
+) -> Result<()> {
+    initialize(ctx, fee_numerator, fee_denominator, protocol_treasury, protocol_fee_bps, lp_decimals, lp_soulbound, false, None)
+}
+
+/// Initialize a protocol-owned pool: every swap fee goes to the treasury and
+/// LP tokens can only be minted to the governance PDA (see PoolState::protocol_owned)
+/// GlobalConfig authority only - the governance PDA's LP is only withdrawable by it
+pub fn protocol_owned_handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, InitializeProtocolOwnedPool<'info>>,
+    fee_numerator: u64,
+    fee_denominator: u64,
+    protocol_treasury: Pubkey,
+    protocol_fee_bps: u16,
+    lp_decimals: Option<u8>,
+    lp_soulbound: Option<bool>,
+) -> Result<()> {
+    // Fees have nowhere to go without a treasury
+    require!(protocol_treasury != Pubkey::default(), ErrorCode::InvalidTreasury);
+    initialize(
+        Context::new(
+            ctx.program_id,
+            &mut ctx.accounts.pool,
+            ctx.remaining_accounts,
+            ctx.bumps.pool,
+        ),
+        fee_numerator,
+        fee_denominator,
+        Some(protocol_treasury),
+        Some(protocol_fee_bps),
+        lp_decimals,
+        lp_soulbound,
+        true,
+        None,
+    )
+}
+
+/// LP mint a migrated pool adopts instead of creating its own
+struct ExistingLpMint {
+    mint: Pubkey,
+    decimals: u8,
+    supply: u64,
+}
+
+/// Initialize a pool around an existing LP mint, for migrating liquidity from an
+/// older pool without making every LP re-mint
+///
+/// lp_mint_authority hands the mint's authority to the pool authority and moves
+/// `reserve0` / `reserve1` (the old pool's reserves) from source0 / source1 into the
+/// vaults in the same instruction, so LPs never see an empty or part-funded pool.
+/// MINIMUM_LIQUIDITY is minted to the locked LP account like on a first deposit, so
+/// total_amount_minted starts at the mint's supply plus MINIMUM_LIQUIDITY
+pub fn with_existing_lp_handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, InitializePoolWithExistingLp<'info>>,
+    fee_numerator: u64,
+    fee_denominator: u64,
+    protocol_treasury: Option<Pubkey>,
+    protocol_fee_bps: Option<u16>,
+    reserve0: u64,
+    reserve1: u64,
+) -> Result<()> {
+    // Existing LP must keep a claim on both reserves
+    require!(reserve0 > 0 && reserve1 > 0, ErrorCode::InvalidInput);
+    let existing_lp_mint = &ctx.accounts.existing_lp_mint;
+    require!(
+        existing_lp_mint.mint_authority == Some(ctx.accounts.lp_mint_authority.key()).into(),
+        ErrorCode::Unauthorized
+    );
+    // A third party able to freeze LP accounts could block withdrawals
+    require!(existing_lp_mint.freeze_authority.is_none(), ErrorCode::InvalidInput);
+    // Nothing to migrate - initialize_pool creates a fresh LP mint
+    require!(existing_lp_mint.supply > 0, ErrorCode::InvalidInput);
+    require!(
+        existing_lp_mint.key() != ctx.accounts.pool.mint0.key()
+            && existing_lp_mint.key() != ctx.accounts.pool.mint1.key(),
+        ErrorCode::InvalidInput
+    );
+    let existing_lp = ExistingLpMint {
+        mint: existing_lp_mint.key(),
+        decimals: existing_lp_mint.decimals,
+        supply: existing_lp_mint.supply,
+    };
+    let pool_authority_bump = ctx.bumps.pool.pool_authority;
+    
+    initialize(
+        Context::new(
+            ctx.program_id,
+            &mut ctx.accounts.pool,
+            ctx.remaining_accounts,
+            ctx.bumps.pool,
+        ),
+        fee_numerator,
+        fee_denominator,
+        protocol_treasury,
+        protocol_fee_bps,
+        None,
+        None,
+        false,
+        Some(existing_lp),
+    )?;
+    
+    // Only the pool authority may mint the LP from here on
+    let pool_authority_key = ctx.accounts.pool.pool_authority.key();
+    token::set_authority(
+        CpiContext::new(
+            ctx.accounts.pool.token_program.to_account_info(),
+            SetAuthority {
+                current_authority: ctx.accounts.lp_mint_authority.to_account_info(),
+                account_or_mint: ctx.accounts.existing_lp_mint.to_account_info(),
+            },
+        ),
+        AuthorityType::MintTokens,
+        Some(pool_authority_key),
+    )?;
+    ctx.accounts.existing_lp_mint.reload()?;
+    require!(
+        ctx.accounts.existing_lp_mint.mint_authority == Some(pool_authority_key).into(),
+        ErrorCode::InvalidInput
+    );
+    
+    // Move the reserves in (sources can be Token or Token 2022, fee and hook mints
+    // included - TransferChecked works for every mint the pool accepts)
+    for (token_index, source, vault, mint, amount) in [
+        (0, &ctx.accounts.source0, ctx.accounts.pool.vault0.to_account_info(), ctx.accounts.pool.mint0.to_account_info(), reserve0),
+        (1, &ctx.accounts.source1, ctx.accounts.pool.vault1.to_account_info(), ctx.accounts.pool.mint1.to_account_info(), reserve1),
+    ] {
+        let token_program = crate::utils::token_program_for(
+            source.to_account_info().owner,
+            &ctx.accounts.pool.token_program.to_account_info(),
+            &ctx.accounts.pool.token_2022_program.to_account_info(),
+        )?;
+        let decimals = ctx.accounts.pool.pool_state.mint_decimals(token_index);
+        crate::utils::transfer_tokens_checked(
+            source.to_account_info(),
+            vault,
+            ctx.accounts.lp_mint_authority.to_account_info(),
+            token_program,
+            Some(mint),
+            decimals,
+            ctx.remaining_accounts,
+            amount,
+            &[],
+        )?;
+    }
+    
+    // Lock MINIMUM_LIQUIDITY (already counted in total_amount_minted) so no burn
+    // can leave the LP supply below it
+    let pool_state_key = ctx.accounts.pool.pool_state.key();
+    let (locked_liquidity_owner, _) = Pubkey::find_program_address(
+        &[LOCKED_LIQUIDITY_SEED, pool_state_key.as_ref()],
+        ctx.program_id,
+    );
+    require!(ctx.accounts.locked_lp_account.owner == locked_liquidity_owner, ErrorCode::InvalidInput);
+    require!(ctx.accounts.locked_lp_account.mint == ctx.accounts.existing_lp_mint.key(), ErrorCode::InvalidInput);
+    let pda_sign = &[b"authority", pool_state_key.as_ref(), &[pool_authority_bump]];
+    token::mint_to(
+        CpiContext::new_with_signer(
+            ctx.accounts.pool.token_program.to_account_info(),
+            MintTo {
+                to: ctx.accounts.locked_lp_account.to_account_info(),
+                mint: ctx.accounts.existing_lp_mint.to_account_info(),
+                authority: ctx.accounts.pool.pool_authority.to_account_info(),
+            },
+            &[pda_sign],
+        ),
+        MINIMUM_LIQUIDITY,
+    )?;
+    
+    Ok(())
+}
+
+/// LP supply a migrated pool starts with: the adopted supply plus the MINIMUM_LIQUIDITY it locks
+fn migrated_total_amount_minted(existing_supply: u64) -> Result<u64> {
+    Ok(existing_supply.checked_add(MINIMUM_LIQUIDITY).ok_or(ErrorCode::MathOverflow)?)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn initialize(
+    ctx: Context<InitializePool>, 
+    fee_numerator: u64,
+    fee_denominator: u64,
+    protocol_treasury: Option<Pubkey>,
+    protocol_fee_bps: Option<u16>,
+    lp_decimals: Option<u8>,
+    lp_soulbound: Option<bool>,
+    protocol_owned: bool,
+    existing_lp: Option<ExistingLpMint>,
 ) -> Result<()> {
     // Verify token programs match mint program IDs
     // Mints are owned by their respective token programs
@@ -54,6 +244,11 @@ pub fn handler(
         ErrorCode::InvalidTreasury
     );
 
+    // Swaps and liquidity move vault tokens with TransferChecked, so transfer fee and
+    // hook mints work - extensions that endanger or lock the vault are refused
+    check_mint_extensions_supported(&ctx.accounts.mint0.to_account_info(), true)?;
+    check_mint_extensions_supported(&ctx.accounts.mint1.to_account_info(), true)?;
+
     // Initialize vaults with the correct token program via CPI
     // Note: Anchor's init allocates space but doesn't initialize the account data
     // We need to call initialize_account3 BEFORE Anchor's init runs, but that's not possible
@@ -111,9 +306,13 @@ pub fn handler(
 // msg!("vault0_token_program_id: {:?}", vault0_token_program_id);
 // msg!("vault1_token_program_id: {:?}", vault1_token_program_id);
     
-    // Calculate rent for TokenAccount (165 bytes)
+    // Token 2022 vaults need room for the account extensions their mint requires
+    // (e.g. TransferFeeAmount), classic Token vaults are always 165 bytes
+    let vault0_len = token_account_len(&ctx.accounts.mint0.to_account_info())?;
+    let vault1_len = token_account_len(&ctx.accounts.mint1.to_account_info())?;
     let rent = anchor_lang::solana_program::rent::Rent::get()?;
-    let rent_lamports = rent.minimum_balance(165);
+    let vault0_rent = rent.minimum_balance(vault0_len);
+    let vault1_rent = rent.minimum_balance(vault1_len);
     
     // Allocate and initialize vault0 using System Program + Token Program CPI
     {
@@ -135,14 +334,14 @@ pub fn handler(
                         to: ctx.accounts.vault0.to_account_info(),
                     },
                 ),
-                rent_lamports,
+                vault0_rent,
             )?;
             
             // Step 2: Allocate space (requires invoke_signed for PDA)
             anchor_lang::solana_program::program::invoke_signed(
                 &anchor_lang::solana_program::system_instruction::allocate(
                     ctx.accounts.vault0.key,
-                    165,
+                    vault0_len as u64,
                 ),
                 &[ctx.accounts.vault0.to_account_info()],
                 &[vault0_seeds],
@@ -201,7 +400,7 @@ pub fn handler(
             if vault0_data_len == 0 {
                 let allocate_ix = system_instruction::allocate(
                     ctx.accounts.vault0.key,
-                    165,
+                    vault0_len as u64,
                 );
                 invoke_signed(
                     &allocate_ix,
@@ -286,14 +485,14 @@ pub fn handler(
                         to: ctx.accounts.vault1.to_account_info(),
                     },
                 ),
-                rent_lamports,
+                vault1_rent,
             )?;
             
             // Step 2: Allocate space (requires invoke_signed for PDA)
             anchor_lang::solana_program::program::invoke_signed(
                 &anchor_lang::solana_program::system_instruction::allocate(
                     ctx.accounts.vault1.key,
-                    165,
+                    vault1_len as u64,
                 ),
                 &[ctx.accounts.vault1.to_account_info()],
                 &[vault1_seeds],
@@ -351,7 +550,7 @@ pub fn handler(
             if vault1_data_len == 0 {
                 let allocate_ix = system_instruction::allocate(
                     ctx.accounts.vault1.key,
-                    165,
+                    vault1_len as u64,
                 );
                 invoke_signed(
                     &allocate_ix,
@@ -416,10 +615,38 @@ pub fn handler(
         }
     }
 
+    // LP mint is created here rather than by `init` so its decimals can vary per pool
+    // Soulbound pools also make the pool authority its freeze authority
+    // A migrated pool adopts its existing LP mint and supply instead
+    let lp_soulbound = lp_soulbound.unwrap_or(false);
+    let (lp_mint, lp_decimals, lp_supply) = match existing_lp.as_ref() {
+        Some(existing_lp) => (existing_lp.mint, existing_lp.decimals, migrated_total_amount_minted(existing_lp.supply)?),
+        None => {
+            let lp_decimals = lp_decimals.unwrap_or(DEFAULT_LP_DECIMALS);
+            let pool_authority_key = ctx.accounts.pool_authority.key();
+            let pool_mint_seeds = &[
+                b"pool_mint",
+                pool_state_key.as_ref(),
+                &[ctx.bumps.pool_mint],
+            ];
+            crate::utils::create_lp_mint(
+                ctx.accounts.payer.to_account_info(),
+                ctx.accounts.pool_mint.to_account_info(),
+                pool_mint_seeds,
+                lp_decimals,
+                &pool_authority_key,
+                lp_soulbound.then_some(&pool_authority_key),
+                ctx.accounts.system_program.to_account_info(),
+                ctx.accounts.token_program.to_account_info(),
+            )?;
+            (ctx.accounts.pool_mint.key(), lp_decimals, 0)
+        }
+    };
+
     let pool_state = &mut ctx.accounts.pool_state;
     pool_state.fee_numerator = fee_numerator;
     pool_state.fee_denominator = fee_denominator;
-    pool_state.total_amount_minted = 0;
+    pool_state.total_amount_minted = lp_supply;
     
     // Set protocol treasury (defaults to Pubkey::default() if None)
     // Pubkey::default() means no treasury - all fees go to LPs (backward compatible)
@@ -429,7 +656,41 @@ pub fn handler(
     // 0 means all fees go to LPs (backward compatible)
     let fee_bps = protocol_fee_bps.unwrap_or(0);
     require!(fee_bps <= 10000, ErrorCode::InvalidProtocolFee);
+    // A higher protocol cut needs set_protocol_fee with allow_high_protocol_fee
+    crate::utils::check_protocol_fee_within_lp_fee(fee_bps, PROTOCOL_FEE_MODE_NOTIONAL, fee_numerator, fee_denominator)?;
     pool_state.protocol_fee_bps = fee_bps;
+    
+    // Pool creator administers the pool, no slippage or swap size cap by default
+    pool_state.admin = ctx.accounts.payer.key();
+    pool_state.max_allowed_slippage_bps = 10000;
+    pool_state.max_swap_out_bps_of_reserve = 10000;
+    pool_state.created_at = Clock::get()?.unix_timestamp;
+    pool_state.pool_kind = POOL_KIND_SPL;
+    
+    // Fixed for the pool's lifetime - no instruction changes it
+    pool_state.emergency_recovery = ctx.accounts.emergency_recovery
+        .as_ref()
+        .map(|account| account.key())
+        .unwrap_or_default();
+    pool_state.lp_mint = lp_mint;
+    pool_state.lp_decimals = lp_decimals;
+    pool_state.lp_soulbound = lp_soulbound;
+    pool_state.record_mint_decimals(&ctx.accounts.mint0.to_account_info(), Some(&ctx.accounts.mint1.to_account_info()))?;
+    pool_state.protocol_owned = protocol_owned;
+    pool_state.swaps_enabled = true;
+    pool_state.deposits_enabled = true;
+    
+    // Make the pool discoverable by mint pair
+    crate::instructions::register_pool(
+        ctx.program_id,
+        &ctx.accounts.registry_entry.to_account_info(),
+        &ctx.accounts.payer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        ctx.accounts.pool_state.key(),
+        POOL_KIND_SPL,
+        ctx.accounts.mint0.key(),
+        ctx.accounts.mint1.key(),
+    )?;
 
     Ok(())
 }
@@ -448,7 +709,7 @@ pub struct InitializePool<'info> {
         payer=payer, 
         seeds=[b"pool_state", mint0.key().as_ref(), mint1.key().as_ref()], 
         bump,
-        space = 8 + 8 + 8 + 8 + 32 + 2, // discriminator + total_amount_minted + fee_numerator + fee_denominator + protocol_treasury + protocol_fee_bps = 66 bytes
+        space = 8 + PoolState::LEN, // discriminator + current PoolState layout
     )]
     pub pool_state: Box<Account<'info, PoolState>>,
 
@@ -467,18 +728,17 @@ pub struct InitializePool<'info> {
     pub vault1: UncheckedAccount<'info>, 
 
     // pool mint : used to track relative contribution amount of LPs
-    #[account(
-        init, 
-        payer=payer,
-        seeds=[b"pool_mint", pool_state.key().as_ref()], 
-        bump, 
-        mint::decimals = 9,
-        mint::authority = pool_authority
-    )] 
-    pub pool_mint: Box<Account<'info, Mint>>, 
+    /// CHECK: Created and initialized in handler with the requested decimals
+    #[account(mut, seeds=[b"pool_mint", pool_state.key().as_ref()], bump)]
+    pub pool_mint: UncheckedAccount<'info>, 
     #[account(mut)]
     pub payer: Signer<'info>,
 
+    // mint-pair registry entry: [b"registry", mint0, mint1]
+    /// CHECK: Created and verified in handler (see instructions::registry)
+    #[account(mut)]
+    pub registry_entry: UncheckedAccount<'info>,
+
     // accounts required to init a new mint
     pub system_program: Program<'info, System>,
     pub token_program: Program<'info, Token>,
@@ -486,4 +746,72 @@ pub struct InitializePool<'info> {
     pub token_2022_program: UncheckedAccount<'info>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub rent: Sysvar<'info, Rent>,
+    
+    // Optional - where emergency_withdraw may drain reserves, fixed at creation
+    /// CHECK: Any wallet, only its address is stored
+    pub emergency_recovery: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeProtocolOwnedPool<'info> {
+    /// Pool creation accounts
+    pub pool: InitializePool<'info>,
+    
+    #[account(seeds = [b"global_config"], bump, has_one = authority @ ErrorCode::Unauthorized)]
+    pub global_config: Account<'info, GlobalConfig>,
+    
+    // GlobalConfig authority - the only one able to withdraw the governance PDA's LP
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializePoolWithExistingLp<'info> {
+    /// Pool creation accounts - pool.pool_mint is left uncreated, existing_lp_mint takes its place
+    pub pool: InitializePool<'info>,
+    
+    // LP mint of the pool being migrated from - LP mints always use the standard Token program
+    #[account(mut)]
+    pub existing_lp_mint: Box<Account<'info, Mint>>,
+    
+    // existing_lp_mint's current mint authority, handing it to pool.pool_authority
+    // Also owns source0 / source1
+    pub lp_mint_authority: Signer<'info>,
+    
+    // Old pool's reserves, moved into pool.vault0 / pool.vault1 - can be Token or Token2022
+    #[account(mut)]
+    pub source0: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut)]
+    pub source1: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    // existing_lp_mint account owned by the LOCKED_LIQUIDITY_SEED PDA
+    #[account(mut)]
+    pub locked_lp_account: Box<InterfaceAccount<'info, TokenAccount>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instructions::liquidity::pro_rata_share;
+    use crate::utils::check_burn_leaves_minimum_liquidity;
+
+    #[test]
+    fn migrated_lp_holders_can_all_withdraw() {
+        // 1,500 LP migrated, held as 900 + 600
+        let total_amount_minted = migrated_total_amount_minted(1_500).unwrap();
+        assert_eq!(total_amount_minted, 1_500 + MINIMUM_LIQUIDITY);
+        
+        // Either holder can exit first, then the other - the locked LP stays
+        assert!(check_burn_leaves_minimum_liquidity(total_amount_minted, 600).is_ok());
+        assert!(check_burn_leaves_minimum_liquidity(total_amount_minted - 600, 900).is_ok());
+        // Without the locked LP the second-to-last exit would be stuck below the floor
+        assert!(check_burn_leaves_minimum_liquidity(1_500, 600).is_err());
+        
+        // Payouts come from the reserves moved in at migration
+        assert_eq!(pro_rata_share(600, 25_000, total_amount_minted).unwrap(), 6_000);
+    }
+
+    #[test]
+    fn migrated_supply_overflow_is_rejected() {
+        assert_eq!(migrated_total_amount_minted(u64::MAX).unwrap_err(), ErrorCode::MathOverflow.into());
+    }
 }
@@ -1,73 +1,135 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke;
 use anchor_spl::{
     token,
-    token::{Mint, MintTo, Token, TokenAccount, Transfer, Burn},
+    token::{Mint, MintTo, Token, Burn},
+    token_interface::{Mint as MintInterface, TokenAccount},
 };
-use anchor_spl::token::spl_token::state::Account as TokenAccountState;
-use spl_token_2022::state::Account as Token2022AccountState;
-use spl_token_2022::extension::StateWithExtensions;
-use anchor_lang::solana_program::program_pack::Pack;
 
-use crate::state::PoolState;
+use crate::state::{PoolState, GOVERNANCE_SEED};
 use crate::error::ErrorCode;
-use crate::utils::{is_token_2022, get_token_program_account};
+use crate::utils::{token_program_for, calculate_lp_to_mint, MINIMUM_LIQUIDITY, LOCKED_LIQUIDITY_SEED};
 
-pub fn add_liquidity(
-    ctx: Context<LiquidityOperation>, 
+pub fn add_liquidity<'info>(
+    ctx: Context<'_, '_, '_, 'info, LiquidityOperation<'info>>, 
     amount_liq0: u64, // amount of token0 
     // amount of token1
         // note: only needed on pool init deposit 
         // ... can derive it once exchange is up
     amount_liq1: u64, 
 ) -> Result<()> {
+    execute_add_liquidity(ctx, amount_liq0, amount_liq1, false)?;
+    Ok(())
+}
 
-    // Helper function to unpack token account (works for both Token and Token2022 with extensions)
-    fn unpack_token_account(account_info: &AccountInfo, name: &str) -> Result<Token2022AccountState> {
-// msg!("Unpacking {}: owner={}, data_len={}", name, account_info.owner, account_info.data_len());
-        
-        let account = if account_info.data_len() == 165 {
-            // Standard size - use regular unpack
-            Token2022AccountState::unpack(&account_info.data.borrow())
-                .map_err(|e| {
-// msg!("❌ Failed to unpack {} (standard): {:?}", name, e);
-                    e
-                })?
-        } else {
-            // Has extensions - use StateWithExtensions
-            let account_data = account_info.data.borrow();
-            let state_with_ext = StateWithExtensions::<Token2022AccountState>::unpack(&account_data)
-                .map_err(|e| {
-// msg!("❌ Failed to unpack {} (with extensions): {:?}", name, e);
-                    e
-                })?;
-            state_with_ext.base
-        };
-        
-// msg!("✅ {} unpacked successfully", name);
-        Ok(account)
-    }
+/// Return data for `add_liquidity_exact_ratio`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct DepositedAmounts {
+    pub amount0: u64,
+    pub amount1: u64,
+}
+
+/// Deposit at the pool ratio using at most `max_amount0` / `max_amount1`
+/// Whichever side is limiting is deposited in full and the other side is matched
+/// to it, so neither side reverts for being short. Returns the amounts deposited.
+pub fn add_liquidity_exact_ratio<'info>(
+    ctx: Context<'_, '_, '_, 'info, LiquidityOperation<'info>>,
+    max_amount0: u64,
+    max_amount1: u64,
+) -> Result<DepositedAmounts> {
+    execute_add_liquidity(ctx, max_amount0, max_amount1, true)
+}
+
+/// Deposit as in add_liquidity, then stake the LP it minted through `staking_program`
+/// The staking instruction is `stake_ix_data` followed by the minted LP amount as a
+/// little-endian u64 (e.g. an Anchor discriminator then `amount: u64`), with the
+/// remaining accounts passed through in order. The deposit looks up any transfer hook
+/// accounts among them too. Only the owner's own signature is forwarded - the pool
+/// authority never signs the staking call
+pub fn add_liquidity_and_stake<'info>(
+    ctx: Context<'_, '_, '_, 'info, AddLiquidityAndStake<'info>>,
+    amount_liq0: u64,
+    amount_liq1: u64,
+    stake_ix_data: Vec<u8>,
+) -> Result<()> {
+    let staking_program = &ctx.accounts.staking_program;
+    require!(staking_program.key() != crate::ID, ErrorCode::InvalidInput);
+    // Staking takes the LP out of the depositor's account
+    require!(!ctx.accounts.liquidity.pool_state.lp_soulbound, ErrorCode::LpSoulbound);
     
-    // Deserialize user accounts
-    let user0_data = ctx.accounts.user0.to_account_info();
-    let user0_account = unpack_token_account(&user0_data, "user0")?;
+    let lp_before = ctx.accounts.liquidity.user_pool_ata.amount;
+    execute_add_liquidity(
+        Context::new(
+            ctx.program_id,
+            &mut ctx.accounts.liquidity,
+            ctx.remaining_accounts,
+            ctx.bumps.liquidity,
+        ),
+        amount_liq0,
+        amount_liq1,
+        false,
+    )?;
+    ctx.accounts.liquidity.user_pool_ata.reload()?;
+    let lp_minted = ctx.accounts.liquidity.user_pool_ata.amount
+        .checked_sub(lp_before)
+        .ok_or(ErrorCode::MathOverflow)?;
     
-    let user1_data = ctx.accounts.user1.to_account_info();
-    let user1_account = unpack_token_account(&user1_data, "user1")?;
+    let mut data = stake_ix_data;
+    data.extend_from_slice(&lp_minted.to_le_bytes());
+    let stake_ix = Instruction {
+        program_id: staking_program.key(),
+        accounts: ctx.remaining_accounts
+            .iter()
+            .map(|account| AccountMeta {
+                pubkey: *account.key,
+                is_signer: account.is_signer,
+                is_writable: account.is_writable,
+            })
+            .collect(),
+        data,
+    };
     
-    // Deserialize vaults
-    let vault0_data = ctx.accounts.vault0.to_account_info();
-    let vault0_account = unpack_token_account(&vault0_data, "vault0")?;
+    let mut account_infos = ctx.remaining_accounts.to_vec();
+    account_infos.push(staking_program.to_account_info());
+    invoke(&stake_ix, &account_infos)?;
     
-    let vault1_data = ctx.accounts.vault1.to_account_info();
-    let vault1_account = unpack_token_account(&vault1_data, "vault1")?;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct AddLiquidityAndStake<'info> {
+    /// Same accounts as add_liquidity
+    pub liquidity: LiquidityOperation<'info>,
+    
+    /// CHECK: Any farm program - it only receives the remaining accounts and the owner's signature
+    #[account(executable)]
+    pub staking_program: UncheckedAccount<'info>,
+}
+
+/// Core SPL deposit - with `match_limiting_side` unset, all of amount_liq0 is
+/// deposited and amount_liq1 must cover the matching token1
+fn execute_add_liquidity<'info>(
+    ctx: Context<'_, '_, '_, 'info, LiquidityOperation<'info>>,
+    amount_liq0: u64,
+    amount_liq1: u64,
+    match_limiting_side: bool,
+) -> Result<DepositedAmounts> {
+
+    // Token accounts are deserialized by Anchor (Token or Token2022, with extensions)
+    let user0_account = &ctx.accounts.user0;
+    let user1_account = &ctx.accounts.user1;
+    let vault0_account = &ctx.accounts.vault0;
+    let vault1_account = &ctx.accounts.vault1;
     
     // Validate owner
     require!(user0_account.owner == ctx.accounts.owner.key(), ErrorCode::NotEnoughBalance);
     require!(user1_account.owner == ctx.accounts.owner.key(), ErrorCode::NotEnoughBalance);
     
     // Validate mint matches (user0 mint should match vault0 mint)
-    require!(user0_account.mint == vault0_account.mint, ErrorCode::InvalidTreasury);
-    require!(user1_account.mint == vault1_account.mint, ErrorCode::InvalidTreasury);
+    require!(user0_account.mint == vault0_account.mint, ErrorCode::MintMismatch);
+    require!(user1_account.mint == vault1_account.mint, ErrorCode::MintMismatch);
+    let (mint0, mint1) = ctx.accounts.checked_mints()?;
     
     let user_balance0 = user0_account.amount; 
     let user_balance1 = user1_account.amount;
@@ -77,68 +139,90 @@ pub fn add_liquidity(
     // ensure enough balance 
     require!(amount_liq0 <= user_balance0, ErrorCode::NotEnoughBalance);
     require!(amount_liq1 <= user_balance1, ErrorCode::NotEnoughBalance);
+    
+    // Nothing moves while the global kill-switch is engaged
+    crate::utils::check_not_paused(&ctx.accounts.global_config)?;
+    
+    // Guard against reentrant calls on this pool
+    crate::utils::begin_pool_operation(&ctx.accounts.pool_state.to_account_info())?;
     let pool_state = &mut ctx.accounts.pool_state; 
+    pool_state.check_spl_pool()?;
+    require!(pool_state.deposits_enabled, ErrorCode::DepositsDisabled);
     
-    let deposit0 = amount_liq0;
-    // vars to fill out during if statement  
-    let deposit1; 
-    let amount_to_mint;
+    // TVL cap applies to the wrapped XNT side, if the pool has one
+    let native_mint = pool_state.xnt_mint();
+    let vault0_is_xnt = vault0_account.mint == native_mint;
+    let vault1_is_xnt = vault1_account.mint == native_mint;
     
-    // initial deposit
-// msg!("vaults: {} {}", vault_balance0, vault_balance1);
-// msg!("init deposits: {} {}", amount_liq0, amount_liq1);
-
-    if vault_balance0 == 0 && vault_balance1 == 0 {
-        // bit shift (a + b)/2
-        amount_to_mint = (amount_liq0 + amount_liq1) >> 1; 
-        deposit1 = amount_liq1;
-    } else { 
-        // require equal amount deposit based on pool exchange rate 
-        let exchange10 = vault_balance1.checked_div(vault_balance0).unwrap();
-        let amount_deposit_1 = amount_liq0.checked_mul(exchange10).unwrap();
-// msg!("new deposits: {} {} {}", exchange10, amount_liq0, amount_deposit_1);
-
-        // enough funds + user is ok with it in single check 
-        require!(amount_deposit_1 <= amount_liq1, ErrorCode::NotEnoughBalance);
-        deposit1 = amount_deposit_1; // update liquidity amount ! 
-
-        // mint = relative to the entire pool + total amount minted 
-        // u128 so we can do multiply first without overflow 
-        // then div and recast back 
-        amount_to_mint = (
-            (deposit1 as u128)
-            .checked_mul(pool_state.total_amount_minted as u128).unwrap()
-            .checked_div(vault_balance1 as u128).unwrap()
-        ) as u64;
-
-// msg!("pmint: {}", amount_to_mint);
-    }
+    let is_first_deposit = pool_state.total_amount_minted == 0;
+    let (deposit0, deposit1) = if is_first_deposit {
+        (amount_liq0, amount_liq1)
+    } else {
+        // require equal amount deposit based on pool exchange rate, rounded up in the pool's favour
+        require!(vault_balance0 > 0, ErrorCode::InsufficientLiquidity);
+        let amount_deposit_1 = u64::try_from(
+            (amount_liq0 as u128 * vault_balance1 as u128).div_ceil(vault_balance0 as u128)
+        ).map_err(|_| ErrorCode::MathOverflow)?;
+
+        if amount_deposit_1 <= amount_liq1 || !match_limiting_side {
+            // enough funds + user is ok with it in single check 
+            require!(amount_deposit_1 <= amount_liq1, ErrorCode::NotEnoughBalance);
+            (amount_liq0, amount_deposit_1)
+        } else {
+            // token1 is limiting: deposit all of it and the token0 it covers, rounded down
+            require!(vault_balance1 > 0, ErrorCode::InsufficientLiquidity);
+            let amount_deposit_0 = u64::try_from(
+                amount_liq1 as u128 * vault_balance0 as u128 / vault_balance1 as u128
+            ).map_err(|_| ErrorCode::MathOverflow)?;
+            (amount_deposit_0, amount_liq1)
+        }
+    };
+    // A Token 2022 transfer fee never reaches the vault - LP is minted for what arrives
+    let epoch = Clock::get()?.epoch;
+    let amount_to_mint = calculate_lp_to_mint(
+        pool_state.total_amount_minted,
+        vault_balance0,
+        vault_balance1,
+        crate::utils::amount_after_transfer_fee(mint0.as_ref().map(|(mint, _)| mint), epoch, deposit0)?,
+        crate::utils::amount_after_transfer_fee(mint1.as_ref().map(|(mint, _)| mint), epoch, deposit1)?,
+    )?;
 
     // saftey checks 
     require!(amount_to_mint > 0, ErrorCode::NoPoolMintOutput);
+    
+    if vault0_is_xnt {
+        crate::utils::check_tvl_cap(vault_balance0, deposit0, pool_state.max_tvl_native)?;
+    }
+    if vault1_is_xnt {
+        crate::utils::check_tvl_cap(vault_balance1, deposit1, pool_state.max_tvl_native)?;
+    }
 
-    // Detect token programs by checking the token account's owner
     // Token accounts are owned by their respective token programs (Token or Token 2022)
-    // If account is owned by Token 2022 Program, use Token 2022 for transfers
-    // If account is owned by standard Token Program, use standard Token for transfers
-    let user0_account_owner = ctx.accounts.user0.to_account_info().owner;
-    let user1_account_owner = ctx.accounts.user1.to_account_info().owner;
-    
-    let mint0_program = user0_account_owner;
-    let mint1_program = user1_account_owner;
-    
-    // Verify token_2022_program if needed
-    if is_token_2022(&mint0_program) || is_token_2022(&mint1_program) {
-        require!(
-            ctx.accounts.token_2022_program.key().to_string() == crate::utils::TOKEN_2022_PROGRAM_ID,
-            ErrorCode::InvalidTreasury
-        );
+    let token0_program = token_program_for(
+        ctx.accounts.user0.to_account_info().owner,
+        &ctx.accounts.token_program.to_account_info(),
+        &ctx.accounts.token_2022_program.to_account_info(),
+    )?;
+    let token1_program = token_program_for(
+        ctx.accounts.user1.to_account_info().owner,
+        &ctx.accounts.token_program.to_account_info(),
+        &ctx.accounts.token_2022_program.to_account_info(),
+    )?;
+
+    // Protocol-owned pools only mint LP to the governance PDA
+    if pool_state.protocol_owned {
+        let (governance, _) = Pubkey::find_program_address(&[GOVERNANCE_SEED], ctx.program_id);
+        require!(ctx.accounts.user_pool_ata.owner == governance, ErrorCode::Unauthorized);
     }
-    
-    // Get appropriate token program accounts for user tokens
 
     // give pool_mints (pool mint always uses standard Token program)
-    pool_state.total_amount_minted += amount_to_mint;
+    // The first deposit also records the locked MINIMUM_LIQUIDITY
+    let locked_liquidity = if is_first_deposit { MINIMUM_LIQUIDITY } else { 0 };
+    pool_state.total_amount_minted = pool_state.total_amount_minted
+        .checked_add(amount_to_mint)
+        .and_then(|x| x.checked_add(locked_liquidity))
+        .ok_or(ErrorCode::MathOverflow)?;
+    let lp_soulbound = pool_state.lp_soulbound;
     let mint_ctx = CpiContext::new(
         ctx.accounts.token_program.to_account_info(), 
         MintTo {
@@ -150,160 +234,224 @@ pub fn add_liquidity(
     let bump = ctx.bumps.pool_authority;
     let pool_key = ctx.accounts.pool_state.key();
     let pda_sign = &[b"authority", pool_key.as_ref(), &[bump]];
+    // Soulbound LP accounts are only thawed for the pool's own mint (see PoolState::lp_soulbound)
+    if lp_soulbound {
+        crate::utils::thaw_lp_account(
+            ctx.accounts.user_pool_ata.to_account_info(),
+            ctx.accounts.pool_mint.to_account_info(),
+            ctx.accounts.pool_authority.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            &[pda_sign],
+        )?;
+    }
     token::mint_to(
         mint_ctx.with_signer(&[pda_sign]), 
         amount_to_mint
     )?;
+    if lp_soulbound {
+        crate::utils::freeze_lp_account(
+            ctx.accounts.user_pool_ata.to_account_info(),
+            ctx.accounts.pool_mint.to_account_info(),
+            ctx.accounts.pool_authority.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            &[pda_sign],
+        )?;
+    }
+    
+    // First deposit: mint the locked minimum liquidity to the dead LP account
+    if is_first_deposit {
+        let locked_lp_account = ctx.accounts.locked_lp_account.as_ref().ok_or(ErrorCode::InvalidInput)?;
+        let (locked_liquidity_owner, _) = Pubkey::find_program_address(
+            &[LOCKED_LIQUIDITY_SEED, pool_key.as_ref()],
+            ctx.program_id,
+        );
+        require!(locked_lp_account.owner == locked_liquidity_owner, ErrorCode::InvalidInput);
+        require!(locked_lp_account.mint == ctx.accounts.pool_mint.key(), ErrorCode::InvalidInput);
+        
+        let lock_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            MintTo {
+                to: locked_lp_account.to_account_info(),
+                mint: ctx.accounts.pool_mint.to_account_info(),
+                authority: ctx.accounts.pool_authority.to_account_info(),
+            },
+        );
+        token::mint_to(lock_ctx.with_signer(&[pda_sign]), MINIMUM_LIQUIDITY)?;
+    }
     
     // deposit user funds into vaults (using appropriate token program)
     // Note: Token 2022 transfer fees are handled automatically by the program
-    let token0_program = if is_token_2022(&mint0_program) {
-        ctx.accounts.token_2022_program.to_account_info()
-    } else {
-        ctx.accounts.token_program.to_account_info()
-    };
-    crate::utils::transfer_tokens(
+    let (mint0, decimals0) = mint0.unzip();
+    let (mint1, decimals1) = mint1.unzip();
+    crate::utils::transfer_tokens_checked(
         ctx.accounts.user0.to_account_info(),
         ctx.accounts.vault0.to_account_info(),
         ctx.accounts.owner.to_account_info(),
         token0_program,
+        mint0,
+        decimals0.unwrap_or_default(),
+        ctx.remaining_accounts,
         deposit0,
+        &[],
     )?;
 
-    let token1_program = if is_token_2022(&mint1_program) {
-        ctx.accounts.token_2022_program.to_account_info()
-    } else {
-        ctx.accounts.token_program.to_account_info()
-    };
-    crate::utils::transfer_tokens(
+    crate::utils::transfer_tokens_checked(
         ctx.accounts.user1.to_account_info(),
         ctx.accounts.vault1.to_account_info(),
         ctx.accounts.owner.to_account_info(),
         token1_program,
+        mint1,
+        decimals1.unwrap_or_default(),
+        ctx.remaining_accounts,
         deposit1,
+        &[],
     )?;
 
-    Ok(())
+    crate::utils::end_pool_operation(&ctx.accounts.pool_state.to_account_info())?;
+
+    Ok(DepositedAmounts { amount0: deposit0, amount1: deposit1 })
 }
 
-pub fn remove_liquidity(
-    ctx: Context<LiquidityOperation>, 
+pub fn remove_liquidity<'info>(
+    ctx: Context<'_, '_, '_, 'info, LiquidityOperation<'info>>, 
     burn_amount: u64,
 ) -> Result<()> {
+    remove_liquidity_and_maybe_close(ctx, burn_amount, false)
+}
 
-    // Helper function to unpack token account (works for both Token and Token2022 with extensions)
-    fn unpack_token_account(account_info: &AccountInfo, name: &str) -> Result<Token2022AccountState> {
-// msg!("Unpacking {}: owner={}, data_len={}", name, account_info.owner, account_info.data_len());
-        
-        let account = if account_info.data_len() == 165 {
-            // Standard size - use regular unpack
-            Token2022AccountState::unpack(&account_info.data.borrow())
-                .map_err(|e| {
-// msg!("❌ Failed to unpack {} (standard): {:?}", name, e);
-                    e
-                })?
-        } else {
-            // Has extensions - use StateWithExtensions
-            let account_data = account_info.data.borrow();
-            let state_with_ext = StateWithExtensions::<Token2022AccountState>::unpack(&account_data)
-                .map_err(|e| {
-// msg!("❌ Failed to unpack {} (with extensions): {:?}", name, e);
-                    e
-                })?;
-            state_with_ext.base
-        };
-        
-// msg!("✅ {} unpacked successfully", name);
-        Ok(account)
+/// remove_liquidity, then close user_pool_ata and return its rent to the owner -
+/// fails unless the burn left the account empty
+pub fn remove_liquidity_and_close<'info>(
+    ctx: Context<'_, '_, '_, 'info, LiquidityOperation<'info>>, 
+    burn_amount: u64,
+) -> Result<()> {
+    remove_liquidity_and_maybe_close(ctx, burn_amount, true)
+}
+
+fn remove_liquidity_and_maybe_close<'info>(
+    ctx: Context<'_, '_, '_, 'info, LiquidityOperation<'info>>, 
+    burn_amount: u64,
+    close_lp_account: bool,
+) -> Result<()> {
+    // Pools with a withdrawal delay only pay out through execute_remove_liquidity
+    require!(ctx.accounts.pool_state.withdrawal_delay_secs == 0, ErrorCode::WithdrawalDelayActive);
+    
+    let pool_key = ctx.accounts.pool_state.key();
+    let bump = ctx.bumps.pool_authority;
+    let lp_soulbound = ctx.accounts.pool_state.lp_soulbound;
+    let user_pool_ata = ctx.accounts.user_pool_ata.to_account_info();
+    let pool_mint = ctx.accounts.pool_mint.to_account_info();
+    let owner = ctx.accounts.owner.to_account_info();
+    let pool_authority = ctx.accounts.pool_authority.to_account_info();
+    let token_program = ctx.accounts.token_program.to_account_info();
+    
+    withdraw_liquidity(ctx, burn_amount)?;
+    
+    if close_lp_account {
+        let pda_sign = &[b"authority", pool_key.as_ref(), &[bump]];
+        crate::utils::close_empty_lp_account(
+            user_pool_ata,
+            pool_mint,
+            owner,
+            pool_authority,
+            token_program,
+            &[pda_sign],
+            lp_soulbound,
+        )?;
     }
     
-    // Deserialize user_pool_ata (LP tokens are always Token Program)
-    let user_pool_ata_data = ctx.accounts.user_pool_ata.to_account_info();
-    let user_pool_ata_account = unpack_token_account(&user_pool_ata_data, "user_pool_ata")?;
+    Ok(())
+}
+
+/// Burn `burn_amount` LP from user_pool_ata and pay out the pro-rata share of both vaults
+pub(crate) fn withdraw_liquidity<'info>(
+    ctx: Context<'_, '_, '_, 'info, LiquidityOperation<'info>>, 
+    burn_amount: u64,
+) -> Result<()> {
+
+    // LP tokens are always Token Program
+    let user_pool_ata_account = &ctx.accounts.user_pool_ata;
     
     // Validate owner and mint
     require!(user_pool_ata_account.owner == ctx.accounts.owner.key(), ErrorCode::NotEnoughBalance);
-    require!(user_pool_ata_account.mint == ctx.accounts.pool_mint.key(), ErrorCode::InvalidTreasury);
+    require!(user_pool_ata_account.mint == ctx.accounts.pool_mint.key(), ErrorCode::MintMismatch);
+    // Payouts go to user0 / user1, reject them swapped before the token program would
+    require!(ctx.accounts.user0.mint == ctx.accounts.vault0.mint, ErrorCode::MintMismatch);
+    require!(ctx.accounts.user1.mint == ctx.accounts.vault1.mint, ErrorCode::MintMismatch);
+    let (mint0, mint1) = ctx.accounts.checked_mints()?;
     
     let pool_mint_balance = user_pool_ata_account.amount; 
     require!(burn_amount <= pool_mint_balance, ErrorCode::NotEnoughBalance);
 
+    // Nothing moves while the global kill-switch is engaged
+    crate::utils::check_not_paused(&ctx.accounts.global_config)?;
+    
+    // Guard against reentrant calls on this pool
+    crate::utils::begin_pool_operation(&ctx.accounts.pool_state.to_account_info())?;
+    
     let pool_key = ctx.accounts.pool_state.key();
     let state = &mut ctx.accounts.pool_state;
+    state.check_spl_pool()?;
     require!(state.total_amount_minted >= burn_amount, ErrorCode::BurnTooMuch);
+    crate::utils::check_burn_leaves_minimum_liquidity(state.total_amount_minted, burn_amount)?;
     
-    // Deserialize vaults
-    let vault0_data = ctx.accounts.vault0.to_account_info();
-    let vault0_account = unpack_token_account(&vault0_data, "vault0 (remove_liquidity)")?;
-    
-    let vault1_data = ctx.accounts.vault1.to_account_info();
-    let vault1_account = unpack_token_account(&vault1_data, "vault1 (remove_liquidity)")?;
-    
-    let vault0_amount = vault0_account.amount as u128;
-    let vault1_amount = vault1_account.amount as u128;
-    let u128_burn_amount = burn_amount as u128;
-
     // compute how much to give back 
-    let [amount0, amount1] = [
-        u128_burn_amount
-            .checked_mul(vault0_amount).unwrap()
-            .checked_div(state.total_amount_minted as u128).unwrap() as u64,
-        u128_burn_amount
-            .checked_mul(vault1_amount).unwrap()
-            .checked_div(state.total_amount_minted as u128).unwrap() as u64
-    ];
-
-    // Detect token programs by checking the token account's owner
-    // Token accounts are owned by their respective token programs (Token or Token 2022)
+    let amount0 = pro_rata_share(burn_amount, ctx.accounts.vault0.amount, state.total_amount_minted)?;
+    let amount1 = pro_rata_share(burn_amount, ctx.accounts.vault1.amount, state.total_amount_minted)?;
+
     // Vault accounts are owned by the Token Program that created their mints
-    let vault0_account_owner = ctx.accounts.vault0.to_account_info().owner;
-    let vault1_account_owner = ctx.accounts.vault1.to_account_info().owner;
-    
-    let mint0_program = vault0_account_owner;
-    let mint1_program = vault1_account_owner;
-    
-    // Verify token_2022_program if needed
-    if is_token_2022(&mint0_program) || is_token_2022(&mint1_program) {
-        require!(
-            ctx.accounts.token_2022_program.key().to_string() == crate::utils::TOKEN_2022_PROGRAM_ID,
-            ErrorCode::InvalidTreasury
-        );
-    }
+    let token0_program = token_program_for(
+        ctx.accounts.vault0.to_account_info().owner,
+        &ctx.accounts.token_program.to_account_info(),
+        &ctx.accounts.token_2022_program.to_account_info(),
+    )?;
+    let token1_program = token_program_for(
+        ctx.accounts.vault1.to_account_info().owner,
+        &ctx.accounts.token_program.to_account_info(),
+        &ctx.accounts.token_2022_program.to_account_info(),
+    )?;
     
     // deposit user funds into vaults (using appropriate token program)
     // Note: Token 2022 transfer fees are handled automatically by the program
     let bump = ctx.bumps.pool_authority;
     let pda_sign = &[b"authority", pool_key.as_ref(), &[bump]];
     
-    let token0_program = if is_token_2022(&mint0_program) {
-        ctx.accounts.token_2022_program.to_account_info()
-    } else {
-        ctx.accounts.token_program.to_account_info()
-    };
-    crate::utils::transfer_tokens_signed(
+    let (mint0, decimals0) = mint0.unzip();
+    let (mint1, decimals1) = mint1.unzip();
+    crate::utils::transfer_tokens_checked(
         ctx.accounts.vault0.to_account_info(),
         ctx.accounts.user0.to_account_info(),
         ctx.accounts.pool_authority.to_account_info(),
         token0_program,
+        mint0,
+        decimals0.unwrap_or_default(),
+        ctx.remaining_accounts,
         amount0,
         &[pda_sign],
     )?;
 
-    let token1_program = if is_token_2022(&mint1_program) {
-        ctx.accounts.token_2022_program.to_account_info()
-    } else {
-        ctx.accounts.token_program.to_account_info()
-    };
-    crate::utils::transfer_tokens_signed(
+    crate::utils::transfer_tokens_checked(
         ctx.accounts.vault1.to_account_info(),
         ctx.accounts.user1.to_account_info(),
         ctx.accounts.pool_authority.to_account_info(),
         token1_program,
+        mint1,
+        decimals1.unwrap_or_default(),
+        ctx.remaining_accounts,
         amount1,
         &[pda_sign],
     )?;
 
     // burn pool tokens (pool mint always uses standard Token program)
+    if state.lp_soulbound {
+        crate::utils::thaw_lp_account(
+            ctx.accounts.user_pool_ata.to_account_info(),
+            ctx.accounts.pool_mint.to_account_info(),
+            ctx.accounts.pool_authority.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            &[pda_sign],
+        )?;
+    }
     token::burn(CpiContext::new(
         ctx.accounts.token_program.to_account_info(), 
         Burn { 
@@ -312,12 +460,178 @@ pub fn remove_liquidity(
             authority:  ctx.accounts.owner.to_account_info(),
         }
     ), burn_amount)?;
+    if state.lp_soulbound {
+        crate::utils::freeze_lp_account(
+            ctx.accounts.user_pool_ata.to_account_info(),
+            ctx.accounts.pool_mint.to_account_info(),
+            ctx.accounts.pool_authority.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            &[pda_sign],
+        )?;
+    }
 
     state.total_amount_minted -= burn_amount; 
 
+    crate::utils::end_pool_operation(&ctx.accounts.pool_state.to_account_info())?;
+
+    Ok(())
+}
+
+/// Vault amount paid out for burning `burn_amount` of `total_amount_minted` LP, rounded down
+pub(crate) fn pro_rata_share(burn_amount: u64, vault_amount: u64, total_amount_minted: u64) -> Result<u64> {
+    let share = (burn_amount as u128)
+        .checked_mul(vault_amount as u128)
+        .and_then(|x| x.checked_div(total_amount_minted as u128))
+        .ok_or(ErrorCode::MathOverflow)?;
+    // burn_amount <= total_amount_minted, so the share never exceeds vault_amount
+    Ok(share as u64)
+}
+
+/// Max pools per `remove_liquidity_batch` call, keeps it within compute limits
+pub const MAX_BATCH_POOLS: usize = 4;
+
+/// Accounts per pool in `remove_liquidity_batch`'s remaining accounts
+pub const BATCH_POOL_ACCOUNTS: usize = 10;
+
+/// Remove liquidity from several SPL pools atomically
+///
+/// `burn_amounts[i]` LP is burned from pool i. Remaining accounts hold one group of
+/// BATCH_POOL_ACCOUNTS per pool, in the same order as `LiquidityOperation`:
+/// pool_state (mut), pool_authority, vault0 (mut), vault1 (mut), pool_mint (mut),
+/// user0 (mut), user1 (mut), user_pool_ata (mut), mint0, mint1
+/// Both sides move with TransferChecked - any transfer hook accounts follow the last group
+pub fn remove_liquidity_batch<'info>(
+    ctx: Context<'_, '_, 'info, 'info, RemoveLiquidityBatch<'info>>,
+    burn_amounts: Vec<u64>,
+) -> Result<()> {
+    require!(!burn_amounts.is_empty() && burn_amounts.len() <= MAX_BATCH_POOLS, ErrorCode::InvalidInput);
+    crate::utils::check_not_paused(&ctx.accounts.global_config)?;
+    require!(
+        ctx.remaining_accounts.len() >= burn_amounts.len() * BATCH_POOL_ACCOUNTS,
+        ErrorCode::InvalidInput
+    );
+    let (pool_accounts, hook_accounts) = ctx.remaining_accounts.split_at(burn_amounts.len() * BATCH_POOL_ACCOUNTS);
+    
+    for (accounts, &burn_amount) in pool_accounts
+        .chunks_exact(BATCH_POOL_ACCOUNTS)
+        .zip(burn_amounts.iter())
+    {
+        let [pool_state_info, pool_authority, vault0_info, vault1_info, pool_mint, user0, user1, user_pool_ata_info, mint0, mint1] = accounts else {
+            return Err(ErrorCode::InvalidInput.into());
+        };
+        
+        // Same checks Anchor runs for LiquidityOperation
+        let state = Account::<PoolState>::try_from(pool_state_info)?;
+        let pool_key = pool_state_info.key();
+        let (authority_pda, bump) = Pubkey::find_program_address(&[b"authority", pool_key.as_ref()], ctx.program_id);
+        let (vault0_pda, _) = Pubkey::find_program_address(&[b"vault0", pool_key.as_ref()], ctx.program_id);
+        let (vault1_pda, _) = Pubkey::find_program_address(&[b"vault1", pool_key.as_ref()], ctx.program_id);
+        let pool_mint_address = state.lp_mint_address(&pool_key, ctx.program_id);
+        require!(pool_authority.key() == authority_pda, anchor_lang::error::ErrorCode::ConstraintSeeds);
+        require!(vault0_info.key() == vault0_pda, anchor_lang::error::ErrorCode::ConstraintSeeds);
+        require!(vault1_info.key() == vault1_pda, anchor_lang::error::ErrorCode::ConstraintSeeds);
+        require!(pool_mint.key() == pool_mint_address, ErrorCode::InvalidInput);
+        
+        let vault0 = InterfaceAccount::<TokenAccount>::try_from(vault0_info)?;
+        let vault1 = InterfaceAccount::<TokenAccount>::try_from(vault1_info)?;
+        let user_pool_ata = InterfaceAccount::<TokenAccount>::try_from(user_pool_ata_info)?;
+        
+        // Validate owner and mint
+        require!(user_pool_ata.owner == ctx.accounts.owner.key(), ErrorCode::NotEnoughBalance);
+        require!(user_pool_ata.mint == pool_mint_address, ErrorCode::MintMismatch);
+        require!(InterfaceAccount::<TokenAccount>::try_from(user0)?.mint == vault0.mint, ErrorCode::MintMismatch);
+        require!(InterfaceAccount::<TokenAccount>::try_from(user1)?.mint == vault1.mint, ErrorCode::MintMismatch);
+        require!(mint0.key() == vault0.mint && mint1.key() == vault1.mint, ErrorCode::MintMismatch);
+        require!(burn_amount <= user_pool_ata.amount, ErrorCode::NotEnoughBalance);
+        state.check_spl_pool()?;
+        require!(state.total_amount_minted >= burn_amount, ErrorCode::BurnTooMuch);
+        crate::utils::check_burn_leaves_minimum_liquidity(state.total_amount_minted, burn_amount)?;
+        require!(state.withdrawal_delay_secs == 0, ErrorCode::WithdrawalDelayActive);
+        
+        // Guard against reentrant calls on this pool
+        crate::utils::begin_pool_operation(pool_state_info)?;
+        
+        // compute how much to give back 
+        let amount0 = pro_rata_share(burn_amount, vault0.amount, state.total_amount_minted)?;
+        let amount1 = pro_rata_share(burn_amount, vault1.amount, state.total_amount_minted)?;
+        
+        let pda_sign = &[b"authority", pool_key.as_ref(), &[bump]];
+        for (token_index, vault, user, mint, amount) in [
+            (0, vault0_info, user0, mint0, amount0),
+            (1, vault1_info, user1, mint1, amount1),
+        ] {
+            let token_program = token_program_for(
+                vault.owner,
+                &ctx.accounts.token_program.to_account_info(),
+                &ctx.accounts.token_2022_program.to_account_info(),
+            )?;
+            crate::utils::transfer_tokens_checked(
+                vault.clone(),
+                user.clone(),
+                pool_authority.clone(),
+                token_program,
+                Some(mint.clone()),
+                state.mint_decimals(token_index),
+                hook_accounts,
+                amount,
+                &[pda_sign],
+            )?;
+        }
+        
+        // burn pool tokens (pool mint always uses standard Token program)
+        if state.lp_soulbound {
+            crate::utils::thaw_lp_account(
+                user_pool_ata_info.clone(),
+                pool_mint.clone(),
+                pool_authority.clone(),
+                ctx.accounts.token_program.to_account_info(),
+                &[pda_sign],
+            )?;
+        }
+        token::burn(CpiContext::new(
+            ctx.accounts.token_program.to_account_info(), 
+            Burn { 
+                mint: pool_mint.clone(), 
+                from: user_pool_ata_info.clone(), 
+                authority: ctx.accounts.owner.to_account_info(),
+            }
+        ), burn_amount)?;
+        if state.lp_soulbound {
+            crate::utils::freeze_lp_account(
+                user_pool_ata_info.clone(),
+                pool_mint.clone(),
+                pool_authority.clone(),
+                ctx.accounts.token_program.to_account_info(),
+                &[pda_sign],
+            )?;
+        }
+        
+        // Not an Anchor-managed account here, write the new supply straight to its data
+        {
+            let mut data = pool_state_info.try_borrow_mut_data()?;
+            PoolState::write_total_amount_minted(&mut data, state.total_amount_minted - burn_amount)?;
+        }
+        
+        crate::utils::end_pool_operation(pool_state_info)?;
+    }
+    
     Ok(())
 }
 
+#[derive(Accounts)]
+pub struct RemoveLiquidityBatch<'info> {
+    pub owner: Signer<'info>,
+    
+    pub token_program: Program<'info, Token>,
+    /// CHECK: Token 2022 program - verified in handler
+    pub token_2022_program: UncheckedAccount<'info>,
+
+    // Program-wide GlobalConfig PDA - swaps and liquidity fail while its kill-switch is engaged
+    /// CHECK: Uninitialized until initialize_global_config (nothing is paused then) - read in handler
+    #[account(seeds = [b"global_config"], bump)]
+    pub global_config: UncheckedAccount<'info>,
+}
+
 #[derive(Accounts)]
 pub struct LiquidityOperation<'info> {
 
@@ -327,29 +641,300 @@ pub struct LiquidityOperation<'info> {
     
     #[account(seeds=[b"authority", pool_state.key().as_ref()], bump)]
     pub pool_authority: AccountInfo<'info>,
-    /// CHECK: Vault can be Token or Token2022, validated in handler
+    // vaults can be Token or Token2022
     #[account(mut, seeds=[b"vault0", pool_state.key().as_ref()], bump)]
-    pub vault0: UncheckedAccount<'info>, 
-    /// CHECK: Vault can be Token or Token2022, validated in handler
+    pub vault0: Box<InterfaceAccount<'info, TokenAccount>>, 
     #[account(mut, seeds=[b"vault1", pool_state.key().as_ref()], bump)]
-    pub vault1: UncheckedAccount<'info>,
-    #[account(mut, seeds=[b"pool_mint", pool_state.key().as_ref()], bump)]
+    pub vault1: Box<InterfaceAccount<'info, TokenAccount>>,
+    // [b"pool_mint", pool_state] PDA, or the existing mint a migrated pool adopted
+    #[account(mut, address = pool_state.lp_mint_address(&pool_state.key(), &crate::ID) @ ErrorCode::InvalidInput)]
     pub pool_mint: Box<Account<'info, Mint>>,  
     
     // user token accounts - can be Token or Token2022
-    /// CHECK: User token account, validated in handler
     #[account(mut)]
-    pub user0: UncheckedAccount<'info>, 
-    /// CHECK: User token account, validated in handler
+    pub user0: Box<InterfaceAccount<'info, TokenAccount>>, 
     #[account(mut)]
-    pub user1: UncheckedAccount<'info>, 
-    /// CHECK: User LP token account, validated in handler
+    pub user1: Box<InterfaceAccount<'info, TokenAccount>>, 
     #[account(mut)]
-    pub user_pool_ata: UncheckedAccount<'info>, 
+    pub user_pool_ata: Box<InterfaceAccount<'info, TokenAccount>>, 
     pub owner: Signer<'info>,
 
     // other 
     pub token_program: Program<'info, Token>,
     /// CHECK: Token 2022 program - verified in handler
     pub token_2022_program: UncheckedAccount<'info>,
+
+    // Program-wide GlobalConfig PDA - swaps and liquidity fail while its kill-switch is engaged
+    /// CHECK: Uninitialized until initialize_global_config (nothing is paused then) - read in handler
+    #[account(seeds = [b"global_config"], bump)]
+    pub global_config: UncheckedAccount<'info>,
+
+    // LP account owned by the LOCKED_LIQUIDITY_SEED PDA - only required on the first deposit
+    #[account(mut)]
+    pub locked_lp_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    // Optional mints of vault0 / vault1 - passing one moves that side with TransferChecked,
+    // which Token 2022 mints with a transfer fee or hook require
+    pub mint0: Option<Box<InterfaceAccount<'info, MintInterface>>>,
+    pub mint1: Option<Box<InterfaceAccount<'info, MintInterface>>>,
+}
+
+impl<'info> LiquidityOperation<'info> {
+    /// The optional vault mints with their stored decimals, checked against the vaults
+    #[allow(clippy::type_complexity)]
+    fn checked_mints(&self) -> Result<(Option<(AccountInfo<'info>, u8)>, Option<(AccountInfo<'info>, u8)>)> {
+        if let Some(mint0) = self.mint0.as_ref() {
+            require!(mint0.key() == self.vault0.mint, ErrorCode::MintMismatch);
+        }
+        if let Some(mint1) = self.mint1.as_ref() {
+            require!(mint1.key() == self.vault1.mint, ErrorCode::MintMismatch);
+        }
+        Ok((
+            self.mint0.as_ref().map(|mint| (mint.to_account_info(), self.pool_state.mint_decimals(0))),
+            self.mint1.as_ref().map(|mint| (mint.to_account_info(), self.pool_state.mint_decimals(1))),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{
+        empty_account, install_stubs, mint_account, pda, program, program_account, remaining_accounts, signer, token_account, Info, TestAccount,
+    };
+
+    /// One pool's BATCH_POOL_ACCOUNTS group, owned by `owner`
+    fn batch_pool(owner: &Pubkey) -> Vec<Info> {
+        let pool_state = PoolState { total_amount_minted: 2_000_000, mint0_decimals: 6, mint1_decimals: 9, ..Default::default() };
+        let pool_key = Pubkey::new_unique();
+        let pool_authority = pda(&[b"authority", pool_key.as_ref()]);
+        let pool_mint = pool_state.lp_mint_address(&pool_key, &crate::ID);
+        let (mint0, mint1) = (Pubkey::new_unique(), Pubkey::new_unique());
+        vec![
+            program_account(pool_key, &pool_state).info(),
+            empty_account(pool_authority).info(),
+            token_account(pda(&[b"vault0", pool_key.as_ref()]), mint0, pool_authority, 1_000_000).info(),
+            token_account(pda(&[b"vault1", pool_key.as_ref()]), mint1, pool_authority, 1_000_000).info(),
+            mint_account(pool_mint, pool_authority, 2_000_000, 9).info(),
+            token_account(Pubkey::new_unique(), mint0, *owner, 0).info(),
+            token_account(Pubkey::new_unique(), mint1, *owner, 0).info(),
+            token_account(Pubkey::new_unique(), pool_mint, *owner, 1_000_000).info(),
+            mint_account(mint0, Pubkey::new_unique(), 1_000_000, 6).info(),
+            mint_account(mint1, Pubkey::new_unique(), 1_000_000, 9).info(),
+        ]
+    }
+
+    fn remove_batch(owner: Info, accounts: &[Info], burn_amounts: Vec<u64>) -> Result<()> {
+        let mut batch = RemoveLiquidityBatch {
+            owner: Signer::try_from(owner).unwrap(),
+            token_program: Program::try_from(program(Token::id()).info()).unwrap(),
+            token_2022_program: UncheckedAccount::try_from(program(spl_token_2022::ID).info()),
+            global_config: UncheckedAccount::try_from(empty_account(pda(&[b"global_config"])).info()),
+        };
+        remove_liquidity_batch(
+            Context::new(&crate::ID, &mut batch, remaining_accounts(accounts), RemoveLiquidityBatchBumps::default()),
+            burn_amounts,
+        )
+    }
+
+    #[test]
+    fn batch_moves_each_side_with_its_vault_mint() {
+        let owner = signer().info();
+        let mut accounts = batch_pool(owner.key);
+        accounts.extend(batch_pool(owner.key));
+        // Transfer hook accounts follow the last group
+        accounts.push(empty_account(Pubkey::new_unique()).info());
+        remove_batch(owner, &accounts, vec![500_000, 500_000]).unwrap();
+        
+        // The supply is written back for every pool in the batch
+        for pool_state in [accounts[0], accounts[BATCH_POOL_ACCOUNTS]] {
+            let pool = PoolState::try_deserialize(&mut &pool_state.try_borrow_data().unwrap()[..]).unwrap();
+            assert_eq!(pool.total_amount_minted, 1_500_000);
+        }
+    }
+
+    #[test]
+    fn batch_rejects_a_mint_other_than_the_vaults() {
+        let owner = signer().info();
+        let mut accounts = batch_pool(owner.key);
+        accounts[9] = mint_account(Pubkey::new_unique(), Pubkey::new_unique(), 1_000_000, 9).info();
+        assert_eq!(remove_batch(owner, &accounts, vec![500_000]).unwrap_err(), ErrorCode::MintMismatch.into());
+        
+        // A group without its mints is short
+        let accounts = batch_pool(owner.key);
+        assert_eq!(
+            remove_batch(owner, &accounts[..BATCH_POOL_ACCOUNTS - 2], vec![500_000]).unwrap_err(),
+            ErrorCode::InvalidInput.into()
+        );
+    }
+
+    /// Token 2022 mint at `key` whose transfers call `hook_program`
+    fn hook_mint(key: Pubkey, hook_program: Pubkey) -> TestAccount {
+        use spl_token_2022::extension::{
+            transfer_hook::TransferHook, BaseStateWithExtensionsMut, ExtensionType, StateWithExtensionsMut,
+        };
+        
+        let mut data = vec![0; ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(&[ExtensionType::TransferHook]).unwrap()];
+        let mut state = StateWithExtensionsMut::<spl_token_2022::state::Mint>::unpack_uninitialized(&mut data).unwrap();
+        state.init_extension::<TransferHook>(true).unwrap().program_id.0 = hook_program;
+        state.base = spl_token_2022::state::Mint { decimals: 6, is_initialized: true, ..Default::default() };
+        state.pack_base();
+        state.init_account_type().unwrap();
+        TestAccount::new(key, spl_token_2022::ID, data)
+    }
+
+    /// add_liquidity_and_stake into a pool whose token0 is a Token 2022 hook mint
+    fn add_and_stake(remaining: &[Info], hook_program: Pubkey) -> Result<()> {
+        install_stubs();
+        let pool_state = PoolState { total_amount_minted: 2_000_000, mint0_decimals: 6, deposits_enabled: true, ..Default::default() };
+        let pool_key = Pubkey::new_unique();
+        let pool_authority = pda(&[b"authority", pool_key.as_ref()]);
+        let pool_mint = pool_state.lp_mint_address(&pool_key, &crate::ID);
+        let (mint0, mint1) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let owner = signer().info();
+        let token_2022_account = |key, mint, owner, amount| TestAccount {
+            owner: spl_token_2022::ID,
+            ..token_account(key, mint, owner, amount)
+        };
+        let mut accounts = AddLiquidityAndStake {
+            liquidity: LiquidityOperation {
+                pool_state: Box::new(Account::try_from(program_account(pool_key, &pool_state).info()).unwrap()),
+                pool_authority: empty_account(pool_authority).info().clone(),
+                vault0: Box::new(InterfaceAccount::try_from(
+                    token_2022_account(pda(&[b"vault0", pool_key.as_ref()]), mint0, pool_authority, 1_000_000).info()
+                ).unwrap()),
+                vault1: Box::new(InterfaceAccount::try_from(
+                    token_account(pda(&[b"vault1", pool_key.as_ref()]), mint1, pool_authority, 1_000_000).info()
+                ).unwrap()),
+                pool_mint: Box::new(Account::try_from(mint_account(pool_mint, pool_authority, 2_000_000, 9).info()).unwrap()),
+                user0: Box::new(InterfaceAccount::try_from(
+                    token_2022_account(Pubkey::new_unique(), mint0, *owner.key, 100_000).info()
+                ).unwrap()),
+                user1: Box::new(InterfaceAccount::try_from(
+                    token_account(Pubkey::new_unique(), mint1, *owner.key, 100_000).info()
+                ).unwrap()),
+                user_pool_ata: Box::new(InterfaceAccount::try_from(
+                    token_account(Pubkey::new_unique(), pool_mint, *owner.key, 0).info()
+                ).unwrap()),
+                owner: Signer::try_from(owner).unwrap(),
+                token_program: Program::try_from(program(Token::id()).info()).unwrap(),
+                token_2022_program: UncheckedAccount::try_from(program(spl_token_2022::ID).info()),
+                global_config: UncheckedAccount::try_from(empty_account(pda(&[b"global_config"])).info()),
+                locked_lp_account: None,
+                mint0: Some(Box::new(InterfaceAccount::try_from(hook_mint(mint0, hook_program).info()).unwrap())),
+                mint1: None,
+            },
+            staking_program: UncheckedAccount::try_from(program(Pubkey::new_unique()).info()),
+        };
+        add_liquidity_and_stake(
+            Context::new(&crate::ID, &mut accounts, remaining_accounts(remaining), AddLiquidityAndStakeBumps::default()),
+            100_000,
+            100_000,
+            Vec::new(),
+        )
+    }
+
+    #[test]
+    fn stake_deposit_finds_hook_accounts_among_the_remaining_accounts() {
+        let hook_program = program(Pubkey::new_unique()).info();
+        let stake_account = empty_account(Pubkey::new_unique()).info();
+        assert!(add_and_stake(&[stake_account, hook_program], *hook_program.key).is_ok());
+        
+        // TransferChecked can't resolve the hook without its program
+        assert!(add_and_stake(&[stake_account], *hook_program.key).is_err());
+    }
+
+    #[test]
+    fn batch_holds_one_to_max_batch_pools_within_each_lp_balance() {
+        let owner = signer().info();
+        let accounts: Vec<Info> = (0..MAX_BATCH_POOLS + 1).flat_map(|_| batch_pool(owner.key)).collect();
+        assert_eq!(remove_batch(owner, &accounts, Vec::new()).unwrap_err(), ErrorCode::InvalidInput.into());
+        assert_eq!(
+            remove_batch(owner, &accounts, vec![1_000; MAX_BATCH_POOLS + 1]).unwrap_err(),
+            ErrorCode::InvalidInput.into()
+        );
+        
+        // The owner holds 1_000_000 LP of each pool
+        let accounts = batch_pool(owner.key);
+        assert_eq!(remove_batch(owner, &accounts, vec![1_000_001]).unwrap_err(), ErrorCode::NotEnoughBalance.into());
+        // and only signs for their own LP account
+        let accounts = batch_pool(&Pubkey::new_unique());
+        assert_eq!(remove_batch(owner, &accounts, vec![1_000]).unwrap_err(), ErrorCode::NotEnoughBalance.into());
+    }
+
+    /// LiquidityOperation on a pool of 1_000_000 token0 / 2_000_000 token1 and 1_000_000 LP,
+    /// the owner holding 1_000_000 of each token
+    fn deposit_accounts() -> LiquidityOperation<'static> {
+        let pool_state = PoolState { total_amount_minted: 1_000_000, deposits_enabled: true, ..Default::default() };
+        let pool_key = Pubkey::new_unique();
+        let pool_authority = pda(&[b"authority", pool_key.as_ref()]);
+        let pool_mint = pool_state.lp_mint_address(&pool_key, &crate::ID);
+        let (mint0, mint1) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let owner = signer().info();
+        let token = |key, mint, owner, amount| Box::new(InterfaceAccount::try_from(token_account(key, mint, owner, amount).info()).unwrap());
+        LiquidityOperation {
+            pool_state: Box::new(Account::try_from(program_account(pool_key, &pool_state).info()).unwrap()),
+            pool_authority: empty_account(pool_authority).info().clone(),
+            vault0: token(pda(&[b"vault0", pool_key.as_ref()]), mint0, pool_authority, 1_000_000),
+            vault1: token(pda(&[b"vault1", pool_key.as_ref()]), mint1, pool_authority, 2_000_000),
+            pool_mint: Box::new(Account::try_from(mint_account(pool_mint, pool_authority, 1_000_000, 9).info()).unwrap()),
+            user0: token(Pubkey::new_unique(), mint0, *owner.key, 1_000_000),
+            user1: token(Pubkey::new_unique(), mint1, *owner.key, 1_000_000),
+            user_pool_ata: token(Pubkey::new_unique(), pool_mint, *owner.key, 0),
+            owner: Signer::try_from(owner).unwrap(),
+            token_program: Program::try_from(program(Token::id()).info()).unwrap(),
+            token_2022_program: UncheckedAccount::try_from(program(spl_token_2022::ID).info()),
+            global_config: UncheckedAccount::try_from(empty_account(pda(&[b"global_config"])).info()),
+            locked_lp_account: None,
+            mint0: None,
+            mint1: None,
+        }
+    }
+
+    #[test]
+    fn exact_ratio_deposit_matches_the_limiting_side() {
+        install_stubs();
+        // 1 token0 : 2 token1 - token0 limits here
+        let mut accounts = deposit_accounts();
+        let ctx = Context::new(&crate::ID, &mut accounts, &[], LiquidityOperationBumps::default());
+        let deposited = add_liquidity_exact_ratio(ctx, 100, 1_000).unwrap();
+        assert_eq!((deposited.amount0, deposited.amount1), (100, 200));
+        
+        // token1 limits here, token0 is rounded down to what it covers
+        let mut accounts = deposit_accounts();
+        let ctx = Context::new(&crate::ID, &mut accounts, &[], LiquidityOperationBumps::default());
+        let deposited = add_liquidity_exact_ratio(ctx, 100, 101).unwrap();
+        assert_eq!((deposited.amount0, deposited.amount1), (50, 101));
+        
+        // add_liquidity takes all of token0 and reverts when token1 falls short
+        let mut accounts = deposit_accounts();
+        let ctx = Context::new(&crate::ID, &mut accounts, &[], LiquidityOperationBumps::default());
+        assert_eq!(add_liquidity(ctx, 100, 101).unwrap_err(), ErrorCode::NotEnoughBalance.into());
+    }
+
+    #[test]
+    fn swapped_user_accounts_fail_as_a_mint_mismatch() {
+        install_stubs();
+        // Each side's account holding the other side's mint
+        let swapped = || {
+            let mut accounts = deposit_accounts();
+            std::mem::swap(&mut accounts.user0, &mut accounts.user1);
+            accounts
+        };
+        let mut accounts = swapped();
+        let ctx = Context::new(&crate::ID, &mut accounts, &[], LiquidityOperationBumps::default());
+        assert_eq!(add_liquidity(ctx, 100, 200).unwrap_err(), ErrorCode::MintMismatch.into());
+        let mut accounts = swapped();
+        let ctx = Context::new(&crate::ID, &mut accounts, &[], LiquidityOperationBumps::default());
+        assert_eq!(remove_liquidity(ctx, 100).unwrap_err(), ErrorCode::MintMismatch.into());
+        
+        // An LP account of another mint
+        let mut accounts = deposit_accounts();
+        let owner = accounts.owner.key();
+        *accounts.user_pool_ata = InterfaceAccount::try_from(
+            token_account(Pubkey::new_unique(), Pubkey::new_unique(), owner, 100).info()
+        ).unwrap();
+        let ctx = Context::new(&crate::ID, &mut accounts, &[], LiquidityOperationBumps::default());
+        assert_eq!(remove_liquidity(ctx, 100).unwrap_err(), ErrorCode::MintMismatch.into());
+    }
 }
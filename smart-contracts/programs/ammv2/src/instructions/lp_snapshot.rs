@@ -0,0 +1,72 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::TokenAccount;
+
+use crate::state::{LpBalanceSnapshot, PoolState};
+use crate::error::ErrorCode;
+
+/// Create the signer's LpBalanceSnapshot for a pool, recording their current LP balance
+/// The snapshot only counts toward the LP holder fee discount from the next slot on
+pub fn initialize_lp_balance_snapshot(ctx: Context<InitializeLpBalanceSnapshot>) -> Result<()> {
+    let pool_key = ctx.accounts.pool_state.key();
+    let lp_mint = ctx.accounts.pool_state.lp_mint_address(&pool_key, ctx.program_id);
+    let snapshot = &mut ctx.accounts.lp_balance_snapshot;
+    snapshot.pool = pool_key;
+    snapshot.owner = ctx.accounts.owner.key();
+    snapshot.bump = ctx.bumps.lp_balance_snapshot;
+    record_lp_balance(snapshot, &ctx.accounts.user_lp_account, &lp_mint)
+}
+
+#[derive(Accounts)]
+pub struct InitializeLpBalanceSnapshot<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub pool_state: Account<'info, PoolState>,
+
+    // Owner's LP tokens of this pool - validated in handler
+    pub user_lp_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + LpBalanceSnapshot::LEN,
+        seeds = [b"lp_snapshot", pool_state.key().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub lp_balance_snapshot: Account<'info, LpBalanceSnapshot>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Re-record the signer's LP balance in their LpBalanceSnapshot for a pool
+pub fn snapshot_lp_balance(ctx: Context<SnapshotLpBalance>) -> Result<()> {
+    let pool_key = ctx.accounts.pool_state.key();
+    let lp_mint = ctx.accounts.pool_state.lp_mint_address(&pool_key, ctx.program_id);
+    record_lp_balance(&mut ctx.accounts.lp_balance_snapshot, &ctx.accounts.user_lp_account, &lp_mint)
+}
+
+#[derive(Accounts)]
+pub struct SnapshotLpBalance<'info> {
+    pub owner: Signer<'info>,
+
+    pub pool_state: Account<'info, PoolState>,
+
+    // Owner's LP tokens of this pool - validated in handler
+    pub user_lp_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"lp_snapshot", pool_state.key().as_ref(), owner.key().as_ref()],
+        bump = lp_balance_snapshot.bump
+    )]
+    pub lp_balance_snapshot: Account<'info, LpBalanceSnapshot>,
+}
+
+fn record_lp_balance(snapshot: &mut LpBalanceSnapshot, user_lp_account: &TokenAccount, lp_mint: &Pubkey) -> Result<()> {
+    require!(user_lp_account.mint == *lp_mint, ErrorCode::InvalidInput);
+    require!(user_lp_account.owner == snapshot.owner, ErrorCode::InvalidInput);
+
+    snapshot.amount = user_lp_account.amount;
+    snapshot.slot = Clock::get()?.slot;
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use anchor_spl::token_interface::Mint;
+
+use crate::state::{GlobalConfig, PoolState};
+use crate::error::ErrorCode;
+
+/// Grow a legacy pool account to the current PoolState layout (GlobalConfig authority only)
+/// Legacy pools predate the admin field, so the program's authority stands in for theirs.
+/// Fields present in the legacy layout are kept, newer ones get their legacy defaults
+/// (see PoolState::try_deserialize) - except the mint decimals, read from the pool's mints
+pub fn migrate_pool(ctx: Context<MigratePool>) -> Result<()> {
+    require!(ctx.accounts.global_config.authority == ctx.accounts.authority.key(), ErrorCode::Unauthorized);
+    
+    let pool_state_info = ctx.accounts.pool_state.to_account_info();
+    let new_len = 8 + PoolState::LEN;
+    
+    let mut pool_state = {
+        let data = pool_state_info.try_borrow_data()?;
+        // The legacy reader does not check the discriminator itself
+        require!(data.len() >= 8 && data[..8] == *PoolState::DISCRIMINATOR, ErrorCode::InvalidAccountData);
+        // Already in the current layout
+        require!(data.len() < new_len, ErrorCode::InvalidInput);
+        PoolState::try_deserialize(&mut &data[..])?
+    };
+    
+    // The mints are the pool address's seeds: [b"pool_state", mint0, mint1] for SPL pools,
+    // [b"pool", token_mint(, native_mint_index)] for native ones
+    let mint0 = ctx.accounts.mint0.to_account_info();
+    let mint1 = ctx.accounts.mint1.as_ref().map(|mint| mint.to_account_info());
+    let pool_key = pool_state_info.key();
+    let seeds_match = match mint1.as_ref() {
+        Some(mint1) => {
+            Pubkey::find_program_address(&[b"pool_state", mint0.key.as_ref(), mint1.key.as_ref()], ctx.program_id).0 == pool_key
+        }
+        None => {
+            let index = [pool_state.native_mint_index];
+            Pubkey::find_program_address(&[b"pool", mint0.key.as_ref(), &index], ctx.program_id).0 == pool_key
+                || Pubkey::find_program_address(&[b"pool", mint0.key.as_ref()], ctx.program_id).0 == pool_key
+        }
+    };
+    require!(seeds_match && pool_state.is_native_pool == mint1.is_none(), ErrorCode::MintMismatch);
+    pool_state.record_mint_decimals(&mint0, mint1.as_ref())?;
+    
+    // Top up rent for the larger account
+    let rent_shortfall = Rent::get()?
+        .minimum_balance(new_len)
+        .saturating_sub(pool_state_info.lamports());
+    if rent_shortfall > 0 {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: pool_state_info.clone(),
+                },
+            ),
+            rent_shortfall,
+        )?;
+    }
+    
+    pool_state_info.resize(new_len)?;
+    pool_state.try_persist(&pool_state_info)?;
+    
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct MigratePool<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(seeds = [b"global_config"], bump)]
+    pub global_config: Account<'info, GlobalConfig>,
+    
+    /// CHECK: Legacy pool state - too small for Account<PoolState>, validated in handler
+    #[account(mut, owner = crate::ID)]
+    pub pool_state: UncheckedAccount<'info>,
+    
+    pub system_program: Program<'info, System>,
+    
+    // The pool's mints, to backfill their decimals - mint0 and mint1 for SPL pools,
+    // the token mint alone for native pools
+    pub mint0: InterfaceAccount<'info, Mint>,
+    pub mint1: Option<InterfaceAccount<'info, Mint>>,
+}
@@ -9,3 +9,52 @@ pub use swap::*;
 
 pub mod native_pool;
 pub use native_pool::*;
+
+pub mod admin;
+pub use admin::*;
+
+pub mod rebalance;
+pub use rebalance::*;
+
+pub mod views;
+pub use views::*;
+
+pub mod registry;
+pub use registry::*;
+
+pub mod donate;
+pub use donate::*;
+
+pub mod global_config;
+pub use global_config::*;
+
+pub mod emergency;
+pub use emergency::*;
+
+pub mod migrate;
+pub use migrate::*;
+
+pub mod fee_split;
+pub use fee_split::*;
+
+pub mod withdrawal_queue;
+pub use withdrawal_queue::*;
+
+pub mod pool_metadata;
+pub use pool_metadata::*;
+
+pub mod lp_snapshot;
+pub use lp_snapshot::*;
+
+pub mod governance;
+pub use governance::*;
+
+#[cfg(feature = "test-helpers")]
+pub mod test_helpers;
+#[cfg(feature = "test-helpers")]
+pub use test_helpers::*;
+
+#[cfg(feature = "bench")]
+pub mod bench;
+#[cfg(feature = "bench")]
+pub use bench::*;
@@ -1,18 +1,57 @@
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer, MintTo, Burn};
+use anchor_spl::token_interface;
+use anchor_spl::associated_token::AssociatedToken;
 use anchor_lang::solana_program::system_instruction;
 use anchor_lang::solana_program::program::invoke_signed;
 use anchor_spl::token::spl_token::instruction::initialize_account3 as initialize_account3_token;
 use spl_token_2022::instruction::initialize_account3 as initialize_account3_token2022;
-use crate::state::PoolState;
+use crate::state::{LpBalanceSnapshot, PoolState, DEFAULT_LP_DECIMALS, POOL_KIND_NATIVE, PROTOCOL_FEE_MODE_NOTIONAL};
 use crate::error::ErrorCode;
-use crate::utils::{is_token, is_token_2022};
+use crate::instructions::swap::SwapResult;
+use crate::utils::{is_token, is_token_2022, calculate_swap_output, calculate_lp_to_mint, MINIMUM_LIQUIDITY, LOCKED_LIQUIDITY_SEED};
 
 // Placeholder for native mint detection (System Program ID)
 // We use this to indicate "this is native XNT, not an SPL token"
 pub const NATIVE_MINT_PLACEHOLDER: Pubkey = Pubkey::new_from_array([0; 32]);
 
+// pool_pda holds native XNT as a plain system account. It must carry no data -
+// the System Program refuses transfers out of accounts with data - so its rent
+// floor is always minimum_balance(POOL_PDA_DATA_LEN)
+pub const POOL_PDA_DATA_LEN: usize = 0;
+
+/// Reject a token_vault other than the pool's [b"vault", pool_state] PDA
+/// The vault's balance is the pool's token reserve, so a substitute would misprice the pool
+fn check_token_vault(token_vault: &Pubkey, pool_state: &Pubkey, program_id: &Pubkey) -> Result<()> {
+    let (vault_pda, _) = Pubkey::find_program_address(&[b"vault", pool_state.as_ref()], program_id);
+    require!(*token_vault == vault_pda, ErrorCode::InvalidTreasury);
+    Ok(())
+}
+
+/// Reject a new native pool for an XNT side a pre-index pool at [b"pool", token_mint]
+/// already covers - the same side would otherwise end up with two pools
+fn check_legacy_native_pool(legacy_pool_state: &AccountInfo, native_mint_index: u8) -> Result<()> {
+    // Only this program can have written the PDA
+    if legacy_pool_state.data_is_empty() || legacy_pool_state.owner != &crate::ID {
+        return Ok(());
+    }
+    
+    let legacy_pool = PoolState::try_deserialize(&mut &legacy_pool_state.try_borrow_data()?[..])?;
+    require!(
+        !legacy_pool.is_native_pool || legacy_pool.native_mint_index != native_mint_index,
+        ErrorCode::PoolAlreadyRegistered
+    );
+    Ok(())
+}
+
+/// Lamports pool_pda must always keep - the single rent basis for every
+/// tradeable-XNT computation (pool_pda lamports above this back native_reserve)
+pub fn pool_pda_rent_floor() -> Result<u64> {
+    Ok(Rent::get()?.minimum_balance(POOL_PDA_DATA_LEN))
+}
+
 /// Initialize a new native XNT pool (XNT + SPL Token)
+#[allow(clippy::too_many_arguments)]
 pub fn initialize_native_pool(
     ctx: Context<InitializeNativePool>,
     fee_numerator: u64,
@@ -20,10 +59,15 @@ pub fn initialize_native_pool(
     protocol_treasury: Pubkey,
     protocol_fee_bps: u16,
     native_mint_index: u8, // 0 = XNT is token0, 1 = XNT is token1
+    lp_decimals: Option<u8>, // None = DEFAULT_LP_DECIMALS
+    lp_soulbound: Option<bool>, // None = transferable LP
 ) -> Result<()> {
     require!(native_mint_index <= 1, ErrorCode::InvalidInput);
+    check_legacy_native_pool(&ctx.accounts.legacy_pool_state, native_mint_index)?;
     require!(fee_denominator > 0, ErrorCode::InvalidInput);
     require!(protocol_fee_bps <= 10000, ErrorCode::InvalidInput); // Max 100%
+    // A higher protocol cut needs set_protocol_fee with allow_high_protocol_fee
+    crate::utils::check_protocol_fee_within_lp_fee(protocol_fee_bps, PROTOCOL_FEE_MODE_NOTIONAL, fee_numerator, fee_denominator)?;
 
     // Validate token_mint is owned by Token or Token2022 program
     let token_mint_owner = ctx.accounts.token_mint.to_account_info().owner;
@@ -46,6 +90,9 @@ pub fn initialize_native_pool(
         ErrorCode::InvalidTreasury
     );
 
+    // Vault transfers use the plain Transfer instruction, which some Token 2022 extensions refuse
+    crate::utils::check_mint_extensions_supported(&ctx.accounts.token_mint.to_account_info(), false)?;
+
     let pool_state_key = ctx.accounts.pool_state.key();
     
     // Derive vault PDA
@@ -76,15 +123,19 @@ pub fn initialize_native_pool(
 // msg!("is_token_2022: {}", is_token_2022(&token_mint_owner));
 // msg!("vault_token_program_id: {:?}", vault_token_program_id);
     
-    // Calculate rent for TokenAccount (165 bytes)
+    // Token 2022 vaults need room for the account extensions their mint requires
+    let vault_len = crate::utils::token_account_len(&ctx.accounts.token_mint.to_account_info())?;
     let rent = anchor_lang::solana_program::rent::Rent::get()?;
-    let rent_lamports = rent.minimum_balance(165);
+    let rent_lamports = rent.minimum_balance(vault_len);
     
     // Create and initialize token vault
     {
         let vault_info = ctx.accounts.token_vault.to_account_info();
         let vault_lamports = vault_info.lamports();
+        let vault_owner = vault_info.owner;
+        let vault_data_len = vault_info.data_len();
         
+        // Case 1: Account doesn't exist - use transfer + allocate + assign pattern
         if vault_lamports == 0 {
 // msg!("Creating token vault");
             
@@ -104,7 +155,7 @@ pub fn initialize_native_pool(
             invoke_signed(
                 &system_instruction::allocate(
                     ctx.accounts.token_vault.key,
-                    165,
+                    vault_len as u64,
                 ),
                 &[ctx.accounts.token_vault.to_account_info()],
                 &[vault_seeds],
@@ -156,7 +207,106 @@ pub fn initialize_native_pool(
             
 // msg!("token_vault initialized");
         }
+        // Case 2: Account exists but owned by System Program (from failed previous attempt,
+        // or lamports sent to the PDA) - top up rent, allocate while still owned by System,
+        // then assign to the token program and initialize
+        else if vault_owner == &anchor_lang::solana_program::system_program::ID {
+            let rent_shortfall = rent_lamports.saturating_sub(vault_lamports);
+            if rent_shortfall > 0 {
+                anchor_lang::system_program::transfer(
+                    CpiContext::new(
+                        ctx.accounts.system_program.to_account_info(),
+                        anchor_lang::system_program::Transfer {
+                            from: ctx.accounts.payer.to_account_info(),
+                            to: ctx.accounts.token_vault.to_account_info(),
+                        },
+                    ),
+                    rent_shortfall,
+                )?;
+            }
+            
+            if vault_data_len == 0 {
+                invoke_signed(
+                    &system_instruction::allocate(
+                        ctx.accounts.token_vault.key,
+                        vault_len as u64,
+                    ),
+                    &[ctx.accounts.token_vault.to_account_info()],
+                    &[vault_seeds],
+                )?;
+            }
+            
+            invoke_signed(
+                &system_instruction::assign(
+                    ctx.accounts.token_vault.key,
+                    &vault_token_program_id,
+                ),
+                &[ctx.accounts.token_vault.to_account_info()],
+                &[vault_seeds],
+            )?;
+            
+            let init_account_ix = if is_token_2022(token_mint_owner) {
+                initialize_account3_token2022(
+                    &vault_token_program_id,
+                    ctx.accounts.token_vault.key,
+                    ctx.accounts.token_mint.key,
+                    ctx.accounts.pool_authority.key,
+                )?
+            } else {
+                initialize_account3_token(
+                    &vault_token_program_id,
+                    ctx.accounts.token_vault.key,
+                    ctx.accounts.token_mint.key,
+                    ctx.accounts.pool_authority.key,
+                )?
+            };
+            
+            let token_program_account = if is_token_2022(token_mint_owner) {
+                ctx.accounts.token_2022_program.to_account_info()
+            } else {
+                ctx.accounts.token_program.to_account_info()
+            };
+            
+            anchor_lang::solana_program::program::invoke(
+                &init_account_ix,
+                &[
+                    ctx.accounts.token_vault.to_account_info(),
+                    ctx.accounts.token_mint.to_account_info(),
+                    ctx.accounts.pool_authority.to_account_info(),
+                    token_program_account,
+                    ctx.accounts.rent.to_account_info(),
+                ],
+            )?;
+        }
+        // Case 3: Account already owned by correct Token Program - already initialized
+        else if vault_owner == &vault_token_program_id {
+// msg!("token_vault already initialized");
+        }
+        // Case 4: Owned by unexpected program - error
+        else {
+            return Err(ErrorCode::InvalidTreasury.into());
+        }
     }
+    
+    // LP mint is created here rather than by `init` so its decimals can vary per pool
+    // Soulbound pools also make the pool authority its freeze authority
+    let lp_decimals = lp_decimals.unwrap_or(DEFAULT_LP_DECIMALS);
+    let lp_soulbound = lp_soulbound.unwrap_or(false);
+    let lp_mint_seeds = &[
+        b"lp_mint",
+        pool_state_key.as_ref(),
+        &[ctx.bumps.lp_mint],
+    ];
+    crate::utils::create_lp_mint(
+        ctx.accounts.payer.to_account_info(),
+        ctx.accounts.lp_mint.to_account_info(),
+        lp_mint_seeds,
+        lp_decimals,
+        ctx.accounts.pool_authority.key,
+        lp_soulbound.then_some(ctx.accounts.pool_authority.key),
+        ctx.accounts.system_program.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+    )?;
 
     let pool_state = &mut ctx.accounts.pool_state;
     pool_state.total_amount_minted = 0;
@@ -167,9 +317,54 @@ pub fn initialize_native_pool(
     
     // Native pool specific fields
     pool_state.is_native_pool = true;
+    pool_state.pool_kind = POOL_KIND_NATIVE;
     pool_state.native_reserve = 0; // Will be set when liquidity is added
     pool_state.native_mint_index = native_mint_index;
     
+    // Pool creator administers the pool, no slippage or swap size cap by default
+    pool_state.admin = ctx.accounts.payer.key();
+    pool_state.max_allowed_slippage_bps = 10000;
+    pool_state.max_swap_out_bps_of_reserve = 10000;
+    pool_state.created_at = Clock::get()?.unix_timestamp;
+    
+    // Fixed for the pool's lifetime - no instruction changes it
+    pool_state.emergency_recovery = ctx.accounts.emergency_recovery
+        .as_ref()
+        .map(|account| account.key())
+        .unwrap_or_default();
+    pool_state.lp_mint = ctx.accounts.lp_mint.key();
+    pool_state.lp_decimals = lp_decimals;
+    pool_state.lp_soulbound = lp_soulbound;
+    pool_state.record_mint_decimals(&ctx.accounts.token_mint.to_account_info(), None)?;
+    pool_state.swaps_enabled = true;
+    pool_state.deposits_enabled = true;
+    
+    // Create pool_pda up front, so it is rent-exempt before any XNT is deposited
+    if let Some(pool_pda) = ctx.accounts.pool_pda.as_ref() {
+        fund_pool_pda_rent(
+            &ctx.accounts.payer.to_account_info(),
+            &pool_pda.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+    }
+    
+    // Make the pool discoverable by mint pair (XNT side uses the placeholder)
+    let (registry_mint0, registry_mint1) = if native_mint_index == 0 {
+        (NATIVE_MINT_PLACEHOLDER, ctx.accounts.token_mint.key())
+    } else {
+        (ctx.accounts.token_mint.key(), NATIVE_MINT_PLACEHOLDER)
+    };
+    crate::instructions::register_pool(
+        ctx.program_id,
+        &ctx.accounts.registry_entry.to_account_info(),
+        &ctx.accounts.payer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        ctx.accounts.pool_state.key(),
+        POOL_KIND_NATIVE,
+        registry_mint0,
+        registry_mint1,
+    )?;
+    
 // msg!("✅ Native XNT pool initialized");
 // msg!("   Fee: {}/{} ({:.2}%)", fee_numerator, fee_denominator, 
 //         (fee_numerator as f64 / fee_denominator as f64) * 100.0);
@@ -180,16 +375,26 @@ pub fn initialize_native_pool(
 }
 
 #[derive(Accounts)]
+#[instruction(
+    fee_numerator: u64,
+    fee_denominator: u64,
+    protocol_treasury: Pubkey,
+    protocol_fee_bps: u16,
+    native_mint_index: u8,
+)]
 pub struct InitializeNativePool<'info> {
     #[account(mut)]
     pub payer: Signer<'info>,
     
     /// The pool state account - stores pool configuration and reserves
+    /// PDA [b"pool", token_mint, native_mint_index]: one native pool per token mint and
+    /// XNT side (index 0 and index 1 are separate pools). Pools created before the index
+    /// was part of the seed stay at [b"pool", token_mint] - see legacy_pool_state
     #[account(
         init,
         payer = payer,
-        space = 8 + std::mem::size_of::<PoolState>(),
-        seeds = [b"pool", token_mint.key().as_ref()],
+        space = 8 + PoolState::LEN, // discriminator + current PoolState layout
+        seeds = [b"pool", token_mint.key().as_ref(), &[native_mint_index]],
         bump
     )]
     pub pool_state: Account<'info, PoolState>,
@@ -198,21 +403,25 @@ pub struct InitializeNativePool<'info> {
     /// CHECK: We manually validate this is a valid mint (Token or Token2022)
     pub token_mint: UncheckedAccount<'info>,
     
+    /// Pre-index native pool address [b"pool", token_mint] - init fails if it holds a
+    /// native pool on the same XNT side
+    /// CHECK: Usually empty - read in handler
+    #[account(seeds = [b"pool", token_mint.key().as_ref()], bump)]
+    pub legacy_pool_state: UncheckedAccount<'info>,
+    
     /// Token vault account - stores the SPL tokens
     /// CHECK: We manually initialize this as a token account
     #[account(mut)]
     pub token_vault: UncheckedAccount<'info>,
     
     /// LP (liquidity provider) token mint
+    /// CHECK: Created and initialized in handler with the requested decimals
     #[account(
-        init,
-        payer = payer,
+        mut,
         seeds = [b"lp_mint", pool_state.key().as_ref()],
-        bump,
-        mint::decimals = 9,
-        mint::authority = pool_authority
+        bump
     )]
-    pub lp_mint: Account<'info, Mint>,
+    pub lp_mint: UncheckedAccount<'info>,
     
     /// Pool authority PDA (can sign on behalf of pool)
     /// CHECK: This is a PDA used for signing
@@ -222,11 +431,30 @@ pub struct InitializeNativePool<'info> {
     )]
     pub pool_authority: UncheckedAccount<'info>,
     
+    /// Mint-pair registry entry
+    /// CHECK: Created and verified in handler (see instructions::registry)
+    #[account(mut)]
+    pub registry_entry: UncheckedAccount<'info>,
+    
     pub token_program: Program<'info, Token>,
     /// CHECK: Token-2022 program
     pub token_2022_program: UncheckedAccount<'info>,
     pub system_program: Program<'info, System>,
     pub rent: Sysvar<'info, Rent>,
+    
+    /// Optional - where emergency_withdraw_native may drain reserves, fixed at creation
+    /// CHECK: Any wallet, only its address is stored
+    pub emergency_recovery: Option<UncheckedAccount<'info>>,
+    
+    /// Pool PDA that will hold native XNT - funded to rent-exempt when passed,
+    /// otherwise on the first deposit
+    /// CHECK: This is a PDA
+    #[account(
+        mut,
+        seeds = [b"pool_pda", pool_state.key().as_ref()],
+        bump
+    )]
+    pub pool_pda: Option<UncheckedAccount<'info>>,
 }
 
 /// Add liquidity to a native XNT pool
@@ -242,53 +470,52 @@ pub fn add_native_liquidity(
     
     // Get pool state key BEFORE taking mutable borrow
     let pool_state_key = ctx.accounts.pool_state.key();
+    check_token_vault(&ctx.accounts.token_vault.key(), &pool_state_key, ctx.program_id)?;
+    
+    // Nothing moves while the global kill-switch is engaged
+    crate::utils::check_not_paused(&ctx.accounts.global_config)?;
+    
+    // Guard against reentrant calls on this pool
+    crate::utils::begin_pool_operation(&ctx.accounts.pool_state.to_account_info())?;
     let pool_state = &mut ctx.accounts.pool_state;
     
 // msg!("  pool_state.is_native_pool: {}", pool_state.is_native_pool);
     
-    require!(pool_state.is_native_pool, ErrorCode::NotNativePool);
+    pool_state.check_native_pool()?;
+    require!(pool_state.deposits_enabled, ErrorCode::DepositsDisabled);
+    pool_state.native_mint_index_checked()?;
     require!(xnt_amount > 0 && token_amount > 0, ErrorCode::InvalidInput);
     
     // Determine which token program to use
-    let token_vault_info = ctx.accounts.token_vault.to_account_info();
-    let is_token_2022 = *token_vault_info.owner == spl_token_2022::ID;
+    let token_program = crate::utils::token_program_for(
+        ctx.accounts.token_vault.to_account_info().owner,
+        &ctx.accounts.token_program.to_account_info(),
+        &ctx.accounts.token_2022_program.to_account_info(),
+    )?;
     
-    // Get token vault balance
-    let token_vault_data = token_vault_info.try_borrow_data()?;
-    let token_vault_balance = u64::from_le_bytes(
-        token_vault_data[64..72]
-            .try_into()
-            .map_err(|_| ErrorCode::InvalidAccountData)?
-    );
-    drop(token_vault_data);
+    // Get token vault balance (token_interface handles Token and Token2022 layouts)
+    let token_vault_balance = ctx.accounts.token_vault.amount;
     
     // Calculate LP tokens to mint
-    let lp_to_mint = if pool_state.total_amount_minted == 0 {
-        // First liquidity provider - use geometric mean
-        ((xnt_amount as u128 * token_amount as u128).integer_sqrt() as u64)
-            .checked_sub(1000) // Minimum liquidity locked
-            .ok_or(ErrorCode::InsufficientLiquidity)?
-    } else {
-        // Subsequent providers - proportional to existing reserves
-        let native_reserve = pool_state.native_reserve;
-        
-        let lp_from_xnt = (xnt_amount as u128)
-            .checked_mul(pool_state.total_amount_minted as u128)
-            .ok_or(ErrorCode::MathOverflow)?
-            .checked_div(native_reserve as u128)
-            .ok_or(ErrorCode::MathOverflow)? as u64;
-            
-        let lp_from_token = (token_amount as u128)
-            .checked_mul(pool_state.total_amount_minted as u128)
-            .ok_or(ErrorCode::MathOverflow)?
-            .checked_div(token_vault_balance as u128)
-            .ok_or(ErrorCode::MathOverflow)? as u64;
-        
-        // Use minimum to maintain ratio
-        std::cmp::min(lp_from_xnt, lp_from_token)
-    };
+    let lp_to_mint = calculate_lp_to_mint(
+        pool_state.total_amount_minted,
+        pool_state.native_reserve,
+        token_vault_balance,
+        xnt_amount,
+        token_amount,
+    )?;
     
     require!(lp_to_mint >= min_lp_tokens, ErrorCode::SlippageExceeded);
+    crate::utils::check_tvl_cap(pool_state.native_reserve, xnt_amount, pool_state.max_tvl_native)?;
+    
+    // The first deposit leaves pool_pda rent-exempt on top of native_reserve
+    if pool_state.total_amount_minted == 0 {
+        fund_pool_pda_rent(
+            &ctx.accounts.user.to_account_info(),
+            &ctx.accounts.pool_pda.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+    }
     
     // Transfer native XNT to pool PDA
     let cpi_context = CpiContext::new(
@@ -301,47 +528,13 @@ pub fn add_native_liquidity(
     anchor_lang::system_program::transfer(cpi_context, xnt_amount)?;
     
     // Transfer SPL tokens to vault (use correct instruction based on token type)
-    if is_token_2022 {
-        // Use Token2022 instruction
-        let transfer_ix = spl_token_2022::instruction::transfer(
-            &spl_token_2022::ID,
-            ctx.accounts.user_token_account.to_account_info().key,
-            ctx.accounts.token_vault.to_account_info().key,
-            ctx.accounts.user.to_account_info().key,
-            &[],
-            token_amount,
-        )?;
-        
-        anchor_lang::solana_program::program::invoke(
-            &transfer_ix,
-            &[
-                ctx.accounts.user_token_account.to_account_info(),
-                ctx.accounts.token_vault.to_account_info(),
-                ctx.accounts.user.to_account_info(),
-                ctx.accounts.token_2022_program.to_account_info(),
-            ],
-        )?;
-    } else {
-        // Use standard Token Program instruction
-        let transfer_ix = spl_token::instruction::transfer(
-            &spl_token::ID,
-            ctx.accounts.user_token_account.to_account_info().key,
-            ctx.accounts.token_vault.to_account_info().key,
-            ctx.accounts.user.to_account_info().key,
-            &[],
-            token_amount,
-        )?;
-        
-        anchor_lang::solana_program::program::invoke(
-            &transfer_ix,
-            &[
-                ctx.accounts.user_token_account.to_account_info(),
-                ctx.accounts.token_vault.to_account_info(),
-                ctx.accounts.user.to_account_info(),
-                ctx.accounts.token_program.to_account_info(),
-            ],
-        )?;
-    }
+    crate::utils::transfer_tokens(
+        ctx.accounts.user_token_account.to_account_info(),
+        ctx.accounts.token_vault.to_account_info(),
+        ctx.accounts.user.to_account_info(),
+        token_program,
+        token_amount,
+    )?;
     
     // Mint LP tokens to user
     let authority_seeds = &[
@@ -361,14 +554,57 @@ pub fn add_native_liquidity(
         mint_accounts,
         signer_seeds,
     );
+    if pool_state.lp_soulbound {
+        crate::utils::thaw_lp_account(
+            ctx.accounts.user_lp_account.to_account_info(),
+            ctx.accounts.lp_mint.to_account_info(),
+            ctx.accounts.pool_authority.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            signer_seeds,
+        )?;
+    }
     token::mint_to(mint_ctx, lp_to_mint)?;
+    if pool_state.lp_soulbound {
+        crate::utils::freeze_lp_account(
+            ctx.accounts.user_lp_account.to_account_info(),
+            ctx.accounts.lp_mint.to_account_info(),
+            ctx.accounts.pool_authority.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            signer_seeds,
+        )?;
+    }
+    
+    // First deposit: mint the locked minimum liquidity to the dead LP account
+    if pool_state.total_amount_minted == 0 {
+        let locked_lp_account = ctx.accounts.locked_lp_account.as_ref().ok_or(ErrorCode::InvalidInput)?;
+        let (locked_liquidity_owner, _) = Pubkey::find_program_address(
+            &[LOCKED_LIQUIDITY_SEED, pool_state_key.as_ref()],
+            ctx.program_id,
+        );
+        require!(locked_lp_account.owner == locked_liquidity_owner, ErrorCode::InvalidInput);
+        require!(locked_lp_account.mint == ctx.accounts.lp_mint.key(), ErrorCode::InvalidInput);
+        
+        let lock_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            token::MintTo {
+                mint: ctx.accounts.lp_mint.to_account_info(),
+                to: locked_lp_account.to_account_info(),
+                authority: ctx.accounts.pool_authority.to_account_info(),
+            },
+            signer_seeds,
+        );
+        token::mint_to(lock_ctx, MINIMUM_LIQUIDITY)?;
+    }
     
     // Update pool state - calculate new values first
     let new_native_reserve = pool_state.native_reserve
         .checked_add(xnt_amount)
         .ok_or(ErrorCode::MathOverflow)?;
+    // The first deposit also records the locked MINIMUM_LIQUIDITY
+    let locked_liquidity = if pool_state.total_amount_minted == 0 { MINIMUM_LIQUIDITY } else { 0 };
     let new_total_minted = pool_state.total_amount_minted
         .checked_add(lp_to_mint)
+        .and_then(|x| x.checked_add(locked_liquidity))
         .ok_or(ErrorCode::MathOverflow)?;
     
     // CRITICAL: Manually serialize to ensure changes are persisted (Anchor auto-serialization buggy for custom layouts)
@@ -376,18 +612,16 @@ pub fn add_native_liquidity(
         let pool_state_info = ctx.accounts.pool_state.to_account_info();
         let mut data = pool_state_info.try_borrow_mut_data()?;
         
-        // Write total_amount_minted at offset 8
-        data[8..16].copy_from_slice(&new_total_minted.to_le_bytes());
-        
-        // Write native_reserve at offset 68 (8 + 8 + 8 + 8 + 32 + 2 + 1 + 1)
-        let reserve_offset = 68;
-        data[reserve_offset..reserve_offset + 8].copy_from_slice(&new_native_reserve.to_le_bytes());
+        PoolState::write_total_amount_minted(&mut data, new_total_minted)?;
+        PoolState::write_native_reserve(&mut data, new_native_reserve)?;
     } // Drop data here
     
     // Update Rust struct too (for consistency in same transaction)
     ctx.accounts.pool_state.native_reserve = new_native_reserve;
     ctx.accounts.pool_state.total_amount_minted = new_total_minted;
     
+    crate::utils::end_pool_operation(&ctx.accounts.pool_state.to_account_info())?;
+    
 // msg!("✅ Added native liquidity: {} XNT + {} tokens → {} LP", xnt_amount, token_amount, lp_to_mint);
 // msg!("   native_reserve updated to: {}", new_native_reserve);
     
@@ -412,14 +646,12 @@ pub struct AddNativeLiquidity<'info> {
     pub pool_pda: UncheckedAccount<'info>,
     
     /// Token vault - can be Token or Token2022
-    /// CHECK: We manually verify this is a valid token account
     #[account(mut)]
-    pub token_vault: UncheckedAccount<'info>,
+    pub token_vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
     
     /// User's token account - can be Token or Token2022
-    /// CHECK: We manually verify this is a valid token account
     #[account(mut)]
-    pub user_token_account: UncheckedAccount<'info>,
+    pub user_token_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
     
     #[account(mut)]
     pub lp_mint: Account<'info, Mint>,
@@ -440,112 +672,325 @@ pub struct AddNativeLiquidity<'info> {
     /// CHECK: Token-2022 program (optional, used for Token2022 tokens)
     pub token_2022_program: UncheckedAccount<'info>,
     pub system_program: Program<'info, System>,
+    
+    /// Program-wide GlobalConfig PDA - swaps and liquidity fail while its kill-switch is engaged
+    /// CHECK: Uninitialized until initialize_global_config (nothing is paused then) - read in handler
+    #[account(seeds = [b"global_config"], bump)]
+    pub global_config: UncheckedAccount<'info>,
+    
+    /// LP account owned by the LOCKED_LIQUIDITY_SEED PDA - only required on the first deposit
+    #[account(mut)]
+    pub locked_lp_account: Option<Box<InterfaceAccount<'info, token_interface::TokenAccount>>>,
+}
+
+/// Top pool_pda up to its rent-exempt minimum, paid by `payer`
+/// The top-up is not part of native_reserve, so it is never swapped or withdrawn
+fn fund_pool_pda_rent<'info>(
+    payer: &AccountInfo<'info>,
+    pool_pda: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    require!(pool_pda.data_len() == POOL_PDA_DATA_LEN, ErrorCode::InvalidAccountData);
+    let rent_minimum = pool_pda_rent_floor()?;
+    let shortfall = rent_minimum.saturating_sub(pool_pda.lamports());
+    if shortfall == 0 {
+        return Ok(());
+    }
+    
+    let cpi_context = CpiContext::new(
+        system_program.clone(),
+        anchor_lang::system_program::Transfer {
+            from: payer.clone(),
+            to: pool_pda.clone(),
+        },
+    );
+    anchor_lang::system_program::transfer(cpi_context, shortfall)
+}
+
+/// Create a native pool and make its first deposit in one instruction
+/// Closes the window between initialize_native_pool and the first
+/// add_native_liquidity in which another depositor could set the initial price
+#[allow(clippy::too_many_arguments)]
+pub fn initialize_native_pool_with_liquidity<'info>(
+    mut ctx: Context<'_, '_, '_, 'info, InitializeNativePoolWithLiquidity<'info>>,
+    fee_numerator: u64,
+    fee_denominator: u64,
+    protocol_treasury: Pubkey,
+    protocol_fee_bps: u16,
+    native_mint_index: u8,
+    xnt_amount: u64,
+    token_amount: u64,
+    min_lp_out: u64,
+    lp_decimals: Option<u8>,
+    lp_soulbound: Option<bool>,
+) -> Result<()> {
+    require!(xnt_amount > 0 && token_amount > 0, ErrorCode::InvalidInput);
+    
+    let pool_authority_bump = ctx.bumps.pool.pool_authority;
+    initialize_native_pool(
+        Context::new(
+            ctx.program_id,
+            &mut ctx.accounts.pool,
+            ctx.remaining_accounts,
+            ctx.bumps.pool,
+        ),
+        fee_numerator,
+        fee_denominator,
+        protocol_treasury,
+        protocol_fee_bps,
+        native_mint_index,
+        lp_decimals,
+        lp_soulbound,
+    )?;
+    
+    let lp_to_mint = calculate_lp_to_mint(0, 0, 0, xnt_amount, token_amount)?;
+    require!(lp_to_mint >= min_lp_out, ErrorCode::SlippageExceeded);
+    
+    let accounts = &mut ctx.accounts;
+    let pool_state_key = accounts.pool.pool_state.key();
+    
+    fund_pool_pda_rent(
+        &accounts.pool.payer.to_account_info(),
+        &accounts.pool_pda.to_account_info(),
+        &accounts.pool.system_program.to_account_info(),
+    )?;
+    
+    // Transfer native XNT to pool PDA
+    let cpi_context = CpiContext::new(
+        accounts.pool.system_program.to_account_info(),
+        anchor_lang::system_program::Transfer {
+            from: accounts.pool.payer.to_account_info(),
+            to: accounts.pool_pda.to_account_info(),
+        },
+    );
+    anchor_lang::system_program::transfer(cpi_context, xnt_amount)?;
+    
+    // The vault was just created under the token mint's program
+    let token_program = crate::utils::token_program_for(
+        accounts.pool.token_mint.to_account_info().owner,
+        &accounts.pool.token_program.to_account_info(),
+        &accounts.pool.token_2022_program.to_account_info(),
+    )?;
+    crate::utils::transfer_tokens(
+        accounts.user_token_account.to_account_info(),
+        accounts.pool.token_vault.to_account_info(),
+        accounts.pool.payer.to_account_info(),
+        token_program,
+        token_amount,
+    )?;
+    
+    // The LP mint only exists from here on, so its ATAs can't use `init` constraints
+    // (the ATA program rejects addresses that aren't the derived ATA)
+    for (lp_account, authority) in [
+        (accounts.user_lp_account.to_account_info(), accounts.pool.payer.to_account_info()),
+        (accounts.locked_lp_account.to_account_info(), accounts.locked_liquidity_owner.to_account_info()),
+    ] {
+        anchor_spl::associated_token::create(CpiContext::new(
+            accounts.associated_token_program.to_account_info(),
+            anchor_spl::associated_token::Create {
+                payer: accounts.pool.payer.to_account_info(),
+                associated_token: lp_account,
+                authority,
+                mint: accounts.pool.lp_mint.to_account_info(),
+                system_program: accounts.system_program.to_account_info(),
+                token_program: accounts.token_program.to_account_info(),
+            },
+        ))?;
+    }
+    
+    // Mint LP to the depositor and the locked minimum liquidity to the dead LP account
+    let authority_seeds = &[
+        b"authority",
+        pool_state_key.as_ref(),
+        &[pool_authority_bump],
+    ];
+    let signer_seeds = &[&authority_seeds[..]];
+    for (lp_account, amount) in [
+        (accounts.user_lp_account.to_account_info(), lp_to_mint),
+        (accounts.locked_lp_account.to_account_info(), MINIMUM_LIQUIDITY),
+    ] {
+        let mint_ctx = CpiContext::new_with_signer(
+            accounts.pool.token_program.to_account_info(),
+            token::MintTo {
+                mint: accounts.pool.lp_mint.to_account_info(),
+                to: lp_account,
+                authority: accounts.pool.pool_authority.to_account_info(),
+            },
+            signer_seeds,
+        );
+        token::mint_to(mint_ctx, amount)?;
+    }
+    // Only the depositor's LP is frozen, the locked LP's owner can never sign a transfer
+    if accounts.pool.pool_state.lp_soulbound {
+        crate::utils::freeze_lp_account(
+            accounts.user_lp_account.to_account_info(),
+            accounts.pool.lp_mint.to_account_info(),
+            accounts.pool.pool_authority.to_account_info(),
+            accounts.pool.token_program.to_account_info(),
+            signer_seeds,
+        )?;
+    }
+    
+    // pool_state was created by this instruction, so Anchor serializes it on exit
+    let pool_state = &mut accounts.pool.pool_state;
+    pool_state.native_reserve = xnt_amount;
+    pool_state.total_amount_minted = lp_to_mint
+        .checked_add(MINIMUM_LIQUIDITY)
+        .ok_or(ErrorCode::MathOverflow)?;
+    
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializeNativePoolWithLiquidity<'info> {
+    /// Pool creation accounts - payer also makes the first deposit
+    pub pool: InitializeNativePool<'info>,
+    
+    /// Pool PDA that holds native XNT
+    /// CHECK: This is a PDA
+    #[account(
+        mut,
+        seeds = [b"pool_pda", pool.pool_state.key().as_ref()],
+        bump
+    )]
+    pub pool_pda: UncheckedAccount<'info>,
+    
+    /// Payer's token account - can be Token or Token2022
+    #[account(mut)]
+    pub user_token_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    
+    /// Payer's LP account - the LP mint is new, so it is always created here
+    /// CHECK: Created in handler as the payer's LP associated token account
+    #[account(mut)]
+    pub user_lp_account: UncheckedAccount<'info>,
+    
+    /// CHECK: PDA that owns the locked minimum liquidity, never signs
+    #[account(seeds = [LOCKED_LIQUIDITY_SEED, pool.pool_state.key().as_ref()], bump)]
+    pub locked_liquidity_owner: UncheckedAccount<'info>,
+    
+    /// CHECK: Created in handler as locked_liquidity_owner's LP associated token account
+    #[account(mut)]
+    pub locked_lp_account: UncheckedAccount<'info>,
+    
+    // Same programs as in `pool`, repeated for creating the LP accounts
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
 }
 
 /// Swap in a native XNT pool (XNT ↔ Token)
-pub fn swap_native(
-    ctx: Context<SwapNative>,
+/// Returns the executed input, output (net of fees) and protocol fee
+pub fn swap_native<'info>(
+    ctx: Context<'_, '_, '_, 'info, SwapNative<'info>>,
     amount_in: u64,
     min_amount_out: u64,
     is_xnt_to_token: bool,
-) -> Result<()> {
-    // Get pool state key and data_len BEFORE taking mutable borrow
+) -> Result<SwapResult> {
+    // Get pool state key BEFORE taking mutable borrow
     let pool_state_key = ctx.accounts.pool_state.key();
-    let pool_state_data_len = ctx.accounts.pool_state.to_account_info().data_len();
+    check_token_vault(&ctx.accounts.token_vault.key(), &pool_state_key, ctx.program_id)?;
+    
+    // LP holders of this pool may pay a discounted LP fee
+    let lp_mint = ctx.accounts.pool_state.lp_mint_address(&pool_state_key, ctx.program_id);
+    let lp_balance = crate::utils::lp_discount_balance(
+        ctx.accounts.user_lp_account.as_deref().map(|account| &**account),
+        ctx.accounts.lp_balance_snapshot.as_deref(),
+        &lp_mint,
+        ctx.accounts.user.key,
+    )?;
+    
+    // Nothing moves while the global kill-switch is engaged
+    crate::utils::check_not_paused(&ctx.accounts.global_config)?;
+    
+    // Guard against reentrant calls on this pool
+    crate::utils::begin_pool_operation(&ctx.accounts.pool_state.to_account_info())?;
     let pool_state = &mut ctx.accounts.pool_state;
     
-    require!(pool_state.is_native_pool, ErrorCode::NotNativePool);
+    pool_state.check_native_pool()?;
+    require!(pool_state.swaps_enabled, ErrorCode::SwapsDisabled);
+    let native_mint_index = pool_state.native_mint_index_checked()?;
     require!(amount_in > 0, ErrorCode::InvalidInput);
     
     // Determine which token program to use
-    let token_vault_info = ctx.accounts.token_vault.to_account_info();
-    let is_token_2022 = *token_vault_info.owner == spl_token_2022::ID;
+    let token_program = crate::utils::token_program_for(
+        ctx.accounts.token_vault.to_account_info().owner,
+        &ctx.accounts.token_program.to_account_info(),
+        &ctx.accounts.token_2022_program.to_account_info(),
+    )?;
     
-    // Get token vault balance
-    let token_vault_data = token_vault_info.try_borrow_data()?;
-    let token_vault_balance = u64::from_le_bytes(
-        token_vault_data[64..72]
-            .try_into()
-            .map_err(|_| ErrorCode::InvalidAccountData)?
-    );
-    drop(token_vault_data);
+    // Get token vault balance (token_interface handles Token and Token2022 layouts)
+    let token_vault_balance = ctx.accounts.token_vault.amount;
+    #[cfg(feature = "invariant-checks")]
+    let native_reserve_before = pool_state.native_reserve;
     
-    let (reserve_in, reserve_out) = if is_xnt_to_token {
-        // XNT → Token
-        (pool_state.native_reserve, token_vault_balance)
+    let NativeSwapAmounts {
+        amount_out,
+        protocol_fee_xnt,
+        lp_rebate_xnt,
+        final_amount_out,
+        final_amount_in,
+    } = compute_native_swap(pool_state, token_vault_balance, amount_in, is_xnt_to_token, lp_balance)?;
+    
+    require!(final_amount_out >= min_amount_out, ErrorCode::SlippageExceeded);
+    crate::utils::check_slippage_cap(final_amount_out, min_amount_out, pool_state.max_allowed_slippage_bps)?;
+    
+    // Track the LP fee per LP unit - XNT is token `native_mint_index`, the SPL token the other side
+    let fee_numerator = pool_state.effective_fee_numerator(lp_balance);
+    let lp_fee = crate::utils::lp_fee_amount(amount_in, fee_numerator, pool_state.fee_denominator)?;
+    let fee_token_index = if is_xnt_to_token {
+        native_mint_index
     } else {
-        // Token → XNT
-        (token_vault_balance, pool_state.native_reserve)
+        1 - native_mint_index
+    };
+    pool_state.accrue_lp_fee(fee_token_index, lp_fee);
+    pool_state.accrue_lp_fee(native_mint_index, lp_rebate_xnt);
+    
+    // The protocol fee (less the LP rebate) goes to protocol_treasury, or is split across
+    // the FeeSplit recipients' wallets, passed in recipient order as remaining accounts.
+    // A compounding pool keeps it in native_reserve and mints its value as LP instead
+    let treasury_fee_xnt = protocol_fee_xnt - lp_rebate_xnt;
+    let xnt_reserve_before = pool_state.native_reserve;
+    let compounded_xnt = if pool_state.compounds_protocol_fee() { treasury_fee_xnt } else { 0 };
+    let fee_payouts = if compounded_xnt == 0 && treasury_fee_xnt > 0 && pool_state.protocol_treasury != Pubkey::default() {
+        match crate::utils::load_fee_split(&ctx.accounts.fee_split)? {
+            Some(fee_split) => crate::utils::fee_split_payouts(
+                &fee_split,
+                &pool_state_key,
+                ctx.remaining_accounts,
+                treasury_fee_xnt,
+                |account, recipient| {
+                    require!(account.key() == *recipient, ErrorCode::InvalidTreasury);
+                    Ok(())
+                },
+            )?,
+            None => {
+                require!(
+                    ctx.accounts.protocol_treasury.key() == pool_state.protocol_treasury,
+                    ErrorCode::InvalidTreasury
+                );
+                vec![(ctx.accounts.protocol_treasury.to_account_info(), treasury_fee_xnt)]
+            }
+        }
+    } else {
+        Vec::new()
     };
-    
-    // Calculate LP fee (total fee - protocol fee)
-    // LP fee = fee_numerator/fee_denominator (e.g., 3/1000 = 0.3%)
-    // Protocol fee is separate and calculated as protocol_fee_bps% of XNT amount
-    
-    // Calculate swap output using LP fee only (protocol fee handled separately)
-    let amount_out = calculate_swap_output(
-        amount_in,
-        reserve_in,
-        reserve_out,
-        pool_state.fee_numerator,
-        pool_state.fee_denominator,
-    )?;
-    
-    // Calculate protocol fee in XNT
-    // Protocol fee = protocol_fee_bps% of XNT amount involved in swap
-    let xnt_amount_for_fee = if is_xnt_to_token {
-        amount_in // XNT input
-    } else {
-        amount_out // XNT output
-    };
-    
-    let protocol_fee_xnt = if pool_state.protocol_treasury != Pubkey::default() 
-        && pool_state.protocol_fee_bps > 0 
-        && xnt_amount_for_fee > 0 {
-        (xnt_amount_for_fee as u128)
-            .checked_mul(pool_state.protocol_fee_bps as u128)
-            .and_then(|x| x.checked_div(10000))
-            .and_then(|x| u64::try_from(x).ok())
-            .unwrap_or(0)
-    } else {
-        0
-    };
-    
-    // Adjust amounts based on protocol fee
-    let final_amount_out = if is_xnt_to_token {
-        // XNT → Token: protocol fee deducted from input, output stays same
-        amount_out
-    } else {
-        // Token → XNT: protocol fee deducted from output
-        amount_out.checked_sub(protocol_fee_xnt).ok_or(ErrorCode::MathOverflow)?
-    };
-    
-    let final_amount_in = if is_xnt_to_token {
-        // XNT → Token: protocol fee deducted from input
-        amount_in.checked_sub(protocol_fee_xnt).ok_or(ErrorCode::MathOverflow)?
-    } else {
-        // Token → XNT: input stays same
-        amount_in
-    };
-    
-    require!(final_amount_out >= min_amount_out, ErrorCode::SlippageExceeded);
     
     if is_xnt_to_token {
         // XNT → Token swap
         
-        // 1. Transfer protocol fee to treasury (if applicable)
-        if protocol_fee_xnt > 0 && pool_state.protocol_treasury != Pubkey::default() {
+        // 1. Transfer protocol fee to treasury / FeeSplit recipients (if applicable)
+        for (fee_account, share) in &fee_payouts {
             let treasury_transfer_ix = anchor_lang::solana_program::system_instruction::transfer(
                 ctx.accounts.user.key,
-                &pool_state.protocol_treasury,
-                protocol_fee_xnt,
+                fee_account.key,
+                *share,
             );
             
             anchor_lang::solana_program::program::invoke(
                 &treasury_transfer_ix,
                 &[
                     ctx.accounts.user.to_account_info(),
-                    ctx.accounts.protocol_treasury.to_account_info(),
+                    fee_account.clone(),
                     ctx.accounts.system_program.to_account_info(),
                 ],
             )?;
@@ -553,7 +998,8 @@ pub fn swap_native(
 // msg!("💰 Protocol fee: {} XNT sent to treasury", protocol_fee_xnt);
         }
         
-        // 2. Transfer XNT from user to pool PDA (after protocol fee deduction)
+        // 2. Transfer XNT from user to pool PDA (after protocol fee deduction, any compounded fee included)
+        let xnt_to_pool = final_amount_in.checked_add(compounded_xnt).ok_or(ErrorCode::MathOverflow)?;
         let cpi_context = CpiContext::new(
             ctx.accounts.system_program.to_account_info(),
             anchor_lang::system_program::Transfer {
@@ -561,7 +1007,7 @@ pub fn swap_native(
                 to: ctx.accounts.pool_pda.to_account_info(),
             },
         );
-        anchor_lang::system_program::transfer(cpi_context, final_amount_in)?;
+        anchor_lang::system_program::transfer(cpi_context, xnt_to_pool)?;
         
         // 3. Transfer tokens from vault to user (use correct instruction based on token type)
         let authority_seeds = &[
@@ -571,58 +1017,24 @@ pub fn swap_native(
         ];
         let signer_seeds = &[&authority_seeds[..]];
         
-        if is_token_2022 {
-            let transfer_ix = spl_token_2022::instruction::transfer(
-                &spl_token_2022::ID,
-                ctx.accounts.token_vault.to_account_info().key,
-                ctx.accounts.user_token_account.to_account_info().key,
-                ctx.accounts.pool_authority.to_account_info().key,
-                &[],
-                amount_out,
-            )?;
-            
-            anchor_lang::solana_program::program::invoke_signed(
-                &transfer_ix,
-                &[
-                    ctx.accounts.token_vault.to_account_info(),
-                    ctx.accounts.user_token_account.to_account_info(),
-                    ctx.accounts.pool_authority.to_account_info(),
-                    ctx.accounts.token_2022_program.to_account_info(),
-                ],
-                signer_seeds,
-            )?;
-        } else {
-            let transfer_ix = spl_token::instruction::transfer(
-                &spl_token::ID,
-                ctx.accounts.token_vault.to_account_info().key,
-                ctx.accounts.user_token_account.to_account_info().key,
-                ctx.accounts.pool_authority.to_account_info().key,
-                &[],
-                amount_out,
-            )?;
-            
-            anchor_lang::solana_program::program::invoke_signed(
-                &transfer_ix,
-                &[
-                    ctx.accounts.token_vault.to_account_info(),
-                    ctx.accounts.user_token_account.to_account_info(),
-                    ctx.accounts.pool_authority.to_account_info(),
-                    ctx.accounts.token_program.to_account_info(),
-                ],
-                signer_seeds,
-            )?;
-        }
+        crate::utils::transfer_tokens_signed(
+            ctx.accounts.token_vault.to_account_info(),
+            ctx.accounts.user_token_account.to_account_info(),
+            ctx.accounts.pool_authority.to_account_info(),
+            token_program,
+            amount_out,
+            signer_seeds,
+        )?;
         
         // 4. Update native reserve with manual serialization (use final_amount_in after protocol fee)
         let new_native_reserve = pool_state.native_reserve
-            .checked_add(final_amount_in)
+            .checked_add(xnt_to_pool)
             .ok_or(ErrorCode::MathOverflow)?;
         
         {
             let pool_state_info = ctx.accounts.pool_state.to_account_info();
             let mut data = pool_state_info.try_borrow_mut_data()?;
-            let reserve_offset = 68;
-            data[reserve_offset..reserve_offset + 8].copy_from_slice(&new_native_reserve.to_le_bytes());
+            PoolState::write_native_reserve(&mut data, new_native_reserve)?;
         }
         
         ctx.accounts.pool_state.native_reserve = new_native_reserve;
@@ -632,59 +1044,37 @@ pub fn swap_native(
         // Token → XNT swap
         
         // 1. Transfer tokens from user to vault (use correct instruction based on token type)
-        if is_token_2022 {
-            let transfer_ix = spl_token_2022::instruction::transfer(
-                &spl_token_2022::ID,
-                ctx.accounts.user_token_account.to_account_info().key,
-                ctx.accounts.token_vault.to_account_info().key,
-                ctx.accounts.user.to_account_info().key,
-                &[],
-                amount_in,
-            )?;
-            
-            anchor_lang::solana_program::program::invoke(
-                &transfer_ix,
-                &[
-                    ctx.accounts.user_token_account.to_account_info(),
-                    ctx.accounts.token_vault.to_account_info(),
-                    ctx.accounts.user.to_account_info(),
-                    ctx.accounts.token_2022_program.to_account_info(),
-                ],
-            )?;
-        } else {
-            let transfer_ix = spl_token::instruction::transfer(
-                &spl_token::ID,
-                ctx.accounts.user_token_account.to_account_info().key,
-                ctx.accounts.token_vault.to_account_info().key,
-                ctx.accounts.user.to_account_info().key,
-                &[],
-                amount_in,
-            )?;
-            
-            anchor_lang::solana_program::program::invoke(
-                &transfer_ix,
-                &[
-                    ctx.accounts.user_token_account.to_account_info(),
-                    ctx.accounts.token_vault.to_account_info(),
-                    ctx.accounts.user.to_account_info(),
-                    ctx.accounts.token_program.to_account_info(),
-                ],
-            )?;
-        }
+        crate::utils::transfer_tokens(
+            ctx.accounts.user_token_account.to_account_info(),
+            ctx.accounts.token_vault.to_account_info(),
+            ctx.accounts.user.to_account_info(),
+            token_program,
+            amount_in,
+        )?;
         
         // 2. CRITICAL: Check rent safety before transferring XNT out
-        let rent = Rent::get()?;
+        // The fee transfers and the user transfer together move exactly amount_out less the
+        // LP rebate and any compounded fee out of pool_pda, and each only lowers its balance, so a final balance at
+        // or above the rent floor also holds after every intermediate transfer
         let pool_pda_info = ctx.accounts.pool_pda.to_account_info();
-        let rent_minimum = rent.minimum_balance(pool_state_data_len);
+        let rent_minimum = pool_pda_rent_floor()?;
         let current_lamports = pool_pda_info.lamports();
+        let total_fee_out = fee_payouts
+            .iter()
+            .try_fold(0u64, |total, (_, share)| total.checked_add(*share))
+            .ok_or(ErrorCode::MathOverflow)?;
+        let total_out = total_fee_out
+            .checked_add(final_amount_out)
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(total_out == amount_out - lp_rebate_xnt - compounded_xnt, ErrorCode::MathOverflow);
         
         require!(
-            current_lamports.checked_sub(amount_out).unwrap_or(0) >= rent_minimum,
+            current_lamports.checked_sub(total_out).unwrap_or(0) >= rent_minimum,
             ErrorCode::InsufficientRentReserve
         );
         
-        // 3. Transfer protocol fee to treasury (if applicable) - deduct from XNT output
-        if protocol_fee_xnt > 0 && pool_state.protocol_treasury != Pubkey::default() {
+        // 3. Transfer protocol fee to treasury / FeeSplit recipients (if applicable) - deduct from XNT output
+        for (fee_account, share) in &fee_payouts {
             let authority_seeds = &[
                 b"pool_pda",
                 pool_state_key.as_ref(),
@@ -694,15 +1084,15 @@ pub fn swap_native(
             
             let treasury_transfer_ix = anchor_lang::solana_program::system_instruction::transfer(
                 ctx.accounts.pool_pda.key,
-                &pool_state.protocol_treasury,
-                protocol_fee_xnt,
+                fee_account.key,
+                *share,
             );
             
             anchor_lang::solana_program::program::invoke_signed(
                 &treasury_transfer_ix,
                 &[
                     ctx.accounts.pool_pda.to_account_info(),
-                    ctx.accounts.protocol_treasury.to_account_info(),
+                    fee_account.clone(),
                     ctx.accounts.system_program.to_account_info(),
                 ],
                 signer_seeds,
@@ -735,16 +1125,21 @@ pub fn swap_native(
             signer_seeds,
         )?;
         
-        // 5. Update native reserve with manual serialization (deduct full amount_out including protocol fee)
+        // Belt and braces: re-read the balance once every XNT transfer has landed
+        require!(
+            ctx.accounts.pool_pda.lamports() >= rent_minimum,
+            ErrorCode::InsufficientRentReserve
+        );
+        
+        // 5. Update native reserve with manual serialization (deduct full amount_out including protocol fee, less the LP rebate)
         let new_native_reserve = pool_state.native_reserve
-            .checked_sub(amount_out) // Deduct full amount_out (includes protocol fee)
+            .checked_sub(total_out) // Deduct full amount_out (includes protocol fee) less the LP rebate
             .ok_or(ErrorCode::MathOverflow)?;
         
         {
             let pool_state_info = ctx.accounts.pool_state.to_account_info();
             let mut data = pool_state_info.try_borrow_mut_data()?;
-            let reserve_offset = 68;
-            data[reserve_offset..reserve_offset + 8].copy_from_slice(&new_native_reserve.to_le_bytes());
+            PoolState::write_native_reserve(&mut data, new_native_reserve)?;
         }
         
         ctx.accounts.pool_state.native_reserve = new_native_reserve;
@@ -752,7 +1147,79 @@ pub fn swap_native(
 // msg!("✅ Swapped {} tokens → {} XNT (protocol fee: {} XNT)", amount_in, final_amount_out, protocol_fee_xnt);
     }
     
-    Ok(())
+    // XNT reserve is tracked in pool_state, the token side is re-read from the vault
+    #[cfg(feature = "invariant-checks")]
+    crate::utils::check_invariant(
+        native_reserve_before,
+        token_vault_balance,
+        ctx.accounts.pool_state.native_reserve,
+        crate::utils::token_account_amount(&ctx.accounts.token_vault.to_account_info())?,
+    )?;
+    
+    // A Token 2022 transfer fee comes out of the token output, re-read what arrived
+    let amount_received = if is_xnt_to_token {
+        crate::utils::token_account_amount(&ctx.accounts.user_token_account.to_account_info())?
+            .checked_sub(ctx.accounts.user_token_account.amount)
+            .ok_or(ErrorCode::MathOverflow)?
+    } else {
+        final_amount_out
+    };
+    // min_amount_out must hold for what arrived, as in the SPL swap
+    require!(amount_received >= min_amount_out, ErrorCode::SlippageExceeded);
+    
+    if compounded_xnt > 0 {
+        let compounded_lp = crate::utils::compound_lp_amount(
+            compounded_xnt,
+            xnt_reserve_before,
+            ctx.accounts.pool_state.total_amount_minted,
+        )?;
+        if compounded_lp > 0 {
+            let lp_mint_account = ctx.accounts.lp_mint.as_ref().ok_or(ErrorCode::InvalidTreasury)?;
+            let protocol_lp_account = ctx.accounts.protocol_lp_account.as_ref().ok_or(ErrorCode::InvalidTreasury)?;
+            require!(
+                lp_mint_account.key() == ctx.accounts.pool_state.lp_mint_address(&pool_state_key, ctx.program_id),
+                ErrorCode::MintMismatch
+            );
+            require!(protocol_lp_account.key() == ctx.accounts.pool_state.protocol_lp_account, ErrorCode::InvalidTreasury);
+            
+            let authority_seeds = &[
+                b"authority",
+                pool_state_key.as_ref(),
+                &[ctx.bumps.pool_authority],
+            ];
+            crate::utils::mint_compounded_lp(
+                lp_mint_account.to_account_info(),
+                protocol_lp_account.to_account_info(),
+                ctx.accounts.pool_authority.to_account_info(),
+                ctx.accounts.token_program.to_account_info(),
+                &[&authority_seeds[..]],
+                compounded_lp,
+                ctx.accounts.pool_state.lp_soulbound,
+            )?;
+            
+            let new_total_amount_minted = ctx.accounts.pool_state.total_amount_minted
+                .checked_add(compounded_lp)
+                .ok_or(ErrorCode::MathOverflow)?;
+            {
+                let pool_state_info = ctx.accounts.pool_state.to_account_info();
+                let mut data = pool_state_info.try_borrow_mut_data()?;
+                PoolState::write_total_amount_minted(&mut data, new_total_amount_minted)?;
+            }
+            ctx.accounts.pool_state.total_amount_minted = new_total_amount_minted;
+        }
+    }
+    
+    // Every payout above has been transferred by now
+    let protocol_fee_paid: u64 = fee_payouts.iter().map(|(_, share)| share).sum();
+    ctx.accounts.pool_state.record_protocol_fee_collected(protocol_fee_paid);
+    
+    crate::utils::end_pool_operation(&ctx.accounts.pool_state.to_account_info())?;
+    
+    Ok(SwapResult {
+        amount_in_used: amount_in,
+        amount_out: amount_received,
+        protocol_fee: protocol_fee_paid,
+    })
 }
 
 #[derive(Accounts)]
@@ -773,14 +1240,12 @@ pub struct SwapNative<'info> {
     pub pool_pda: UncheckedAccount<'info>,
     
     /// Token vault - can be Token or Token2022
-    /// CHECK: We manually verify this is a valid token account
     #[account(mut)]
-    pub token_vault: UncheckedAccount<'info>,
+    pub token_vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
     
     /// User's token account - can be Token or Token2022
-    /// CHECK: We manually verify this is a valid token account
     #[account(mut)]
-    pub user_token_account: UncheckedAccount<'info>,
+    pub user_token_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
     
     /// CHECK: This is a PDA used for signing
     #[account(
@@ -798,68 +1263,291 @@ pub struct SwapNative<'info> {
     /// CHECK: This account is only used in CPI calls, may be default if no treasury
     #[account(mut)]
     pub protocol_treasury: UncheckedAccount<'info>,
+    
+    /// Program-wide GlobalConfig PDA - swaps and liquidity fail while its kill-switch is engaged
+    /// CHECK: Uninitialized until initialize_global_config (nothing is paused then) - read in handler
+    #[account(seeds = [b"global_config"], bump)]
+    pub global_config: UncheckedAccount<'info>,
+    
+    /// Pool's FeeSplit PDA - once created, the recipients' wallets follow as remaining accounts
+    /// CHECK: Uninitialized until initialize_fee_split (the treasury is paid then) - read in handler
+    #[account(seeds = [b"fee_split", pool_state.key().as_ref()], bump)]
+    pub fee_split: UncheckedAccount<'info>,
+    
+    // Every account below is optional and positional: the program ID stands for None, and
+    // only a trailing run of them may be left out. FeeSplit recipients start after all 4,
+    // so a client passing them must pad every unused optional with the program ID first
+    
+    /// Swapper's LP tokens of this pool - qualifies for the LP holder fee discount
+    pub user_lp_account: Option<Box<InterfaceAccount<'info, token_interface::TokenAccount>>>,
+    
+    /// Only required when the pool compounds its protocol fee (see PoolState::protocol_lp_account)
+    /// CHECK: Pool's LP mint - verified in handler
+    #[account(mut)]
+    pub lp_mint: Option<UncheckedAccount<'info>>,
+    /// CHECK: Must equal pool_state.protocol_lp_account, verified in handler
+    #[account(mut)]
+    pub protocol_lp_account: Option<UncheckedAccount<'info>>,
+    
+    /// Swapper's LpBalanceSnapshot - required with user_lp_account for the LP holder fee discount
+    #[account(seeds = [b"lp_snapshot", pool_state.key().as_ref(), user.key().as_ref()], bump)]
+    pub lp_balance_snapshot: Option<Account<'info, LpBalanceSnapshot>>,
 }
 
-// === HELPER FUNCTIONS ===
+/// Amounts for a native swap, before any transfer happens
+pub struct NativeSwapAmounts {
+    /// Gross curve output (includes the protocol fee on Token → XNT)
+    pub amount_out: u64,
+    pub protocol_fee_xnt: u64,
+    /// Part of protocol_fee_xnt kept in native_reserve as the LP rebate
+    pub lp_rebate_xnt: u64,
+    /// Output delivered to the user
+    pub final_amount_out: u64,
+    /// Input credited to the pool
+    pub final_amount_in: u64,
+}
 
-/// Calculate swap output using constant product formula (x * y = k)
-/// Includes fee deduction
-fn calculate_swap_output(
+/// Price a native swap against the pool's tracked reserves
+/// Shared by swap_native and quote_swap_native so quotes match execution exactly
+/// `lp_balance` is the swapper's LP holding, for the LP holder fee discount
+pub fn compute_native_swap(
+    pool_state: &PoolState,
+    token_vault_balance: u64,
     amount_in: u64,
-    reserve_in: u64,
-    reserve_out: u64,
-    fee_numerator: u64,
-    fee_denominator: u64,
+    is_xnt_to_token: bool,
+    lp_balance: u64,
+) -> Result<NativeSwapAmounts> {
+    let fee_numerator = pool_state.effective_fee_numerator(lp_balance);
+    
+    let (reserve_in, reserve_out) = if is_xnt_to_token {
+        // XNT → Token
+        (pool_state.native_reserve, token_vault_balance)
+    } else {
+        // Token → XNT
+        (token_vault_balance, pool_state.native_reserve)
+    };
+    // Reported apart from a dust output below, which needs a larger amount_in rather than liquidity
+    require!(reserve_in > 0 && reserve_out > 0, ErrorCode::PoolReserveEmpty);
+    
+    // Calculate LP fee (total fee - protocol fee)
+    // LP fee = fee_numerator/fee_denominator (e.g., 3/1000 = 0.3%)
+    // Protocol fee is separate and calculated as protocol_fee_bps% of XNT amount
+    
+    // Calculate swap output using LP fee only (protocol fee handled separately)
+    let amount_out = calculate_swap_output(
+        amount_in,
+        reserve_in,
+        reserve_out,
+        fee_numerator,
+        pool_state.fee_denominator,
+    )?;
+    crate::utils::check_swap_size(amount_out as u128, reserve_out as u128, pool_state.max_swap_out_bps_of_reserve)?;
+    
+    // Calculate protocol fee in XNT
+    // Protocol fee = protocol_fee_bps% of XNT amount involved in swap (or of its LP fee, per protocol_fee_mode)
+    let xnt_amount_for_fee = if is_xnt_to_token {
+        amount_in // XNT input
+    } else {
+        amount_out // XNT output
+    };
+    
+    let protocol_fee_xnt = native_protocol_fee(pool_state, xnt_amount_for_fee, fee_numerator);
+    // lp_rebate never exceeds the fee it is taken from
+    let lp_rebate_xnt = pool_state.lp_rebate(protocol_fee_xnt as u128) as u64;
+    
+    // Adjust amounts based on protocol fee
+    let final_amount_out = if is_xnt_to_token {
+        // XNT → Token: protocol fee deducted from input, output stays same
+        amount_out
+    } else {
+        // Token → XNT: protocol fee deducted from output
+        amount_out.checked_sub(protocol_fee_xnt).ok_or(ErrorCode::MathOverflow)?
+    };
+    
+    let final_amount_in = if is_xnt_to_token {
+        // XNT → Token: protocol fee deducted from input, the LP rebate stays in the pool
+        amount_in.checked_sub(protocol_fee_xnt).ok_or(ErrorCode::MathOverflow)? + lp_rebate_xnt
+    } else {
+        // Token → XNT: input stays same
+        amount_in
+    };
+    // A zero output would take amount_in for nothing even with min_amount_out = 0
+    require!(final_amount_out > 0, ErrorCode::SwapOutputZero);
+    
+    Ok(NativeSwapAmounts {
+        amount_out,
+        protocol_fee_xnt,
+        lp_rebate_xnt,
+        final_amount_out,
+        final_amount_in,
+    })
+}
+
+/// Protocol fee in XNT on `xnt_amount`, 0 when the pool has no treasury or fee
+/// or is still in its bootstrap window
+fn native_protocol_fee(pool_state: &PoolState, xnt_amount: u64, fee_numerator: u64) -> u64 {
+    if pool_state.protocol_treasury == Pubkey::default()
+        || pool_state.protocol_fee_bps == 0
+        || xnt_amount == 0
+        || Clock::get().is_ok_and(|clock| pool_state.in_bootstrap_window(clock.unix_timestamp)) {
+        return 0;
+    }
+    crate::utils::protocol_fee_amount(
+        xnt_amount as u128,
+        pool_state.protocol_fee_bps,
+        pool_state.protocol_fee_mode,
+        fee_numerator,
+        pool_state.fee_denominator,
+    )
+        .and_then(|x| u64::try_from(x).ok())
+        .unwrap_or(0)
+}
+
+/// Input a native swap needs to deliver at least `amount_out`, the inverse of compute_native_swap
+/// On Token → XNT the protocol fee comes out of the XNT output, so the curve must
+/// release enough extra to cover it
+pub fn compute_native_swap_input(
+    pool_state: &PoolState,
+    token_vault_balance: u64,
+    amount_out: u64,
+    is_xnt_to_token: bool,
+    lp_balance: u64,
 ) -> Result<u64> {
-    require!(reserve_in > 0 && reserve_out > 0, ErrorCode::InsufficientLiquidity);
-    
-    // Deduct fee from input amount
-    let amount_in_with_fee = (amount_in as u128)
-        .checked_mul((fee_denominator - fee_numerator) as u128)
-        .ok_or(ErrorCode::MathOverflow)?
-        .checked_div(fee_denominator as u128)
-        .ok_or(ErrorCode::MathOverflow)? as u64;
-    
-    // Calculate output: (amount_in_with_fee * reserve_out) / (reserve_in + amount_in_with_fee)
-    let numerator = (amount_in_with_fee as u128)
-        .checked_mul(reserve_out as u128)
-        .ok_or(ErrorCode::MathOverflow)?;
+    require!(amount_out > 0, ErrorCode::InvalidInput);
+    let fee_numerator = pool_state.effective_fee_numerator(lp_balance);
     
-    let denominator = (reserve_in as u128)
-        .checked_add(amount_in_with_fee as u128)
-        .ok_or(ErrorCode::MathOverflow)?;
+    let (reserve_in, reserve_out) = if is_xnt_to_token {
+        (pool_state.native_reserve, token_vault_balance)
+    } else {
+        (token_vault_balance, pool_state.native_reserve)
+    };
     
-    let amount_out = numerator
-        .checked_div(denominator)
-        .ok_or(ErrorCode::MathOverflow)? as u64;
+    // Smallest curve output whose net of the protocol fee reaches amount_out.
+    // output - fee(output) never decreases as output grows, so stepping up by the
+    // shortfall converges from below. The curve releases less than reserve_out, so
+    // once gross_out gets there (a fee taking the whole output never converges)
+    // no input is enough
+    let mut gross_out = amount_out;
+    if !is_xnt_to_token {
+        let mut converged = false;
+        for _ in 0..64 {
+            require!(gross_out < reserve_out, ErrorCode::OutputUnreachable);
+            let net_out = gross_out - native_protocol_fee(pool_state, gross_out, fee_numerator);
+            if net_out >= amount_out {
+                converged = true;
+                break;
+            }
+            gross_out = gross_out.saturating_add(amount_out - net_out);
+        }
+        require!(converged, ErrorCode::OutputUnreachable);
+    }
+    crate::utils::check_swap_size(gross_out as u128, reserve_out as u128, pool_state.max_swap_out_bps_of_reserve)?;
     
-    Ok(amount_out)
+    crate::utils::calculate_swap_input(
+        gross_out,
+        reserve_in,
+        reserve_out,
+        fee_numerator,
+        pool_state.fee_denominator,
+    )
+}
+
+/// Remove liquidity from a native XNT pool
+/// Pays out pro-rata against total_amount_minted, which includes the locked
+/// MINIMUM_LIQUIDITY, so withdrawing every circulating LP token leaves the
+/// locked share of both reserves in the pool. Pools created before the lock was
+/// recorded have no locked share and can be drained completely.
+pub fn remove_native_liquidity(
+    ctx: Context<RemoveNativeLiquidity>,
+    lp_amount: u64,
+    min_xnt_out: u64,
+    min_token_out: u64,
+) -> Result<()> {
+    remove_native_liquidity_and_maybe_close(ctx, lp_amount, min_xnt_out, min_token_out, false)
 }
 
-/// Reconcile native reserve with actual PDA balance
-/// Call this periodically or if drift is suspected
-pub fn remove_native_liquidity(ctx: Context<RemoveNativeLiquidity>, lp_amount: u64) -> Result<()> {
+/// remove_native_liquidity, then close user_lp_account and return its rent to the
+/// user - fails unless the burn left the account empty
+pub fn remove_native_liquidity_and_close(
+    ctx: Context<RemoveNativeLiquidity>,
+    lp_amount: u64,
+    min_xnt_out: u64,
+    min_token_out: u64,
+) -> Result<()> {
+    remove_native_liquidity_and_maybe_close(ctx, lp_amount, min_xnt_out, min_token_out, true)
+}
+
+fn remove_native_liquidity_and_maybe_close(
+    ctx: Context<RemoveNativeLiquidity>,
+    lp_amount: u64,
+    min_xnt_out: u64,
+    min_token_out: u64,
+    close_lp_account: bool,
+) -> Result<()> {
+    // Pools with a withdrawal delay only pay out through execute_remove_native_liquidity
+    require!(ctx.accounts.pool_state.withdrawal_delay_secs == 0, ErrorCode::WithdrawalDelayActive);
+    
+    let pool_state_key = ctx.accounts.pool_state.key();
+    let bump = ctx.bumps.pool_authority;
+    let lp_soulbound = ctx.accounts.pool_state.lp_soulbound;
+    let user_lp_account = ctx.accounts.user_lp_account.to_account_info();
+    let lp_mint = ctx.accounts.lp_mint.to_account_info();
+    let user = ctx.accounts.user.to_account_info();
+    let pool_authority = ctx.accounts.pool_authority.to_account_info();
+    let token_program = ctx.accounts.token_program.to_account_info();
+    
+    withdraw_native_liquidity(ctx, lp_amount, min_xnt_out, min_token_out)?;
+    
+    if close_lp_account {
+        let authority_seeds = &[
+            b"authority",
+            pool_state_key.as_ref(),
+            &[bump],
+        ];
+        crate::utils::close_empty_lp_account(
+            user_lp_account,
+            lp_mint,
+            user,
+            pool_authority,
+            token_program,
+            &[&authority_seeds[..]],
+            lp_soulbound,
+        )?;
+    }
+    
+    Ok(())
+}
+
+/// Burn `lp_amount` LP from user_lp_account and pay out the pro-rata XNT and tokens
+pub(crate) fn withdraw_native_liquidity(
+    ctx: Context<RemoveNativeLiquidity>,
+    lp_amount: u64,
+    min_xnt_out: u64,
+    min_token_out: u64,
+) -> Result<()> {
     let pool_state = &ctx.accounts.pool_state;
     
-    require!(pool_state.is_native_pool, ErrorCode::NotNativePool);
+    pool_state.check_native_pool()?;
+    pool_state.native_mint_index_checked()?;
     require!(lp_amount > 0, ErrorCode::InvalidInput);
+    check_token_vault(&ctx.accounts.token_vault.key(), &pool_state.key(), ctx.program_id)?;
     
     let total_supply = pool_state.total_amount_minted;
     require!(total_supply > 0, ErrorCode::InsufficientLiquidity);
     
+    // Nothing moves while the global kill-switch is engaged
+    crate::utils::check_not_paused(&ctx.accounts.global_config)?;
+    
+    // Guard against reentrant calls on this pool
+    crate::utils::begin_pool_operation(&ctx.accounts.pool_state.to_account_info())?;
+    
 // msg!("🔴 remove_native_liquidity called");
 // msg!("  lp_amount: {}", lp_amount);
 // msg!("  total_supply: {}", total_supply);
 // msg!("  native_reserve: {}", pool_state.native_reserve);
     
     // Get token vault balance
-    let token_vault_balance = {
-        let token_vault_info = ctx.accounts.token_vault.to_account_info();
-        let token_vault_data = token_vault_info.try_borrow_data()?;
-        use anchor_lang::solana_program::program_pack::Pack;
-        let token_account = spl_token::state::Account::unpack(&token_vault_data)?;
-        token_account.amount
-    };
+    let token_vault_balance = ctx.accounts.token_vault.amount;
     
     // Calculate amounts to return (pro-rata)
     let xnt_amount = (pool_state.native_reserve as u128)
@@ -874,10 +1562,14 @@ pub fn remove_native_liquidity(ctx: Context<RemoveNativeLiquidity>, lp_amount: u
         .and_then(|x| u64::try_from(x).ok())
         .ok_or(ErrorCode::MathOverflow)?;
     
+    require!(xnt_amount >= min_xnt_out, ErrorCode::SlippageExceeded);
+    require!(token_amount >= min_token_out, ErrorCode::SlippageExceeded);
+    
 // msg!("  xnt_to_return: {}", xnt_amount);
 // msg!("  token_to_return: {}", token_amount);
     
     // Burn LP tokens (user is the authority, already a signer)
+    let pool_state_key = pool_state.key();
     let burn_ctx = CpiContext::new(
         ctx.accounts.token_program.to_account_info(),
         token::Burn {
@@ -886,10 +1578,32 @@ pub fn remove_native_liquidity(ctx: Context<RemoveNativeLiquidity>, lp_amount: u
             authority: ctx.accounts.user.to_account_info(),
         },
     );
+    let lp_authority_seeds = &[
+        b"authority",
+        pool_state_key.as_ref(),
+        &[ctx.bumps.pool_authority],
+    ];
+    if pool_state.lp_soulbound {
+        crate::utils::thaw_lp_account(
+            ctx.accounts.user_lp_account.to_account_info(),
+            ctx.accounts.lp_mint.to_account_info(),
+            ctx.accounts.pool_authority.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            &[&lp_authority_seeds[..]],
+        )?;
+    }
     token::burn(burn_ctx, lp_amount)?;
+    if pool_state.lp_soulbound {
+        crate::utils::freeze_lp_account(
+            ctx.accounts.user_lp_account.to_account_info(),
+            ctx.accounts.lp_mint.to_account_info(),
+            ctx.accounts.pool_authority.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            &[&lp_authority_seeds[..]],
+        )?;
+    }
     
     // Transfer native XNT back to user using System Program CPI (raw invoke_signed)
-    let pool_state_key = pool_state.key();
     let authority_seeds = &[
         b"pool_pda",
         pool_state_key.as_ref(),
@@ -915,8 +1629,11 @@ pub fn remove_native_liquidity(ctx: Context<RemoveNativeLiquidity>, lp_amount: u
     )?;
     
     // Transfer SPL tokens back to user (detect Token vs Token2022)
-    let token_vault_owner = ctx.accounts.token_vault.to_account_info().owner;
-    let is_token_2022 = *token_vault_owner == spl_token_2022::ID;
+    let token_program = crate::utils::token_program_for(
+        ctx.accounts.token_vault.to_account_info().owner,
+        &ctx.accounts.token_program.to_account_info(),
+        &ctx.accounts.token_2022_program.to_account_info(),
+    )?;
     
     // Use pool_authority seeds for token transfers (not pool_pda seeds)
     let authority_seeds_for_tokens = &[
@@ -926,38 +1643,14 @@ pub fn remove_native_liquidity(ctx: Context<RemoveNativeLiquidity>, lp_amount: u
     ];
     let signer_seeds_for_tokens = &[&authority_seeds_for_tokens[..]];
     
-    if is_token_2022 {
-        let transfer_ix = spl_token_2022::instruction::transfer(
-            &spl_token_2022::ID,
-            ctx.accounts.token_vault.to_account_info().key,
-            ctx.accounts.user_token_account.to_account_info().key,
-            ctx.accounts.pool_authority.to_account_info().key,
-            &[],
-            token_amount,
-        )?;
-        
-        anchor_lang::solana_program::program::invoke_signed(
-            &transfer_ix,
-            &[
-                ctx.accounts.token_vault.to_account_info(),
-                ctx.accounts.user_token_account.to_account_info(),
-                ctx.accounts.pool_authority.to_account_info(),
-                ctx.accounts.token_2022_program.to_account_info(),
-            ],
-            signer_seeds_for_tokens, // Use pool_authority seeds, not pool_pda seeds!
-        )?;
-    } else {
-        let transfer_ctx = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            token::Transfer {
-                from: ctx.accounts.token_vault.to_account_info(),
-                to: ctx.accounts.user_token_account.to_account_info(),
-                authority: ctx.accounts.pool_authority.to_account_info(),
-            },
-            signer_seeds_for_tokens, // Use pool_authority seeds, not pool_pda seeds!
-        );
-        token::transfer(transfer_ctx, token_amount)?;
-    }
+    crate::utils::transfer_tokens_signed(
+        ctx.accounts.token_vault.to_account_info(),
+        ctx.accounts.user_token_account.to_account_info(),
+        ctx.accounts.pool_authority.to_account_info(),
+        token_program,
+        token_amount,
+        signer_seeds_for_tokens,
+    )?;
     
     // Update pool state with manual serialization
     let new_native_reserve = pool_state.native_reserve
@@ -971,13 +1664,15 @@ pub fn remove_native_liquidity(ctx: Context<RemoveNativeLiquidity>, lp_amount: u
         let pool_state_info = ctx.accounts.pool_state.to_account_info();
         let mut data = pool_state_info.try_borrow_mut_data()?;
         
-        data[8..16].copy_from_slice(&new_total_minted.to_le_bytes());
-        data[68..76].copy_from_slice(&new_native_reserve.to_le_bytes());
+        PoolState::write_total_amount_minted(&mut data, new_total_minted)?;
+        PoolState::write_native_reserve(&mut data, new_native_reserve)?;
     }
     
     ctx.accounts.pool_state.native_reserve = new_native_reserve;
     ctx.accounts.pool_state.total_amount_minted = new_total_minted;
     
+    crate::utils::end_pool_operation(&ctx.accounts.pool_state.to_account_info())?;
+    
 // msg!("✅ Removed native liquidity: {} LP → {} XNT + {} tokens", lp_amount, xnt_amount, token_amount);
 // msg!("   native_reserve updated to: {}", new_native_reserve);
     
@@ -1001,15 +1696,13 @@ pub struct RemoveNativeLiquidity<'info> {
     )]
     pub pool_pda: UncheckedAccount<'info>,
     
-    /// Token vault
-    /// CHECK: We manually verify this is a valid token account
+    /// Token vault - can be Token or Token2022
     #[account(mut)]
-    pub token_vault: UncheckedAccount<'info>,
+    pub token_vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
     
-    /// User's token account
-    /// CHECK: We manually verify this is a valid token account
+    /// User's token account - can be Token or Token2022
     #[account(mut)]
-    pub user_token_account: UncheckedAccount<'info>,
+    pub user_token_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
     
     #[account(mut)]
     pub lp_mint: Account<'info, Mint>,
@@ -1030,23 +1723,26 @@ pub struct RemoveNativeLiquidity<'info> {
     /// CHECK: Token-2022 program
     pub token_2022_program: UncheckedAccount<'info>,
     pub system_program: Program<'info, System>,
+    
+    /// Program-wide GlobalConfig PDA - swaps and liquidity fail while its kill-switch is engaged
+    /// CHECK: Uninitialized until initialize_global_config (nothing is paused then) - read in handler
+    #[account(seeds = [b"global_config"], bump)]
+    pub global_config: UncheckedAccount<'info>,
 }
 
 pub fn recover_stuck_native_xnt(ctx: Context<RecoverStuckNativeXnt>) -> Result<()> {
     let pool_state = &ctx.accounts.pool_state;
     let pool_pda_info = ctx.accounts.pool_pda.to_account_info();
     
-    require!(pool_state.is_native_pool, ErrorCode::NotNativePool);
+    pool_state.check_native_pool()?;
     require!(pool_state.total_amount_minted == 0, ErrorCode::InvalidInput);
     
 // msg!("🔴 Recovering stuck native XNT");
 // msg!("   Pool PDA lamports: {}", pool_pda_info.lamports());
 // msg!("   Total LP supply: {}", pool_state.total_amount_minted);
     
-    // Calculate rent-exempt minimum for pool_state account (not pool_pda)
-    let rent = Rent::get()?;
-    let pool_state_data_len = ctx.accounts.pool_state.to_account_info().data_len();
-    let rent_minimum = rent.minimum_balance(pool_state_data_len);
+    // pool_pda keeps its rent floor
+    let rent_minimum = pool_pda_rent_floor()?;
     
     // Get all lamports except rent
     let total_lamports = pool_pda_info.lamports();
@@ -1108,17 +1804,41 @@ pub struct RecoverStuckNativeXnt<'info> {
     pub system_program: Program<'info, System>,
 }
 
-pub fn reconcile_native_reserve(ctx: Context<ReconcileNativeReserve>) -> Result<()> {
+/// Most lamports reconcile_native_reserve may add to native_reserve without the admin,
+/// in total across calls since the last admin-approved reconcile - enough to absorb
+/// rounding drift, far too little for a donation to move LP value
+pub const RECONCILE_MAX_UNAUTHORIZED_INCREASE: u64 = 1_000;
+
+/// Account for a reconcile raising native_reserve by `increase`
+/// An admin-approved reconcile resets the running total; otherwise the increase is added
+/// to it and the total may not exceed RECONCILE_MAX_UNAUTHORIZED_INCREASE, so a donation
+/// can't be folded in a little at a time
+fn record_reserve_increase(pool_state: &mut PoolState, increase: u64, approved: bool) -> Result<()> {
+    if approved {
+        pool_state.unapproved_reserve_increase = 0;
+        return Ok(());
+    }
+    let total = pool_state.unapproved_reserve_increase
+        .checked_add(increase)
+        .ok_or(ErrorCode::MathOverflow)?;
+    require!(total <= RECONCILE_MAX_UNAUTHORIZED_INCREASE, ErrorCode::Unauthorized);
+    pool_state.unapproved_reserve_increase = total;
+    Ok(())
+}
+
+/// Set native_reserve to pool_pda's lamports above the rent reserve
+/// Anyone may lower it or raise it by up to RECONCILE_MAX_UNAUTHORIZED_INCREASE in total
+/// between admin-approved reconciles; more would fold donated lamports into LP value, so
+/// it needs the admin to sign and pass `allow_increase`
+pub fn reconcile_native_reserve(ctx: Context<ReconcileNativeReserve>, allow_increase: bool) -> Result<()> {
     let pool_state = &mut ctx.accounts.pool_state;
     let pool_pda_info = ctx.accounts.pool_pda.to_account_info();
     
-    require!(pool_state.is_native_pool, ErrorCode::NotNativePool);
+    pool_state.check_native_pool()?;
     
     // Calculate actual tradeable XNT (total - rent reserve)
-    let rent = Rent::get()?;
-    let data_len = pool_pda_info.data_len();
     let total_lamports = pool_pda_info.lamports();
-    let rent_minimum = rent.minimum_balance(data_len);
+    let rent_minimum = pool_pda_rent_floor()?;
     
 // msg!("🔍 Reconcile debug:");
 // msg!("   Pool PDA data_len: {} bytes", data_len);
@@ -1138,6 +1858,11 @@ pub fn reconcile_native_reserve(ctx: Context<ReconcileNativeReserve>) -> Result<
 //             (actual_tradeable as i128 - pool_state.native_reserve as i128).abs());
     }
     
+    let increase = actual_tradeable.saturating_sub(pool_state.native_reserve);
+    let approved = allow_increase
+        && ctx.accounts.admin.as_ref().is_some_and(|admin| admin.key() == pool_state.admin);
+    record_reserve_increase(pool_state, increase, approved)?;
+    
     // Update to actual balance
     pool_state.native_reserve = actual_tradeable;
     
@@ -1158,27 +1883,244 @@ pub struct ReconcileNativeReserve<'info> {
         bump
     )]
     pub pool_pda: UncheckedAccount<'info>,
+    
+    /// Pool admin - only required to raise native_reserve by more than RECONCILE_MAX_UNAUTHORIZED_INCREASE
+    /// in total since the last reconcile it approved
+    pub admin: Option<Signer<'info>>,
+}
+
+/// Sweep XNT in pool_pda above native_reserve and the rent reserve to the treasury (admin only)
+/// Only native pools track their XNT reserve separately from the account balance;
+/// reconcile_native_reserve (with allow_increase) is the alternative that credits the surplus to LPs instead
+pub fn sweep_dust(ctx: Context<SweepDust>) -> Result<()> {
+    let pool_state = &ctx.accounts.pool_state;
+    
+    require!(pool_state.admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+    pool_state.check_native_pool()?;
+    require!(pool_state.protocol_treasury != Pubkey::default(), ErrorCode::InvalidTreasury);
+    require!(ctx.accounts.protocol_treasury.key() == pool_state.protocol_treasury, ErrorCode::InvalidTreasury);
+    
+    let pool_pda_info = ctx.accounts.pool_pda.to_account_info();
+    let rent_minimum = pool_pda_rent_floor()?;
+    let dust = pool_pda_info.lamports()
+        .checked_sub(rent_minimum)
+        .and_then(|tradeable| tradeable.checked_sub(pool_state.native_reserve))
+        .ok_or(ErrorCode::InsufficientRentReserve)?;
+    require!(dust > 0, ErrorCode::InvalidInput);
+    
+    let pool_state_key = pool_state.key();
+    let authority_seeds = &[
+        b"pool_pda",
+        pool_state_key.as_ref(),
+        &[ctx.bumps.pool_pda],
+    ];
+    let signer_seeds = &[&authority_seeds[..]];
+    
+    let transfer_ix = anchor_lang::solana_program::system_instruction::transfer(
+        ctx.accounts.pool_pda.key,
+        ctx.accounts.protocol_treasury.key,
+        dust,
+    );
+    
+    anchor_lang::solana_program::program::invoke_signed(
+        &transfer_ix,
+        &[
+            ctx.accounts.pool_pda.to_account_info(),
+            ctx.accounts.protocol_treasury.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+        ],
+        signer_seeds,
+    )?;
+    
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SweepDust<'info> {
+    pub admin: Signer<'info>,
+    
+    pub pool_state: Account<'info, PoolState>,
+    
+    /// Pool PDA that holds native XNT
+    /// CHECK: This is a PDA
+    #[account(
+        mut,
+        seeds = [b"pool_pda", pool_state.key().as_ref()],
+        bump
+    )]
+    pub pool_pda: UncheckedAccount<'info>,
+    
+    /// Treasury wallet receiving the dust
+    /// CHECK: Must equal pool_state.protocol_treasury, verified in handler
+    #[account(mut)]
+    pub protocol_treasury: UncheckedAccount<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+/// Reclaim the reserves backing the locked MINIMUM_LIQUIDITY of a wound-down native
+/// pool (admin only). The pool must be closed (swaps and deposits disabled) with only
+/// the locked LP left outstanding. Its XNT reserve and token vault go to the pool's
+/// emergency_recovery wallet, the locked LP is burned and total_amount_minted zeroed.
+pub fn reclaim_minimum_liquidity(ctx: Context<ReclaimMinimumLiquidity>) -> Result<()> {
+    let pool_state = &ctx.accounts.pool_state;
+    
+    require!(pool_state.admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+    pool_state.check_native_pool()?;
+    require!(!pool_state.swaps_enabled && !pool_state.deposits_enabled, ErrorCode::PoolNotWoundDown);
+    require!(pool_state.total_amount_minted == MINIMUM_LIQUIDITY, ErrorCode::PoolNotWoundDown);
+    require!(pool_state.emergency_recovery != Pubkey::default(), ErrorCode::EmergencyRecoveryNotSet);
+    require!(pool_state.emergency_recovery == ctx.accounts.emergency_recovery.key(), ErrorCode::Unauthorized);
+    require!(
+        ctx.accounts.recovery_token_account.owner == ctx.accounts.emergency_recovery.key(),
+        ErrorCode::Unauthorized
+    );
+    
+    // Guard against reentrant calls on this pool
+    crate::utils::begin_pool_operation(&ctx.accounts.pool_state.to_account_info())?;
+    
+    let pool_state_key = ctx.accounts.pool_state.key();
+    let xnt_amount = ctx.accounts.pool_state.native_reserve;
+    
+    // native_reserve excludes pool_pda's rent reserve, so the PDA stays rent-exempt
+    if xnt_amount > 0 {
+        let pool_pda_seeds = &[b"pool_pda", pool_state_key.as_ref(), &[ctx.bumps.pool_pda]];
+        let transfer_ix = anchor_lang::solana_program::system_instruction::transfer(
+            ctx.accounts.pool_pda.key,
+            ctx.accounts.emergency_recovery.key,
+            xnt_amount,
+        );
+        anchor_lang::solana_program::program::invoke_signed(
+            &transfer_ix,
+            &[
+                ctx.accounts.pool_pda.to_account_info(),
+                ctx.accounts.emergency_recovery.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+            &[pool_pda_seeds],
+        )?;
+    }
+    
+    let token_amount = ctx.accounts.token_vault.amount;
+    if token_amount > 0 {
+        let token_program = crate::utils::token_program_for(
+            ctx.accounts.token_vault.to_account_info().owner,
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.token_2022_program.to_account_info(),
+        )?;
+        let pda_sign = &[b"authority", pool_state_key.as_ref(), &[ctx.bumps.pool_authority]];
+        crate::utils::transfer_tokens_signed(
+            ctx.accounts.token_vault.to_account_info(),
+            ctx.accounts.recovery_token_account.to_account_info(),
+            ctx.accounts.pool_authority.to_account_info(),
+            token_program,
+            token_amount,
+            &[pda_sign],
+        )?;
+    }
+    
+    // The locked LP no longer backs anything - burn it so the LP supply matches
+    let locked_owner_seeds = &[
+        LOCKED_LIQUIDITY_SEED,
+        pool_state_key.as_ref(),
+        &[ctx.bumps.locked_liquidity_owner],
+    ];
+    token::burn(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Burn {
+                mint: ctx.accounts.lp_mint.to_account_info(),
+                from: ctx.accounts.locked_lp_account.to_account_info(),
+                authority: ctx.accounts.locked_liquidity_owner.to_account_info(),
+            },
+        ).with_signer(&[locked_owner_seeds]),
+        ctx.accounts.locked_lp_account.amount,
+    )?;
+    
+    {
+        let pool_state_info = ctx.accounts.pool_state.to_account_info();
+        let mut data = pool_state_info.try_borrow_mut_data()?;
+        PoolState::write_native_reserve(&mut data, 0)?;
+        PoolState::write_total_amount_minted(&mut data, 0)?;
+    }
+    ctx.accounts.pool_state.native_reserve = 0;
+    ctx.accounts.pool_state.total_amount_minted = 0;
+    
+    crate::utils::end_pool_operation(&ctx.accounts.pool_state.to_account_info())?;
+    
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ReclaimMinimumLiquidity<'info> {
+    pub admin: Signer<'info>,
+    
+    #[account(mut)]
+    pub pool_state: Account<'info, PoolState>,
+    
+    /// Pool PDA that holds native XNT
+    /// CHECK: This is a PDA
+    #[account(mut, seeds = [b"pool_pda", pool_state.key().as_ref()], bump)]
+    pub pool_pda: UncheckedAccount<'info>,
+    
+    /// CHECK: This is a PDA used for signing
+    #[account(seeds = [b"authority", pool_state.key().as_ref()], bump)]
+    pub pool_authority: UncheckedAccount<'info>,
+    
+    /// Token vault - can be Token or Token2022
+    #[account(mut, seeds = [b"vault", pool_state.key().as_ref()], bump)]
+    pub token_vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    
+    /// The pool's LP mint, resolved like sync_lp_supply does (an adopted lp_mint, else the PDA)
+    #[account(mut, address = pool_state.lp_mint_address(&pool_state.key(), &crate::ID) @ ErrorCode::MintMismatch)]
+    pub lp_mint: Account<'info, Mint>,
+    
+    /// CHECK: PDA owning the locked minimum liquidity
+    #[account(seeds = [LOCKED_LIQUIDITY_SEED, pool_state.key().as_ref()], bump)]
+    pub locked_liquidity_owner: UncheckedAccount<'info>,
+    
+    #[account(mut, token::mint = lp_mint, token::authority = locked_liquidity_owner)]
+    pub locked_lp_account: Account<'info, TokenAccount>,
+    
+    /// CHECK: Must equal pool_state.emergency_recovery, verified in handler
+    #[account(mut)]
+    pub emergency_recovery: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub recovery_token_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    
+    pub token_program: Program<'info, Token>,
+    /// CHECK: Token-2022 program
+    pub token_2022_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
 }
 
 /// Emergency pause for native pool (admin only)
+/// Turns off swaps and deposits in one call - set_swaps_enabled / set_deposits_enabled
+/// turn them back on. Withdrawals stay open
 pub fn pause_native_pool(ctx: Context<PauseNativePool>) -> Result<()> {
     let pool_state = &mut ctx.accounts.pool_state;
     
-    require!(pool_state.is_native_pool, ErrorCode::NotNativePool);
-    
-    // TODO: Add admin check when admin system is implemented
-    // For now, anyone can call (will add proper admin in production)
+    pool_state.check_native_pool()?;
+    require!(pool_state.admin == ctx.accounts.authority.key(), ErrorCode::Unauthorized);
     
-// msg!("🛑 Native pool PAUSED!");
+    let old_swaps_enabled = pool_state.swaps_enabled;
+    let old_deposits_enabled = pool_state.deposits_enabled;
+    pool_state.swaps_enabled = false;
+    pool_state.deposits_enabled = false;
     
-    // Note: We'd need to add is_paused field to PoolState
-    // For now, just log. Full implementation requires state update.
+    crate::instructions::admin::emit_pause_change(
+        pool_state,
+        ctx.accounts.authority.key(),
+        old_swaps_enabled,
+        old_deposits_enabled,
+    );
     
     Ok(())
 }
 
 #[derive(Accounts)]
 pub struct PauseNativePool<'info> {
+    /// Must be the pool's admin
     #[account(mut)]
     pub authority: Signer<'info>,
     
@@ -1186,23 +2128,822 @@ pub struct PauseNativePool<'info> {
     pub pool_state: Account<'info, PoolState>,
 }
 
-// Integer square root helper
-trait IntegerSquareRoot {
-    fn integer_sqrt(self) -> Self;
-}
 
-impl IntegerSquareRoot for u128 {
-    fn integer_sqrt(self) -> Self {
-        if self == 0 {
-            return 0;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{
+        empty_account, install_stubs, mint_account, pda, program, program_account, set_token_amount, signer,
+        take_system_transfers, token_account, Info, TestAccount,
+    };
+
+    fn check_legacy(data: &mut [u8], native_mint_index: u8) -> Result<()> {
+        let key = Pubkey::new_unique();
+        let mut lamports = 1_000_000;
+        let owner = if data.is_empty() { anchor_lang::solana_program::system_program::ID } else { crate::ID };
+        let info = AccountInfo::new(&key, false, false, &mut lamports, data, &owner, false, 0);
+        check_legacy_native_pool(&info, native_mint_index)
+    }
+
+    fn legacy_native_pool(native_mint_index: u8) -> Vec<u8> {
+        let mut data = Vec::new();
+        PoolState { is_native_pool: true, pool_kind: POOL_KIND_NATIVE, native_mint_index, ..Default::default() }
+            .try_serialize(&mut data)
+            .unwrap();
+        data
+    }
+
+    #[test]
+    fn two_native_pools_per_token_mint() {
+        // No legacy pool: one pool per XNT side
+        assert!(check_legacy(&mut [], 0).is_ok());
+        assert!(check_legacy(&mut [], 1).is_ok());
+    }
+
+    #[test]
+    fn legacy_pool_blocks_only_its_own_side() {
+        assert_eq!(
+            check_legacy(&mut legacy_native_pool(0), 0).unwrap_err(),
+            ErrorCode::PoolAlreadyRegistered.into()
+        );
+        assert!(check_legacy(&mut legacy_native_pool(0), 1).is_ok());
+        assert!(check_legacy(&mut legacy_native_pool(1), 0).is_ok());
+    }
+
+    #[test]
+    fn large_donation_needs_admin() {
+        let mut pool = PoolState { is_native_pool: true, ..Default::default() };
+        assert_eq!(
+            record_reserve_increase(&mut pool, 1_000_000_000, false).unwrap_err(),
+            ErrorCode::Unauthorized.into()
+        );
+        assert_eq!(pool.unapproved_reserve_increase, 0);
+        assert!(record_reserve_increase(&mut pool, 1_000_000_000, true).is_ok());
+    }
+
+    #[test]
+    fn donation_cannot_be_folded_in_piecemeal() {
+        let mut pool = PoolState { is_native_pool: true, ..Default::default() };
+        for _ in 0..4 {
+            assert!(record_reserve_increase(&mut pool, RECONCILE_MAX_UNAUTHORIZED_INCREASE / 4, false).is_ok());
         }
-        let mut x = self;
-        let mut y = (x + 1) / 2;
-        while y < x {
-            x = y;
-            y = (x + self / x) / 2;
+        assert_eq!(pool.unapproved_reserve_increase, RECONCILE_MAX_UNAUTHORIZED_INCREASE);
+        assert_eq!(
+            record_reserve_increase(&mut pool, 1, false).unwrap_err(),
+            ErrorCode::Unauthorized.into()
+        );
+        // Lowering the reserve is always allowed
+        assert!(record_reserve_increase(&mut pool, 0, false).is_ok());
+
+        // The admin approving a reconcile starts a fresh allowance
+        assert!(record_reserve_increase(&mut pool, 1, true).is_ok());
+        assert_eq!(pool.unapproved_reserve_increase, 0);
+        assert!(record_reserve_increase(&mut pool, RECONCILE_MAX_UNAUTHORIZED_INCREASE, false).is_ok());
+    }
+
+    fn fee_pool(protocol_fee_bps: u16) -> PoolState {
+        PoolState {
+            is_native_pool: true,
+            native_reserve: 1_000_000_000,
+            protocol_treasury: Pubkey::new_unique(),
+            protocol_fee_bps,
+            fee_numerator: 30,
+            fee_denominator: 10000,
+            max_swap_out_bps_of_reserve: 10000,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn swap_input_covers_the_protocol_fee_on_xnt_out() {
+        let pool = fee_pool(100);
+        let amount_in = compute_native_swap_input(&pool, 1_000_000_000, 10_000_000, false, 0).unwrap();
+        let amounts = compute_native_swap(&pool, 1_000_000_000, amount_in, false, 0).unwrap();
+        assert!(amounts.final_amount_out >= 10_000_000);
+        assert!(amounts.protocol_fee_xnt > 0);
+    }
+
+    #[test]
+    fn swap_input_for_an_output_the_fee_swallows_is_unreachable() {
+        // A fee taking the whole XNT output leaves nothing however much goes in
+        let pool = fee_pool(10000);
+        assert_eq!(
+            compute_native_swap_input(&pool, 1_000_000_000, 1_000, false, 0).unwrap_err(),
+            ErrorCode::OutputUnreachable.into()
+        );
+        // So does asking for the whole reserve
+        let pool = fee_pool(100);
+        assert_eq!(
+            compute_native_swap_input(&pool, 1_000_000_000, 999_999_999, false, 0).unwrap_err(),
+            ErrorCode::OutputUnreachable.into()
+        );
+    }
+
+    fn native_pool(admin: Pubkey) -> Info {
+        let pool_state = PoolState {
+            is_native_pool: true,
+            pool_kind: POOL_KIND_NATIVE,
+            admin,
+            native_reserve: 1_000_000_000,
+            fee_numerator: 30,
+            fee_denominator: 10000,
+            swaps_enabled: true,
+            deposits_enabled: true,
+            max_allowed_slippage_bps: 10000,
+            max_swap_out_bps_of_reserve: 10000,
+            ..Default::default()
+        };
+        program_account(Pubkey::new_unique(), &pool_state).info()
+    }
+
+    fn pause(pool_state: Info, authority: Info) -> Result<()> {
+        let mut accounts = PauseNativePool {
+            authority: Signer::try_from(authority).unwrap(),
+            pool_state: Account::try_from(pool_state).unwrap(),
+        };
+        pause_native_pool(Context::new(&crate::ID, &mut accounts, &[], PauseNativePoolBumps::default()))?;
+        accounts.exit(&crate::ID)
+    }
+
+    /// swap_native accounts against a 1e9 token vault of `user_token_account`'s mint,
+    /// paying the pool's own protocol_treasury
+    fn swap_accounts(pool_state: Info, user: Info, user_token_account: Info) -> SwapNative<'static> {
+        let token_mint = TokenAccount::try_deserialize(&mut &user_token_account.try_borrow_data().unwrap()[..]).unwrap().mint;
+        let pool_authority = pda(&[b"authority", pool_state.key.as_ref()]);
+        let protocol_treasury = PoolState::try_deserialize(&mut &pool_state.try_borrow_data().unwrap()[..]).unwrap().protocol_treasury;
+        SwapNative {
+            user: Signer::try_from(user).unwrap(),
+            pool_state: Account::try_from(pool_state).unwrap(),
+            pool_pda: UncheckedAccount::try_from(empty_account(pda(&[b"pool_pda", pool_state.key.as_ref()])).info()),
+            token_vault: Box::new(InterfaceAccount::try_from(
+                token_account(pda(&[b"vault", pool_state.key.as_ref()]), token_mint, pool_authority, 1_000_000_000).info()
+            ).unwrap()),
+            user_token_account: Box::new(InterfaceAccount::try_from(user_token_account).unwrap()),
+            pool_authority: UncheckedAccount::try_from(empty_account(pool_authority).info()),
+            token_program: Program::try_from(program(Token::id()).info()).unwrap(),
+            token_2022_program: UncheckedAccount::try_from(program(spl_token_2022::ID).info()),
+            system_program: Program::try_from(program(System::id()).info()).unwrap(),
+            protocol_treasury: UncheckedAccount::try_from(empty_account(protocol_treasury).info()),
+            global_config: UncheckedAccount::try_from(empty_account(pda(&[b"global_config"])).info()),
+            fee_split: UncheckedAccount::try_from(empty_account(pda(&[b"fee_split", pool_state.key.as_ref()])).info()),
+            user_lp_account: None,
+            lp_mint: None,
+            protocol_lp_account: None,
+            lp_balance_snapshot: None,
+        }
+    }
+
+    /// swap_native of 1_000_000 in - `delivered` stands in for the token output that
+    /// arrives at the user on XNT → Token
+    fn swap(pool_state: Info, is_xnt_to_token: bool, min_amount_out: u64, delivered: u64) -> Result<SwapResult> {
+        install_stubs();
+        let user = signer().info();
+        let user_token_account = token_account(Pubkey::new_unique(), Pubkey::new_unique(), *user.key, 1_000_000_000).info();
+        let mut accounts = swap_accounts(pool_state, user, user_token_account);
+        // The handler re-reads the balance after the (off-chain no-op) transfer
+        set_token_amount(user_token_account, 1_000_000_000 + delivered);
+        swap_native(
+            Context::new(&crate::ID, &mut accounts, &[], SwapNativeBumps::default()),
+            1_000_000,
+            min_amount_out,
+            is_xnt_to_token,
+        )
+    }
+
+    #[test]
+    fn only_the_admin_pauses_a_native_pool() {
+        let admin = signer().info();
+        let pool_state = native_pool(*admin.key);
+        
+        assert_eq!(pause(pool_state, signer().info()).unwrap_err(), ErrorCode::Unauthorized.into());
+        let pool = PoolState::try_deserialize(&mut &pool_state.try_borrow_data().unwrap()[..]).unwrap();
+        assert!(pool.swaps_enabled && pool.deposits_enabled);
+        
+        pause(pool_state, admin).unwrap();
+        let pool = PoolState::try_deserialize(&mut &pool_state.try_borrow_data().unwrap()[..]).unwrap();
+        assert!(!pool.swaps_enabled && !pool.deposits_enabled);
+    }
+
+    #[test]
+    fn paused_native_pool_rejects_swaps_both_ways() {
+        // A failed handler doesn't roll back off-chain, so each direction gets its own pool
+        for is_xnt_to_token in [true, false] {
+            let admin = signer().info();
+            let pool_state = native_pool(*admin.key);
+            pause(pool_state, admin).unwrap();
+            assert_eq!(swap(pool_state, is_xnt_to_token, 0, 0).unwrap_err(), ErrorCode::SwapsDisabled.into());
+        }
+    }
+
+    #[test]
+    fn xnt_to_token_min_amount_out_holds_for_what_arrives() {
+        let pool_state = native_pool(Pubkey::new_unique());
+        let pool = PoolState::try_deserialize(&mut &pool_state.try_borrow_data().unwrap()[..]).unwrap();
+        let quoted = compute_native_swap(&pool, 1_000_000_000, 1_000_000, true, 0).unwrap().final_amount_out;
+        assert_eq!(swap(pool_state, true, quoted, quoted).unwrap().amount_out, quoted);
+        
+        // A transfer fee withheld from the output leaves the user short of min_amount_out
+        let pool_state = native_pool(Pubkey::new_unique());
+        assert_eq!(
+            swap(pool_state, true, quoted, quoted - quoted / 100).unwrap_err(),
+            ErrorCode::SlippageExceeded.into()
+        );
+    }
+
+    #[test]
+    fn native_swap_rejects_a_corrupt_native_mint_index() {
+        let pool_state = native_pool(Pubkey::new_unique());
+        // native_mint_index is the byte before native_reserve
+        pool_state.try_borrow_mut_data().unwrap()[PoolState::NATIVE_RESERVE_OFFSET - 1] = 2;
+        assert_eq!(swap(pool_state, true, 0, 0).unwrap_err(), ErrorCode::InvalidAccountData.into());
+    }
+
+    #[test]
+    fn native_protocol_fee_follows_the_pools_fee_mode() {
+        // 1000 bps: 10% of the XNT notional, or 10% of the 0.3% LP fee on it
+        let notional = PoolState { protocol_fee_mode: crate::state::PROTOCOL_FEE_MODE_NOTIONAL, ..fee_pool(1000) };
+        let lp_fee_share = PoolState { protocol_fee_mode: crate::state::PROTOCOL_FEE_MODE_LP_FEE, ..fee_pool(1000) };
+        for is_xnt_to_token in [true, false] {
+            let xnt_amount = if is_xnt_to_token {
+                10_000_000
+            } else {
+                calculate_swap_output(10_000_000, 1_000_000_000, 1_000_000_000, 30, 10000).unwrap()
+            };
+            let fee = |pool: &PoolState| compute_native_swap(pool, 1_000_000_000, 10_000_000, is_xnt_to_token, 0).unwrap().protocol_fee_xnt;
+            assert_eq!(fee(&notional), xnt_amount / 10);
+            assert_eq!(fee(&lp_fee_share), xnt_amount * 30 / 10000 / 10);
+        }
+    }
+
+    /// remove_native_liquidity against a pool of 1e9 XNT / 1e9 tokens and 1_000_000 LP,
+    /// MINIMUM_LIQUIDITY of it locked
+    fn remove(pool_state: Info, lp_amount: u64, min_xnt_out: u64, min_token_out: u64) -> Result<()> {
+        install_stubs();
+        let token_mint = Pubkey::new_unique();
+        let lp_mint = Pubkey::new_unique();
+        let user = signer().info();
+        let pool_authority = pda(&[b"authority", pool_state.key.as_ref()]);
+        let mut accounts = RemoveNativeLiquidity {
+            user: Signer::try_from(user).unwrap(),
+            pool_state: Account::try_from(pool_state).unwrap(),
+            pool_pda: UncheckedAccount::try_from(empty_account(pda(&[b"pool_pda", pool_state.key.as_ref()])).info()),
+            token_vault: Box::new(InterfaceAccount::try_from(
+                token_account(pda(&[b"vault", pool_state.key.as_ref()]), token_mint, pool_authority, 1_000_000_000).info()
+            ).unwrap()),
+            user_token_account: Box::new(InterfaceAccount::try_from(
+                token_account(Pubkey::new_unique(), token_mint, *user.key, 0).info()
+            ).unwrap()),
+            lp_mint: Account::try_from(mint_account(lp_mint, pool_authority, 1_000_000, 9).info()).unwrap(),
+            user_lp_account: UncheckedAccount::try_from(token_account(Pubkey::new_unique(), lp_mint, *user.key, lp_amount).info()),
+            pool_authority: UncheckedAccount::try_from(empty_account(pool_authority).info()),
+            token_program: Program::try_from(program(Token::id()).info()).unwrap(),
+            token_2022_program: UncheckedAccount::try_from(program(spl_token_2022::ID).info()),
+            system_program: Program::try_from(program(System::id()).info()).unwrap(),
+            global_config: UncheckedAccount::try_from(empty_account(pda(&[b"global_config"])).info()),
+        };
+        withdraw_native_liquidity(
+            Context::new(&crate::ID, &mut accounts, &[], RemoveNativeLiquidityBumps::default()),
+            lp_amount,
+            min_xnt_out,
+            min_token_out,
+        )
+    }
+
+    fn lp_pool() -> Info {
+        let pool_state = native_pool(Pubkey::new_unique());
+        PoolState::write_total_amount_minted(&mut pool_state.try_borrow_mut_data().unwrap(), 1_000_000).unwrap();
+        pool_state
+    }
+
+    #[test]
+    fn native_withdrawal_holds_both_min_outputs_and_leaves_the_locked_minimum() {
+        // Every circulating LP token redeems 1000 XNT and 1000 tokens
+        let circulating = 1_000_000 - MINIMUM_LIQUIDITY;
+        let out = circulating * 1000;
+        assert_eq!(remove(lp_pool(), circulating, out + 1, 0).unwrap_err(), ErrorCode::SlippageExceeded.into());
+        assert_eq!(remove(lp_pool(), circulating, 0, out + 1).unwrap_err(), ErrorCode::SlippageExceeded.into());
+
+        let pool_state = lp_pool();
+        remove(pool_state, circulating, out, out).unwrap();
+        let pool = PoolState::try_deserialize(&mut &pool_state.try_borrow_data().unwrap()[..]).unwrap();
+        assert_eq!(pool.total_amount_minted, MINIMUM_LIQUIDITY);
+        assert_eq!(pool.native_reserve, MINIMUM_LIQUIDITY * 1000);
+    }
+
+    /// add_native_liquidity of 1_000_000 XNT and 1_000_000 tokens - `locked_lp_account`
+    /// maps the pool's key and LP mint to the dead LP account passed, if any
+    fn deposit(pool_state: Info, locked_lp_account: impl Fn(&Pubkey, Pubkey) -> Option<Info>) -> Result<()> {
+        install_stubs();
+        let token_mint = Pubkey::new_unique();
+        let lp_mint = Pubkey::new_unique();
+        let user = signer().info();
+        let pool_authority = pda(&[b"authority", pool_state.key.as_ref()]);
+        let mut accounts = AddNativeLiquidity {
+            user: Signer::try_from(user).unwrap(),
+            pool_state: Account::try_from(pool_state).unwrap(),
+            pool_pda: UncheckedAccount::try_from(empty_account(pda(&[b"pool_pda", pool_state.key.as_ref()])).info()),
+            token_vault: Box::new(InterfaceAccount::try_from(
+                token_account(pda(&[b"vault", pool_state.key.as_ref()]), token_mint, pool_authority, 0).info()
+            ).unwrap()),
+            user_token_account: Box::new(InterfaceAccount::try_from(
+                token_account(Pubkey::new_unique(), token_mint, *user.key, 1_000_000).info()
+            ).unwrap()),
+            lp_mint: Account::try_from(mint_account(lp_mint, pool_authority, 0, 9).info()).unwrap(),
+            user_lp_account: UncheckedAccount::try_from(token_account(Pubkey::new_unique(), lp_mint, *user.key, 0).info()),
+            pool_authority: UncheckedAccount::try_from(empty_account(pool_authority).info()),
+            token_program: Program::try_from(program(Token::id()).info()).unwrap(),
+            token_2022_program: UncheckedAccount::try_from(program(spl_token_2022::ID).info()),
+            system_program: Program::try_from(program(System::id()).info()).unwrap(),
+            global_config: UncheckedAccount::try_from(empty_account(pda(&[b"global_config"])).info()),
+            locked_lp_account: locked_lp_account(pool_state.key, lp_mint)
+                .map(|account| Box::new(InterfaceAccount::try_from(account).unwrap())),
+        };
+        add_native_liquidity(
+            Context::new(&crate::ID, &mut accounts, &[], AddNativeLiquidityBumps::default()),
+            1_000_000,
+            1_000_000,
+            0,
+        )
+    }
+
+    #[test]
+    fn first_native_deposit_locks_the_minimum_in_the_dead_lp_account() {
+        let empty_pool = || {
+            let pool_state = native_pool(Pubkey::new_unique());
+            PoolState::write_native_reserve(&mut pool_state.try_borrow_mut_data().unwrap(), 0).unwrap();
+            pool_state
+        };
+        let dead_owner = |pool_key: &Pubkey| pda(&[LOCKED_LIQUIDITY_SEED, pool_key.as_ref()]);
+        
+        assert_eq!(deposit(empty_pool(), |_, _| None).unwrap_err(), ErrorCode::InvalidInput.into());
+        // The dead account must belong to the pool's LOCKED_LIQUIDITY_SEED PDA and hold its LP mint
+        let someone_elses = |_: &Pubkey, lp_mint| Some(token_account(Pubkey::new_unique(), lp_mint, Pubkey::new_unique(), 0).info());
+        assert_eq!(deposit(empty_pool(), someone_elses).unwrap_err(), ErrorCode::InvalidInput.into());
+        let other_mint = |pool_key: &Pubkey, _| Some(token_account(Pubkey::new_unique(), Pubkey::new_unique(), dead_owner(pool_key), 0).info());
+        assert_eq!(deposit(empty_pool(), other_mint).unwrap_err(), ErrorCode::InvalidInput.into());
+        
+        let pool_state = empty_pool();
+        let dead = |pool_key: &Pubkey, lp_mint| Some(token_account(Pubkey::new_unique(), lp_mint, dead_owner(pool_key), 0).info());
+        deposit(pool_state, dead).unwrap();
+        let pool = PoolState::try_deserialize(&mut &pool_state.try_borrow_data().unwrap()[..]).unwrap();
+        // sqrt(1_000_000 * 1_000_000), MINIMUM_LIQUIDITY of it locked
+        assert_eq!(pool.total_amount_minted, 1_000_000);
+        assert_eq!(pool.native_reserve, 1_000_000);
+    }
+
+    /// A new pool of `token_mint` with XNT as token0, as `init` leaves its state account
+    fn new_pool_state(token_mint: Info) -> Info {
+        program_account(pda(&[b"pool", token_mint.key.as_ref(), &[0]]), &PoolState::default()).info()
+    }
+
+    /// initialize_native_pool accounts creating `pool_state` (see new_pool_state)
+    fn new_pool_accounts(payer: Info, token_mint: Info, pool_state: Info) -> InitializeNativePool<'static> {
+        let pool_key = *pool_state.key;
+        let pool_authority = pda(&[b"authority", pool_key.as_ref()]);
+        // Rent as the sysvar account serializes it
+        let mut rent = Rent::default().lamports_per_byte_year.to_le_bytes().to_vec();
+        rent.extend(Rent::default().exemption_threshold.to_le_bytes());
+        rent.push(Rent::default().burn_percent);
+        let registry_key = pda(&[b"registry", NATIVE_MINT_PLACEHOLDER.as_ref(), token_mint.key.as_ref()]);
+        InitializeNativePool {
+            payer: Signer::try_from(payer).unwrap(),
+            pool_state: Account::try_from(pool_state).unwrap(),
+            token_mint: UncheckedAccount::try_from(token_mint),
+            legacy_pool_state: UncheckedAccount::try_from(empty_account(pda(&[b"pool", token_mint.key.as_ref()])).info()),
+            token_vault: UncheckedAccount::try_from(
+                token_account(pda(&[b"vault", pool_key.as_ref()]), *token_mint.key, pool_authority, 0).info()
+            ),
+            lp_mint: UncheckedAccount::try_from(empty_account(pda(&[b"lp_mint", pool_key.as_ref()])).info()),
+            pool_authority: UncheckedAccount::try_from(empty_account(pool_authority).info()),
+            // Sized as allocate leaves it - the off-chain system program CPIs don't run
+            registry_entry: UncheckedAccount::try_from(
+                TestAccount::new(registry_key, System::id(), vec![0; 8 + crate::state::PoolRegistryEntry::LEN]).info()
+            ),
+            token_program: Program::try_from(program(Token::id()).info()).unwrap(),
+            token_2022_program: UncheckedAccount::try_from(program(spl_token_2022::ID).info()),
+            system_program: Program::try_from(program(System::id()).info()).unwrap(),
+            rent: Sysvar::from_account_info(
+                TestAccount::new(anchor_lang::solana_program::sysvar::rent::ID, anchor_lang::solana_program::sysvar::ID, rent).info()
+            ).unwrap(),
+            emergency_recovery: None,
+            pool_pda: None,
         }
-        x
     }
-}
 
+    /// initialize_native_pool_with_liquidity for a new Token mint, XNT as token0 - the
+    /// created pool's state account, if the instruction succeeded
+    fn create_with_liquidity(xnt_amount: u64, token_amount: u64, min_lp_out: u64) -> Result<Info> {
+        install_stubs();
+        let payer = signer().info();
+        let token_mint = mint_account(Pubkey::new_unique(), Pubkey::new_unique(), 1_000_000_000, 6).info();
+        let pool_state = new_pool_state(token_mint);
+        let pool_key = *pool_state.key;
+        let mut accounts = InitializeNativePoolWithLiquidity {
+            pool: new_pool_accounts(payer, token_mint, pool_state),
+            pool_pda: UncheckedAccount::try_from(empty_account(pda(&[b"pool_pda", pool_key.as_ref()])).info()),
+            user_token_account: Box::new(InterfaceAccount::try_from(
+                token_account(Pubkey::new_unique(), *token_mint.key, *payer.key, token_amount).info()
+            ).unwrap()),
+            user_lp_account: UncheckedAccount::try_from(empty_account(Pubkey::new_unique()).info()),
+            locked_liquidity_owner: UncheckedAccount::try_from(empty_account(pda(&[LOCKED_LIQUIDITY_SEED, pool_key.as_ref()])).info()),
+            locked_lp_account: UncheckedAccount::try_from(empty_account(Pubkey::new_unique()).info()),
+            token_program: Program::try_from(program(Token::id()).info()).unwrap(),
+            associated_token_program: Program::try_from(program(AssociatedToken::id()).info()).unwrap(),
+            system_program: Program::try_from(program(System::id()).info()).unwrap(),
+        };
+        initialize_native_pool_with_liquidity(
+            Context::new(&crate::ID, &mut accounts, &[], InitializeNativePoolWithLiquidityBumps::default()),
+            30,
+            10000,
+            Pubkey::new_unique(),
+            0,
+            0,
+            xnt_amount,
+            token_amount,
+            min_lp_out,
+            None,
+            None,
+        )?;
+        accounts.pool.pool_state.exit(&crate::ID)?;
+        Ok(pool_state)
+    }
+
+    #[test]
+    fn pool_created_with_liquidity_starts_with_its_first_deposit() {
+        assert_eq!(create_with_liquidity(0, 1_000_000, 0).unwrap_err(), ErrorCode::InvalidInput.into());
+        assert_eq!(
+            create_with_liquidity(1_000_000, 1_000_000, 1_000_000).unwrap_err(),
+            ErrorCode::SlippageExceeded.into()
+        );
+        
+        let pool_state = create_with_liquidity(4_000_000, 1_000_000, 2_000_000 - MINIMUM_LIQUIDITY).unwrap();
+        let pool = PoolState::try_deserialize(&mut &pool_state.try_borrow_data().unwrap()[..]).unwrap();
+        assert!(pool.is_native_pool && pool.deposits_enabled);
+        assert_eq!(pool.native_reserve, 4_000_000);
+        // sqrt(4_000_000 * 1_000_000), MINIMUM_LIQUIDITY of it locked
+        assert_eq!(pool.total_amount_minted, 2_000_000);
+    }
+
+    /// sweep_dust by `admin` to `protocol_treasury`, pool_pda holding `pool_pda_lamports`
+    fn sweep(pool_state: Info, admin: Info, protocol_treasury: Pubkey, pool_pda_lamports: u64) -> Result<()> {
+        install_stubs();
+        let pool_pda = TestAccount {
+            lamports: pool_pda_lamports,
+            ..empty_account(pda(&[b"pool_pda", pool_state.key.as_ref()]))
+        };
+        let mut accounts = SweepDust {
+            admin: Signer::try_from(admin).unwrap(),
+            pool_state: Account::try_from(pool_state).unwrap(),
+            pool_pda: UncheckedAccount::try_from(pool_pda.info()),
+            protocol_treasury: UncheckedAccount::try_from(empty_account(protocol_treasury).info()),
+            system_program: Program::try_from(program(System::id()).info()).unwrap(),
+        };
+        sweep_dust(Context::new(&crate::ID, &mut accounts, &[], SweepDustBumps::default()))
+    }
+
+    #[test]
+    fn only_the_admin_sweeps_dust_and_only_to_the_treasury() {
+        let admin = signer().info();
+        let treasury = Pubkey::new_unique();
+        let pool_state = program_account(Pubkey::new_unique(), &PoolState {
+            is_native_pool: true,
+            pool_kind: POOL_KIND_NATIVE,
+            admin: *admin.key,
+            protocol_treasury: treasury,
+            native_reserve: 1_000_000_000,
+            ..Default::default()
+        }).info();
+        // Rent reserve and the 1e9 native_reserve, plus 5 dust
+        let rent_floor = Rent::default().minimum_balance(0);
+        let with_dust = rent_floor + 1_000_000_000 + 5;
+        
+        assert_eq!(sweep(pool_state, signer().info(), treasury, with_dust).unwrap_err(), ErrorCode::Unauthorized.into());
+        assert_eq!(
+            sweep(pool_state, admin, Pubkey::new_unique(), with_dust).unwrap_err(),
+            ErrorCode::InvalidTreasury.into()
+        );
+        // Nothing above the reserves to sweep, or less than them
+        assert_eq!(sweep(pool_state, admin, treasury, with_dust - 5).unwrap_err(), ErrorCode::InvalidInput.into());
+        assert_eq!(
+            sweep(pool_state, admin, treasury, with_dust - 6).unwrap_err(),
+            ErrorCode::InsufficientRentReserve.into()
+        );
+        sweep(pool_state, admin, treasury, with_dust).unwrap();
+    }
+
+    #[test]
+    fn pool_pda_is_topped_up_to_its_rent_floor() {
+        install_stubs();
+        let payer = signer().info();
+        let system_program = program(System::id()).info();
+        let rent_floor = Rent::default().minimum_balance(0);
+        let pool_pda = |lamports| TestAccount { lamports, ..empty_account(Pubkey::new_unique()) }.info();
+        
+        take_system_transfers();
+        for (lamports, shortfall) in [(0, rent_floor), (rent_floor - 10, 10)] {
+            let pool_pda = pool_pda(lamports);
+            fund_pool_pda_rent(payer, pool_pda, system_program).unwrap();
+            assert_eq!(take_system_transfers(), vec![(*payer.key, *pool_pda.key, shortfall)]);
+        }
+        // Already rent-exempt, or holding XNT on top
+        for lamports in [rent_floor, rent_floor + 1_000] {
+            fund_pool_pda_rent(payer, pool_pda(lamports), system_program).unwrap();
+            assert!(take_system_transfers().is_empty());
+        }
+    }
+
+    #[test]
+    fn pool_pda_passed_to_initialize_is_made_rent_exempt() {
+        install_stubs();
+        let rent_floor = Rent::default().minimum_balance(0);
+        for with_pool_pda in [false, true] {
+            let payer = signer().info();
+            let token_mint = mint_account(Pubkey::new_unique(), Pubkey::new_unique(), 1_000_000_000, 6).info();
+            let pool_state = new_pool_state(token_mint);
+            let pool_pda = TestAccount { lamports: 0, ..empty_account(pda(&[b"pool_pda", pool_state.key.as_ref()])) }.info();
+            let mut accounts = new_pool_accounts(payer, token_mint, pool_state);
+            if with_pool_pda {
+                accounts.pool_pda = Some(UncheckedAccount::try_from(pool_pda));
+            }
+            take_system_transfers();
+            initialize_native_pool(
+                Context::new(&crate::ID, &mut accounts, &[], InitializeNativePoolBumps::default()),
+                30,
+                10000,
+                Pubkey::new_unique(),
+                0,
+                0,
+                None,
+                None,
+            ).unwrap();
+            let funded: Vec<_> = take_system_transfers().into_iter().filter(|(_, to, _)| to == pool_pda.key).collect();
+            if with_pool_pda {
+                assert_eq!(funded, vec![(*payer.key, *pool_pda.key, rent_floor)]);
+            } else {
+                // Left to the first deposit
+                assert!(funded.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn pool_pda_rent_floor_is_that_of_a_dataless_account() {
+        install_stubs();
+        assert_eq!(pool_pda_rent_floor().unwrap(), Rent::default().minimum_balance(POOL_PDA_DATA_LEN));
+        // Not the pool_state account's floor, which native rent math once used
+        assert!(pool_pda_rent_floor().unwrap() < Rent::default().minimum_balance(8 + PoolState::LEN));
+        
+        // A pool_pda with data could never send XNT out, so it is never funded
+        let pool_pda = TestAccount::new(Pubkey::new_unique(), System::id(), vec![0; 8]).info();
+        assert_eq!(
+            fund_pool_pda_rent(signer().info(), pool_pda, program(System::id()).info()).unwrap_err(),
+            ErrorCode::InvalidAccountData.into()
+        );
+    }
+
+    #[test]
+    fn pre_funded_token_vault_is_taken_over_not_rejected() {
+        install_stubs();
+        let vault_rent = Rent::default().minimum_balance(<anchor_spl::token::spl_token::state::Account as anchor_lang::solana_program::program_pack::Pack>::LEN);
+        let init = |vault_lamports: u64, vault_owner: Pubkey| {
+            let payer = signer().info();
+            let token_mint = mint_account(Pubkey::new_unique(), Pubkey::new_unique(), 1_000_000_000, 6).info();
+            let pool_state = new_pool_state(token_mint);
+            let vault = TestAccount {
+                lamports: vault_lamports,
+                ..TestAccount::new(pda(&[b"vault", pool_state.key.as_ref()]), vault_owner, Vec::new())
+            }.info();
+            let mut accounts = new_pool_accounts(payer, token_mint, pool_state);
+            accounts.token_vault = UncheckedAccount::try_from(vault);
+            take_system_transfers();
+            initialize_native_pool(
+                Context::new(&crate::ID, &mut accounts, &[], InitializeNativePoolBumps::default()),
+                30,
+                10000,
+                Pubkey::new_unique(),
+                0,
+                0,
+                None,
+                None,
+            )?;
+            Ok::<_, Error>(take_system_transfers().into_iter().filter(|(_, to, _)| to == vault.key).map(|(_, _, lamports)| lamports).collect::<Vec<_>>())
+        };
+        
+        // Lamports sent to the vault PDA before the pool existed - only the rest of its rent is paid
+        assert_eq!(init(500, System::id()).unwrap(), vec![vault_rent - 500]);
+        assert_eq!(init(vault_rent, System::id()).unwrap(), Vec::<u64>::new());
+        // An account some other program owns can't become the vault
+        assert_eq!(init(vault_rent, Pubkey::new_unique()).unwrap_err(), ErrorCode::InvalidTreasury.into());
+    }
+
+    /// reclaim_minimum_liquidity by `admin`, paying out to `emergency_recovery`
+    fn reclaim(pool_state: Info, admin: Info, emergency_recovery: Pubkey) -> Result<()> {
+        install_stubs();
+        let token_mint = Pubkey::new_unique();
+        let lp_mint = Pubkey::new_unique();
+        let pool_authority = pda(&[b"authority", pool_state.key.as_ref()]);
+        let locked_liquidity_owner = pda(&[LOCKED_LIQUIDITY_SEED, pool_state.key.as_ref()]);
+        let mut accounts = ReclaimMinimumLiquidity {
+            admin: Signer::try_from(admin).unwrap(),
+            pool_state: Account::try_from(pool_state).unwrap(),
+            pool_pda: UncheckedAccount::try_from(empty_account(pda(&[b"pool_pda", pool_state.key.as_ref()])).info()),
+            pool_authority: UncheckedAccount::try_from(empty_account(pool_authority).info()),
+            token_vault: Box::new(InterfaceAccount::try_from(
+                token_account(pda(&[b"vault", pool_state.key.as_ref()]), token_mint, pool_authority, 1_000_000).info()
+            ).unwrap()),
+            lp_mint: Account::try_from(mint_account(lp_mint, pool_authority, MINIMUM_LIQUIDITY, 9).info()).unwrap(),
+            locked_liquidity_owner: UncheckedAccount::try_from(empty_account(locked_liquidity_owner).info()),
+            locked_lp_account: Account::try_from(
+                token_account(Pubkey::new_unique(), lp_mint, locked_liquidity_owner, MINIMUM_LIQUIDITY).info()
+            ).unwrap(),
+            emergency_recovery: UncheckedAccount::try_from(empty_account(emergency_recovery).info()),
+            recovery_token_account: Box::new(InterfaceAccount::try_from(
+                token_account(Pubkey::new_unique(), token_mint, emergency_recovery, 0).info()
+            ).unwrap()),
+            token_program: Program::try_from(program(Token::id()).info()).unwrap(),
+            token_2022_program: UncheckedAccount::try_from(program(spl_token_2022::ID).info()),
+            system_program: Program::try_from(program(System::id()).info()).unwrap(),
+        };
+        reclaim_minimum_liquidity(Context::new(&crate::ID, &mut accounts, &[], ReclaimMinimumLiquidityBumps::default()))
+    }
+
+    #[test]
+    fn only_a_wound_down_pool_gives_up_its_locked_minimum() {
+        let admin = signer().info();
+        let recovery = Pubkey::new_unique();
+        let wound_down = PoolState {
+            is_native_pool: true,
+            pool_kind: POOL_KIND_NATIVE,
+            admin: *admin.key,
+            native_reserve: 1_000_000,
+            total_amount_minted: MINIMUM_LIQUIDITY,
+            emergency_recovery: recovery,
+            ..Default::default()
+        };
+        let pool = |pool_state: PoolState| program_account(Pubkey::new_unique(), &pool_state).info();
+        
+        assert_eq!(
+            reclaim(pool(PoolState { swaps_enabled: true, ..wound_down.clone() }), admin, recovery).unwrap_err(),
+            ErrorCode::PoolNotWoundDown.into()
+        );
+        // Someone still holds LP beyond the locked minimum
+        assert_eq!(
+            reclaim(pool(PoolState { total_amount_minted: MINIMUM_LIQUIDITY + 1, ..wound_down.clone() }), admin, recovery).unwrap_err(),
+            ErrorCode::PoolNotWoundDown.into()
+        );
+        assert_eq!(
+            reclaim(pool(PoolState { emergency_recovery: Pubkey::default(), ..wound_down.clone() }), admin, recovery).unwrap_err(),
+            ErrorCode::EmergencyRecoveryNotSet.into()
+        );
+        assert_eq!(
+            reclaim(pool(wound_down.clone()), admin, Pubkey::new_unique()).unwrap_err(),
+            ErrorCode::Unauthorized.into()
+        );
+        assert_eq!(reclaim(pool(wound_down.clone()), signer().info(), recovery).unwrap_err(), ErrorCode::Unauthorized.into());
+        
+        let pool_state = pool(wound_down);
+        take_system_transfers();
+        reclaim(pool_state, admin, recovery).unwrap();
+        assert_eq!(
+            take_system_transfers(),
+            vec![(pda(&[b"pool_pda", pool_state.key.as_ref()]), recovery, 1_000_000)]
+        );
+        let pool = PoolState::try_deserialize(&mut &pool_state.try_borrow_data().unwrap()[..]).unwrap();
+        assert_eq!(pool.native_reserve, 0);
+        assert_eq!(pool.total_amount_minted, 0);
+    }
+
+    #[test]
+    fn lp_decimals_are_chosen_at_creation() {
+        install_stubs();
+        let init = |lp_decimals: Option<u8>| {
+            let payer = signer().info();
+            let token_mint = mint_account(Pubkey::new_unique(), Pubkey::new_unique(), 1_000_000_000, 6).info();
+            let pool_state = new_pool_state(token_mint);
+            let mut accounts = new_pool_accounts(payer, token_mint, pool_state);
+            initialize_native_pool(
+                Context::new(&crate::ID, &mut accounts, &[], InitializeNativePoolBumps::default()),
+                30,
+                10000,
+                Pubkey::new_unique(),
+                0,
+                0,
+                lp_decimals,
+                None,
+            )?;
+            Ok::<_, Error>(accounts.pool_state.lp_decimals)
+        };
+        
+        assert_eq!(init(None).unwrap(), DEFAULT_LP_DECIMALS);
+        assert_eq!(init(Some(6)).unwrap(), 6);
+        assert_eq!(init(Some(0)).unwrap(), 0);
+        assert_eq!(init(Some(crate::state::MAX_LP_DECIMALS + 1)).unwrap_err(), ErrorCode::InvalidInput.into());
+    }
+
+    #[test]
+    fn pool_pda_rent_holds_after_the_fee_and_the_user_payout_together() {
+        install_stubs();
+        let treasury = Pubkey::new_unique();
+        let fee_pool_state = || {
+            let pool_state = native_pool(Pubkey::new_unique());
+            let mut pool = PoolState::try_deserialize(&mut &pool_state.try_borrow_data().unwrap()[..]).unwrap();
+            pool.protocol_treasury = treasury;
+            pool.protocol_fee_bps = 1000;
+            pool.try_serialize(&mut &mut pool_state.try_borrow_mut_data().unwrap()[..]).unwrap();
+            (pool_state, pool)
+        };
+        let amounts = compute_native_swap(&fee_pool_state().1, 1_000_000_000, 1_000_000, false, 0).unwrap();
+        assert!(amounts.protocol_fee_xnt > 0);
+        let amount_out = amounts.final_amount_out + amounts.protocol_fee_xnt;
+        let rent_floor = Rent::default().minimum_balance(0);
+        // Token → XNT out of a pool_pda holding `lamports`
+        let swap_out_of = |lamports: u64| {
+            let (pool_state, _) = fee_pool_state();
+            let user = signer().info();
+            let user_token_account = token_account(Pubkey::new_unique(), Pubkey::new_unique(), *user.key, 1_000_000_000).info();
+            let mut accounts = swap_accounts(pool_state, user, user_token_account);
+            let pool_pda = TestAccount { lamports, ..empty_account(pda(&[b"pool_pda", pool_state.key.as_ref()])) }.info();
+            accounts.pool_pda = UncheckedAccount::try_from(pool_pda);
+            take_system_transfers();
+            swap_native(Context::new(&crate::ID, &mut accounts, &[], SwapNativeBumps::default()), 1_000_000, 0, false)?;
+            Ok::<_, Error>((*pool_pda.key, *user.key, take_system_transfers()))
+        };
+        
+        // Enough for the user's output alone, not for the fee on top
+        assert_eq!(
+            swap_out_of(rent_floor + amount_out - 1).unwrap_err(),
+            ErrorCode::InsufficientRentReserve.into()
+        );
+        let (pool_pda, user, transfers) = swap_out_of(rent_floor + amount_out).unwrap();
+        assert_eq!(
+            transfers,
+            vec![(pool_pda, treasury, amounts.protocol_fee_xnt), (pool_pda, user, amounts.final_amount_out)]
+        );
+    }
+
+    #[test]
+    fn native_pool_reserves_are_read_only_from_its_vault_pda() {
+        install_stubs();
+        let pool_state = native_pool(Pubkey::new_unique());
+        let user = signer().info();
+        let user_token_account = token_account(Pubkey::new_unique(), Pubkey::new_unique(), *user.key, 1_000_000_000).info();
+        let mut accounts = swap_accounts(pool_state, user, user_token_account);
+        // Same mint and authority, a far larger balance, but not the [b"vault", pool_state] PDA
+        let pool_authority = pda(&[b"authority", pool_state.key.as_ref()]);
+        let substitute = token_account(Pubkey::new_unique(), accounts.token_vault.mint, pool_authority, u64::MAX / 2).info();
+        *accounts.token_vault = InterfaceAccount::try_from(substitute).unwrap();
+        assert_eq!(
+            swap_native(Context::new(&crate::ID, &mut accounts, &[], SwapNativeBumps::default()), 1_000_000, 0, true)
+                .unwrap_err(),
+            ErrorCode::InvalidTreasury.into()
+        );
+        // The real vault still prices the pool
+        let pool_state = native_pool(Pubkey::new_unique());
+        assert!(swap(pool_state, true, 0, 0).is_ok());
+    }
+
+    #[test]
+    fn empty_reserves_and_dust_outputs_fail_apart() {
+        let pool = fee_pool(0);
+        let empty_xnt = PoolState { native_reserve: 0, ..fee_pool(0) };
+        for is_xnt_to_token in [true, false] {
+            let out = |pool: &PoolState, token_vault_balance: u64, amount_in: u64| {
+                compute_native_swap(pool, token_vault_balance, amount_in, is_xnt_to_token, 0).map(|amounts| amounts.final_amount_out)
+            };
+            assert_eq!(out(&empty_xnt, 1_000_000_000, 1_000_000).unwrap_err(), ErrorCode::PoolReserveEmpty.into());
+            assert_eq!(out(&pool, 0, 1_000_000).unwrap_err(), ErrorCode::PoolReserveEmpty.into());
+            // A single unit in rounds to nothing out, and is refused rather than kept
+            assert_eq!(out(&pool, 1_000_000_000, 1).unwrap_err(), ErrorCode::SwapOutputZero.into());
+            assert!(out(&pool, 1_000_000_000, 1_000).unwrap() > 0);
+        }
+    }
+
+    #[test]
+    fn native_pool_counts_the_protocol_fees_it_paid_out() {
+        install_stubs();
+        let treasury = Pubkey::new_unique();
+        let pool_state = native_pool(Pubkey::new_unique());
+        let mut pool = PoolState::try_deserialize(&mut &pool_state.try_borrow_data().unwrap()[..]).unwrap();
+        pool.protocol_fee_bps = 1000;
+        pool.try_serialize(&mut &mut pool_state.try_borrow_mut_data().unwrap()[..]).unwrap();
+        // Token → XNT, the fee paid to `protocol_treasury`
+        let swap_paying = |protocol_treasury: Pubkey| {
+            let user = signer().info();
+            let user_token_account = token_account(Pubkey::new_unique(), Pubkey::new_unique(), *user.key, 1_000_000_000).info();
+            let mut accounts = swap_accounts(pool_state, user, user_token_account);
+            accounts.pool_state.protocol_treasury = protocol_treasury;
+            accounts.protocol_treasury = UncheckedAccount::try_from(empty_account(protocol_treasury).info());
+            let result = swap_native(Context::new(&crate::ID, &mut accounts, &[], SwapNativeBumps::default()), 1_000_000, 0, false).unwrap();
+            accounts.exit(&crate::ID).unwrap();
+            (result.protocol_fee, accounts.pool_state.cumulative_protocol_fees_collected)
+        };
+        
+        let (first_fee, collected) = swap_paying(treasury);
+        assert!(first_fee > 0);
+        assert_eq!(collected, first_fee as u128);
+        let (second_fee, collected) = swap_paying(treasury);
+        assert_eq!(collected, (first_fee + second_fee) as u128);
+        // No treasury, no fee paid out - and none counted
+        assert_eq!(swap_paying(Pubkey::default()), (0, collected));
+    }
+}
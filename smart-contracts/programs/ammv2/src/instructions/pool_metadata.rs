@@ -0,0 +1,121 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{PoolMetadata, PoolState, MAX_POOL_NAME_LEN, MAX_POOL_SYMBOL_LEN, MAX_POOL_URI_LEN};
+use crate::error::ErrorCode;
+
+/// Create the pool's PoolMetadata label (admin only)
+pub fn initialize_pool_metadata(
+    ctx: Context<InitializePoolMetadata>,
+    name: String,
+    symbol: String,
+    uri: String,
+) -> Result<()> {
+    require!(ctx.accounts.pool_state.admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+
+    let pool_metadata = &mut ctx.accounts.pool_metadata;
+    pool_metadata.pool = ctx.accounts.pool_state.key();
+    write_pool_metadata(pool_metadata, name, symbol, uri)
+}
+
+#[derive(Accounts)]
+pub struct InitializePoolMetadata<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub pool_state: Account<'info, PoolState>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + PoolMetadata::LEN,
+        seeds = [b"metadata", pool_state.key().as_ref()],
+        bump
+    )]
+    pub pool_metadata: Account<'info, PoolMetadata>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Replace the name, symbol and URI of the pool's PoolMetadata (admin only)
+pub fn set_pool_metadata(
+    ctx: Context<UpdatePoolMetadata>,
+    name: String,
+    symbol: String,
+    uri: String,
+) -> Result<()> {
+    require!(ctx.accounts.pool_state.admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+
+    write_pool_metadata(&mut ctx.accounts.pool_metadata, name, symbol, uri)
+}
+
+#[derive(Accounts)]
+pub struct UpdatePoolMetadata<'info> {
+    pub admin: Signer<'info>,
+
+    pub pool_state: Account<'info, PoolState>,
+
+    #[account(mut, seeds = [b"metadata", pool_state.key().as_ref()], bump)]
+    pub pool_metadata: Account<'info, PoolMetadata>,
+}
+
+// Lengths are bounded so the strings always fit the account's fixed space
+fn write_pool_metadata(pool_metadata: &mut PoolMetadata, name: String, symbol: String, uri: String) -> Result<()> {
+    require!(name.len() <= MAX_POOL_NAME_LEN, ErrorCode::InvalidInput);
+    require!(symbol.len() <= MAX_POOL_SYMBOL_LEN, ErrorCode::InvalidInput);
+    require!(uri.len() <= MAX_POOL_URI_LEN, ErrorCode::InvalidInput);
+
+    pool_metadata.name = name;
+    pool_metadata.symbol = symbol;
+    pool_metadata.uri = uri;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{program_account, signer, Info};
+
+    fn set(admin: Info, pool_state: Info, pool_metadata: Info, name: &str, symbol: &str, uri: &str) -> Result<()> {
+        let mut accounts = UpdatePoolMetadata {
+            admin: Signer::try_from(admin).unwrap(),
+            pool_state: Account::try_from(pool_state).unwrap(),
+            pool_metadata: Account::try_from(pool_metadata).unwrap(),
+        };
+        set_pool_metadata(
+            Context::new(&crate::ID, &mut accounts, &[], UpdatePoolMetadataBumps::default()),
+            name.to_string(),
+            symbol.to_string(),
+            uri.to_string(),
+        )?;
+        accounts.exit(&crate::ID)
+    }
+
+    #[test]
+    fn only_the_admin_labels_a_pool_within_the_accounts_space() {
+        let admin = signer().info();
+        let pool_state = program_account(Pubkey::new_unique(), &PoolState { admin: *admin.key, ..Default::default() }).info();
+        let label = PoolMetadata { pool: *pool_state.key, name: String::new(), symbol: String::new(), uri: String::new() };
+        let mut pool_metadata = program_account(Pubkey::new_unique(), &label);
+        pool_metadata.data.resize(8 + PoolMetadata::LEN, 0);
+        let pool_metadata = pool_metadata.info();
+
+        assert_eq!(
+            set(signer().info(), pool_state, pool_metadata, "XNT/USDC", "XLP", "").unwrap_err(),
+            ErrorCode::Unauthorized.into()
+        );
+        let too_long = "x".repeat(MAX_POOL_NAME_LEN + 1);
+        assert_eq!(
+            set(admin, pool_state, pool_metadata, &too_long, "XLP", "").unwrap_err(),
+            ErrorCode::InvalidInput.into()
+        );
+
+        // The longest strings allowed still fit the space the account was created with
+        let name = "n".repeat(MAX_POOL_NAME_LEN);
+        let symbol = "s".repeat(MAX_POOL_SYMBOL_LEN);
+        let uri = "u".repeat(MAX_POOL_URI_LEN);
+        set(admin, pool_state, pool_metadata, &name, &symbol, &uri).unwrap();
+        let label = PoolMetadata::try_deserialize(&mut &pool_metadata.try_borrow_data().unwrap()[..]).unwrap();
+        assert_eq!((label.name, label.symbol, label.uri), (name, symbol, uri));
+    }
+}
@@ -0,0 +1,251 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::Token,
+    token_interface::{Mint, TokenAccount},
+};
+
+use crate::state::PoolState;
+use crate::error::ErrorCode;
+use crate::events::Rebalanced;
+use crate::utils::token_program_for;
+
+/// Swap size that moves an SPL pool's spot price (token1 per token0) to `target_price_x64`
+/// Returns (token0 in?, amount_in), or None when the pool is already at the target.
+/// The reserves land on the curve point sqrt(k / P), sqrt(k * P) ignoring the LP fee,
+/// which only leaves the price short of the target, never past it
+pub fn rebalance_amount_in(reserve0: u64, reserve1: u64, target_price_x64: u128) -> Result<Option<(bool, u64)>> {
+    require!(reserve0 > 0 && reserve1 > 0, ErrorCode::PoolReserveEmpty);
+    require!(target_price_x64 > 0, ErrorCode::InvalidInput);
+    
+    // sqrt(k) < 2^64 and sqrt(P * 2^64) = sqrt(P) * 2^32
+    let sqrt_k = crate::utils::geometric_mean(reserve0, reserve1) as u128;
+    let sqrt_price_x32 = crate::utils::sqrt_u128(target_price_x64);
+    require!(sqrt_price_x32 > 0, ErrorCode::InvalidInput);
+    let target_reserve0 = (sqrt_k << 32) / sqrt_price_x32;
+    let target_reserve1 = sqrt_k.checked_mul(sqrt_price_x32).ok_or(ErrorCode::MathOverflow)? >> 32;
+    
+    let (token0_in, amount_in) = if target_reserve0 > reserve0 as u128 {
+        // token0 is priced above target - sell token0 into the pool
+        (true, target_reserve0 - reserve0 as u128)
+    } else if target_reserve1 > reserve1 as u128 {
+        (false, target_reserve1 - reserve1 as u128)
+    } else {
+        return Ok(None);
+    };
+    
+    let amount_in = u64::try_from(amount_in).map_err(|_| ErrorCode::MathOverflow)?;
+    Ok((amount_in > 0).then_some((token0_in, amount_in)))
+}
+
+/// Move a protocol-owned SPL pool's spot price (token1 per token0, Q64.64) toward
+/// `target_price_x64` (admin only). The admin's token accounts are the counterparty of
+/// an internal swap priced with the pool's swap math and LP fee; no protocol fee is taken.
+/// Fails rather than spend more than `max_cost` of the input token
+///
+/// The swap goes through the same guards as a user swap: swaps_enabled, the kill-switch,
+/// the pool's swap size cap and, with `invariant-checks`, the x * y check. Its LP fee
+/// accrues to LPs like any other
+///
+/// A side whose mint is passed moves with TransferChecked (hook accounts follow as
+/// remaining accounts). The input is priced on what reaches the vault after any transfer fee
+pub fn rebalance<'info>(ctx: Context<'_, '_, '_, 'info, Rebalance<'info>>, target_price_x64: u128, max_cost: u64) -> Result<()> {
+    let pool_state = &ctx.accounts.pool_state;
+    
+    require!(pool_state.admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+    require!(pool_state.protocol_owned, ErrorCode::InvalidInput);
+    pool_state.check_spl_pool()?;
+    require!(pool_state.swaps_enabled, ErrorCode::SwapsDisabled);
+    crate::utils::check_not_paused(&ctx.accounts.global_config)?;
+    crate::utils::check_fee_fraction(pool_state.fee_numerator, pool_state.fee_denominator)?;
+    
+    let vault0 = &ctx.accounts.vault0;
+    let vault1 = &ctx.accounts.vault1;
+    require!(ctx.accounts.admin_token0.mint == vault0.mint, ErrorCode::MintMismatch);
+    require!(ctx.accounts.admin_token1.mint == vault1.mint, ErrorCode::MintMismatch);
+    if let Some(mint0) = ctx.accounts.mint0.as_ref() {
+        require!(mint0.key() == vault0.mint, ErrorCode::MintMismatch);
+    }
+    if let Some(mint1) = ctx.accounts.mint1.as_ref() {
+        require!(mint1.key() == vault1.mint, ErrorCode::MintMismatch);
+    }
+    
+    let Some((token0_in, amount_in)) = rebalance_amount_in(vault0.amount, vault1.amount, target_price_x64)? else {
+        return Ok(());
+    };
+    require!(amount_in <= max_cost, ErrorCode::SlippageExceeded);
+    
+    let (vault_in, vault_out, admin_src, admin_dst, mint_in, mint_out) = if token0_in {
+        (vault0, vault1, &ctx.accounts.admin_token0, &ctx.accounts.admin_token1, &ctx.accounts.mint0, &ctx.accounts.mint1)
+    } else {
+        (vault1, vault0, &ctx.accounts.admin_token1, &ctx.accounts.admin_token0, &ctx.accounts.mint1, &ctx.accounts.mint0)
+    };
+    let mint_in = mint_in.as_ref().map(|mint| mint.to_account_info());
+    let mint_out = mint_out.as_ref().map(|mint| mint.to_account_info());
+    let in_token_index = if token0_in { 0 } else { 1 };
+    // A transfer fee on the input never reaches the vault
+    let amount_in_received = crate::utils::amount_after_transfer_fee(mint_in.as_ref(), Clock::get()?.epoch, amount_in)?;
+    let amount_out = crate::utils::calculate_swap_output(
+        amount_in_received,
+        vault_in.amount,
+        vault_out.amount,
+        pool_state.fee_numerator,
+        pool_state.fee_denominator,
+    )?;
+    require!(amount_out > 0 && amount_out < vault_out.amount, ErrorCode::InsufficientLiquidity);
+    crate::utils::check_swap_size(amount_out as u128, vault_out.amount as u128, pool_state.max_swap_out_bps_of_reserve)?;
+    // Same LP fee as calculate_swap_output deducted from the input
+    let lp_fee_amount = (amount_in_received as u128)
+        .checked_mul(pool_state.fee_numerator as u128)
+        .ok_or(ErrorCode::MathOverflow)?
+        / pool_state.fee_denominator as u128;
+    let lp_fee_amount = u64::try_from(lp_fee_amount).map_err(|_| ErrorCode::MathOverflow)?;
+    
+    // Guard against reentrant calls on this pool
+    crate::utils::begin_pool_operation(&ctx.accounts.pool_state.to_account_info())?;
+    
+    let in_program = token_program_for(
+        vault_in.to_account_info().owner,
+        &ctx.accounts.token_program.to_account_info(),
+        &ctx.accounts.token_2022_program.to_account_info(),
+    )?;
+    let out_program = token_program_for(
+        vault_out.to_account_info().owner,
+        &ctx.accounts.token_program.to_account_info(),
+        &ctx.accounts.token_2022_program.to_account_info(),
+    )?;
+    
+    crate::utils::transfer_tokens_checked(
+        admin_src.to_account_info(),
+        vault_in.to_account_info(),
+        ctx.accounts.admin.to_account_info(),
+        in_program,
+        mint_in,
+        ctx.accounts.pool_state.mint_decimals(in_token_index),
+        ctx.remaining_accounts,
+        amount_in,
+        &[],
+    )?;
+    
+    let pool_key = ctx.accounts.pool_state.key();
+    let pda_sign = &[b"authority", pool_key.as_ref(), &[ctx.bumps.pool_authority]];
+    crate::utils::transfer_tokens_checked(
+        vault_out.to_account_info(),
+        admin_dst.to_account_info(),
+        ctx.accounts.pool_authority.to_account_info(),
+        out_program,
+        mint_out,
+        ctx.accounts.pool_state.mint_decimals(1 - in_token_index),
+        ctx.remaining_accounts,
+        amount_out,
+        &[pda_sign],
+    )?;
+    
+    // Vault balances loaded by Anchor are pre-swap, re-read the post-swap ones
+    #[cfg(feature = "invariant-checks")]
+    crate::utils::check_invariant(
+        vault_in.amount,
+        vault_out.amount,
+        crate::utils::token_account_amount(&vault_in.to_account_info())?,
+        crate::utils::token_account_amount(&vault_out.to_account_info())?,
+    )?;
+    
+    ctx.accounts.pool_state.accrue_lp_fee(in_token_index, lp_fee_amount);
+    
+    emit!(Rebalanced {
+        pool: pool_key,
+        actor: ctx.accounts.admin.key(),
+        target_price_x64,
+        token0_in,
+        amount_in,
+        amount_out,
+    });
+    
+    crate::utils::end_pool_operation(&ctx.accounts.pool_state.to_account_info())?;
+    
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct Rebalance<'info> {
+    pub admin: Signer<'info>,
+    
+    #[account(mut)]
+    pub pool_state: Box<Account<'info, PoolState>>,
+    
+    /// CHECK: This is a PDA used for signing
+    #[account(seeds=[b"authority", pool_state.key().as_ref()], bump)]
+    pub pool_authority: UncheckedAccount<'info>,
+    
+    // vaults can be Token or Token2022
+    #[account(mut, seeds=[b"vault0", pool_state.key().as_ref()], bump)]
+    pub vault0: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, seeds=[b"vault1", pool_state.key().as_ref()], bump)]
+    pub vault1: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    // admin token accounts on each side - one pays the input, the other takes the output
+    #[account(mut)]
+    pub admin_token0: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut)]
+    pub admin_token1: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    pub token_program: Program<'info, Token>,
+    /// CHECK: Token 2022 program - verified in handler
+    pub token_2022_program: UncheckedAccount<'info>,
+    
+    // Program-wide GlobalConfig PDA - rebalancing fails while its kill-switch is engaged
+    /// CHECK: Uninitialized until initialize_global_config (nothing is paused then) - read in handler
+    #[account(seeds = [b"global_config"], bump)]
+    pub global_config: UncheckedAccount<'info>,
+    
+    // Optional mints of vault0 / vault1 - passing one moves that side with TransferChecked
+    pub mint0: Option<Box<InterfaceAccount<'info, Mint>>>,
+    pub mint1: Option<Box<InterfaceAccount<'info, Mint>>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Spot price as read_prices reports it
+    fn price_x64(reserve0: u64, reserve1: u64) -> u128 {
+        ((reserve1 as u128) << 64) / reserve0 as u128
+    }
+
+    #[test]
+    fn rebalance_moves_a_skewed_pool_to_the_target_price() {
+        // token0 at 4 token1, target 1:1
+        let (reserve0, reserve1) = (1_000_000_000u64, 4_000_000_000u64);
+        let target_price_x64 = 1u128 << 64;
+        
+        // token0 is overpriced - sell it into the pool until both sides hold sqrt(k)
+        let (token0_in, amount_in) = rebalance_amount_in(reserve0, reserve1, target_price_x64).unwrap().unwrap();
+        assert!(token0_in);
+        assert_eq!(amount_in, 2_000_000_000 - reserve0);
+        
+        let amount_out = crate::utils::calculate_swap_output(amount_in, reserve0, reserve1, 0, 10000).unwrap();
+        let price = price_x64(reserve0 + amount_in, reserve1 - amount_out);
+        assert!(price.abs_diff(target_price_x64) <= target_price_x64 / 1_000_000);
+        
+        // The LP fee leaves the price short of the target, never past it
+        let amount_out = crate::utils::calculate_swap_output(amount_in, reserve0, reserve1, 30, 10000).unwrap();
+        let price = price_x64(reserve0 + amount_in, reserve1 - amount_out);
+        assert!(price > target_price_x64);
+        assert!(price - target_price_x64 <= target_price_x64 / 100);
+        
+        // The other way round: token0 at 1/4 token1 is bought with token1
+        let (reserve0, reserve1) = (4_000_000_000u64, 1_000_000_000u64);
+        let (token0_in, amount_in) = rebalance_amount_in(reserve0, reserve1, target_price_x64).unwrap().unwrap();
+        assert!(!token0_in);
+        let amount_out = crate::utils::calculate_swap_output(amount_in, reserve1, reserve0, 0, 10000).unwrap();
+        let price = price_x64(reserve0 - amount_out, reserve1 + amount_in);
+        assert!(price.abs_diff(target_price_x64) <= target_price_x64 / 1_000_000);
+    }
+
+    #[test]
+    fn rebalance_is_a_no_op_at_the_target_price() {
+        let (reserve0, reserve1) = (1_000_000_000u64, 2_000_000_000u64);
+        assert_eq!(rebalance_amount_in(reserve0, reserve1, price_x64(reserve0, reserve1)).unwrap(), None);
+        assert_eq!(rebalance_amount_in(reserve0, reserve1, 0).unwrap_err(), ErrorCode::InvalidInput.into());
+        assert_eq!(rebalance_amount_in(0, reserve1, 1 << 64).unwrap_err(), ErrorCode::PoolReserveEmpty.into());
+    }
+}
@@ -0,0 +1,118 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::system_instruction;
+use anchor_lang::solana_program::program::invoke_signed;
+use crate::state::PoolRegistryEntry;
+use crate::error::ErrorCode;
+
+/// Record a new pool in the mint-pair registry
+///
+/// The entry lives at PDA `[b"registry", mint0, mint1]`, so clients can look up
+/// a pair without knowing how the pool itself is seeded. Native pools use
+/// `NATIVE_MINT_PLACEHOLDER` for the XNT side, at position `native_mint_index`.
+/// Fails if the pair is already registered.
+#[allow(clippy::too_many_arguments)]
+pub fn register_pool<'info>(
+    program_id: &Pubkey,
+    registry_entry: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    pool: Pubkey,
+    pool_kind: u8,
+    mint0: Pubkey,
+    mint1: Pubkey,
+) -> Result<()> {
+    let (expected_entry, bump) = Pubkey::find_program_address(
+        &[b"registry", mint0.as_ref(), mint1.as_ref()],
+        program_id,
+    );
+    require!(registry_entry.key() == expected_entry, ErrorCode::InvalidInput);
+    require!(
+        registry_entry.owner == &anchor_lang::solana_program::system_program::ID,
+        ErrorCode::PoolAlreadyRegistered
+    );
+    
+    let entry_seeds = &[
+        b"registry",
+        mint0.as_ref(),
+        mint1.as_ref(),
+        &[bump],
+    ];
+    
+    // Top up to rent-exempt (the PDA may already hold lamports), then allocate + assign
+    let space = 8 + PoolRegistryEntry::LEN;
+    let rent_lamports = Rent::get()?.minimum_balance(space);
+    let top_up = rent_lamports.saturating_sub(registry_entry.lamports());
+    if top_up > 0 {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                anchor_lang::system_program::Transfer {
+                    from: payer.clone(),
+                    to: registry_entry.clone(),
+                },
+            ),
+            top_up,
+        )?;
+    }
+    invoke_signed(
+        &system_instruction::allocate(registry_entry.key, space as u64),
+        std::slice::from_ref(registry_entry),
+        &[entry_seeds],
+    )?;
+    invoke_signed(
+        &system_instruction::assign(registry_entry.key, program_id),
+        std::slice::from_ref(registry_entry),
+        &[entry_seeds],
+    )?;
+    
+    let entry = PoolRegistryEntry {
+        pool,
+        pool_kind,
+        mint0,
+        mint1,
+    };
+    let mut data = registry_entry.try_borrow_mut_data()?;
+    let mut writer: &mut [u8] = &mut data;
+    entry.try_serialize(&mut writer)?;
+    
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::POOL_KIND_SPL;
+    use crate::test_fixtures::{install_stubs, pda, program, signer, Info, TestAccount};
+
+    fn register(registry_entry: Info, mint0: Pubkey, mint1: Pubkey) -> Result<()> {
+        install_stubs();
+        register_pool(
+            &crate::ID,
+            registry_entry,
+            signer().info(),
+            program(System::id()).info(),
+            Pubkey::new_unique(),
+            POOL_KIND_SPL,
+            mint0,
+            mint1,
+        )
+    }
+
+    #[test]
+    fn pool_is_registered_once_under_its_mint_pair() {
+        let (mint0, mint1) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let entry_key = pda(&[b"registry", mint0.as_ref(), mint1.as_ref()]);
+        // Sized as allocate leaves it - the off-chain system program CPIs don't run
+        let registry_entry = TestAccount::new(entry_key, System::id(), vec![0; 8 + PoolRegistryEntry::LEN]).info();
+        register(registry_entry, mint0, mint1).unwrap();
+        let entry = PoolRegistryEntry::try_deserialize(&mut &registry_entry.try_borrow_data().unwrap()[..]).unwrap();
+        assert_eq!((entry.mint0, entry.mint1, entry.pool_kind), (mint0, mint1, POOL_KIND_SPL));
+        
+        // Once assigned to the program, the pair is taken
+        let registered = TestAccount::new(entry_key, crate::ID, vec![0; 8 + PoolRegistryEntry::LEN]).info();
+        assert_eq!(register(registered, mint0, mint1).unwrap_err(), ErrorCode::PoolAlreadyRegistered.into());
+        // The entry must sit at the pair's PDA
+        let elsewhere = TestAccount::new(Pubkey::new_unique(), System::id(), vec![0; 8 + PoolRegistryEntry::LEN]).info();
+        assert_eq!(register(elsewhere, mint0, mint1).unwrap_err(), ErrorCode::InvalidInput.into());
+    }
+}
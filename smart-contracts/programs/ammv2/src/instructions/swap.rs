@@ -1,75 +1,207 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
-    token,
-    token::{Token, TokenAccount, Transfer, Mint, CloseAccount},
+    associated_token::AssociatedToken,
+    token::Token,
+    token_interface::{Mint, TokenAccount},
 };
-use spl_token_2022::state::Account as Token2022AccountState;
-use spl_token_2022::extension::StateWithExtensions;
-use spl_token_2022::instruction as token_2022_instruction;
-use anchor_lang::solana_program::program_pack::Pack;
-use anchor_lang::solana_program::system_instruction;
-use anchor_lang::solana_program::system_program;
-
-use crate::state::PoolState;
+
+use crate::state::{LpBalanceSnapshot, PoolState, PROTOCOL_FEE_MODE_LP_FEE};
 use crate::error::ErrorCode;
-use crate::utils::{is_token_2022, get_token_program_account};
+use crate::utils::token_program_for;
+
+/// Return data for `swap`, `swap_to` and `swap_native` - the amounts actually executed
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct SwapResult {
+    /// Input taken from the swapper, protocol fee included
+    pub amount_in_used: u64,
+    /// Output received, net of the protocol fee and any Token 2022 transfer fee
+    pub amount_out: u64,
+    /// XNT protocol fee collected (0 when it was skipped)
+    pub protocol_fee: u64,
+}
 
-pub fn swap(
-    ctx: Context<Swap>, 
+pub fn swap<'info>(
+    ctx: Context<'_, '_, '_, 'info, Swap<'info>>, 
     amount_in: u64, 
     min_amount_out: u64,
-) -> Result<()> {
-
-    // Helper function to unpack token account (works for both Token and Token2022 with extensions)
-    fn unpack_token_account(account_info: &AccountInfo, name: &str) -> Result<Token2022AccountState> {
-// msg!("Unpacking {}: owner={}, data_len={}", name, account_info.owner, account_info.data_len());
-        
-        let account = if account_info.data_len() == 165 {
-            // Standard size - use regular unpack
-            Token2022AccountState::unpack(&account_info.data.borrow())
-                .map_err(|e| {
-// msg!("❌ Failed to unpack {} (standard): {:?}", name, e);
-                    e
-                })?
-        } else {
-            // Has extensions - use StateWithExtensions
-            let account_data = account_info.data.borrow();
-            let state_with_ext = StateWithExtensions::<Token2022AccountState>::unpack(&account_data)
-                .map_err(|e| {
-// msg!("❌ Failed to unpack {} (with extensions): {:?}", name, e);
-                    e
-                })?;
-            state_with_ext.base
-        };
-        
-// msg!("✅ {} unpacked successfully", name);
-        Ok(account)
-    }
+) -> Result<SwapResult> {
+    execute_swap(ctx, amount_in, min_amount_out, SlippageQuote::OnChain, false, false)
+}
+
+/// Safe-mode swap for high-value trades: after the transfers, re-read the vaults and
+/// user accounts and fail with ExecutionMismatch unless each moved by exactly the
+/// computed amount (a Token 2022 credit net of its mint's transfer fee)
+pub fn swap_verified<'info>(
+    ctx: Context<'_, '_, '_, 'info, Swap<'info>>, 
+    amount_in: u64, 
+    min_amount_out: u64,
+) -> Result<SwapResult> {
+    execute_swap(ctx, amount_in, min_amount_out, SlippageQuote::OnChain, false, true)
+}
+
+/// Swap and deliver the output to `recipient_token_account` instead of `user_dst`
+/// The signer still funds the input from `user_src`
+pub fn swap_to<'info>(
+    ctx: Context<'_, '_, '_, 'info, Swap<'info>>, 
+    amount_in: u64, 
+    min_amount_out: u64,
+) -> Result<SwapResult> {
+    execute_swap(ctx, amount_in, min_amount_out, SlippageQuote::OnChain, true, false)
+}
+
+/// Return data for `swap_partial`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct SwapPartialResult {
+    pub amount_filled: u64,
+    pub amount_out: u64,
+}
 
-    // Unpack all token accounts
-    let user_src_data = ctx.accounts.user_src.to_account_info();
-    let user_src_account = unpack_token_account(&user_src_data, "user_src")?;
+/// Swap only the largest part of `amount_in` whose price impact stays within
+/// `max_price_impact_bps`. The unfilled remainder is never taken from the user.
+/// `min_amount_out` applies to the filled portion.
+/// The pool's max_allowed_slippage_bps cap doesn't apply: the fill is only known on-chain,
+/// so the client can't quote the output its min_amount_out bounds, and the fill's price
+/// is already held within `max_price_impact_bps`
+pub fn swap_partial<'info>(
+    ctx: Context<'_, '_, '_, 'info, Swap<'info>>,
+    amount_in: u64,
+    max_price_impact_bps: u16,
+    min_amount_out: u64,
+) -> Result<SwapPartialResult> {
+    require!(amount_in > 0, ErrorCode::InvalidInput);
+    require!(max_price_impact_bps <= 10000, ErrorCode::InvalidInput);
+    
+    // Vaults are fully validated by execute_swap, only balances are needed here
+    let amount_filled = crate::utils::max_amount_within_price_impact(
+        amount_in,
+        ctx.accounts.vault_src.amount,
+        ctx.accounts.vault_dst.amount,
+        max_price_impact_bps,
+    )?;
+    require!(amount_filled > 0, ErrorCode::NotEnoughOut);
     
-    let user_dst_data = ctx.accounts.user_dst.to_account_info();
-    let user_dst_account = unpack_token_account(&user_dst_data, "user_dst")?;
+    let amount_out = execute_swap(ctx, amount_filled, min_amount_out, SlippageQuote::Uncapped, false, false)?.amount_out;
     
-    let vault_src_data = ctx.accounts.vault_src.to_account_info();
-    let vault_src_account = unpack_token_account(&vault_src_data, "vault_src")?;
+    Ok(SwapPartialResult { amount_filled, amount_out })
+}
+
+/// min_amount_out allowing the output to fall `slippage_bps` short of `quoted_amount_out`
+pub(crate) fn min_amount_out_for_slippage(quoted_amount_out: u64, slippage_bps: u16) -> Result<u64> {
+    require!(slippage_bps <= 10000, ErrorCode::InvalidInput);
+    // (10000 - slippage_bps) <= 10000, so the result never exceeds quoted_amount_out
+    Ok((quoted_amount_out as u128 * (10000 - slippage_bps) as u128 / 10000) as u64)
+}
+
+/// Swap with min_amount_out derived on-chain from the client's quote: `quoted_amount_out`
+/// (the output the client showed the user, e.g. calculate_swap_output on the reserves
+/// it read) less `slippage_bps`. The tolerance is applied here so every frontend gets
+/// the same rounding, and since the quote predates the transaction it also bounds
+/// price moves landing before the swap
+/// The pool's max_allowed_slippage_bps cap bounds `slippage_bps` against that quote
+pub fn swap_with_slippage_bps<'info>(
+    ctx: Context<'_, '_, '_, 'info, Swap<'info>>,
+    amount_in: u64,
+    quoted_amount_out: u64,
+    slippage_bps: u16,
+) -> Result<SwapResult> {
+    require!(amount_in > 0, ErrorCode::InvalidInput);
+    require!(quoted_amount_out > 0, ErrorCode::InvalidInput);
+    let min_amount_out = min_amount_out_for_slippage(quoted_amount_out, slippage_bps)?;
+    
+    execute_swap(ctx, amount_in, min_amount_out, SlippageQuote::Client(quoted_amount_out), false, false)
+}
+
+/// Swap reverting if the realized average price (output received / amount_in) is below
+/// `limit_price`, a Q64.64 fixed-point output-per-input price - the bound expressed as
+/// min_amount_out = ceil(amount_in * limit_price / 2^64)
+/// On a pool with a max_allowed_slippage_bps cap that min_amount_out is held to the cap like
+/// any other, so a limit far below the pool's price (e.g. 0 for "no limit") is rejected
+pub fn swap_with_price_limit<'info>(
+    ctx: Context<'_, '_, '_, 'info, Swap<'info>>,
+    amount_in: u64,
+    limit_price: u128,
+) -> Result<SwapResult> {
+    require!(amount_in > 0, ErrorCode::InvalidInput);
+    let min_amount_out = min_amount_out_for_price_limit(amount_in, limit_price)?;
+    
+    execute_swap(ctx, amount_in, min_amount_out, SlippageQuote::OnChain, false, false)
+}
+
+/// min_amount_out = ceil(amount_in * limit_price / 2^64), rounded up so the realized
+/// price can never land below the limit
+pub(crate) fn min_amount_out_for_price_limit(amount_in: u64, limit_price: u128) -> Result<u64> {
+    // A limit that needs more than u64::MAX out can never be met
+    (amount_in as u128)
+        .checked_mul(limit_price)
+        .map(|x| x.div_ceil(1u128 << 64))
+        .and_then(|x| u64::try_from(x).ok())
+        .ok_or(ErrorCode::NotEnoughOut.into())
+}
+
+/// The quote the pool's max_allowed_slippage_bps cap holds a swap's min_amount_out to
+#[derive(Clone, Copy)]
+enum SlippageQuote {
+    /// The output priced on-chain, net of any transfer fee the output mint withholds -
+    /// min_amount_out is enforced on what arrives, so it is bounded against the same
+    OnChain,
+    /// The client's quote min_amount_out was derived from
+    Client(u64),
+    /// No cap (see swap_partial)
+    Uncapped,
+}
+
+impl SlippageQuote {
+    /// SlippageToleranceTooHigh if min_amount_out allows more slippage below the quote than
+    /// `max_allowed_slippage_bps` - `quoted_amount_received` is the OnChain quote
+    fn check_cap(self, quoted_amount_received: u64, min_amount_out: u64, max_allowed_slippage_bps: u16) -> Result<()> {
+        match self {
+            SlippageQuote::OnChain => {
+                crate::utils::check_slippage_cap(quoted_amount_received, min_amount_out, max_allowed_slippage_bps)
+            }
+            SlippageQuote::Client(quoted_amount_out) => {
+                crate::utils::check_slippage_cap(quoted_amount_out, min_amount_out, max_allowed_slippage_bps)
+            }
+            SlippageQuote::Uncapped => Ok(()),
+        }
+    }
+}
+
+/// Core SPL swap - amount_out is what `user_dst` received, or
+/// `recipient_token_account` when `deliver_to_recipient` is set
+/// Token 2022 transfer fees are withheld from the recipient, so for fee-bearing
+/// output mints the amount received is below the quoted output - min_amount_out
+/// is enforced on the amount received
+fn execute_swap<'info>(
+    ctx: Context<'_, '_, '_, 'info, Swap<'info>>, 
+    amount_in: u64, 
+    min_amount_out: u64,
+    slippage_quote: SlippageQuote,
+    deliver_to_recipient: bool,
+    verify_execution: bool,
+) -> Result<SwapResult> {
+    // Token accounts are deserialized by Anchor (Token or Token2022, with extensions)
+    let user_src_account = &ctx.accounts.user_src;
+    let vault_src_account = &ctx.accounts.vault_src;
+    let vault_dst_account = &ctx.accounts.vault_dst;
     
-    let vault_dst_data = ctx.accounts.vault_dst.to_account_info();
-    let vault_dst_account = unpack_token_account(&vault_dst_data, "vault_dst")?;
+    // Output goes to the signer's user_dst, or to any holder's account for swap_to
+    let dst_account = if deliver_to_recipient {
+        ctx.accounts.recipient_token_account.as_ref().ok_or(ErrorCode::InvalidInput)?
+    } else {
+        require!(ctx.accounts.user_dst.owner == ctx.accounts.owner.key(), ErrorCode::NotEnoughBalance);
+        &ctx.accounts.user_dst
+    };
 
     // Validate user accounts owned by signer
     require!(user_src_account.owner == ctx.accounts.owner.key(), ErrorCode::NotEnoughBalance);
-    require!(user_dst_account.owner == ctx.accounts.owner.key(), ErrorCode::NotEnoughBalance);
     
     // Validate vaults owned by pool authority
     require!(vault_src_account.owner == ctx.accounts.pool_authority.key(), ErrorCode::InvalidTreasury);
     require!(vault_dst_account.owner == ctx.accounts.pool_authority.key(), ErrorCode::InvalidTreasury);
     
     // Validate mint matches
-    require!(user_src_account.mint == vault_src_account.mint, ErrorCode::InvalidTreasury);
-    require!(user_dst_account.mint == vault_dst_account.mint, ErrorCode::InvalidTreasury);
+    require!(user_src_account.mint == vault_src_account.mint, ErrorCode::MintMismatch);
+    require!(dst_account.mint == vault_dst_account.mint, ErrorCode::MintMismatch);
 
     let src_balance = user_src_account.amount;
     require!(src_balance >= amount_in, ErrorCode::NotEnoughBalance);
@@ -78,7 +210,11 @@ pub fn swap(
 
     // Load pool state with backward compatibility
     // Handles both old (32 bytes) and new (66 bytes) formats
-    let pool_state = PoolState::try_deserialize(&mut &ctx.accounts.pool_state.to_account_info().data.borrow()[..])?;
+    let mut pool_state = PoolState::try_deserialize(&mut &ctx.accounts.pool_state.to_account_info().data.borrow()[..])?;
+    // Native pools keep XNT in native_reserve and go through swap_native
+    pool_state.check_spl_pool()?;
+    require!(pool_state.swaps_enabled, ErrorCode::SwapsDisabled);
+    crate::utils::check_fee_fraction(pool_state.fee_numerator, pool_state.fee_denominator)?;
     
     // Verify pool authority matches expected PDA
     let (expected_pool_authority, _) = Pubkey::find_program_address(
@@ -90,29 +226,81 @@ pub fn swap(
         anchor_lang::error::ErrorCode::ConstraintSeeds
     );
     
+    // Vaults must be this pool's vault PDAs, one on each side - authority-owned
+    // accounts alone could be any token accounts the authority happens to own
+    let (vault0_pda, _) = Pubkey::find_program_address(
+        &[b"vault0", ctx.accounts.pool_state.key().as_ref()],
+        ctx.program_id
+    );
+    let (vault1_pda, _) = Pubkey::find_program_address(
+        &[b"vault1", ctx.accounts.pool_state.key().as_ref()],
+        ctx.program_id
+    );
+    let src_token_index = match (vault_src_account.key(), vault_dst_account.key()) {
+        (src, dst) if src == vault0_pda && dst == vault1_pda => 0,
+        (src, dst) if src == vault1_pda && dst == vault0_pda => 1,
+        _ => return err!(ErrorCode::InvalidTreasury),
+    };
+    
+    // Optional explicit mints must be the pool's tokens on the matching side
+    // Passing them moves that side with TransferChecked, which transfer fee and hook mints need
+    if let Some(mint_src) = ctx.accounts.mint_src.as_ref() {
+        require!(mint_src.key() == vault_src_account.mint, ErrorCode::InvalidTreasury);
+    }
+    if let Some(mint_dst) = ctx.accounts.mint_dst.as_ref() {
+        require!(mint_dst.key() == vault_dst_account.mint, ErrorCode::InvalidTreasury);
+    }
+    let mint_src_info = ctx.accounts.mint_src.as_ref().map(|mint| mint.to_account_info());
+    let mint_dst_info = ctx.accounts.mint_dst.as_ref().map(|mint| mint.to_account_info());
+    let decimals_src = pool_state.mint_decimals(src_token_index);
+    let decimals_dst = pool_state.mint_decimals(1 - src_token_index);
+    let epoch = Clock::get()?.epoch;
+    
+    // Nothing moves while the global kill-switch is engaged
+    crate::utils::check_not_paused(&ctx.accounts.global_config)?;
+    
+    // Guard against reentrant calls on this pool
+    crate::utils::begin_pool_operation(&ctx.accounts.pool_state.to_account_info())?;
+    
+    // LP holders of this pool may pay a discounted LP fee
+    let pool_mint = pool_state.lp_mint_address(&ctx.accounts.pool_state.key(), ctx.program_id);
+    let lp_balance = crate::utils::lp_discount_balance(
+        ctx.accounts.user_lp_account.as_deref().map(|account| &**account),
+        ctx.accounts.lp_balance_snapshot.as_deref(),
+        &pool_mint,
+        &ctx.accounts.owner.key(),
+    )?;
+    let fee_numerator = pool_state.effective_fee_numerator(lp_balance);
+    
     let src_vault_amount = vault_src_account.amount as u128;
     let dst_vault_amount = vault_dst_account.amount as u128;
 
     // Protocol fee always collected in XNT (native token)
     // Check if input or output is XNT to determine where to collect fee
-    let native_mint = anchor_spl::token::spl_token::native_mint::id();
+    let native_mint = pool_state.xnt_mint();
     let is_input_xnt = user_src_account.mint == native_mint;
-    let is_output_xnt = user_dst_account.mint == native_mint;
+    let is_output_xnt = dst_account.mint == native_mint;
+    
+    // Protocol-owned pools charge the LP fee on the XNT side and send it to the
+    // treasury with the protocol fee, instead of leaving it in the vault
+    let routes_lp_fee = pool_state.routes_lp_fee(is_input_xnt || is_output_xnt);
+    let curve_fee_numerator = if routes_lp_fee { 0 } else { fee_numerator };
+    
+    // A Token 2022 transfer fee on the input never reaches the vault - price the swap on
+    // what arrives so the fee isn't paid out of the reserves
+    let amount_in_received = crate::utils::amount_after_transfer_fee(mint_src_info.as_ref(), epoch, amount_in)? as u128;
     
     // Calculate swap output first (needed to determine XNT amount for protocol fee)
     // LP fee calculated on input amount (standard AMM fee)
-    let lp_fee_amount = u128_amount_in
-        .checked_mul(pool_state.fee_numerator as u128).unwrap()
-        .checked_div(pool_state.fee_denominator as u128).unwrap();
+    let lp_fee_amount = amount_in_received
+        .checked_mul(curve_fee_numerator as u128).ok_or(ErrorCode::MathOverflow)?
+        .checked_div(pool_state.fee_denominator as u128).ok_or(ErrorCode::MathOverflow)?;
     
     // Amount after LP fee (used in swap calculation)
-    let amount_in_minus_fees = u128_amount_in - lp_fee_amount; 
+    let amount_in_minus_fees = amount_in_received.checked_sub(lp_fee_amount).ok_or(ErrorCode::MathOverflow)?;
 
     // Compute output amount using constant product equation 
-    let invariant = src_vault_amount.checked_mul(dst_vault_amount).unwrap();
-    let new_src_vault = src_vault_amount + amount_in_minus_fees; 
-    let new_dst_vault = invariant.checked_div(new_src_vault).unwrap(); 
-    let output_amount = dst_vault_amount.checked_sub(new_dst_vault).unwrap();
+    let output_amount = curve_output(src_vault_amount, dst_vault_amount, amount_in_minus_fees)?;
 
     // Calculate protocol fee in XNT (always collected in XNT)
     // Protocol fee = protocol_fee_bps% of XNT amount (input if swapping FROM XNT, output if swapping TO XNT)
@@ -124,69 +312,189 @@ pub fn swap(
         0 // No XNT involved, no protocol fee
     };
     
+    // No protocol fee during the pool's bootstrap window - a routed LP fee is still charged below
     let protocol_fee_xnt = if pool_state.protocol_treasury != Pubkey::default() 
         && pool_state.protocol_fee_bps > 0 
-        && xnt_amount_for_fee > 0 {
-        // Protocol fee = protocol_fee_bps% of XNT amount
-        xnt_amount_for_fee
-            .checked_mul(pool_state.protocol_fee_bps as u128).unwrap()
-            .checked_div(10000).unwrap()
+        && xnt_amount_for_fee > 0
+        && !pool_state.in_bootstrap_window(Clock::get()?.unix_timestamp) {
+        // Protocol fee = protocol_fee_bps% of XNT amount (or of its LP fee, per protocol_fee_mode)
+        crate::utils::protocol_fee_amount(
+            xnt_amount_for_fee,
+            pool_state.protocol_fee_bps,
+            pool_state.protocol_fee_mode,
+            fee_numerator,
+            pool_state.fee_denominator,
+        ).ok_or(ErrorCode::MathOverflow)?
     } else {
         0
     };
+    // The LP rebate share of it stays in the vaults - a routed LP fee is never rebated
+    let lp_rebate_xnt = pool_state.lp_rebate(protocol_fee_xnt);
+    let protocol_fee_xnt = if routes_lp_fee {
+        with_routed_lp_fee(protocol_fee_xnt, xnt_amount_for_fee, fee_numerator, pool_state.fee_denominator)
+            .ok_or(ErrorCode::MathOverflow)?
+    } else {
+        protocol_fee_xnt
+    };
+    // The part meant for the treasury / FeeSplit recipients
+    let treasury_fee_xnt = protocol_fee_xnt.checked_sub(lp_rebate_xnt).ok_or(ErrorCode::MathOverflow)?;
+    // A compounding pool keeps that part too and mints its value as LP to protocol_lp_account
+    let compounds = pool_state.compounds_protocol_fee();
+    let compounded_xnt = if compounds { treasury_fee_xnt } else { 0 };
+    let paid_out_fee_xnt = treasury_fee_xnt - compounded_xnt;
+
+    // Once created, the FeeSplit table takes the place of the treasury ATA - the fee is split
+    // across its recipients' XNT token accounts, passed in recipient order as remaining accounts
+    let fee_split = crate::utils::load_fee_split(&ctx.accounts.fee_split)?;
+    let fee_split_payouts = match fee_split.as_ref() {
+        Some(fee_split) if protocol_fee_xnt > 0 && !compounds => Some(crate::utils::fee_split_payouts(
+            fee_split,
+            &ctx.accounts.pool_state.key(),
+            ctx.remaining_accounts,
+            u64::try_from(treasury_fee_xnt).map_err(|_| ErrorCode::MathOverflow)?,
+            |account, recipient| {
+                require!(
+                    crate::utils::is_token(account.owner) || crate::utils::is_token_2022(account.owner),
+                    ErrorCode::InvalidTreasury
+                );
+                let recipient_account = TokenAccount::try_deserialize(&mut &account.try_borrow_data()?[..])?;
+                require!(recipient_account.owner == *recipient, ErrorCode::InvalidTreasury);
+                require!(recipient_account.mint == native_mint, ErrorCode::InvalidTreasury);
+                Ok(())
+            },
+        )?),
+        _ => None,
+    };
+
+    // Pools that opt in create a missing treasury ATA rather than skipping the fee
+    if pool_state.create_treasury_ata
+        && !compounds
+        && fee_split_payouts.is_none()
+        && protocol_fee_xnt > 0
+        && ctx.accounts.protocol_treasury_ata.data_is_empty() {
+        let (Some(protocol_treasury), Some(native_mint_account), Some(associated_token_program), Some(system_program)) = (
+            ctx.accounts.protocol_treasury.as_ref(),
+            ctx.accounts.native_mint.as_ref(),
+            ctx.accounts.associated_token_program.as_ref(),
+            ctx.accounts.system_program.as_ref(),
+        ) else {
+            return Err(ErrorCode::InvalidTreasury.into());
+        };
+        require!(protocol_treasury.key() == pool_state.protocol_treasury, ErrorCode::InvalidTreasury);
+        let native_mint_program = token_program_for(
+            native_mint_account.owner,
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.token_2022_program.to_account_info(),
+        )?;
+        
+        crate::utils::create_treasury_ata_if_missing(
+            ctx.accounts.owner.to_account_info(),
+            ctx.accounts.protocol_treasury_ata.to_account_info(),
+            protocol_treasury.to_account_info(),
+            native_mint_account.to_account_info(),
+            &native_mint,
+            system_program.to_account_info(),
+            native_mint_program,
+            associated_token_program.to_account_info(),
+        )?;
+    }
 
     // Check if treasury ATA exists and is valid (before deducting fees)
     let treasury_ata_valid = pool_state.protocol_treasury != Pubkey::default()
+        && !compounds
+        && fee_split_payouts.is_none()
         && protocol_fee_xnt > 0
         && !ctx.accounts.protocol_treasury_ata.data_is_empty()
-        && *ctx.accounts.protocol_treasury_ata.owner == ctx.accounts.token_program.key();
+        && (crate::utils::is_token(ctx.accounts.protocol_treasury_ata.owner)
+            || crate::utils::is_token_2022(ctx.accounts.protocol_treasury_ata.owner));
 
-    // Adjust output if protocol fee is deducted from XNT output
-    // Only deduct if treasury ATA is valid (otherwise user gets full amount)
-    let final_output_amount = if is_output_xnt && treasury_ata_valid {
-        // Deduct protocol fee from XNT output
-        output_amount.checked_sub(protocol_fee_xnt).unwrap()
+    // The treasury ATA must belong to the treasury wallet and hold XNT,
+    // otherwise any caller-controlled token account could collect the fee
+    if treasury_ata_valid {
+        // Unchecked because it may not exist yet, deserialize it now that it does
+        let treasury_ata_account = TokenAccount::try_deserialize(
+            &mut &ctx.accounts.protocol_treasury_ata.try_borrow_data()?[..]
+        )?;
+        require!(treasury_ata_account.owner == pool_state.protocol_treasury, ErrorCode::InvalidTreasury);
+        require!(treasury_ata_account.mint == native_mint, ErrorCode::InvalidTreasury);
+    }
+
+    // Where the protocol fee goes - nowhere if neither a FeeSplit nor a valid treasury ATA
+    let fee_destination_valid = compounds || fee_split_payouts.is_some() || treasury_ata_valid;
+    let fee_payouts = match fee_split_payouts {
+        Some(payouts) => payouts,
+        None if treasury_ata_valid && paid_out_fee_xnt > 0 => vec![(
+            ctx.accounts.protocol_treasury_ata.to_account_info(),
+            u64::try_from(paid_out_fee_xnt).map_err(|_| ErrorCode::MathOverflow)?,
+        )],
+        None => Vec::new(),
+    };
+    // A fee kept in the pool (rebated or compounded) pays nobody but is still charged,
+    // as it would be if paid out
+    let retained_fee_xnt = lp_rebate_xnt + compounded_xnt;
+    let fee_collectable = !fee_payouts.is_empty() || (retained_fee_xnt > 0 && fee_destination_valid);
+
+    // The routed LP fee must not be skipped like an uncollectable protocol fee
+    require!(!routes_lp_fee || protocol_fee_xnt == 0 || fee_collectable, ErrorCode::InvalidTreasury);
+
+    // Adjust input if protocol fee is deducted from XNT input
+    // Only deduct if the fee is collectable (otherwise user sends full amount)
+    let final_amount_to_vault = if is_input_xnt && fee_collectable {
+        // Deduct protocol fee from XNT input before sending to vault, keeping any retained part in it
+        u128_amount_in.checked_sub(paid_out_fee_xnt).ok_or(ErrorCode::MathOverflow)?
+    } else {
+        u128_amount_in
+    };
+    
+    // A routed LP fee leaves the curve fee-free, so with XNT in the curve must be priced on
+    // what reaches the vault once the fees are paid out - pricing it on amount_in would
+    // pay the fees out of the reserves
+    let output_amount = if routes_lp_fee && is_input_xnt && fee_collectable {
+        let amount_to_vault = u64::try_from(final_amount_to_vault).map_err(|_| ErrorCode::MathOverflow)?;
+        let amount_to_curve = crate::utils::amount_after_transfer_fee(mint_src_info.as_ref(), epoch, amount_to_vault)?;
+        curve_output(src_vault_amount, dst_vault_amount, amount_to_curve as u128)?
     } else {
         output_amount
     };
     
-    // Adjust input if protocol fee is deducted from XNT input
-    // Only deduct if treasury ATA is valid (otherwise user sends full amount)
-    let final_amount_to_vault = if is_input_xnt && treasury_ata_valid {
-        // Deduct protocol fee from XNT input before sending to vault
-        u128_amount_in.checked_sub(protocol_fee_xnt).unwrap()
+    // Adjust output if protocol fee is deducted from XNT output
+    // Only deduct if the fee is collectable (otherwise user gets full amount)
+    let final_output_amount = if is_output_xnt && fee_collectable {
+        // Deduct protocol fee from XNT output - a fee above the gross output reverts cleanly
+        output_amount.checked_sub(protocol_fee_xnt).ok_or(ErrorCode::NotEnoughOut)?
     } else {
-        u128_amount_in
+        output_amount
     };
 
-    // Revert if not enough out (after protocol fee deduction)
-    require!(final_output_amount >= min_amount_out as u128, ErrorCode::NotEnoughOut);
+    // Amounts move through u64 token transfers - fail rather than truncate the high bits
+    let final_output_amount = u64::try_from(final_output_amount).map_err(|_| ErrorCode::MathOverflow)?;
+    let final_amount_to_vault = u64::try_from(final_amount_to_vault).map_err(|_| ErrorCode::MathOverflow)?;
+    let lp_fee_amount = u64::try_from(lp_fee_amount).map_err(|_| ErrorCode::MathOverflow)?;
+
+    // Revert if not enough out (after protocol fee deduction) - a fee consuming the
+    // whole output would otherwise take the input for nothing when min_amount_out is 0
+    require!(final_output_amount > 0, ErrorCode::NotEnoughOut);
+    require!(final_output_amount >= min_amount_out, ErrorCode::NotEnoughOut);
+    let quoted_amount_received = crate::utils::amount_after_transfer_fee(mint_dst_info.as_ref(), epoch, final_output_amount)?;
+    slippage_quote.check_cap(quoted_amount_received, min_amount_out, pool_state.max_allowed_slippage_bps)?;
+
+    // The vault pays the user's output plus any protocol fee taken from it (together
+    // output_amount) and must never be emptied - fail cleanly instead of in the token program
+    require!(output_amount < dst_vault_amount, ErrorCode::InsufficientLiquidity);
+    crate::utils::check_swap_size(output_amount, dst_vault_amount, pool_state.max_swap_out_bps_of_reserve)?;
 
-    // Detect token programs by checking the owner of the token accounts
     // Token accounts are owned by their respective token programs (Token or Token 2022)
-    // If account is owned by Token 2022 Program, use Token 2022 for transfers
-    // If account is owned by standard Token Program, use standard Token for transfers
-    let src_token_account_owner = ctx.accounts.user_src.to_account_info().owner;
-    let dst_token_account_owner = ctx.accounts.user_dst.to_account_info().owner;
-    
-    // Also check vault owners to ensure consistency
-    let src_vault_owner = ctx.accounts.vault_src.to_account_info().owner;
-    let dst_vault_owner = ctx.accounts.vault_dst.to_account_info().owner;
-    
     // Use vault owners for determining token program (more reliable)
-    let src_mint_program = src_vault_owner;
-    let dst_mint_program = dst_vault_owner;
-    
-    // Verify token_2022_program if needed
-    if is_token_2022(&src_mint_program) || is_token_2022(&dst_mint_program) {
-        require!(
-            ctx.accounts.token_2022_program.key().to_string() == crate::utils::TOKEN_2022_PROGRAM_ID,
-            ErrorCode::InvalidTreasury
-        );
-    }
-    
-    // Helper function to get the correct token program account info
-    // We'll inline this in each transfer call to avoid lifetime issues
+    let src_program = token_program_for(
+        ctx.accounts.vault_src.to_account_info().owner,
+        &ctx.accounts.token_program.to_account_info(),
+        &ctx.accounts.token_2022_program.to_account_info(),
+    )?;
+    let dst_program = token_program_for(
+        ctx.accounts.vault_dst.to_account_info().owner,
+        &ctx.accounts.token_program.to_account_info(),
+        &ctx.accounts.token_2022_program.to_account_info(),
+    )?;
 
     // output_amount -> user_dst
     let pool_state_key = ctx.accounts.pool_state.key();
@@ -198,83 +506,208 @@ pub fn swap(
     
     // Transfer output to user (after protocol fee deduction if XNT output and treasury valid)
     // Note: Token 2022 transfer fees are handled automatically by the program
-    let dst_program = if is_token_2022(&dst_mint_program) {
-        ctx.accounts.token_2022_program.to_account_info()
-    } else {
-        ctx.accounts.token_program.to_account_info()
-    };
-    crate::utils::transfer_tokens_signed(
+    crate::utils::transfer_tokens_checked(
         ctx.accounts.vault_dst.to_account_info(),
-        ctx.accounts.user_dst.to_account_info(),
+        dst_account.to_account_info(),
         ctx.accounts.pool_authority.to_account_info(),
-        dst_program,
-        final_output_amount as u64,
+        dst_program.clone(),
+        mint_dst_info.clone(),
+        decimals_dst,
+        ctx.remaining_accounts,
+        final_output_amount,
         &[pda_sign],
     )?;
+    // dst_account holds its pre-swap balance, re-read what actually arrived
+    let amount_received = crate::utils::token_account_amount(&dst_account.to_account_info())?
+        .checked_sub(dst_account.amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    // A Token 2022 transfer fee comes out of the recipient's side, so min_amount_out
+    // must also hold for what arrived, not just for the amount sent
+    require!(amount_received >= min_amount_out, ErrorCode::NotEnoughOut);
     
     // Protocol fee ALWAYS sent as NATIVE XNT (not wrapped) directly to treasury wallet
     // For regular pools with wrapped XNT, we transfer wrapped XNT to treasury's wrapped XNT account,
     // but the treasury should unwrap it. However, the preferred approach is to use native pools.
     
+    // What the fee recipients actually received - less than the shares sent when a
+    // Token 2022 XNT mint charges a transfer fee, which comes out of the recipient's side
+    let mut protocol_fee_received: u64 = 0;
+    
     // If protocol fee deducted from output (Token → XNT swap)
-    if is_output_xnt && protocol_fee_xnt > 0 && pool_state.protocol_treasury != Pubkey::default() {
-        // Transfer wrapped XNT fee to treasury's wrapped XNT account
+    if is_output_xnt {
+        // Transfer wrapped XNT fee to treasury's (or each FeeSplit recipient's) wrapped XNT account
         // Treasury will receive wrapped XNT, which can be unwrapped to native XNT
         // NOTE: For true native XNT only, use native pools instead of regular pools
-        let dst_program_fee = if is_token_2022(&dst_mint_program) {
-            ctx.accounts.token_2022_program.to_account_info()
-        } else {
-            ctx.accounts.token_program.to_account_info()
-        };
-        crate::utils::transfer_tokens_signed(
-            ctx.accounts.vault_dst.to_account_info(),
-            ctx.accounts.protocol_treasury_ata.to_account_info(),
-            ctx.accounts.pool_authority.to_account_info(),
-            dst_program_fee,
-            protocol_fee_xnt as u64,
-            &[pda_sign],
-        )?;
+        for (fee_account, share) in &fee_payouts {
+            let fee_account_before = crate::utils::token_account_amount(fee_account)?;
+            crate::utils::transfer_tokens_checked(
+                ctx.accounts.vault_dst.to_account_info(),
+                fee_account.clone(),
+                ctx.accounts.pool_authority.to_account_info(),
+                dst_program.clone(),
+                mint_dst_info.clone(),
+                decimals_dst,
+                ctx.remaining_accounts,
+                *share,
+                &[pda_sign],
+            )?;
+            let received = crate::utils::token_account_amount(fee_account)?
+                .checked_sub(fee_account_before)
+                .ok_or(ErrorCode::MathOverflow)?;
+            protocol_fee_received = protocol_fee_received.checked_add(received).ok_or(ErrorCode::MathOverflow)?;
+        }
         
 // msg!("💰 Protocol fee: {} wrapped XNT sent to treasury (can be unwrapped to native XNT)", protocol_fee_xnt);
     }
 
     // Transfer protocol fee from input if swapping FROM XNT
-    if is_input_xnt && protocol_fee_xnt > 0 && pool_state.protocol_treasury != Pubkey::default() {
-        // Transfer wrapped XNT fee from user to treasury's wrapped XNT account
+    if is_input_xnt {
+        // Transfer wrapped XNT fee from user to treasury's (or each FeeSplit recipient's) wrapped XNT account
         // Treasury will receive wrapped XNT, which can be unwrapped to native XNT
         // NOTE: For true native XNT only, use native pools instead of regular pools
-        let src_program_fee = if is_token_2022(&src_mint_program) {
-            ctx.accounts.token_2022_program.to_account_info()
-        } else {
-            ctx.accounts.token_program.to_account_info()
-        };
-        crate::utils::transfer_tokens(
-            ctx.accounts.user_src.to_account_info(),
-            ctx.accounts.protocol_treasury_ata.to_account_info(),
-            ctx.accounts.owner.to_account_info(),
-            src_program_fee,
-            protocol_fee_xnt as u64,
-        )?;
+        for (fee_account, share) in &fee_payouts {
+            let fee_account_before = crate::utils::token_account_amount(fee_account)?;
+            crate::utils::transfer_tokens_checked(
+                ctx.accounts.user_src.to_account_info(),
+                fee_account.clone(),
+                ctx.accounts.owner.to_account_info(),
+                src_program.clone(),
+                mint_src_info.clone(),
+                decimals_src,
+                ctx.remaining_accounts,
+                *share,
+                &[],
+            )?;
+            let received = crate::utils::token_account_amount(fee_account)?
+                .checked_sub(fee_account_before)
+                .ok_or(ErrorCode::MathOverflow)?;
+            protocol_fee_received = protocol_fee_received.checked_add(received).ok_or(ErrorCode::MathOverflow)?;
+        }
         
 // msg!("💰 Protocol fee: {} wrapped XNT sent to treasury (can be unwrapped to native XNT)", protocol_fee_xnt);
     }
     
     // Transfer input to vault (after protocol fee deduction if XNT input)
     // Note: Token 2022 transfer fees are handled automatically by the program
-    let src_program = if is_token_2022(&src_mint_program) {
-        ctx.accounts.token_2022_program.to_account_info()
-    } else {
-        ctx.accounts.token_program.to_account_info()
-    };
-    crate::utils::transfer_tokens(
+    crate::utils::transfer_tokens_checked(
         ctx.accounts.user_src.to_account_info(),
         ctx.accounts.vault_src.to_account_info(),
         ctx.accounts.owner.to_account_info(),
         src_program,
-        final_amount_to_vault as u64,
+        mint_src_info.clone(),
+        decimals_src,
+        ctx.remaining_accounts,
+        final_amount_to_vault,
+        &[],
     )?;
 
-    Ok(())
+    // Re-read every balance the swap moved and match it against the computed amounts,
+    // catching a partial transfer or unexpected Token 2022 extension behaviour
+    if verify_execution {
+        let fee_paid: u64 = fee_payouts.iter().map(|(_, share)| share).sum();
+        let (src_fee, dst_fee) = if is_input_xnt { (fee_paid, 0) } else if is_output_xnt { (0, fee_paid) } else { (0, 0) };
+        let user_src_debit = final_amount_to_vault.checked_add(src_fee).ok_or(ErrorCode::MathOverflow)?;
+        let vault_dst_debit = final_output_amount.checked_add(dst_fee).ok_or(ErrorCode::MathOverflow)?;
+        // Transfer fees come out of the receiving side
+        let vault_src_credit = crate::utils::amount_after_transfer_fee(mint_src_info.as_ref(), epoch, final_amount_to_vault)?;
+        let dst_credit = crate::utils::amount_after_transfer_fee(mint_dst_info.as_ref(), epoch, final_output_amount)?;
+        
+        crate::utils::check_balance_delta(&ctx.accounts.user_src.to_account_info(), user_src_account.amount, user_src_debit, false)?;
+        crate::utils::check_balance_delta(&ctx.accounts.vault_src.to_account_info(), vault_src_account.amount, vault_src_credit, true)?;
+        crate::utils::check_balance_delta(&ctx.accounts.vault_dst.to_account_info(), vault_dst_account.amount, vault_dst_debit, false)?;
+        crate::utils::check_balance_delta(&dst_account.to_account_info(), dst_account.amount, dst_credit, true)?;
+    }
+
+    // Track the LP fee per LP unit, on the side it was paid in, and the protocol
+    // fee that was actually transferred (nothing when it was skipped)
+    pool_state.accrue_lp_fee(src_token_index, lp_fee_amount);
+    if fee_collectable {
+        // The LP rebate is in XNT, on whichever side XNT is
+        let xnt_token_index = if is_input_xnt { src_token_index } else { 1 - src_token_index };
+        let lp_rebate_xnt = u64::try_from(lp_rebate_xnt).map_err(|_| ErrorCode::MathOverflow)?;
+        pool_state.accrue_lp_fee(xnt_token_index, lp_rebate_xnt);
+    }
+    if fee_collectable && compounded_xnt > 0 {
+        // Value the fee against the XNT reserve it was kept in, as it stood before the swap
+        let xnt_reserve = if is_input_xnt { src_vault_amount } else { dst_vault_amount };
+        let compounded_lp = crate::utils::compound_lp_amount(
+            u64::try_from(compounded_xnt).map_err(|_| ErrorCode::MathOverflow)?,
+            u64::try_from(xnt_reserve).map_err(|_| ErrorCode::MathOverflow)?,
+            pool_state.total_amount_minted,
+        )?;
+        if compounded_lp > 0 {
+            let pool_mint = ctx.accounts.pool_mint.as_ref().ok_or(ErrorCode::InvalidTreasury)?;
+            let protocol_lp_account = ctx.accounts.protocol_lp_account.as_ref().ok_or(ErrorCode::InvalidTreasury)?;
+            require!(
+                pool_mint.key() == pool_state.lp_mint_address(&pool_state_key, ctx.program_id),
+                ErrorCode::MintMismatch
+            );
+            require!(protocol_lp_account.key() == pool_state.protocol_lp_account, ErrorCode::InvalidTreasury);
+            
+            // LP mint always uses standard Token program
+            crate::utils::mint_compounded_lp(
+                pool_mint.to_account_info(),
+                protocol_lp_account.to_account_info(),
+                ctx.accounts.pool_authority.to_account_info(),
+                ctx.accounts.token_program.to_account_info(),
+                &[pda_sign],
+                compounded_lp,
+                pool_state.lp_soulbound,
+            )?;
+            pool_state.total_amount_minted = pool_state.total_amount_minted
+                .checked_add(compounded_lp)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+    }
+    // The swapper is charged the full shares, the treasury is credited what it received
+    let protocol_fee_paid: u64 = fee_payouts.iter().map(|(_, share)| share).sum();
+    pool_state.record_protocol_fee_collected(protocol_fee_received);
+    pool_state.try_persist(&ctx.accounts.pool_state.to_account_info())?;
+
+    // Vault balances loaded by Anchor are pre-swap, re-read the post-swap ones
+    #[cfg(feature = "invariant-checks")]
+    crate::utils::check_invariant(
+        vault_src_account.amount,
+        vault_dst_account.amount,
+        crate::utils::token_account_amount(&ctx.accounts.vault_src.to_account_info())?,
+        crate::utils::token_account_amount(&ctx.accounts.vault_dst.to_account_info())?,
+    )?;
+
+    crate::utils::end_pool_operation(&ctx.accounts.pool_state.to_account_info())?;
+
+    Ok(SwapResult {
+        amount_in_used: amount_in,
+        amount_out: amount_received,
+        protocol_fee: protocol_fee_paid,
+    })
+}
+
+/// Output the constant-product curve pays for `amount_in` against the pre-swap vault balances
+/// The new dst balance rounds up, so k never decreases even on a fee-free curve (and the
+/// output matches calculate_swap_output)
+fn curve_output(src_vault_amount: u128, dst_vault_amount: u128, amount_in: u128) -> Result<u128> {
+    let invariant = src_vault_amount.checked_mul(dst_vault_amount).ok_or(ErrorCode::MathOverflow)?;
+    let new_src_vault = src_vault_amount.checked_add(amount_in).ok_or(ErrorCode::MathOverflow)?;
+    require!(new_src_vault > 0, ErrorCode::MathOverflow);
+    let new_dst_vault = invariant.div_ceil(new_src_vault);
+    Ok(dst_vault_amount.checked_sub(new_dst_vault).ok_or(ErrorCode::MathOverflow)?)
+}
+
+/// `protocol_fee_xnt` plus the whole LP fee on `xnt_amount`, for pools that route it to the treasury
+fn with_routed_lp_fee(
+    protocol_fee_xnt: u128,
+    xnt_amount: u128,
+    fee_numerator: u64,
+    fee_denominator: u64,
+) -> Option<u128> {
+    let routed_lp_fee = crate::utils::protocol_fee_amount(
+        xnt_amount,
+        10000,
+        PROTOCOL_FEE_MODE_LP_FEE,
+        fee_numerator,
+        fee_denominator,
+    )?;
+    protocol_fee_xnt.checked_add(routed_lp_fee)
 }
 
 #[derive(Accounts)]
@@ -289,20 +722,19 @@ pub struct Swap<'info> {
     #[account(mut)]
     /// CHECK: Pool authority PDA - verified in handler
     pub pool_authority: AccountInfo<'info>,
-    /// CHECK: Vault can be Token or Token2022, validated in handler
+    // vaults can be Token or Token2022, ownership validated in handler
     #[account(mut)]
-    pub vault_src: UncheckedAccount<'info>,
-    /// CHECK: Vault can be Token or Token2022, validated in handler
+    pub vault_src: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(mut)]
-    pub vault_dst: UncheckedAccount<'info>,
+    pub vault_dst: Box<InterfaceAccount<'info, TokenAccount>>,
     
     // user token accounts 
-    /// CHECK: User token account, validated in handler
     #[account(mut)]
-    pub user_src: UncheckedAccount<'info>,
-    /// CHECK: User token account, validated in handler
+    pub user_src: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut)]
+    pub user_dst: Box<InterfaceAccount<'info, TokenAccount>>, 
+    // Pays rent when the treasury ATA is created (create_treasury_ata pools)
     #[account(mut)]
-    pub user_dst: UncheckedAccount<'info>, 
     pub owner: Signer<'info>,
 
     // Protocol treasury ATA (optional - only used if treasury is configured)
@@ -316,4 +748,733 @@ pub struct Swap<'info> {
     pub token_program: Program<'info, Token>,
     /// CHECK: Token 2022 program - verified in handler
     pub token_2022_program: UncheckedAccount<'info>,
+
+    // Program-wide GlobalConfig PDA - swaps and liquidity fail while its kill-switch is engaged
+    /// CHECK: Uninitialized until initialize_global_config (nothing is paused then) - read in handler
+    #[account(seeds = [b"global_config"], bump)]
+    pub global_config: UncheckedAccount<'info>,
+
+    // Pool's FeeSplit PDA - once created, its recipients' XNT token accounts follow as remaining accounts
+    /// CHECK: Uninitialized until initialize_fee_split (the treasury is paid then) - read in handler
+    #[account(seeds = [b"fee_split", pool_state.key().as_ref()], bump)]
+    pub fee_split: UncheckedAccount<'info>,
+
+    // Every account below is optional and positional: the program ID stands for None, and
+    // only a trailing run of them may be left out. Remaining accounts (FeeSplit recipients,
+    // transfer hook accounts) start after all 11, so a client passing any must pass every
+    // optional first - padding unused ones with the program ID - or the first of them would
+    // be read as protocol_treasury. A compounding pool pays no FeeSplit recipient
+
+    // Only required when the pool has create_treasury_ata set and the ATA is missing
+    /// CHECK: Treasury wallet - must equal pool_state.protocol_treasury, verified in handler
+    pub protocol_treasury: Option<UncheckedAccount<'info>>,
+    /// CHECK: XNT mint - verified in handler
+    pub native_mint: Option<UncheckedAccount<'info>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+    pub system_program: Option<Program<'info, System>>,
+
+    // Only required by swap_to - receives the output, may belong to any wallet
+    #[account(mut)]
+    pub recipient_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    // Swapper's LP tokens of this pool - qualifies for the LP holder fee discount
+    pub user_lp_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    // Optional mints of vault_src / vault_dst - cross-checked against the vaults when passed
+    // Required for Token 2022 mints with a transfer fee or hook (moved with TransferChecked)
+    pub mint_src: Option<Box<InterfaceAccount<'info, Mint>>>,
+    pub mint_dst: Option<Box<InterfaceAccount<'info, Mint>>>,
+
+    // Only required when the pool compounds its protocol fee (see PoolState::protocol_lp_account)
+    /// CHECK: Pool's LP mint - verified in handler
+    #[account(mut)]
+    pub pool_mint: Option<UncheckedAccount<'info>>,
+    /// CHECK: Must equal pool_state.protocol_lp_account, verified in handler
+    #[account(mut)]
+    pub protocol_lp_account: Option<UncheckedAccount<'info>>,
+
+    // Swapper's LpBalanceSnapshot - required with user_lp_account for the LP holder fee discount
+    #[account(seeds = [b"lp_snapshot", pool_state.key().as_ref(), owner.key().as_ref()], bump)]
+    pub lp_balance_snapshot: Option<Account<'info, LpBalanceSnapshot>>,
+}
+
+/// Create the protocol treasury's XNT associated token account if it doesn't exist
+/// Anyone may call and pays the rent; a no-op when the ATA already exists.
+/// Swaps skip the protocol fee while the ATA is missing, so operators can run this up front.
+pub fn ensure_treasury_ata(ctx: Context<EnsureTreasuryAta>) -> Result<()> {
+    let pool_state = PoolState::try_deserialize(&mut &ctx.accounts.pool_state.to_account_info().data.borrow()[..])?;
+    
+    require!(pool_state.protocol_treasury != Pubkey::default(), ErrorCode::InvalidTreasury);
+    require!(ctx.accounts.protocol_treasury.key() == pool_state.protocol_treasury, ErrorCode::InvalidTreasury);
+    
+    // A Token 2022 XNT mint needs the trailing token_2022_program
+    let token_program = ctx.accounts.token_program.to_account_info();
+    let native_mint_program = token_program_for(
+        ctx.accounts.native_mint.owner,
+        &token_program,
+        ctx.accounts.token_2022_program.as_ref().map(|program| program.as_ref()).unwrap_or(&token_program),
+    )?;
+    
+    crate::utils::create_treasury_ata_if_missing(
+        ctx.accounts.payer.to_account_info(),
+        ctx.accounts.protocol_treasury_ata.to_account_info(),
+        ctx.accounts.protocol_treasury.to_account_info(),
+        ctx.accounts.native_mint.to_account_info(),
+        &pool_state.xnt_mint(),
+        ctx.accounts.system_program.to_account_info(),
+        native_mint_program,
+        ctx.accounts.associated_token_program.to_account_info(),
+    )
+}
+
+#[derive(Accounts)]
+pub struct EnsureTreasuryAta<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    /// CHECK: Pool state - manually deserialized for backward compatibility
+    pub pool_state: UncheckedAccount<'info>,
+    
+    /// CHECK: Treasury wallet - must equal pool_state.protocol_treasury, verified in handler
+    pub protocol_treasury: UncheckedAccount<'info>,
+    
+    // Address derivation is checked by the associated token program
+    #[account(mut)]
+    /// CHECK: Treasury XNT ATA - may not exist yet
+    pub protocol_treasury_ata: UncheckedAccount<'info>,
+    
+    /// CHECK: XNT mint - verified in create_treasury_ata_if_missing
+    pub native_mint: UncheckedAccount<'info>,
+    
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    
+    // Only required when the pool's XNT mint is a Token 2022 mint
+    /// CHECK: Token 2022 program - verified in handler
+    pub token_2022_program: Option<UncheckedAccount<'info>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::PROTOCOL_FEE_MODE_NOTIONAL;
+    use crate::test_fixtures::{
+        empty_account, install_stubs, mint_account, pda, program, program_account, set_token_amount, signer, token_account, Info,
+    };
+
+    /// An SPL pool's accounts - 1_000_000_000 of each token, token0 being XNT
+    struct TestPool {
+        pool_state: Info,
+        vault0: Info,
+        vault1: Info,
+    }
+
+    fn spl_pool(pool_state: PoolState) -> TestPool {
+        let pool_key = Pubkey::new_unique();
+        let pool_authority = pda(&[b"authority", pool_key.as_ref()]);
+        TestPool {
+            vault0: token_account(pda(&[b"vault0", pool_key.as_ref()]), pool_state.xnt_mint(), pool_authority, 1_000_000_000).info(),
+            vault1: token_account(pda(&[b"vault1", pool_key.as_ref()]), Pubkey::new_unique(), pool_authority, 1_000_000_000).info(),
+            pool_state: program_account(pool_key, &pool_state).info(),
+        }
+    }
+
+    /// A 0.3% fee pool open for swaps
+    fn swap_pool() -> PoolState {
+        PoolState {
+            fee_numerator: 30,
+            fee_denominator: 10000,
+            swaps_enabled: true,
+            max_allowed_slippage_bps: 10000,
+            max_swap_out_bps_of_reserve: 10000,
+            ..Default::default()
+        }
+    }
+
+    /// Swap accounts for `pool` from `vault_src` to `vault_dst`, the swapper holding
+    /// 1_000_000_000 of each side
+    fn swap_accounts(pool: &TestPool, vault_src: Info, vault_dst: Info) -> Swap<'static> {
+        let owner = signer().info();
+        let mint = |vault: Info| TokenAccount::try_deserialize(&mut &vault.try_borrow_data().unwrap()[..]).unwrap().mint;
+        Swap {
+            pool_state: UncheckedAccount::try_from(pool.pool_state),
+            pool_authority: empty_account(pda(&[b"authority", pool.pool_state.key.as_ref()])).info().clone(),
+            vault_src: Box::new(InterfaceAccount::try_from(vault_src).unwrap()),
+            vault_dst: Box::new(InterfaceAccount::try_from(vault_dst).unwrap()),
+            user_src: Box::new(InterfaceAccount::try_from(
+                token_account(Pubkey::new_unique(), mint(vault_src), *owner.key, 1_000_000_000).info()
+            ).unwrap()),
+            user_dst: Box::new(InterfaceAccount::try_from(
+                token_account(Pubkey::new_unique(), mint(vault_dst), *owner.key, 1_000_000_000).info()
+            ).unwrap()),
+            owner: Signer::try_from(owner).unwrap(),
+            protocol_treasury_ata: UncheckedAccount::try_from(empty_account(Pubkey::new_unique()).info()),
+            token_program: Program::try_from(program(Token::id()).info()).unwrap(),
+            token_2022_program: UncheckedAccount::try_from(program(spl_token_2022::ID).info()),
+            global_config: UncheckedAccount::try_from(empty_account(pda(&[b"global_config"])).info()),
+            fee_split: UncheckedAccount::try_from(empty_account(pda(&[b"fee_split", pool.pool_state.key.as_ref()])).info()),
+            protocol_treasury: None,
+            native_mint: None,
+            associated_token_program: None,
+            system_program: None,
+            recipient_token_account: None,
+            user_lp_account: None,
+            mint_src: None,
+            mint_dst: None,
+            pool_mint: None,
+            protocol_lp_account: None,
+            lp_balance_snapshot: None,
+        }
+    }
+
+    /// `swap` of `amount_in` - `delivered` stands in for the output arriving at user_dst,
+    /// which the handler re-reads after the (off-chain no-op) transfer
+    fn run_swap(accounts: &mut Swap<'static>, amount_in: u64, min_amount_out: u64, delivered: u64) -> Result<SwapResult> {
+        install_stubs();
+        set_token_amount(&accounts.user_dst.to_account_info(), accounts.user_dst.amount + delivered);
+        swap(Context::new(&crate::ID, accounts, &[], SwapBumps::default()), amount_in, min_amount_out)
+    }
+
+    #[test]
+    fn protocol_owned_pool_sends_every_fee_to_treasury() {
+        let pool_state = PoolState {
+            protocol_owned: true,
+            protocol_treasury: Pubkey::new_unique(),
+            fee_numerator: 30,
+            fee_denominator: 10000,
+            protocol_fee_bps: 10,
+            protocol_fee_mode: PROTOCOL_FEE_MODE_NOTIONAL,
+            ..Default::default()
+        };
+        let xnt_amount = 1_000_000u128;
+        
+        // The curve charges no LP fee, the treasury takes it along with the protocol fee
+        assert!(pool_state.routes_lp_fee(true));
+        let protocol_fee = crate::utils::protocol_fee_amount(
+            xnt_amount,
+            pool_state.protocol_fee_bps,
+            pool_state.protocol_fee_mode,
+            pool_state.fee_numerator,
+            pool_state.fee_denominator,
+        ).unwrap();
+        assert_eq!(protocol_fee, 1_000);
+        assert_eq!(
+            with_routed_lp_fee(protocol_fee, xnt_amount, pool_state.fee_numerator, pool_state.fee_denominator),
+            Some(1_000 + 3_000)
+        );
+        
+        // Without an XNT side there is nothing to route
+        assert!(!pool_state.routes_lp_fee(false));
+    }
+
+    #[test]
+    fn lp_fee_stays_in_vault_outside_protocol_owned_pools() {
+        let pool_state = PoolState { protocol_treasury: Pubkey::new_unique(), ..Default::default() };
+        assert!(!pool_state.routes_lp_fee(true));
+        
+        // A protocol-owned pool without a treasury has nowhere to send it
+        let pool_state = PoolState { protocol_owned: true, ..Default::default() };
+        assert!(!pool_state.routes_lp_fee(true));
+    }
+
+    #[test]
+    fn slippage_bps_applies_to_the_client_quote() {
+        let quoted = 1_000_000;
+        assert_eq!(min_amount_out_for_slippage(quoted, 50).unwrap(), 995_000);
+        
+        // Quote on the reserves the client read, then someone swaps the same way first
+        let amount_in = 1_000_000;
+        let quoted = crate::utils::calculate_swap_output(amount_in, 1_000_000_000, 1_000_000_000, 30, 10000).unwrap();
+        let min_amount_out = min_amount_out_for_slippage(quoted, 50).unwrap();
+        let small_move = crate::utils::calculate_swap_output(amount_in, 1_002_000_000, 998_004_000, 30, 10000).unwrap();
+        let large_move = crate::utils::calculate_swap_output(amount_in, 1_010_000_000, 990_099_010, 30, 10000).unwrap();
+        // execute_swap requires the output to reach min_amount_out
+        assert!(small_move >= min_amount_out);
+        assert!(large_move < min_amount_out);
+        
+        assert_eq!(min_amount_out_for_slippage(quoted, 0).unwrap(), quoted);
+        assert_eq!(min_amount_out_for_slippage(quoted, 10000).unwrap(), 0);
+        assert_eq!(min_amount_out_for_slippage(u64::MAX, 1).unwrap(), (u64::MAX as u128 * 9999 / 10000) as u64);
+        assert_eq!(min_amount_out_for_slippage(quoted, 10001).unwrap_err(), ErrorCode::InvalidInput.into());
+    }
+
+    #[test]
+    fn compounded_protocol_fee_grows_protocol_lp_with_volume() {
+        let pool_state = PoolState {
+            protocol_treasury: Pubkey::new_unique(),
+            protocol_lp_account: Pubkey::new_unique(),
+            fee_numerator: 30,
+            fee_denominator: 10000,
+            protocol_fee_bps: 10,
+            protocol_fee_mode: PROTOCOL_FEE_MODE_NOTIONAL,
+            ..Default::default()
+        };
+        assert!(pool_state.compounds_protocol_fee());
+        
+        let initial_xnt = 1_000_000_000u64;
+        let (mut xnt_reserve, mut token_reserve, mut total_minted) = (initial_xnt, 1_000_000_000u64, 1_000_000_000u64);
+        let (mut protocol_lp, mut fees_retained) = (0u64, 0u64);
+        let amount_in = 10_000_000u64;
+        for _ in 0..10 {
+            // XNT in: the protocol fee is charged on the input and kept in the vault
+            let fee = crate::utils::protocol_fee_amount(
+                amount_in as u128,
+                pool_state.protocol_fee_bps,
+                pool_state.protocol_fee_mode,
+                pool_state.fee_numerator,
+                pool_state.fee_denominator,
+            ).unwrap() as u64;
+            let amount_out = crate::utils::calculate_swap_output(amount_in, xnt_reserve, token_reserve, 30, 10000).unwrap();
+            let compounded_lp = crate::utils::compound_lp_amount(fee, xnt_reserve, total_minted).unwrap();
+            assert!(compounded_lp > 0);
+            
+            xnt_reserve += amount_in;
+            token_reserve -= amount_out;
+            total_minted += compounded_lp;
+            protocol_lp += compounded_lp;
+            fees_retained += fee;
+        }
+        
+        // Nothing was paid out - the reserves hold every input, fee included
+        assert_eq!(xnt_reserve, initial_xnt + 10 * amount_in);
+        assert_eq!(fees_retained, 10 * 10_000);
+        // The protocol's LP is worth at least the fees it was minted for
+        let protocol_value = protocol_lp as u128 * 2 * xnt_reserve as u128 / total_minted as u128;
+        assert!(protocol_value >= fees_retained as u128);
+        
+        // Twice the volume, about twice the LP
+        let mut more_lp = protocol_lp;
+        for _ in 0..10 {
+            let compounded_lp = crate::utils::compound_lp_amount(10_000, xnt_reserve, total_minted).unwrap();
+            xnt_reserve += amount_in;
+            total_minted += compounded_lp;
+            more_lp += compounded_lp;
+        }
+        assert!(more_lp > protocol_lp * 19 / 10);
+    }
+
+    #[test]
+    fn price_limit_rounds_min_amount_out_up() {
+        // 1.5 out per in
+        let limit_price = 3u128 << 63;
+        assert_eq!(min_amount_out_for_price_limit(1_000, limit_price).unwrap(), 1_500);
+        // 1.5 * 3 = 4.5 - an output of 4 would fill below the limit
+        assert_eq!(min_amount_out_for_price_limit(3, limit_price).unwrap(), 5);
+        // The smallest nonzero limit still requires some output
+        assert_eq!(min_amount_out_for_price_limit(1, 1).unwrap(), 1);
+        assert_eq!(min_amount_out_for_price_limit(1_000, 0).unwrap(), 0);
+        
+        // Exactly u64::MAX out is reachable, one more unit is not
+        assert_eq!(min_amount_out_for_price_limit(u64::MAX, 1u128 << 64).unwrap(), u64::MAX);
+        assert_eq!(
+            min_amount_out_for_price_limit(u64::MAX, (1u128 << 64) + 1).unwrap_err(),
+            ErrorCode::NotEnoughOut.into()
+        );
+        // amount_in * limit_price overflowing u128 can never be met either
+        assert_eq!(
+            min_amount_out_for_price_limit(u64::MAX, u128::MAX).unwrap_err(),
+            ErrorCode::NotEnoughOut.into()
+        );
+    }
+
+    #[test]
+    fn routed_fee_swaps_never_decrease_k() {
+        let pool_state = PoolState {
+            protocol_owned: true,
+            protocol_treasury: Pubkey::new_unique(),
+            fee_numerator: 30,
+            fee_denominator: 10000,
+            protocol_fee_bps: 10,
+            protocol_fee_mode: PROTOCOL_FEE_MODE_NOTIONAL,
+            ..Default::default()
+        };
+        // Protocol fee and routed LP fee on an XNT amount, all paid out to the treasury
+        let routed_fees = |xnt_amount: u64| {
+            let protocol_fee = crate::utils::protocol_fee_amount(
+                xnt_amount as u128,
+                pool_state.protocol_fee_bps,
+                pool_state.protocol_fee_mode,
+                pool_state.fee_numerator,
+                pool_state.fee_denominator,
+            ).unwrap();
+            with_routed_lp_fee(protocol_fee, xnt_amount as u128, pool_state.fee_numerator, pool_state.fee_denominator)
+                .unwrap() as u64
+        };
+        let (xnt_reserve, token_reserve) = (1_000_000_000u64, 3_000_000_000u64);
+        
+        for amount_in in [1_000_000u64, 50_000_000, 900_000_000] {
+            // XNT in: the vault is credited the input less the fees, and the curve is priced on that
+            let to_vault = amount_in - routed_fees(amount_in);
+            let out = curve_output(xnt_reserve as u128, token_reserve as u128, to_vault as u128).unwrap() as u64;
+            assert!(crate::utils::check_invariant(xnt_reserve, token_reserve, xnt_reserve + to_vault, token_reserve - out).is_ok());
+            // The handler's curve pays what quotes promise
+            assert_eq!(out, crate::utils::calculate_swap_output(to_vault, xnt_reserve, token_reserve, 0, 10000).unwrap());
+            // Priced on the whole input, the reserves would pay the fees
+            let out = curve_output(xnt_reserve as u128, token_reserve as u128, amount_in as u128).unwrap() as u64;
+            assert_eq!(
+                crate::utils::check_invariant(xnt_reserve, token_reserve, xnt_reserve + to_vault, token_reserve - out).unwrap_err(),
+                ErrorCode::InvariantViolated.into()
+            );
+            
+            // XNT out: the whole input reaches the vault and the fees come out of the curve's output,
+            // so the vault pays the swapper and the treasury no more than the output
+            let out = curve_output(token_reserve as u128, xnt_reserve as u128, amount_in as u128).unwrap() as u64;
+            assert!(routed_fees(out) < out);
+            assert!(crate::utils::check_invariant(token_reserve, xnt_reserve, token_reserve + amount_in, xnt_reserve - out).is_ok());
+        }
+    }
+
+    #[test]
+    fn slippage_cap_bounds_min_amount_out_against_its_quote() {
+        let cap_bps = 100;
+        
+        // On-chain, against what arrives: with a 2% output transfer fee a 10_000 quote delivers
+        // 9_800, and a 1% tolerance on that must pass even though it is 3% below the gross output
+        assert!(SlippageQuote::OnChain.check_cap(9_800, 9_702, cap_bps).is_ok());
+        assert_eq!(
+            SlippageQuote::OnChain.check_cap(9_800, 9_701, cap_bps).unwrap_err(),
+            ErrorCode::SlippageToleranceTooHigh.into()
+        );
+        
+        // swap_with_slippage_bps - the client's quote, whatever the on-chain one
+        let quoted = 10_000;
+        let within_cap = min_amount_out_for_slippage(quoted, cap_bps).unwrap();
+        assert!(SlippageQuote::Client(quoted).check_cap(0, within_cap, cap_bps).is_ok());
+        let over_cap = min_amount_out_for_slippage(quoted, cap_bps + 1).unwrap();
+        assert_eq!(
+            SlippageQuote::Client(quoted).check_cap(quoted, over_cap, cap_bps).unwrap_err(),
+            ErrorCode::SlippageToleranceTooHigh.into()
+        );
+        
+        // swap_with_price_limit - a "no limit" price is loose like a zero min_amount_out
+        let no_limit = min_amount_out_for_price_limit(1_000, 0).unwrap();
+        assert_eq!(
+            SlippageQuote::OnChain.check_cap(9_800, no_limit, cap_bps).unwrap_err(),
+            ErrorCode::SlippageToleranceTooHigh.into()
+        );
+        assert!(SlippageQuote::OnChain.check_cap(9_800, no_limit, 10000).is_ok());
+        
+        // swap_partial isn't capped
+        assert!(SlippageQuote::Uncapped.check_cap(9_800, 0, 0).is_ok());
+    }
+
+    #[test]
+    fn protocol_fee_goes_only_to_the_treasurys_xnt_account() {
+        let treasury = Pubkey::new_unique();
+        let fee_pool = || spl_pool(PoolState { protocol_treasury: treasury, protocol_fee_bps: 10, ..swap_pool() });
+        let xnt_mint = swap_pool().xnt_mint();
+        
+        // Someone else's XNT account, or the treasury's account of another mint, can't collect it
+        for treasury_ata in [
+            token_account(Pubkey::new_unique(), xnt_mint, Pubkey::new_unique(), 0),
+            token_account(Pubkey::new_unique(), Pubkey::new_unique(), treasury, 0),
+        ] {
+            let pool = fee_pool();
+            let mut accounts = swap_accounts(&pool, pool.vault0, pool.vault1);
+            accounts.protocol_treasury_ata = UncheckedAccount::try_from(treasury_ata.info());
+            assert_eq!(run_swap(&mut accounts, 1_000_000, 0, 996_000).unwrap_err(), ErrorCode::InvalidTreasury.into());
+        }
+        
+        let pool = fee_pool();
+        let mut accounts = swap_accounts(&pool, pool.vault0, pool.vault1);
+        accounts.protocol_treasury_ata = UncheckedAccount::try_from(token_account(Pubkey::new_unique(), xnt_mint, treasury, 0).info());
+        assert_eq!(run_swap(&mut accounts, 1_000_000, 0, 996_000).unwrap().protocol_fee, 1_000);
+    }
+
+    #[test]
+    fn swap_is_rejected_while_the_pool_is_mid_instruction() {
+        let pool = spl_pool(swap_pool());
+        let mut accounts = swap_accounts(&pool, pool.vault0, pool.vault1);
+        run_swap(&mut accounts, 1_000_000, 0, 996_000).unwrap();
+        // A completed swap leaves the pool free for the next one
+        assert_eq!(pool.pool_state.try_borrow_data().unwrap()[PoolState::IN_PROGRESS_OFFSET], 0);
+        
+        crate::utils::begin_pool_operation(pool.pool_state).unwrap();
+        let mut accounts = swap_accounts(&pool, pool.vault1, pool.vault0);
+        assert_eq!(run_swap(&mut accounts, 1_000_000, 0, 996_000).unwrap_err(), ErrorCode::Reentrancy.into());
+    }
+
+    #[test]
+    fn partial_swap_fills_only_up_to_the_price_impact_cap() {
+        let pool = spl_pool(swap_pool());
+        let mut accounts = swap_accounts(&pool, pool.vault0, pool.vault1);
+        install_stubs();
+        set_token_amount(&accounts.user_dst.to_account_info(), 1_000_000_000 + 9_000_000);
+        let result = swap_partial(Context::new(&crate::ID, &mut accounts, &[], SwapBumps::default()), 100_000_000, 100, 0).unwrap();
+        
+        // The largest fill within 1% impact - one unit more would exceed it
+        let reserve = 1_000_000_000;
+        assert!(result.amount_filled < 100_000_000);
+        assert!(crate::utils::price_impact_bps(result.amount_filled, reserve, reserve).unwrap() <= 100);
+        assert!(crate::utils::price_impact_bps(result.amount_filled + 1, reserve, reserve).unwrap() > 100);
+        assert_eq!(result.amount_out, 9_000_000);
+        
+        // A trade already within the cap fills whole
+        assert_eq!(crate::utils::max_amount_within_price_impact(1_000_000, reserve, reserve, 100).unwrap(), 1_000_000);
+    }
+
+    #[test]
+    fn opted_in_pool_creates_the_missing_treasury_ata_instead_of_skipping_the_fee() {
+        let treasury = Pubkey::new_unique();
+        let fee_pool = |create_treasury_ata| spl_pool(PoolState {
+            protocol_treasury: treasury,
+            protocol_fee_bps: 10,
+            create_treasury_ata,
+            ..swap_pool()
+        });
+        let with_creation_accounts = |accounts: &mut Swap<'static>, treasury: Pubkey, native_mint: Pubkey| {
+            accounts.protocol_treasury = Some(UncheckedAccount::try_from(empty_account(treasury).info()));
+            accounts.native_mint = Some(UncheckedAccount::try_from(
+                crate::test_fixtures::mint_account(native_mint, Pubkey::new_unique(), 0, 9).info()
+            ));
+            accounts.associated_token_program = Some(Program::try_from(program(AssociatedToken::id()).info()).unwrap());
+            accounts.system_program = Some(Program::try_from(program(System::id()).info()).unwrap());
+        };
+        
+        // Without the opt-in a missing ATA skips the fee
+        let pool = fee_pool(false);
+        let mut accounts = swap_accounts(&pool, pool.vault0, pool.vault1);
+        assert_eq!(run_swap(&mut accounts, 1_000_000, 0, 996_000).unwrap().protocol_fee, 0);
+        
+        // With it, the swap needs what creating the ATA takes
+        let pool = fee_pool(true);
+        let mut accounts = swap_accounts(&pool, pool.vault0, pool.vault1);
+        assert_eq!(run_swap(&mut accounts, 1_000_000, 0, 996_000).unwrap_err(), ErrorCode::InvalidTreasury.into());
+        for (wallet, mint) in [(Pubkey::new_unique(), swap_pool().xnt_mint()), (treasury, Pubkey::new_unique())] {
+            let pool = fee_pool(true);
+            let mut accounts = swap_accounts(&pool, pool.vault0, pool.vault1);
+            with_creation_accounts(&mut accounts, wallet, mint);
+            assert_eq!(run_swap(&mut accounts, 1_000_000, 0, 996_000).unwrap_err(), ErrorCode::InvalidTreasury.into());
+        }
+        let pool = fee_pool(true);
+        let mut accounts = swap_accounts(&pool, pool.vault0, pool.vault1);
+        with_creation_accounts(&mut accounts, treasury, swap_pool().xnt_mint());
+        assert!(run_swap(&mut accounts, 1_000_000, 0, 996_000).is_ok());
+    }
+
+    #[test]
+    fn swap_to_delivers_to_any_wallets_account_of_the_output_mint() {
+        install_stubs();
+        // The recipient's account needn't belong to the signer
+        let recipient = |mint| token_account(Pubkey::new_unique(), mint, Pubkey::new_unique(), 0).info();
+        let quoted = crate::utils::calculate_swap_output(1_000_000, 1_000_000_000, 1_000_000_000, 30, 10000).unwrap();
+        
+        // swap_to needs a recipient
+        let pool = spl_pool(swap_pool());
+        let mut accounts = swap_accounts(&pool, pool.vault0, pool.vault1);
+        assert_eq!(
+            swap_to(Context::new(&crate::ID, &mut accounts, &[], SwapBumps::default()), 1_000_000, 0).unwrap_err(),
+            ErrorCode::InvalidInput.into()
+        );
+        
+        // holding the output mint
+        let pool = spl_pool(swap_pool());
+        let mut accounts = swap_accounts(&pool, pool.vault0, pool.vault1);
+        accounts.recipient_token_account = Some(Box::new(InterfaceAccount::try_from(recipient(Pubkey::new_unique())).unwrap()));
+        assert_eq!(
+            swap_to(Context::new(&crate::ID, &mut accounts, &[], SwapBumps::default()), 1_000_000, 0).unwrap_err(),
+            ErrorCode::MintMismatch.into()
+        );
+        
+        let pool = spl_pool(swap_pool());
+        let mut accounts = swap_accounts(&pool, pool.vault0, pool.vault1);
+        let output_mint = accounts.vault_dst.mint;
+        let recipient_info = recipient(output_mint);
+        accounts.recipient_token_account = Some(Box::new(InterfaceAccount::try_from(recipient_info).unwrap()));
+        set_token_amount(recipient_info, quoted);
+        let result = swap_to(Context::new(&crate::ID, &mut accounts, &[], SwapBumps::default()), 1_000_000, quoted).unwrap();
+        assert_eq!(result.amount_out, quoted);
+    }
+
+    #[test]
+    fn swap_that_would_empty_the_output_vault_is_rejected() {
+        // Against an empty input vault the curve prices the whole output vault
+        let pool = spl_pool(swap_pool());
+        set_token_amount(pool.vault0, 0);
+        let mut accounts = swap_accounts(&pool, pool.vault0, pool.vault1);
+        assert_eq!(
+            run_swap(&mut accounts, 1_000_000, 0, 0).unwrap_err(),
+            ErrorCode::InsufficientLiquidity.into()
+        );
+    }
+
+    #[test]
+    fn swap_vaults_must_be_the_pools_own_vault_pdas() {
+        // An authority-owned token account of the right mint that isn't vault0
+        let pool = spl_pool(swap_pool());
+        let pool_authority = pda(&[b"authority", pool.pool_state.key.as_ref()]);
+        let vault0_mint = TokenAccount::try_deserialize(&mut &pool.vault0.try_borrow_data().unwrap()[..]).unwrap().mint;
+        let substitute = token_account(Pubkey::new_unique(), vault0_mint, pool_authority, 1_000_000_000).info();
+        let mut accounts = swap_accounts(&pool, substitute, pool.vault1);
+        assert_eq!(run_swap(&mut accounts, 1_000_000, 0, 0).unwrap_err(), ErrorCode::InvalidTreasury.into());
+        
+        // Another pool's vaults
+        let pool = spl_pool(swap_pool());
+        let other = spl_pool(swap_pool());
+        let mut accounts = swap_accounts(&pool, other.vault0, other.vault1);
+        assert_eq!(run_swap(&mut accounts, 1_000_000, 0, 0).unwrap_err(), ErrorCode::InvalidTreasury.into());
+        
+        // The same vault on both sides
+        let pool = spl_pool(swap_pool());
+        let mut accounts = swap_accounts(&pool, pool.vault0, pool.vault0);
+        assert_eq!(run_swap(&mut accounts, 1_000_000, 0, 0).unwrap_err(), ErrorCode::InvalidTreasury.into());
+    }
+
+    #[test]
+    fn explicit_swap_mints_must_match_their_vaults() {
+        let mint = |key: Pubkey| Some(Box::new(InterfaceAccount::try_from(
+            mint_account(key, Pubkey::new_unique(), 1_000_000_000, 9).info()
+        ).unwrap()));
+        
+        let pool = spl_pool(swap_pool());
+        let mut accounts = swap_accounts(&pool, pool.vault1, pool.vault0);
+        accounts.mint_src = mint(Pubkey::new_unique());
+        assert_eq!(run_swap(&mut accounts, 1_000_000, 0, 0).unwrap_err(), ErrorCode::InvalidTreasury.into());
+        
+        let pool = spl_pool(swap_pool());
+        let mut accounts = swap_accounts(&pool, pool.vault1, pool.vault0);
+        // Each side's mint on the other side
+        accounts.mint_src = mint(accounts.vault_dst.mint);
+        accounts.mint_dst = mint(accounts.vault_src.mint);
+        assert_eq!(run_swap(&mut accounts, 1_000_000, 0, 0).unwrap_err(), ErrorCode::InvalidTreasury.into());
+        
+        let pool = spl_pool(swap_pool());
+        let mut accounts = swap_accounts(&pool, pool.vault1, pool.vault0);
+        accounts.mint_src = mint(accounts.vault_src.mint);
+        accounts.mint_dst = mint(accounts.vault_dst.mint);
+        let quoted = crate::utils::calculate_swap_output(1_000_000, 1_000_000_000, 1_000_000_000, 30, 10000).unwrap();
+        assert_eq!(run_swap(&mut accounts, 1_000_000, quoted, quoted).unwrap().amount_out, quoted);
+    }
+
+    #[test]
+    fn protocol_fee_above_the_xnt_output_reverts_cleanly() {
+        // Setters cap protocol_fee_bps, but a pool's stored fee is all a swap has to go on
+        let treasury = Pubkey::new_unique();
+        let pool = spl_pool(PoolState {
+            protocol_treasury: treasury,
+            protocol_fee_bps: 20000,
+            protocol_fee_mode: PROTOCOL_FEE_MODE_NOTIONAL,
+            ..swap_pool()
+        });
+        let mut accounts = swap_accounts(&pool, pool.vault1, pool.vault0);
+        accounts.protocol_treasury_ata = UncheckedAccount::try_from(
+            token_account(Pubkey::new_unique(), swap_pool().xnt_mint(), treasury, 0).info()
+        );
+        assert_eq!(run_swap(&mut accounts, 1_000_000, 0, 0).unwrap_err(), ErrorCode::NotEnoughOut.into());
+    }
+
+    #[test]
+    fn swap_returns_the_output_that_arrived() {
+        let quoted = crate::utils::calculate_swap_output(1_000_000, 1_000_000_000, 1_000_000_000, 30, 10000).unwrap();
+        for delivered in [quoted, quoted - quoted / 100] {
+            // A Token 2022 transfer fee on the output leaves the recipient with less
+            let pool = spl_pool(swap_pool());
+            let mut accounts = swap_accounts(&pool, pool.vault0, pool.vault1);
+            assert_eq!(run_swap(&mut accounts, 1_000_000, 0, delivered).unwrap().amount_out, delivered);
+        }
+    }
+
+    fn ensure(pool_state: &PoolState, protocol_treasury: Pubkey, native_mint: Pubkey) -> Result<()> {
+        let mut accounts = EnsureTreasuryAta {
+            payer: Signer::try_from(signer().info()).unwrap(),
+            pool_state: UncheckedAccount::try_from(program_account(Pubkey::new_unique(), pool_state).info()),
+            protocol_treasury: UncheckedAccount::try_from(empty_account(protocol_treasury).info()),
+            protocol_treasury_ata: UncheckedAccount::try_from(empty_account(Pubkey::new_unique()).info()),
+            native_mint: UncheckedAccount::try_from(mint_account(native_mint, Pubkey::new_unique(), 0, 9).info()),
+            token_program: Program::try_from(program(Token::id()).info()).unwrap(),
+            associated_token_program: Program::try_from(program(AssociatedToken::id()).info()).unwrap(),
+            system_program: Program::try_from(program(System::id()).info()).unwrap(),
+            token_2022_program: None,
+        };
+        ensure_treasury_ata(Context::new(&crate::ID, &mut accounts, &[], EnsureTreasuryAtaBumps::default()))
+    }
+
+    #[test]
+    fn treasury_ata_is_created_only_for_the_pools_treasury_and_xnt_mint() {
+        let treasury = Pubkey::new_unique();
+        let pool_state = PoolState { protocol_treasury: treasury, ..swap_pool() };
+        let xnt_mint = pool_state.xnt_mint();
+        
+        assert!(ensure(&pool_state, treasury, xnt_mint).is_ok());
+        assert_eq!(ensure(&pool_state, Pubkey::new_unique(), xnt_mint).unwrap_err(), ErrorCode::InvalidTreasury.into());
+        assert_eq!(ensure(&pool_state, treasury, Pubkey::new_unique()).unwrap_err(), ErrorCode::InvalidTreasury.into());
+        // A pool without a treasury collects no protocol fee
+        assert_eq!(
+            ensure(&swap_pool(), Pubkey::default(), xnt_mint).unwrap_err(),
+            ErrorCode::InvalidTreasury.into()
+        );
+    }
+
+    #[test]
+    fn swap_math_fails_with_math_overflow_instead_of_panicking() {
+        // A 100% fee into an empty input vault leaves the curve dividing by zero
+        let pool = spl_pool(PoolState { fee_numerator: 10000, ..swap_pool() });
+        set_token_amount(pool.vault0, 0);
+        let mut accounts = swap_accounts(&pool, pool.vault0, pool.vault1);
+        assert_eq!(run_swap(&mut accounts, 1_000_000, 0, 0).unwrap_err(), ErrorCode::MathOverflow.into());
+    }
+
+    #[test]
+    fn swap_reports_what_it_executed() {
+        let treasury = Pubkey::new_unique();
+        let fee_pool = || spl_pool(PoolState { protocol_treasury: treasury, protocol_fee_bps: 10, ..swap_pool() });
+        
+        // XNT in: the 10 bps protocol fee comes out of the input, which is still used in full
+        let pool = fee_pool();
+        let mut accounts = swap_accounts(&pool, pool.vault0, pool.vault1);
+        accounts.protocol_treasury_ata = UncheckedAccount::try_from(
+            token_account(Pubkey::new_unique(), swap_pool().xnt_mint(), treasury, 0).info()
+        );
+        let result = run_swap(&mut accounts, 1_000_000, 0, 996_000).unwrap();
+        assert_eq!((result.amount_in_used, result.amount_out, result.protocol_fee), (1_000_000, 996_000, 1_000));
+        
+        // Without the treasury's account the fee is skipped, and reported as 0
+        let pool = fee_pool();
+        let mut accounts = swap_accounts(&pool, pool.vault0, pool.vault1);
+        let result = run_swap(&mut accounts, 1_000_000, 0, 996_000).unwrap();
+        assert_eq!((result.amount_in_used, result.protocol_fee), (1_000_000, 0));
+    }
+
+    #[test]
+    fn protocol_fee_is_taken_in_the_pools_own_xnt_mint() {
+        // A pool whose XNT is, say, a Token 2022 wrapped XNT rather than the SPL native mint
+        let treasury = Pubkey::new_unique();
+        let native_mint = Pubkey::new_unique();
+        let fee_pool = || spl_pool(PoolState { protocol_treasury: treasury, protocol_fee_bps: 10, native_mint, ..swap_pool() });
+        
+        let pool = fee_pool();
+        let mut accounts = swap_accounts(&pool, pool.vault0, pool.vault1);
+        accounts.protocol_treasury_ata = UncheckedAccount::try_from(token_account(Pubkey::new_unique(), native_mint, treasury, 0).info());
+        assert_eq!(run_swap(&mut accounts, 1_000_000, 0, 996_000).unwrap().protocol_fee, 1_000);
+        
+        // The treasury's SPL native mint account no longer collects it
+        let pool = fee_pool();
+        let mut accounts = swap_accounts(&pool, pool.vault0, pool.vault1);
+        accounts.protocol_treasury_ata = UncheckedAccount::try_from(
+            token_account(Pubkey::new_unique(), anchor_spl::token::spl_token::native_mint::id(), treasury, 0).info()
+        );
+        assert_eq!(run_swap(&mut accounts, 1_000_000, 0, 996_000).unwrap_err(), ErrorCode::InvalidTreasury.into());
+    }
+
+    #[test]
+    fn swap_whose_fee_takes_the_whole_output_is_rejected() {
+        let treasury = Pubkey::new_unique();
+        let swap_paying_fee = |protocol_fee_bps| {
+            let pool = spl_pool(PoolState {
+                protocol_treasury: treasury,
+                protocol_fee_bps,
+                protocol_fee_mode: PROTOCOL_FEE_MODE_NOTIONAL,
+                ..swap_pool()
+            });
+            let mut accounts = swap_accounts(&pool, pool.vault1, pool.vault0);
+            accounts.protocol_treasury_ata = UncheckedAccount::try_from(
+                token_account(Pubkey::new_unique(), swap_pool().xnt_mint(), treasury, 0).info()
+            );
+            run_swap(&mut accounts, 1_000_000, 0, 0)
+        };
+        // Even with min_amount_out 0, the input isn't taken for nothing
+        assert_eq!(swap_paying_fee(10000).unwrap_err(), ErrorCode::NotEnoughOut.into());
+        assert!(swap_paying_fee(9999).is_ok());
+    }
 }
@@ -0,0 +1,75 @@
+use anchor_lang::prelude::*;
+
+use crate::state::PoolState;
+use crate::error::ErrorCode;
+
+/// Overwrite a pool's tracked amounts to drive the swap math through extreme states
+/// Only built with the `test-helpers` feature. Vault balances belong to the token
+/// program, so tests set those by minting into the vaults directly
+pub fn set_reserves_for_test(
+    ctx: Context<SetReservesForTest>,
+    native_reserve: u64,
+    total_amount_minted: u64,
+) -> Result<()> {
+    require!(ctx.accounts.pool_state.admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+    
+    {
+        let pool_state_info = ctx.accounts.pool_state.to_account_info();
+        let mut data = pool_state_info.try_borrow_mut_data()?;
+        PoolState::write_total_amount_minted(&mut data, total_amount_minted)?;
+        PoolState::write_native_reserve(&mut data, native_reserve)?;
+    }
+    ctx.accounts.pool_state.native_reserve = native_reserve;
+    ctx.accounts.pool_state.total_amount_minted = total_amount_minted;
+    
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetReservesForTest<'info> {
+    pub admin: Signer<'info>,
+    
+    #[account(mut)]
+    pub pool_state: Account<'info, PoolState>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{program_account, signer, Info};
+
+    #[test]
+    fn only_the_admin_overwrites_the_tracked_amounts() {
+        let admin = signer().info();
+        let pool_state = program_account(Pubkey::new_unique(), &PoolState {
+            admin: *admin.key,
+            native_reserve: 1_000,
+            total_amount_minted: 1_000,
+            ..Default::default()
+        }).info();
+        let set = |admin: Info, native_reserve, total_amount_minted| {
+            let mut accounts = SetReservesForTest {
+                admin: Signer::try_from(admin).unwrap(),
+                pool_state: Account::try_from(pool_state).unwrap(),
+            };
+            set_reserves_for_test(
+                Context::new(&crate::ID, &mut accounts, &[], SetReservesForTestBumps::default()),
+                native_reserve,
+                total_amount_minted,
+            )?;
+            accounts.exit(&crate::ID)
+        };
+        let stored = || {
+            let pool = PoolState::try_deserialize(&mut &pool_state.try_borrow_data().unwrap()[..]).unwrap();
+            (pool.native_reserve, pool.total_amount_minted)
+        };
+        
+        assert_eq!(set(signer().info(), 0, 0).unwrap_err(), ErrorCode::Unauthorized.into());
+        assert_eq!(stored(), (1_000, 1_000));
+        // The extremes the helper exists to reach
+        set(admin, u64::MAX, 1).unwrap();
+        assert_eq!(stored(), (u64::MAX, 1));
+        set(admin, 0, u64::MAX).unwrap();
+        assert_eq!(stored(), (0, u64::MAX));
+    }
+}
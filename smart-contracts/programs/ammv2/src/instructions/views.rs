@@ -0,0 +1,782 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface;
+use anchor_lang::Discriminator;
+use crate::state::{
+    PoolMetadata, PoolState, POOL_KIND_NATIVE, POOL_KIND_SPL,
+    POOL_VERSION_V1_BASIC, POOL_VERSION_V2_PROTOCOL, POOL_VERSION_V3_NATIVE, POOL_VERSION_CURRENT,
+};
+use crate::error::ErrorCode;
+use crate::events::ReserveSnapshot;
+use crate::instructions::native_pool::{compute_native_swap, compute_native_swap_input, pool_pda_rent_floor};
+use crate::utils::calculate_lp_to_mint;
+
+/// Most amounts quote_swap_batch prices in one call, to stay within compute and
+/// return data (1024 bytes) limits
+pub const MAX_QUOTE_BATCH_LEN: usize = 32;
+
+/// Return data for `get_claimable_fees`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct ClaimableFees {
+    pub fees0: u64,
+    pub fees1: u64,
+}
+
+/// Fees earned by an LP position since its fee growth checkpoints were recorded
+/// Checkpoints are the pool's fee_growth_global0/1 at the time the position was opened
+pub fn get_claimable_fees(
+    ctx: Context<PoolView>,
+    lp_balance: u64,
+    fee_growth_checkpoint0: u128,
+    fee_growth_checkpoint1: u128,
+) -> Result<ClaimableFees> {
+    let pool_state = PoolState::try_deserialize(&mut &ctx.accounts.pool_state.to_account_info().data.borrow()[..])?;
+    
+    Ok(ClaimableFees {
+        fees0: PoolState::fees_earned(pool_state.fee_growth_global0, fee_growth_checkpoint0, lp_balance),
+        fees1: PoolState::fees_earned(pool_state.fee_growth_global1, fee_growth_checkpoint1, lp_balance),
+    })
+}
+
+/// Return data for `get_lp_mint`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct LpMintInfo {
+    pub lp_mint: Pubkey,
+    /// POOL_KIND_SPL or POOL_KIND_NATIVE
+    pub pool_kind: u8,
+}
+
+/// LP mint of a pool of either kind
+/// SPL and native pools seed the LP mint PDA differently (see PoolState::lp_mint_seed)
+pub fn get_lp_mint(ctx: Context<PoolView>) -> Result<LpMintInfo> {
+    let pool_state_info = ctx.accounts.pool_state.to_account_info();
+    let pool_state = PoolState::try_deserialize(&mut &pool_state_info.data.borrow()[..])?;
+    
+    // is_native_pool is present in every layout that can hold a native pool
+    let pool_kind = if pool_state.is_native_pool { POOL_KIND_NATIVE } else { POOL_KIND_SPL };
+    
+    Ok(LpMintInfo {
+        lp_mint: pool_state.lp_mint_address(pool_state_info.key, ctx.program_id),
+        pool_kind,
+    })
+}
+
+/// Layout version of a pool account (POOL_VERSION_*), from its data length
+/// Mirrors the length checks of PoolState::try_deserialize. Anything short of
+/// POOL_VERSION_CURRENT can be upgraded with migrate_pool
+pub fn get_pool_version(ctx: Context<PoolView>) -> Result<u8> {
+    let pool_state_info = ctx.accounts.pool_state.to_account_info();
+    let data = pool_state_info.try_borrow_data()?;
+    require!(data.len() >= 8 && data[..8] == *PoolState::DISCRIMINATOR, ErrorCode::InvalidAccountData);
+    
+    match data.len() {
+        len if len >= 8 + PoolState::LEN => Ok(POOL_VERSION_CURRENT),
+        len if len >= PoolState::V3_DATA_LEN => Ok(POOL_VERSION_V3_NATIVE),
+        len if len >= PoolState::V2_DATA_LEN => Ok(POOL_VERSION_V2_PROTOCOL),
+        len if len >= PoolState::V1_DATA_LEN => Ok(POOL_VERSION_V1_BASIC),
+        _ => err!(ErrorCode::InvalidAccountData),
+    }
+}
+
+/// Return data for `get_pool_info`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct PoolInfo {
+    pub is_native_pool: bool,
+    pub fee_numerator: u64,
+    pub fee_denominator: u64,
+    pub protocol_fee_bps: u16,
+    pub protocol_treasury: Pubkey,
+    pub total_amount_minted: u64,
+    /// XNT reserve of a native pool, 0 for SPL pools
+    pub native_reserve: u64,
+    pub swaps_enabled: bool,
+    pub deposits_enabled: bool,
+    /// Protocol fees swaps have actually paid out (see PoolState)
+    pub cumulative_protocol_fees_collected: u128,
+}
+
+/// Fee configuration, supply and protocol revenue of a pool of either kind
+/// Legacy layouts read the defaults of fields they predate
+pub fn get_pool_info(ctx: Context<PoolView>) -> Result<PoolInfo> {
+    let pool_state = PoolState::try_deserialize(&mut &ctx.accounts.pool_state.to_account_info().data.borrow()[..])?;
+    
+    Ok(PoolInfo {
+        is_native_pool: pool_state.is_native_pool,
+        fee_numerator: pool_state.fee_numerator,
+        fee_denominator: pool_state.fee_denominator,
+        protocol_fee_bps: pool_state.protocol_fee_bps,
+        protocol_treasury: pool_state.protocol_treasury,
+        total_amount_minted: pool_state.total_amount_minted,
+        native_reserve: pool_state.native_reserve,
+        swaps_enabled: pool_state.swaps_enabled,
+        deposits_enabled: pool_state.deposits_enabled,
+        cumulative_protocol_fees_collected: pool_state.cumulative_protocol_fees_collected,
+    })
+}
+
+#[derive(Accounts)]
+pub struct PoolView<'info> {
+    /// CHECK: Pool state - manually deserialized for backward compatibility
+    #[account(owner = crate::ID)]
+    pub pool_state: UncheckedAccount<'info>,
+}
+
+/// Return data for `quote_swap_native`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct NativeSwapQuote {
+    /// Output the user would receive (net of the protocol fee on Token → XNT)
+    pub amount_out: u64,
+    pub protocol_fee_xnt: u64,
+    /// Execution price shortfall vs spot, excluding fees
+    pub price_impact_bps: u64,
+}
+
+/// Quote a native swap with the exact math swap_native executes
+/// Prices against native_reserve (the XNT reserve swap_native uses), so the
+/// quote only diverges from pool_pda lamports if the reserve has drifted
+/// `lp_balance` is the swapper's LP holding, for the LP holder fee discount
+pub fn quote_swap_native(
+    ctx: Context<QuoteSwapNative>,
+    amount_in: u64,
+    is_xnt_to_token: bool,
+    lp_balance: u64,
+) -> Result<NativeSwapQuote> {
+    let pool_state = &ctx.accounts.pool_state;
+    
+    pool_state.check_native_pool()?;
+    require!(amount_in > 0, ErrorCode::InvalidInput);
+    
+    // Get token vault balance (token_interface handles Token and Token2022 layouts)
+    let token_vault_balance = ctx.accounts.token_vault.amount;
+    
+    let amounts = compute_native_swap(pool_state, token_vault_balance, amount_in, is_xnt_to_token, lp_balance)?;
+    
+    let (reserve_in, reserve_out) = if is_xnt_to_token {
+        (pool_state.native_reserve, token_vault_balance)
+    } else {
+        (token_vault_balance, pool_state.native_reserve)
+    };
+    let price_impact_bps = crate::utils::price_impact_bps(amount_in, reserve_in, reserve_out)?;
+    
+    Ok(NativeSwapQuote {
+        amount_out: amounts.final_amount_out,
+        protocol_fee_xnt: amounts.protocol_fee_xnt,
+        price_impact_bps,
+    })
+}
+
+/// Outputs (net of the protocol fee on Token → XNT) of native swaps of each of
+/// `amounts`, all priced against the same reserves - one point per amount of a
+/// depth curve. Each entry matches quote_swap_native's amount_out for that amount
+/// Native pools only, like quote_swap_native - there is no on-chain swap quote for SPL
+/// pools, whose vault balances clients price with calculate_swap_output directly
+pub fn quote_swap_batch(
+    ctx: Context<QuoteSwapNative>,
+    amounts: Vec<u64>,
+    is_xnt_to_token: bool,
+    lp_balance: u64,
+) -> Result<Vec<u64>> {
+    let pool_state = &ctx.accounts.pool_state;
+    
+    pool_state.check_native_pool()?;
+    require!(!amounts.is_empty() && amounts.len() <= MAX_QUOTE_BATCH_LEN, ErrorCode::InvalidInput);
+    
+    let token_vault_balance = ctx.accounts.token_vault.amount;
+    amounts
+        .into_iter()
+        .map(|amount_in| {
+            require!(amount_in > 0, ErrorCode::InvalidInput);
+            compute_native_swap(pool_state, token_vault_balance, amount_in, is_xnt_to_token, lp_balance)
+                .map(|amounts| amounts.final_amount_out)
+        })
+        .collect()
+}
+
+/// Input a native swap needs so the user receives at least `amount_out`
+/// (net of the protocol fee on Token → XNT) - the exact-out counterpart of quote_swap_native
+/// Native pools only, as quote_swap_batch (NotNativePool for an SPL pool)
+pub fn quote_amount_in(
+    ctx: Context<QuoteSwapNative>,
+    amount_out: u64,
+    is_xnt_to_token: bool,
+    lp_balance: u64,
+) -> Result<u64> {
+    let pool_state = &ctx.accounts.pool_state;
+    
+    pool_state.check_native_pool()?;
+    
+    compute_native_swap_input(pool_state, ctx.accounts.token_vault.amount, amount_out, is_xnt_to_token, lp_balance)
+}
+
+#[derive(Accounts)]
+pub struct QuoteSwapNative<'info> {
+    pub pool_state: Account<'info, PoolState>,
+    
+    /// Token vault - can be Token or Token2022
+    #[account(seeds = [b"vault", pool_state.key().as_ref()], bump)]
+    pub token_vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+}
+
+/// Emit a ReserveSnapshot of the pool's current reserves and LP supply
+/// Changes no state, so anyone may call it
+pub fn snapshot_reserves(ctx: Context<SnapshotReserves>) -> Result<()> {
+    let pool_state = &ctx.accounts.pool_state;
+    let pool_key = pool_state.key();
+    let vault0 = &ctx.accounts.vault0;
+    
+    let (reserve0, reserve1_or_native) = if pool_state.is_native_pool {
+        let (token_vault, _) = Pubkey::find_program_address(&[b"vault", pool_key.as_ref()], ctx.program_id);
+        require!(vault0.key() == token_vault, ErrorCode::InvalidTreasury);
+        (vault0.amount, pool_state.native_reserve)
+    } else {
+        let vault1 = ctx.accounts.vault1.as_ref().ok_or(ErrorCode::InvalidInput)?;
+        let (vault0_pda, _) = Pubkey::find_program_address(&[b"vault0", pool_key.as_ref()], ctx.program_id);
+        let (vault1_pda, _) = Pubkey::find_program_address(&[b"vault1", pool_key.as_ref()], ctx.program_id);
+        require!(vault0.key() == vault0_pda && vault1.key() == vault1_pda, ErrorCode::InvalidTreasury);
+        (vault0.amount, vault1.amount)
+    };
+    
+    let clock = Clock::get()?;
+    emit!(ReserveSnapshot {
+        pool: pool_key,
+        reserve0,
+        reserve1_or_native,
+        total_lp: pool_state.total_amount_minted,
+        slot: clock.slot,
+        timestamp: clock.unix_timestamp,
+    });
+    
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SnapshotReserves<'info> {
+    pub pool_state: Account<'info, PoolState>,
+    
+    /// vault0 of an SPL pool, or the token vault of a native pool - validated in handler
+    pub vault0: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    
+    /// vault1 of an SPL pool, omitted for native pools
+    pub vault1: Option<Box<InterfaceAccount<'info, token_interface::TokenAccount>>>,
+}
+
+/// Current constant-product invariant k = reserve0 * reserve1 of a pool of either kind
+/// Read from the actual balances (for a native pool, pool_pda's lamports above its rent
+/// reserve rather than native_reserve), so a donation or skim shows up as a change in k
+pub fn get_invariant(ctx: Context<InvariantView>) -> Result<u128> {
+    let pool_state = &ctx.accounts.pool_state;
+    let pool_key = pool_state.key();
+    let vault0 = &ctx.accounts.vault0;
+    
+    let (reserve0, reserve1) = if pool_state.is_native_pool {
+        let (token_vault, _) = Pubkey::find_program_address(&[b"vault", pool_key.as_ref()], ctx.program_id);
+        let (pool_pda, _) = Pubkey::find_program_address(&[b"pool_pda", pool_key.as_ref()], ctx.program_id);
+        let pool_pda_info = ctx.accounts.pool_pda.as_ref().ok_or(ErrorCode::InvalidInput)?;
+        require!(vault0.key() == token_vault && pool_pda_info.key() == pool_pda, ErrorCode::InvalidTreasury);
+        let tradeable_xnt = pool_pda_info.lamports().saturating_sub(pool_pda_rent_floor()?);
+        (vault0.amount, tradeable_xnt)
+    } else {
+        let vault1 = ctx.accounts.vault1.as_ref().ok_or(ErrorCode::InvalidInput)?;
+        let (vault0_pda, _) = Pubkey::find_program_address(&[b"vault0", pool_key.as_ref()], ctx.program_id);
+        let (vault1_pda, _) = Pubkey::find_program_address(&[b"vault1", pool_key.as_ref()], ctx.program_id);
+        require!(vault0.key() == vault0_pda && vault1.key() == vault1_pda, ErrorCode::InvalidTreasury);
+        (vault0.amount, vault1.amount)
+    };
+    
+    // u64 * u64 always fits in u128
+    Ok(reserve0 as u128 * reserve1 as u128)
+}
+
+#[derive(Accounts)]
+pub struct InvariantView<'info> {
+    pub pool_state: Account<'info, PoolState>,
+    
+    /// vault0 of an SPL pool, or the token vault of a native pool - validated in handler
+    pub vault0: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    
+    /// vault1 of an SPL pool, omitted for native pools
+    pub vault1: Option<Box<InterfaceAccount<'info, token_interface::TokenAccount>>>,
+    
+    /// CHECK: pool_pda holding a native pool's XNT, omitted for SPL pools - validated in handler
+    pub pool_pda: Option<UncheckedAccount<'info>>,
+}
+
+/// Most pools read_prices reads in one call - 48 accounts, which needs an address
+/// lookup table, and well within compute and return data (1024 bytes) limits
+pub const MAX_READ_PRICES_POOLS: usize = 16;
+
+/// Accounts per pool in `read_prices`'s remaining accounts
+pub const READ_PRICES_POOL_ACCOUNTS: usize = 3;
+
+/// Spot prices of several pools of either kind in one call, in pool order
+///
+/// Each price is token1 per token0 as a Q64.64 fixed-point number (the scale
+/// rebalance's target_price_x64 uses), or 0 for a pool with an empty token0 side.
+/// Remaining accounts hold one group of READ_PRICES_POOL_ACCOUNTS per pool:
+/// - SPL pool: pool_state, vault0, vault1
+/// - native pool: pool_state, token vault, pool_pda
+///
+/// Like get_invariant, prices are read from the actual balances, so a native pool's
+/// XNT reserve is pool_pda's lamports above its rent reserve. XNT is token
+/// native_mint_index of a native pool
+pub fn read_prices<'info>(ctx: Context<'_, '_, 'info, 'info, ReadPrices>) -> Result<Vec<u128>> {
+    let pool_count = ctx.remaining_accounts.len() / READ_PRICES_POOL_ACCOUNTS;
+    require!(pool_count > 0 && pool_count <= MAX_READ_PRICES_POOLS, ErrorCode::InvalidInput);
+    require!(
+        ctx.remaining_accounts.len() == pool_count * READ_PRICES_POOL_ACCOUNTS,
+        ErrorCode::InvalidInput
+    );
+    
+    let rent_floor = pool_pda_rent_floor()?;
+    let mut prices = Vec::with_capacity(pool_count);
+    for accounts in ctx.remaining_accounts.chunks_exact(READ_PRICES_POOL_ACCOUNTS) {
+        let [pool_state_info, vault0_info, third_info] = accounts else {
+            return Err(ErrorCode::InvalidInput.into());
+        };
+        
+        let pool_state = Account::<PoolState>::try_from(pool_state_info)?;
+        let pool_key = pool_state_info.key();
+        let vault0 = InterfaceAccount::<token_interface::TokenAccount>::try_from(vault0_info)?;
+        
+        let (reserve0, reserve1) = if pool_state.is_native_pool {
+            let (token_vault, _) = Pubkey::find_program_address(&[b"vault", pool_key.as_ref()], ctx.program_id);
+            let (pool_pda, _) = Pubkey::find_program_address(&[b"pool_pda", pool_key.as_ref()], ctx.program_id);
+            require!(vault0_info.key() == token_vault && third_info.key() == pool_pda, ErrorCode::InvalidTreasury);
+            let tradeable_xnt = third_info.lamports().saturating_sub(rent_floor);
+            if pool_state.native_mint_index_checked()? == 0 {
+                (tradeable_xnt, vault0.amount)
+            } else {
+                (vault0.amount, tradeable_xnt)
+            }
+        } else {
+            let (vault0_pda, _) = Pubkey::find_program_address(&[b"vault0", pool_key.as_ref()], ctx.program_id);
+            let (vault1_pda, _) = Pubkey::find_program_address(&[b"vault1", pool_key.as_ref()], ctx.program_id);
+            require!(vault0_info.key() == vault0_pda && third_info.key() == vault1_pda, ErrorCode::InvalidTreasury);
+            let vault1 = InterfaceAccount::<token_interface::TokenAccount>::try_from(third_info)?;
+            (vault0.amount, vault1.amount)
+        };
+        
+        // A u64 shifted by 64 always fits in u128
+        let price_x64 = if reserve0 == 0 { 0 } else { ((reserve1 as u128) << 64) / reserve0 as u128 };
+        prices.push(price_x64);
+    }
+    
+    Ok(prices)
+}
+
+#[derive(Accounts)]
+pub struct ReadPrices {}
+
+/// Return data for `quote_add_liquidity`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct AddLiquidityQuote {
+    /// Pool has no LP supply yet - the first deposit sets the ratio, so any
+    /// amount1 is accepted and required_amount1 / lp_out are 0
+    pub any_ratio: bool,
+    /// token1 add_liquidity takes alongside amount0 (rounded up, as it deposits)
+    pub required_amount1: u64,
+    pub lp_out: u64,
+}
+
+/// Quote an SPL add_liquidity of `amount0` token0 with the same ratio and LP math it executes
+pub fn quote_add_liquidity(ctx: Context<QuoteAddLiquidity>, amount0: u64) -> Result<AddLiquidityQuote> {
+    let pool_state = &ctx.accounts.pool_state;
+    
+    require!(!pool_state.is_native_pool, ErrorCode::InvalidInput);
+    require!(amount0 > 0, ErrorCode::InvalidInput);
+    
+    if pool_state.total_amount_minted == 0 {
+        return Ok(AddLiquidityQuote { any_ratio: true, required_amount1: 0, lp_out: 0 });
+    }
+    
+    let vault_balance0 = ctx.accounts.vault0.amount;
+    let vault_balance1 = ctx.accounts.vault1.amount;
+    require!(vault_balance0 > 0, ErrorCode::InsufficientLiquidity);
+    let required_amount1 = u64::try_from(
+        (amount0 as u128 * vault_balance1 as u128).div_ceil(vault_balance0 as u128)
+    ).map_err(|_| ErrorCode::MathOverflow)?;
+    let lp_out = calculate_lp_to_mint(
+        pool_state.total_amount_minted,
+        vault_balance0,
+        vault_balance1,
+        amount0,
+        required_amount1,
+    )?;
+    
+    Ok(AddLiquidityQuote { any_ratio: false, required_amount1, lp_out })
+}
+
+#[derive(Accounts)]
+pub struct QuoteAddLiquidity<'info> {
+    pub pool_state: Account<'info, PoolState>,
+    
+    // vaults can be Token or Token2022
+    #[account(seeds = [b"vault0", pool_state.key().as_ref()], bump)]
+    pub vault0: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    #[account(seeds = [b"vault1", pool_state.key().as_ref()], bump)]
+    pub vault1: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+}
+
+/// Return data for `get_pool_metadata`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct PoolMetadataInfo {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+}
+
+/// Name, symbol and URI of a pool that has a PoolMetadata label
+pub fn get_pool_metadata(ctx: Context<PoolMetadataView>) -> Result<PoolMetadataInfo> {
+    let pool_metadata = &ctx.accounts.pool_metadata;
+    
+    Ok(PoolMetadataInfo {
+        name: pool_metadata.name.clone(),
+        symbol: pool_metadata.symbol.clone(),
+        uri: pool_metadata.uri.clone(),
+    })
+}
+
+#[derive(Accounts)]
+pub struct PoolMetadataView<'info> {
+    /// CHECK: Pool state - only its address seeds the metadata PDA
+    #[account(owner = crate::ID)]
+    pub pool_state: UncheckedAccount<'info>,
+    
+    #[account(seeds = [b"metadata", pool_state.key().as_ref()], bump)]
+    pub pool_metadata: Account<'info, PoolMetadata>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{
+        install_stubs, program_account, set_clock, take_events, token_2022_account, token_account, TestAccount,
+    };
+    use anchor_lang::solana_program::program_pack::Pack;
+
+    // A standard Token account at the `seed` PDA of `pool_key`
+    fn vault_account(seed: &[u8], pool_key: &Pubkey, amount: u64) -> TestAccount {
+        let (key, _) = Pubkey::find_program_address(&[seed, pool_key.as_ref()], &crate::ID);
+        token_account(key, Pubkey::new_unique(), Pubkey::new_unique(), amount)
+    }
+
+    #[test]
+    fn quote_swap_batch_matches_compute_native_swap() {
+        let pool_key = Pubkey::new_unique();
+        let pool = PoolState {
+            is_native_pool: true,
+            pool_kind: POOL_KIND_NATIVE,
+            native_reserve: 500_000_000,
+            total_amount_minted: 1_000_000,
+            protocol_treasury: Pubkey::new_unique(),
+            protocol_fee_bps: 20,
+            fee_numerator: 30,
+            fee_denominator: 10000,
+            max_swap_out_bps_of_reserve: 10000,
+            ..Default::default()
+        };
+        let token_vault_balance = 2_000_000_000;
+        let pool_account = program_account(pool_key, &pool);
+        let token_vault = vault_account(b"vault", &pool_key, token_vault_balance);
+        let (pool_info, token_vault_info) = (pool_account.info(), token_vault.info());
+        let mut accounts = QuoteSwapNative {
+            pool_state: Account::try_from(pool_info).unwrap(),
+            token_vault: Box::new(InterfaceAccount::try_from(token_vault_info).unwrap()),
+        };
+        
+        let amounts = vec![10_000, 1_000_000, 100_000_000];
+        for is_xnt_to_token in [true, false] {
+            let ctx = Context::new(&crate::ID, &mut accounts, &[], QuoteSwapNativeBumps::default());
+            let quotes = quote_swap_batch(ctx, amounts.clone(), is_xnt_to_token, 0).unwrap();
+            assert_eq!(quotes.len(), amounts.len());
+            for (amount_in, quote) in amounts.iter().zip(quotes) {
+                let expected = compute_native_swap(&pool, token_vault_balance, *amount_in, is_xnt_to_token, 0).unwrap();
+                assert_eq!(quote, expected.final_amount_out);
+            }
+        }
+        
+        // A zero amount fails the whole batch, as does an oversized one
+        let ctx = Context::new(&crate::ID, &mut accounts, &[], QuoteSwapNativeBumps::default());
+        assert_eq!(quote_swap_batch(ctx, vec![1_000, 0], true, 0).unwrap_err(), ErrorCode::InvalidInput.into());
+        let ctx = Context::new(&crate::ID, &mut accounts, &[], QuoteSwapNativeBumps::default());
+        assert_eq!(
+            quote_swap_batch(ctx, vec![1_000; MAX_QUOTE_BATCH_LEN + 1], true, 0).unwrap_err(),
+            ErrorCode::InvalidInput.into()
+        );
+    }
+
+    #[test]
+    fn read_prices_reads_spl_and_native_pools_in_order() {
+        install_stubs();
+        let rent_floor = pool_pda_rent_floor().unwrap();
+        
+        // SPL pool: 2 token1 per token0
+        let spl_key = Pubkey::new_unique();
+        let spl_pool = program_account(spl_key, &PoolState { pool_kind: POOL_KIND_SPL, ..Default::default() });
+        let spl_vault0 = vault_account(b"vault0", &spl_key, 1_000_000_000);
+        let spl_vault1 = vault_account(b"vault1", &spl_key, 2_000_000_000);
+        
+        // Native pools read XNT from pool_pda's lamports above its rent reserve
+        let native_pool = |native_mint_index| PoolState {
+            is_native_pool: true,
+            pool_kind: POOL_KIND_NATIVE,
+            native_mint_index,
+            ..Default::default()
+        };
+        let pool_pda = |pool_key: &Pubkey, xnt: u64| TestAccount {
+            lamports: rent_floor + xnt,
+            ..TestAccount::new(
+                Pubkey::find_program_address(&[b"pool_pda", pool_key.as_ref()], &crate::ID).0,
+                crate::ID,
+                Vec::new(),
+            )
+        };
+        // XNT is token0: 500 XNT against 2000 tokens, 4 token1 per token0
+        let xnt0_key = Pubkey::new_unique();
+        let xnt0_pool = program_account(xnt0_key, &native_pool(0));
+        let xnt0_vault = vault_account(b"vault", &xnt0_key, 2_000_000_000);
+        let xnt0_pda = pool_pda(&xnt0_key, 500_000_000);
+        // XNT is token1: 2000 tokens against 500 XNT, 1/4 token1 per token0
+        let xnt1_key = Pubkey::new_unique();
+        let xnt1_pool = program_account(xnt1_key, &native_pool(1));
+        let xnt1_vault = vault_account(b"vault", &xnt1_key, 2_000_000_000);
+        let xnt1_pda = pool_pda(&xnt1_key, 500_000_000);
+        
+        let infos = [
+            spl_pool, spl_vault0, spl_vault1,
+            xnt0_pool, xnt0_vault, xnt0_pda,
+            xnt1_pool, xnt1_vault, xnt1_pda,
+        ].map(TestAccount::info);
+        let remaining_accounts = crate::test_fixtures::remaining_accounts(&infos);
+        let mut accounts = ReadPrices {};
+        let ctx = Context::new(&crate::ID, &mut accounts, remaining_accounts, ReadPricesBumps::default());
+        let prices = read_prices(ctx).unwrap();
+        assert_eq!(prices, vec![2u128 << 64, 4u128 << 64, 1u128 << 62]);
+        
+        // Accounts must come in whole groups of READ_PRICES_POOL_ACCOUNTS
+        let ctx = Context::new(&crate::ID, &mut accounts, &remaining_accounts[..8], ReadPricesBumps::default());
+        assert_eq!(read_prices(ctx).unwrap_err(), ErrorCode::InvalidInput.into());
+        // and each pool's vaults must be its own
+        let swapped = crate::test_fixtures::remaining_accounts(&[infos[0], infos[2], infos[1]]);
+        let ctx = Context::new(&crate::ID, &mut accounts, swapped, ReadPricesBumps::default());
+        assert_eq!(read_prices(ctx).unwrap_err(), ErrorCode::InvalidTreasury.into());
+    }
+
+    #[test]
+    fn swap_quotes_are_native_pool_only() {
+        let pool_key = Pubkey::new_unique();
+        let pool_info = program_account(pool_key, &PoolState { pool_kind: POOL_KIND_SPL, ..Default::default() }).info();
+        let mut accounts = QuoteSwapNative {
+            pool_state: Account::try_from(pool_info).unwrap(),
+            token_vault: Box::new(InterfaceAccount::try_from(vault_account(b"vault", &pool_key, 1_000).info()).unwrap()),
+        };
+        
+        let ctx = Context::new(&crate::ID, &mut accounts, &[], QuoteSwapNativeBumps::default());
+        assert_eq!(quote_swap_batch(ctx, vec![1_000], true, 0).unwrap_err(), ErrorCode::NotNativePool.into());
+        let ctx = Context::new(&crate::ID, &mut accounts, &[], QuoteSwapNativeBumps::default());
+        assert_eq!(quote_amount_in(ctx, 1_000, true, 0).unwrap_err(), ErrorCode::NotNativePool.into());
+    }
+
+    #[test]
+    fn quote_swap_native_prices_with_the_swap_math() {
+        let pool_key = Pubkey::new_unique();
+        let pool = PoolState {
+            is_native_pool: true,
+            pool_kind: POOL_KIND_NATIVE,
+            native_reserve: 1_000_000_000,
+            protocol_treasury: Pubkey::new_unique(),
+            protocol_fee_bps: 20,
+            fee_numerator: 30,
+            fee_denominator: 10000,
+            max_swap_out_bps_of_reserve: 10000,
+            ..Default::default()
+        };
+        let mut accounts = QuoteSwapNative {
+            pool_state: Account::try_from(program_account(pool_key, &pool).info()).unwrap(),
+            token_vault: Box::new(InterfaceAccount::try_from(vault_account(b"vault", &pool_key, 1_000_000_000).info()).unwrap()),
+        };
+        
+        for is_xnt_to_token in [true, false] {
+            let ctx = Context::new(&crate::ID, &mut accounts, &[], QuoteSwapNativeBumps::default());
+            let quote = quote_swap_native(ctx, 10_000_000, is_xnt_to_token, 0).unwrap();
+            let expected = compute_native_swap(&pool, 1_000_000_000, 10_000_000, is_xnt_to_token, 0).unwrap();
+            assert_eq!((quote.amount_out, quote.protocol_fee_xnt), (expected.final_amount_out, expected.protocol_fee_xnt));
+            assert!(quote.protocol_fee_xnt > 0);
+            // 1% of a balanced pool moves the price about 1%
+            assert_eq!(quote.price_impact_bps, 99);
+        }
+    }
+
+    #[test]
+    fn quote_reads_a_token_2022_vault_past_its_extensions() {
+        let pool_key = Pubkey::new_unique();
+        let pool = PoolState {
+            is_native_pool: true,
+            pool_kind: POOL_KIND_NATIVE,
+            native_reserve: 1_000_000_000,
+            fee_numerator: 30,
+            fee_denominator: 10000,
+            max_swap_out_bps_of_reserve: 10000,
+            ..Default::default()
+        };
+        let (vault_key, _) = Pubkey::find_program_address(&[b"vault", pool_key.as_ref()], &crate::ID);
+        let token_vault = token_2022_account(vault_key, Pubkey::new_unique(), Pubkey::new_unique(), 2_000_000_000);
+        assert!(token_vault.data.len() > spl_token_2022::state::Account::LEN);
+        let mut accounts = QuoteSwapNative {
+            pool_state: Account::try_from(program_account(pool_key, &pool).info()).unwrap(),
+            token_vault: Box::new(InterfaceAccount::try_from(token_vault.info()).unwrap()),
+        };
+        
+        let ctx = Context::new(&crate::ID, &mut accounts, &[], QuoteSwapNativeBumps::default());
+        let quote = quote_swap_native(ctx, 10_000_000, true, 0).unwrap();
+        let expected = compute_native_swap(&pool, 2_000_000_000, 10_000_000, true, 0).unwrap();
+        assert_eq!(quote.amount_out, expected.final_amount_out);
+    }
+
+    #[test]
+    fn lp_mint_is_seeded_by_pool_kind() {
+        for (pool, seed) in [
+            (PoolState { pool_kind: POOL_KIND_SPL, ..Default::default() }, &b"pool_mint"[..]),
+            (PoolState { is_native_pool: true, pool_kind: POOL_KIND_NATIVE, ..Default::default() }, &b"lp_mint"[..]),
+        ] {
+            let pool_key = Pubkey::new_unique();
+            let mut accounts = PoolView {
+                pool_state: UncheckedAccount::try_from(program_account(pool_key, &pool).info()),
+            };
+            let info = get_lp_mint(Context::new(&crate::ID, &mut accounts, &[], PoolViewBumps::default())).unwrap();
+            assert_eq!(info.lp_mint, Pubkey::find_program_address(&[seed, pool_key.as_ref()], &crate::ID).0);
+            assert_eq!(info.pool_kind, pool.pool_kind);
+        }
+    }
+
+    #[test]
+    fn pool_version_follows_the_account_length() {
+        let mut data = Vec::new();
+        PoolState::default().try_serialize(&mut data).unwrap();
+        let version = |data: &[u8]| {
+            let mut accounts = PoolView {
+                pool_state: UncheckedAccount::try_from(TestAccount::new(Pubkey::new_unique(), crate::ID, data.to_vec()).info()),
+            };
+            get_pool_version(Context::new(&crate::ID, &mut accounts, &[], PoolViewBumps::default()))
+        };
+        
+        assert_eq!(version(&data).unwrap(), POOL_VERSION_CURRENT);
+        assert_eq!(version(&data[..PoolState::V3_DATA_LEN]).unwrap(), POOL_VERSION_V3_NATIVE);
+        assert_eq!(version(&data[..PoolState::V2_DATA_LEN]).unwrap(), POOL_VERSION_V2_PROTOCOL);
+        assert_eq!(version(&data[..PoolState::V1_DATA_LEN]).unwrap(), POOL_VERSION_V1_BASIC);
+        assert_eq!(version(&data[..PoolState::V1_DATA_LEN - 1]).unwrap_err(), ErrorCode::InvalidAccountData.into());
+        // Only pool accounts have a version
+        data[0] ^= 1;
+        assert_eq!(version(&data).unwrap_err(), ErrorCode::InvalidAccountData.into());
+    }
+
+    #[test]
+    fn snapshot_reports_each_pool_kinds_reserves() {
+        set_clock(1_700_000_000);
+        let snapshot = |pool: &PoolState, pool_key: Pubkey, vault0: TestAccount, vault1: Option<TestAccount>| {
+            let mut accounts = SnapshotReserves {
+                pool_state: Account::try_from(program_account(pool_key, pool).info()).unwrap(),
+                vault0: Box::new(InterfaceAccount::try_from(vault0.info()).unwrap()),
+                vault1: vault1.map(|vault1| Box::new(InterfaceAccount::try_from(vault1.info()).unwrap())),
+            };
+            snapshot_reserves(Context::new(&crate::ID, &mut accounts, &[], SnapshotReservesBumps::default()))?;
+            let events = take_events::<ReserveSnapshot>();
+            assert_eq!(events.len(), 1);
+            let event = &events[0];
+            assert_eq!((event.pool, event.timestamp), (pool_key, 1_700_000_000));
+            Ok::<_, Error>((event.reserve0, event.reserve1_or_native, event.total_lp))
+        };
+        
+        let spl_key = Pubkey::new_unique();
+        let spl_pool = PoolState { pool_kind: POOL_KIND_SPL, total_amount_minted: 3_000, ..Default::default() };
+        let spl_vaults = || (vault_account(b"vault0", &spl_key, 1_000), vault_account(b"vault1", &spl_key, 2_000));
+        let (vault0, vault1) = spl_vaults();
+        assert_eq!(snapshot(&spl_pool, spl_key, vault0, Some(vault1)).unwrap(), (1_000, 2_000, 3_000));
+        // Swapped vaults, or vault1 missing
+        let (vault0, vault1) = spl_vaults();
+        assert_eq!(
+            snapshot(&spl_pool, spl_key, vault1, Some(vault0)).unwrap_err(),
+            ErrorCode::InvalidTreasury.into()
+        );
+        assert_eq!(snapshot(&spl_pool, spl_key, spl_vaults().0, None).unwrap_err(), ErrorCode::InvalidInput.into());
+        
+        let native_key = Pubkey::new_unique();
+        let native_pool = PoolState {
+            is_native_pool: true,
+            pool_kind: POOL_KIND_NATIVE,
+            native_reserve: 5_000,
+            total_amount_minted: 4_000,
+            ..Default::default()
+        };
+        let token_vault = vault_account(b"vault", &native_key, 6_000);
+        assert_eq!(snapshot(&native_pool, native_key, token_vault, None).unwrap(), (6_000, 5_000, 4_000));
+    }
+
+    #[test]
+    fn add_liquidity_quote_rounds_the_second_side_up() {
+        let quote = |pool: PoolState, vault0_amount: u64, vault1_amount: u64, amount0: u64| {
+            let pool_key = Pubkey::new_unique();
+            let mut accounts = QuoteAddLiquidity {
+                pool_state: Account::try_from(program_account(pool_key, &pool).info()).unwrap(),
+                vault0: Box::new(InterfaceAccount::try_from(vault_account(b"vault0", &pool_key, vault0_amount).info()).unwrap()),
+                vault1: Box::new(InterfaceAccount::try_from(vault_account(b"vault1", &pool_key, vault1_amount).info()).unwrap()),
+            };
+            quote_add_liquidity(Context::new(&crate::ID, &mut accounts, &[], QuoteAddLiquidityBumps::default()), amount0)
+                .map(|quote| (quote.any_ratio, quote.required_amount1, quote.lp_out))
+        };
+        let spl_pool = PoolState { pool_kind: POOL_KIND_SPL, total_amount_minted: 1_000, ..Default::default() };
+        
+        // 100 * 10_000 / 3_000 = 333.3, so 334 token1 - and 33 LP for either side
+        assert_eq!(quote(spl_pool.clone(), 3_000, 10_000, 100).unwrap(), (false, 334, 33));
+        // The first deposit sets the ratio
+        let empty_pool = PoolState { total_amount_minted: 0, ..spl_pool.clone() };
+        assert_eq!(quote(empty_pool, 0, 0, 100).unwrap(), (true, 0, 0));
+        assert_eq!(quote(spl_pool, 3_000, 10_000, 0).unwrap_err(), ErrorCode::InvalidInput.into());
+        let native_pool = PoolState { is_native_pool: true, pool_kind: POOL_KIND_NATIVE, total_amount_minted: 1_000, ..Default::default() };
+        assert_eq!(quote(native_pool, 3_000, 10_000, 100).unwrap_err(), ErrorCode::InvalidInput.into());
+    }
+
+    #[test]
+    fn invariant_is_read_from_actual_balances() {
+        install_stubs();
+        let invariant = |pool: &PoolState, pool_key, vault0: TestAccount, vault1: Option<TestAccount>, pool_pda: Option<TestAccount>| {
+            let mut accounts = InvariantView {
+                pool_state: Account::try_from(program_account(pool_key, pool).info()).unwrap(),
+                vault0: Box::new(InterfaceAccount::try_from(vault0.info()).unwrap()),
+                vault1: vault1.map(|vault1| Box::new(InterfaceAccount::try_from(vault1.info()).unwrap())),
+                pool_pda: pool_pda.map(|pool_pda| UncheckedAccount::try_from(pool_pda.info())),
+            };
+            get_invariant(Context::new(&crate::ID, &mut accounts, &[], InvariantViewBumps::default()))
+        };
+        
+        let spl_key = Pubkey::new_unique();
+        let spl_pool = PoolState { pool_kind: POOL_KIND_SPL, ..Default::default() };
+        let vault0 = vault_account(b"vault0", &spl_key, u64::MAX);
+        let vault1 = vault_account(b"vault1", &spl_key, u64::MAX);
+        // u64 * u64 can't overflow the u128
+        assert_eq!(
+            invariant(&spl_pool, spl_key, vault0, Some(vault1), None).unwrap(),
+            u64::MAX as u128 * u64::MAX as u128
+        );
+        
+        // A native pool's XNT side is pool_pda above its rent floor - here 1_000 more than
+        // native_reserve, as after a donation
+        let native_key = Pubkey::new_unique();
+        let native_pool = PoolState {
+            is_native_pool: true,
+            pool_kind: POOL_KIND_NATIVE,
+            native_reserve: 5_000,
+            ..Default::default()
+        };
+        let (pool_pda_key, _) = Pubkey::find_program_address(&[b"pool_pda", native_key.as_ref()], &crate::ID);
+        let pool_pda = || TestAccount {
+            lamports: pool_pda_rent_floor().unwrap() + 6_000,
+            ..TestAccount::new(pool_pda_key, crate::ID, Vec::new())
+        };
+        let token_vault = || vault_account(b"vault", &native_key, 2_000);
+        assert_eq!(invariant(&native_pool, native_key, token_vault(), None, Some(pool_pda())).unwrap(), 12_000_000);
+        assert_eq!(
+            invariant(&native_pool, native_key, token_vault(), None, None).unwrap_err(),
+            ErrorCode::InvalidInput.into()
+        );
+    }
+}
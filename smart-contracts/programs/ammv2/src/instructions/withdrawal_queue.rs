@@ -0,0 +1,442 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{self, CloseAccount, Mint, Token, TokenAccount, Transfer};
+
+use crate::state::{PoolState, WithdrawalRequest};
+use crate::error::ErrorCode;
+// Globs bring in the Anchor-generated companions of the nested account structs
+use crate::instructions::liquidity::*;
+use crate::instructions::native_pool::*;
+
+/// Start a delayed withdrawal: escrow `lp_amount` LP until the pool's withdrawal delay passes
+/// One pending request per LP per pool, completed by execute_remove_liquidity /
+/// execute_remove_native_liquidity or undone by cancel_remove_liquidity
+pub fn request_remove_liquidity(ctx: Context<RequestRemoveLiquidity>, lp_amount: u64) -> Result<()> {
+    require!(lp_amount > 0, ErrorCode::InvalidInput);
+
+    let pool_state = &ctx.accounts.pool_state;
+    // Escrowing would move LP out of the holder's (frozen) account
+    require!(!pool_state.lp_soulbound, ErrorCode::LpSoulbound);
+    let delay = i64::try_from(pool_state.withdrawal_delay_secs).map_err(|_| ErrorCode::MathOverflow)?;
+    let unlock_at = Clock::get()?
+        .unix_timestamp
+        .checked_add(delay)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    token::transfer(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.user_lp_account.to_account_info(),
+                to: ctx.accounts.escrow_lp_account.to_account_info(),
+                authority: ctx.accounts.owner.to_account_info(),
+            },
+        ),
+        lp_amount,
+    )?;
+
+    let withdrawal_request = &mut ctx.accounts.withdrawal_request;
+    withdrawal_request.pool = pool_state.key();
+    withdrawal_request.owner = ctx.accounts.owner.key();
+    withdrawal_request.lp_amount = lp_amount;
+    withdrawal_request.unlock_at = unlock_at;
+    withdrawal_request.bump = ctx.bumps.withdrawal_request;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct RequestRemoveLiquidity<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub pool_state: Box<Account<'info, PoolState>>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + WithdrawalRequest::LEN,
+        seeds = [b"withdrawal_request", pool_state.key().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub withdrawal_request: Account<'info, WithdrawalRequest>,
+
+    #[account(address = pool_state.lp_mint_address(&pool_state.key(), &crate::ID) @ ErrorCode::InvalidInput)]
+    pub lp_mint: Box<Account<'info, Mint>>,
+
+    #[account(mut, token::mint = lp_mint, token::authority = owner)]
+    pub user_lp_account: Box<Account<'info, TokenAccount>>,
+
+    // Holds the LP until the request is executed or cancelled
+    #[account(
+        init,
+        payer = owner,
+        associated_token::mint = lp_mint,
+        associated_token::authority = withdrawal_request,
+    )]
+    pub escrow_lp_account: Box<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Complete an SPL pool withdrawal request once its delay has passed
+/// The escrowed LP goes back to user_pool_ata and is burned there as in remove_liquidity
+pub fn execute_remove_liquidity<'info>(
+    ctx: Context<'_, '_, '_, 'info, ExecuteRemoveLiquidity<'info>>,
+) -> Result<()> {
+    require!(
+        Clock::get()?.unix_timestamp >= ctx.accounts.withdrawal_request.unlock_at,
+        ErrorCode::WithdrawalNotReady
+    );
+
+    let lp_amount = ctx.accounts.withdrawal_request.lp_amount;
+    release_escrow(
+        &ctx.accounts.withdrawal_request,
+        &ctx.accounts.escrow_lp_account,
+        ctx.accounts.liquidity.user_pool_ata.to_account_info(),
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.liquidity.token_program.to_account_info(),
+    )?;
+    // withdraw_liquidity checks the burn against user_pool_ata's balance - re-read it
+    // now that the escrowed LP is back in it
+    ctx.accounts.liquidity.user_pool_ata.reload()?;
+
+    withdraw_liquidity(
+        Context::new(
+            ctx.program_id,
+            &mut ctx.accounts.liquidity,
+            ctx.remaining_accounts,
+            ctx.bumps.liquidity,
+        ),
+        lp_amount,
+    )
+}
+
+#[derive(Accounts)]
+pub struct ExecuteRemoveLiquidity<'info> {
+    /// Same accounts as remove_liquidity - liquidity.owner must be `owner`
+    pub liquidity: LiquidityOperation<'info>,
+
+    // Receives the rent of the request and its escrow account
+    #[account(mut, address = liquidity.owner.key() @ ErrorCode::Unauthorized)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"withdrawal_request", liquidity.pool_state.key().as_ref(), owner.key().as_ref()],
+        bump = withdrawal_request.bump,
+        has_one = owner,
+        close = owner
+    )]
+    pub withdrawal_request: Account<'info, WithdrawalRequest>,
+
+    #[account(
+        mut,
+        associated_token::mint = liquidity.pool_mint,
+        associated_token::authority = withdrawal_request,
+    )]
+    pub escrow_lp_account: Box<Account<'info, TokenAccount>>,
+}
+
+/// Complete a native pool withdrawal request once its delay has passed
+/// The escrowed LP goes back to user_lp_account and is burned there as in remove_native_liquidity
+pub fn execute_remove_native_liquidity<'info>(
+    ctx: Context<'_, '_, '_, 'info, ExecuteRemoveNativeLiquidity<'info>>,
+    min_xnt_out: u64,
+    min_token_out: u64,
+) -> Result<()> {
+    require!(
+        Clock::get()?.unix_timestamp >= ctx.accounts.withdrawal_request.unlock_at,
+        ErrorCode::WithdrawalNotReady
+    );
+
+    let lp_amount = ctx.accounts.withdrawal_request.lp_amount;
+    release_escrow(
+        &ctx.accounts.withdrawal_request,
+        &ctx.accounts.escrow_lp_account,
+        ctx.accounts.pool.user_lp_account.to_account_info(),
+        ctx.accounts.pool.user.to_account_info(),
+        ctx.accounts.pool.token_program.to_account_info(),
+    )?;
+
+    withdraw_native_liquidity(
+        Context::new(
+            ctx.program_id,
+            &mut ctx.accounts.pool,
+            ctx.remaining_accounts,
+            ctx.bumps.pool,
+        ),
+        lp_amount,
+        min_xnt_out,
+        min_token_out,
+    )
+}
+
+#[derive(Accounts)]
+pub struct ExecuteRemoveNativeLiquidity<'info> {
+    /// Same accounts as remove_native_liquidity - pool.user is the request owner
+    pub pool: RemoveNativeLiquidity<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"withdrawal_request", pool.pool_state.key().as_ref(), pool.user.key().as_ref()],
+        bump = withdrawal_request.bump,
+        constraint = withdrawal_request.owner == pool.user.key() @ ErrorCode::Unauthorized,
+        close = user
+    )]
+    pub withdrawal_request: Account<'info, WithdrawalRequest>,
+
+    // pool.lp_mint must be the pool's own LP mint for the escrow to be the right account
+    #[account(
+        mut,
+        associated_token::mint = pool.lp_mint,
+        associated_token::authority = withdrawal_request,
+        constraint = pool.lp_mint.key() == pool.pool_state.lp_mint_address(&pool.pool_state.key(), &crate::ID) @ ErrorCode::InvalidInput,
+    )]
+    pub escrow_lp_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: Same account as pool.user, listed again as the rent destination of `close`
+    #[account(mut, address = pool.user.key())]
+    pub user: UncheckedAccount<'info>,
+}
+
+/// Return the escrowed LP of a pending request to the owner
+pub fn cancel_remove_liquidity(ctx: Context<CancelRemoveLiquidity>) -> Result<()> {
+    release_escrow(
+        &ctx.accounts.withdrawal_request,
+        &ctx.accounts.escrow_lp_account,
+        ctx.accounts.user_lp_account.to_account_info(),
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+    )
+}
+
+#[derive(Accounts)]
+pub struct CancelRemoveLiquidity<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub pool_state: Box<Account<'info, PoolState>>,
+
+    #[account(
+        mut,
+        seeds = [b"withdrawal_request", pool_state.key().as_ref(), owner.key().as_ref()],
+        bump = withdrawal_request.bump,
+        has_one = owner,
+        close = owner
+    )]
+    pub withdrawal_request: Account<'info, WithdrawalRequest>,
+
+    #[account(address = pool_state.lp_mint_address(&pool_state.key(), &crate::ID) @ ErrorCode::InvalidInput)]
+    pub lp_mint: Box<Account<'info, Mint>>,
+
+    #[account(mut, token::mint = lp_mint, token::authority = owner)]
+    pub user_lp_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        associated_token::mint = lp_mint,
+        associated_token::authority = withdrawal_request,
+    )]
+    pub escrow_lp_account: Box<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+// Move everything in the escrow to `destination` and close it, rent to `owner`
+// The request account itself is closed by its `close` constraint
+fn release_escrow<'info>(
+    withdrawal_request: &Account<'info, WithdrawalRequest>,
+    escrow_lp_account: &Account<'info, TokenAccount>,
+    destination: AccountInfo<'info>,
+    owner: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+) -> Result<()> {
+    let request_seeds = &[
+        b"withdrawal_request",
+        withdrawal_request.pool.as_ref(),
+        withdrawal_request.owner.as_ref(),
+        &[withdrawal_request.bump],
+    ];
+    let signer_seeds = &[&request_seeds[..]];
+
+    token::transfer(
+        CpiContext::new_with_signer(
+            token_program.clone(),
+            Transfer {
+                from: escrow_lp_account.to_account_info(),
+                to: destination,
+                authority: withdrawal_request.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        escrow_lp_account.amount,
+    )?;
+
+    token::close_account(CpiContext::new_with_signer(
+        token_program,
+        CloseAccount {
+            account: escrow_lp_account.to_account_info(),
+            destination: owner,
+            authority: withdrawal_request.to_account_info(),
+        },
+        signer_seeds,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_spl::associated_token::get_associated_token_address;
+    use crate::test_fixtures::*;
+
+    const DELAY_SECS: u64 = 3_600;
+    const TOTAL_LP: u64 = 1_500_000;
+    const HOLDER_LP: u64 = 500_000;
+
+    // An SPL pool with a withdrawal delay and the accounts of one LP holder
+    struct Pool {
+        pool_state: Info,
+        pool_authority: Info,
+        vault0: Info,
+        vault1: Info,
+        pool_mint: Info,
+        user0: Info,
+        user1: Info,
+        user_pool_ata: Info,
+        owner: Info,
+        token_program: Info,
+        token_2022_program: Info,
+        global_config: Info,
+        withdrawal_request: Info,
+        escrow_lp_account: Info,
+    }
+
+    fn pool() -> Pool {
+        let pool_key = Pubkey::new_unique();
+        let pool_state = PoolState {
+            total_amount_minted: TOTAL_LP,
+            fee_numerator: 30,
+            fee_denominator: 10000,
+            withdrawal_delay_secs: DELAY_SECS,
+            ..Default::default()
+        };
+        let pool_authority = pda(&[b"authority", pool_key.as_ref()]);
+        let pool_mint = pool_state.lp_mint_address(&pool_key, &crate::ID);
+        let (mint0, mint1) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let owner = signer().info();
+        let withdrawal_request = pda(&[b"withdrawal_request", pool_key.as_ref(), owner.key.as_ref()]);
+        let escrow = get_associated_token_address(&withdrawal_request, &pool_mint);
+        Pool {
+            pool_state: program_account(pool_key, &pool_state).info(),
+            pool_authority: empty_account(pool_authority).info(),
+            vault0: token_account(pda(&[b"vault0", pool_key.as_ref()]), mint0, pool_authority, 3_000_000).info(),
+            vault1: token_account(pda(&[b"vault1", pool_key.as_ref()]), mint1, pool_authority, 6_000_000).info(),
+            pool_mint: mint_account(pool_mint, pool_authority, TOTAL_LP, 9).info(),
+            user0: token_account(Pubkey::new_unique(), mint0, *owner.key, 0).info(),
+            user1: token_account(Pubkey::new_unique(), mint1, *owner.key, 0).info(),
+            user_pool_ata: token_account(Pubkey::new_unique(), pool_mint, *owner.key, HOLDER_LP).info(),
+            owner,
+            token_program: program(anchor_spl::token::ID).info(),
+            token_2022_program: program(anchor_spl::token_2022::ID).info(),
+            global_config: empty_account(pda(&[b"global_config"])).info(),
+            withdrawal_request: program_account(
+                withdrawal_request,
+                &WithdrawalRequest { pool: Pubkey::default(), owner: Pubkey::default(), lp_amount: 0, unlock_at: 0, bump: 0 },
+            ).info(),
+            escrow_lp_account: token_account(escrow, pool_mint, withdrawal_request, 0).info(),
+        }
+    }
+
+    // Escrow the holder's whole balance at `now` through request_remove_liquidity
+    fn request(pool: &Pool, now: i64) -> WithdrawalRequest {
+        set_clock(now);
+        let (_, bump) = Pubkey::find_program_address(
+            &[b"withdrawal_request", pool.pool_state.key.as_ref(), pool.owner.key.as_ref()],
+            &crate::ID,
+        );
+        let mut accounts = RequestRemoveLiquidity {
+            owner: Signer::try_from(pool.owner).unwrap(),
+            pool_state: Box::new(Account::try_from(pool.pool_state).unwrap()),
+            withdrawal_request: Account::try_from(pool.withdrawal_request).unwrap(),
+            lp_mint: Box::new(Account::try_from(pool.pool_mint).unwrap()),
+            user_lp_account: Box::new(Account::try_from(pool.user_pool_ata).unwrap()),
+            escrow_lp_account: Box::new(Account::try_from(pool.escrow_lp_account).unwrap()),
+            token_program: Program::try_from(pool.token_program).unwrap(),
+            associated_token_program: Program::try_from(program(anchor_spl::associated_token::ID).info()).unwrap(),
+            system_program: Program::try_from(program(System::id()).info()).unwrap(),
+        };
+        let bumps = RequestRemoveLiquidityBumps { withdrawal_request: bump };
+        request_remove_liquidity(Context::new(&crate::ID, &mut accounts, &[], bumps), HOLDER_LP).unwrap();
+        accounts.exit(&crate::ID).unwrap();
+        
+        // The escrow transfer the handler made
+        set_token_amount(pool.user_pool_ata, 0);
+        set_token_amount(pool.escrow_lp_account, HOLDER_LP);
+        (*accounts.withdrawal_request).clone()
+    }
+
+    // Run execute_remove_liquidity at `now`, returning the pool's LP supply afterwards
+    fn execute(pool: &Pool, now: i64) -> Result<u64> {
+        set_clock(now);
+        let mut accounts = ExecuteRemoveLiquidity {
+            liquidity: LiquidityOperation {
+                pool_state: Box::new(Account::try_from(pool.pool_state).unwrap()),
+                pool_authority: pool.pool_authority.clone(),
+                vault0: Box::new(InterfaceAccount::try_from(pool.vault0).unwrap()),
+                vault1: Box::new(InterfaceAccount::try_from(pool.vault1).unwrap()),
+                pool_mint: Box::new(Account::try_from(pool.pool_mint).unwrap()),
+                user0: Box::new(InterfaceAccount::try_from(pool.user0).unwrap()),
+                user1: Box::new(InterfaceAccount::try_from(pool.user1).unwrap()),
+                user_pool_ata: Box::new(InterfaceAccount::try_from(pool.user_pool_ata).unwrap()),
+                owner: Signer::try_from(pool.owner).unwrap(),
+                token_program: Program::try_from(pool.token_program).unwrap(),
+                token_2022_program: UncheckedAccount::try_from(pool.token_2022_program),
+                global_config: UncheckedAccount::try_from(pool.global_config),
+                locked_lp_account: None,
+                mint0: None,
+                mint1: None,
+            },
+            owner: Signer::try_from(pool.owner).unwrap(),
+            withdrawal_request: Account::try_from(pool.withdrawal_request).unwrap(),
+            escrow_lp_account: Box::new(Account::try_from(pool.escrow_lp_account).unwrap()),
+        };
+        // The handler moves the escrow back to user_pool_ata after its accounts were loaded
+        set_token_amount(pool.user_pool_ata, HOLDER_LP);
+        
+        let result = execute_remove_liquidity(Context::new(
+            &crate::ID,
+            &mut accounts,
+            &[],
+            ExecuteRemoveLiquidityBumps::default(),
+        ));
+        set_token_amount(pool.user_pool_ata, 0);
+        result.map(|()| accounts.liquidity.pool_state.total_amount_minted)
+    }
+
+    #[test]
+    fn escrowed_lp_is_burned_once_the_delay_passes() {
+        let pool = pool();
+        let withdrawal_request = request(&pool, 1_000);
+        assert_eq!(withdrawal_request.owner, *pool.owner.key);
+        assert_eq!(withdrawal_request.lp_amount, HOLDER_LP);
+        assert_eq!(withdrawal_request.unlock_at, 1_000 + DELAY_SECS as i64);
+        
+        // The holder escrowed their whole balance, the burn goes through on what came back
+        assert_eq!(execute(&pool, withdrawal_request.unlock_at).unwrap(), TOTAL_LP - HOLDER_LP);
+    }
+
+    #[test]
+    fn execute_before_the_delay_is_rejected() {
+        let pool = pool();
+        let withdrawal_request = request(&pool, 1_000);
+        
+        assert_eq!(
+            execute(&pool, withdrawal_request.unlock_at - 1).unwrap_err(),
+            ErrorCode::WithdrawalNotReady.into()
+        );
+        assert_eq!(execute(&pool, withdrawal_request.unlock_at).unwrap(), TOTAL_LP - HOLDER_LP);
+    }
+}
@@ -1,9 +1,12 @@
 use anchor_lang::prelude::*;
 
 pub mod error; 
+pub mod events;
 pub mod state; 
 pub mod instructions;
 pub mod utils;
+#[cfg(test)]
+mod test_fixtures;
 
 use instructions::*;
 
@@ -20,34 +23,197 @@ pub mod ammv2 {
         protocol_treasury: Option<Pubkey>,
         protocol_fee_bps: Option<u16>,
     ) -> Result<()> {
-        init_pool::handler(ctx, fee_numerator, fee_denominator, protocol_treasury, protocol_fee_bps)
+        init_pool::handler(ctx, fee_numerator, fee_denominator, protocol_treasury, protocol_fee_bps, None, None)
     }
 
-    pub fn remove_liquidity(
-        ctx: Context<LiquidityOperation>, 
+    /// initialize_pool with a chosen LP mint decimals (0-9) and soulbound (non-transferable) LP
+    /// A separate instruction so initialize_pool's arguments stay what existing clients send
+    pub fn initialize_pool_with_lp_options(
+        ctx: Context<InitializePool>,
+        fee_numerator: u64,
+        fee_denominator: u64,
+        protocol_treasury: Option<Pubkey>,
+        protocol_fee_bps: Option<u16>,
+        lp_decimals: u8,
+        lp_soulbound: bool,
+    ) -> Result<()> {
+        init_pool::handler(ctx, fee_numerator, fee_denominator, protocol_treasury, protocol_fee_bps, Some(lp_decimals), Some(lp_soulbound))
+    }
+
+    /// Initialize a protocol-owned pool: all swap fees go to protocol_treasury and
+    /// LP tokens can only be minted to the governance PDA (GlobalConfig authority only)
+    pub fn initialize_protocol_owned_pool<'info>(
+        ctx: Context<'_, '_, '_, 'info, InitializeProtocolOwnedPool<'info>>,
+        fee_numerator: u64,
+        fee_denominator: u64,
+        protocol_treasury: Pubkey,
+        protocol_fee_bps: u16,
+        lp_decimals: Option<u8>,
+        lp_soulbound: Option<bool>,
+    ) -> Result<()> {
+        init_pool::protocol_owned_handler(ctx, fee_numerator, fee_denominator, protocol_treasury, protocol_fee_bps, lp_decimals, lp_soulbound)
+    }
+
+    /// Initialize a pool that adopts an existing LP mint and its supply (liquidity migration)
+    /// The old pool's reserves move into the vaults in the same instruction
+    #[allow(clippy::too_many_arguments)]
+    pub fn initialize_pool_with_existing_lp<'info>(
+        ctx: Context<'_, '_, '_, 'info, InitializePoolWithExistingLp<'info>>,
+        fee_numerator: u64,
+        fee_denominator: u64,
+        protocol_treasury: Option<Pubkey>,
+        protocol_fee_bps: Option<u16>,
+        reserve0: u64,
+        reserve1: u64,
+    ) -> Result<()> {
+        init_pool::with_existing_lp_handler(ctx, fee_numerator, fee_denominator, protocol_treasury, protocol_fee_bps, reserve0, reserve1)
+    }
+
+    pub fn remove_liquidity<'info>(
+        ctx: Context<'_, '_, '_, 'info, LiquidityOperation<'info>>, 
         burn_amount: u64,
     ) -> Result<()> {
         liquidity::remove_liquidity(ctx, burn_amount)
     }
 
-    pub fn add_liquidity(
-        ctx: Context<LiquidityOperation>, 
+    /// Remove liquidity, then close the emptied LP account and return its rent
+    pub fn remove_liquidity_and_close<'info>(
+        ctx: Context<'_, '_, '_, 'info, LiquidityOperation<'info>>, 
+        burn_amount: u64,
+    ) -> Result<()> {
+        liquidity::remove_liquidity_and_close(ctx, burn_amount)
+    }
+
+    /// Remove liquidity from up to MAX_BATCH_POOLS pools at once
+    /// See liquidity::remove_liquidity_batch for the remaining accounts layout
+    pub fn remove_liquidity_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RemoveLiquidityBatch<'info>>,
+        burn_amounts: Vec<u64>,
+    ) -> Result<()> {
+        liquidity::remove_liquidity_batch(ctx, burn_amounts)
+    }
+
+    /// Escrow lp_amount LP until the pool's withdrawal delay passes (SPL or native pool)
+    pub fn request_remove_liquidity(ctx: Context<RequestRemoveLiquidity>, lp_amount: u64) -> Result<()> {
+        withdrawal_queue::request_remove_liquidity(ctx, lp_amount)
+    }
+
+    /// Burn a matured withdrawal request's LP and pay out, as remove_liquidity
+    pub fn execute_remove_liquidity<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExecuteRemoveLiquidity<'info>>,
+    ) -> Result<()> {
+        withdrawal_queue::execute_remove_liquidity(ctx)
+    }
+
+    /// Return a pending withdrawal request's LP to its owner
+    pub fn cancel_remove_liquidity(ctx: Context<CancelRemoveLiquidity>) -> Result<()> {
+        withdrawal_queue::cancel_remove_liquidity(ctx)
+    }
+
+    pub fn add_liquidity<'info>(
+        ctx: Context<'_, '_, '_, 'info, LiquidityOperation<'info>>, 
         amount_liq0: u64, 
         amount_liq1: u64, 
     ) -> Result<()> {
         liquidity::add_liquidity(ctx, amount_liq0, amount_liq1)
     }
 
-    pub fn swap(
-        ctx: Context<Swap>, 
+    /// Deposit at the pool ratio, matching the other side to whichever is limiting
+    /// Returns the amounts actually deposited
+    pub fn add_liquidity_exact_ratio<'info>(
+        ctx: Context<'_, '_, '_, 'info, LiquidityOperation<'info>>,
+        max_amount0: u64,
+        max_amount1: u64,
+    ) -> Result<DepositedAmounts> {
+        liquidity::add_liquidity_exact_ratio(ctx, max_amount0, max_amount1)
+    }
+
+    /// Deposit, then stake the minted LP in a caller-provided staking program
+    /// Remaining accounts are the staking instruction's accounts - the deposit finds any
+    /// transfer hook accounts among them
+    pub fn add_liquidity_and_stake<'info>(
+        ctx: Context<'_, '_, '_, 'info, AddLiquidityAndStake<'info>>,
+        amount_liq0: u64,
+        amount_liq1: u64,
+        stake_ix_data: Vec<u8>,
+    ) -> Result<()> {
+        liquidity::add_liquidity_and_stake(ctx, amount_liq0, amount_liq1, stake_ix_data)
+    }
+
+    /// Returns the executed input, output (net of fees) and protocol fee
+    pub fn swap<'info>(
+        ctx: Context<'_, '_, '_, 'info, Swap<'info>>, 
         amount_in: u64, 
         min_amount_out: u64,
-    ) -> Result<()> {
+    ) -> Result<SwapResult> {
         swap::swap(ctx, amount_in, min_amount_out)
     }
     
+    /// Swap as above, then re-read every balance moved and fail on any mismatch
+    pub fn swap_verified<'info>(
+        ctx: Context<'_, '_, '_, 'info, Swap<'info>>, 
+        amount_in: u64, 
+        min_amount_out: u64,
+    ) -> Result<SwapResult> {
+        swap::swap_verified(ctx, amount_in, min_amount_out)
+    }
+    
+    /// Swap and deliver the output to recipient_token_account (any wallet)
+    pub fn swap_to<'info>(
+        ctx: Context<'_, '_, '_, 'info, Swap<'info>>, 
+        amount_in: u64, 
+        min_amount_out: u64,
+    ) -> Result<SwapResult> {
+        swap::swap_to(ctx, amount_in, min_amount_out)
+    }
+    
+    /// Swap with min_amount_out set to the client's quoted output less slippage_bps
+    pub fn swap_with_slippage_bps<'info>(
+        ctx: Context<'_, '_, '_, 'info, Swap<'info>>,
+        amount_in: u64,
+        quoted_amount_out: u64,
+        slippage_bps: u16,
+    ) -> Result<SwapResult> {
+        swap::swap_with_slippage_bps(ctx, amount_in, quoted_amount_out, slippage_bps)
+    }
+    
+    /// Swap that reverts if the average price (output per input, Q64.64) is worse than limit_price
+    pub fn swap_with_price_limit<'info>(
+        ctx: Context<'_, '_, '_, 'info, Swap<'info>>,
+        amount_in: u64,
+        limit_price: u128,
+    ) -> Result<SwapResult> {
+        swap::swap_with_price_limit(ctx, amount_in, limit_price)
+    }
+    
+    /// Swap only as much of amount_in as fits within max_price_impact_bps
+    /// Returns the filled input and delivered output
+    pub fn swap_partial<'info>(
+        ctx: Context<'_, '_, '_, 'info, Swap<'info>>,
+        amount_in: u64,
+        max_price_impact_bps: u16,
+        min_amount_out: u64,
+    ) -> Result<SwapPartialResult> {
+        swap::swap_partial(ctx, amount_in, max_price_impact_bps, min_amount_out)
+    }
+    
+    /// Create the protocol treasury's XNT ATA if missing (anyone may call, idempotent)
+    pub fn ensure_treasury_ata(ctx: Context<EnsureTreasuryAta>) -> Result<()> {
+        swap::ensure_treasury_ata(ctx)
+    }
+    
+    /// Donate tokens to the pool's LPs without minting LP tokens
+    pub fn donate_liquidity<'info>(
+        ctx: Context<'_, '_, '_, 'info, DonateLiquidity<'info>>,
+        amount0: u64,
+        amount1: u64,
+    ) -> Result<()> {
+        donate::donate_liquidity(ctx, amount0, amount1)
+    }
+    
     // === NATIVE XNT POOL INSTRUCTIONS ===
     
+    #[allow(clippy::too_many_arguments)]
     pub fn initialize_native_pool(
         ctx: Context<InitializeNativePool>,
         fee_numerator: u64,
@@ -63,9 +229,65 @@ pub mod ammv2 {
             protocol_treasury,
             protocol_fee_bps,
             native_mint_index,
+            None,
+            None,
+        )
+    }
+    
+    /// initialize_native_pool with a chosen LP mint decimals (0-9) and soulbound (non-transferable) LP
+    #[allow(clippy::too_many_arguments)]
+    pub fn initialize_native_pool_with_lp_options(
+        ctx: Context<InitializeNativePool>,
+        fee_numerator: u64,
+        fee_denominator: u64,
+        protocol_treasury: Pubkey,
+        protocol_fee_bps: u16,
+        native_mint_index: u8,
+        lp_decimals: u8,
+        lp_soulbound: bool,
+    ) -> Result<()> {
+        native_pool::initialize_native_pool(
+            ctx,
+            fee_numerator,
+            fee_denominator,
+            protocol_treasury,
+            protocol_fee_bps,
+            native_mint_index,
+            Some(lp_decimals),
+            Some(lp_soulbound),
         )
     }
     
+    /// Create a native pool and make its first deposit atomically
+    #[allow(clippy::too_many_arguments)]
+    pub fn initialize_native_pool_with_liquidity<'info>(
+        ctx: Context<'_, '_, '_, 'info, InitializeNativePoolWithLiquidity<'info>>,
+        fee_numerator: u64,
+        fee_denominator: u64,
+        protocol_treasury: Pubkey,
+        protocol_fee_bps: u16,
+        native_mint_index: u8,
+        xnt_amount: u64,
+        token_amount: u64,
+        min_lp_out: u64,
+        lp_decimals: Option<u8>,
+        lp_soulbound: Option<bool>,
+    ) -> Result<()> {
+        native_pool::initialize_native_pool_with_liquidity(
+            ctx,
+            fee_numerator,
+            fee_denominator,
+            protocol_treasury,
+            protocol_fee_bps,
+            native_mint_index,
+            xnt_amount,
+            token_amount,
+            min_lp_out,
+            lp_decimals,
+            lp_soulbound,
+        )
+    }
+
     pub fn add_native_liquidity(
         ctx: Context<AddNativeLiquidity>,
         xnt_amount: u64,
@@ -78,31 +300,395 @@ pub mod ammv2 {
     pub fn remove_native_liquidity(
         ctx: Context<RemoveNativeLiquidity>,
         lp_amount: u64,
+        min_xnt_out: u64,
+        min_token_out: u64,
     ) -> Result<()> {
-        native_pool::remove_native_liquidity(ctx, lp_amount)
+        native_pool::remove_native_liquidity(ctx, lp_amount, min_xnt_out, min_token_out)
     }
     
-    pub fn swap_native(
-        ctx: Context<SwapNative>,
+    /// Remove native liquidity, then close the emptied LP account and return its rent
+    pub fn remove_native_liquidity_and_close(
+        ctx: Context<RemoveNativeLiquidity>,
+        lp_amount: u64,
+        min_xnt_out: u64,
+        min_token_out: u64,
+    ) -> Result<()> {
+        native_pool::remove_native_liquidity_and_close(ctx, lp_amount, min_xnt_out, min_token_out)
+    }
+    
+    /// Burn a matured withdrawal request's LP and pay out, as remove_native_liquidity
+    pub fn execute_remove_native_liquidity<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExecuteRemoveNativeLiquidity<'info>>,
+        min_xnt_out: u64,
+        min_token_out: u64,
+    ) -> Result<()> {
+        withdrawal_queue::execute_remove_native_liquidity(ctx, min_xnt_out, min_token_out)
+    }
+    
+    pub fn swap_native<'info>(
+        ctx: Context<'_, '_, '_, 'info, SwapNative<'info>>,
         amount_in: u64,
         min_amount_out: u64,
         is_xnt_to_token: bool,
-    ) -> Result<()> {
+    ) -> Result<SwapResult> {
         native_pool::swap_native(ctx, amount_in, min_amount_out, is_xnt_to_token)
     }
     
+    /// Donate XNT and/or tokens to the pool's LPs without minting LP tokens
+    pub fn donate_native_liquidity(
+        ctx: Context<DonateNativeLiquidity>,
+        xnt_amount: u64,
+        token_amount: u64,
+    ) -> Result<()> {
+        donate::donate_native_liquidity(ctx, xnt_amount, token_amount)
+    }
+    
     /// Reconcile native reserve with actual PDA balance
-    /// Use this to fix any reserve drift
-    pub fn reconcile_native_reserve(ctx: Context<ReconcileNativeReserve>) -> Result<()> {
-        native_pool::reconcile_native_reserve(ctx)
+    /// Use this to fix any reserve drift - a large increase needs the admin and allow_increase
+    pub fn reconcile_native_reserve(ctx: Context<ReconcileNativeReserve>, allow_increase: bool) -> Result<()> {
+        native_pool::reconcile_native_reserve(ctx, allow_increase)
     }
     
-    /// Emergency pause for native pool
+    /// Emergency pause for native pool (admin only) - stops swaps and deposits
     pub fn pause_native_pool(ctx: Context<PauseNativePool>) -> Result<()> {
         native_pool::pause_native_pool(ctx)
     }
     
+    /// Sweep untracked XNT (above native_reserve) from a native pool to its treasury
+    pub fn sweep_dust(ctx: Context<SweepDust>) -> Result<()> {
+        native_pool::sweep_dust(ctx)
+    }
+    
+    /// Reclaim the reserves behind the locked minimum liquidity of a closed, emptied native pool
+    pub fn reclaim_minimum_liquidity(ctx: Context<ReclaimMinimumLiquidity>) -> Result<()> {
+        native_pool::reclaim_minimum_liquidity(ctx)
+    }
+    
     pub fn recover_stuck_native_xnt(ctx: Context<RecoverStuckNativeXnt>) -> Result<()> {
         native_pool::recover_stuck_native_xnt(ctx)
     }
+    
+    // === ADMIN INSTRUCTIONS ===
+    
+    /// Cap how much slippage a swap's min_amount_out may imply (10000 = unrestricted)
+    pub fn set_max_slippage(ctx: Context<UpdatePoolConfig>, max_allowed_slippage_bps: u16) -> Result<()> {
+        admin::set_max_slippage(ctx, max_allowed_slippage_bps)
+    }
+    
+    /// Cap the share of the output reserve one swap may take (10000 = unrestricted)
+    pub fn set_max_swap_out_bps(ctx: Context<UpdatePoolConfig>, max_swap_out_bps_of_reserve: u16) -> Result<()> {
+        admin::set_max_swap_out_bps(ctx, max_swap_out_bps_of_reserve)
+    }
+    
+    /// Cap the XNT reserve deposits may bring the pool to (0 = unlimited)
+    pub fn set_max_tvl_native(ctx: Context<UpdatePoolConfig>, max_tvl_native: u64) -> Result<()> {
+        admin::set_max_tvl_native(ctx, max_tvl_native)
+    }
+    
+    /// Discount the LP fee by discount_bps for swappers holding at least threshold LP tokens
+    pub fn set_lp_fee_discount(ctx: Context<UpdatePoolConfig>, threshold: u64, discount_bps: u16) -> Result<()> {
+        admin::set_lp_fee_discount(ctx, threshold, discount_bps)
+    }
+    
+    /// Create the signer's LP balance snapshot for a pool (counts for the fee discount from the next slot)
+    pub fn initialize_lp_balance_snapshot(ctx: Context<InitializeLpBalanceSnapshot>) -> Result<()> {
+        lp_snapshot::initialize_lp_balance_snapshot(ctx)
+    }
+    
+    /// Re-record the signer's LP balance snapshot for a pool
+    pub fn snapshot_lp_balance(ctx: Context<SnapshotLpBalance>) -> Result<()> {
+        lp_snapshot::snapshot_lp_balance(ctx)
+    }
+    
+    /// Change protocol_fee_bps - above the LP fee only with allow_high_protocol_fee
+    pub fn set_protocol_fee(
+        ctx: Context<UpdatePoolConfig>,
+        protocol_fee_bps: u16,
+        allow_high_protocol_fee: bool,
+    ) -> Result<()> {
+        admin::set_protocol_fee(ctx, protocol_fee_bps, allow_high_protocol_fee)
+    }
+    
+    /// Charge protocol_fee_bps on the XNT notional (0) or on the LP fee (1)
+    pub fn set_protocol_fee_mode(ctx: Context<UpdatePoolConfig>, protocol_fee_mode: u8) -> Result<()> {
+        admin::set_protocol_fee_mode(ctx, protocol_fee_mode)
+    }
+    
+    /// Set the wait between propose_fee_change and apply_fee_change - can only grow
+    pub fn set_fee_timelock(ctx: Context<UpdatePoolConfig>, fee_timelock_secs: u64) -> Result<()> {
+        admin::set_fee_timelock(ctx, fee_timelock_secs)
+    }
+    
+    /// Schedule a new fee_numerator and protocol_fee_bps, applicable after the fee timelock
+    pub fn propose_fee_change(
+        ctx: Context<UpdatePoolConfig>,
+        fee_numerator: u64,
+        protocol_fee_bps: u16,
+        allow_high_protocol_fee: bool,
+    ) -> Result<()> {
+        admin::propose_fee_change(ctx, fee_numerator, protocol_fee_bps, allow_high_protocol_fee)
+    }
+    
+    /// Activate the pending fee change once its timelock has passed
+    pub fn apply_fee_change(ctx: Context<UpdatePoolConfig>) -> Result<()> {
+        admin::apply_fee_change(ctx)
+    }
+    
+    /// Drop the pending fee change
+    pub fn cancel_fee_change(ctx: Context<UpdatePoolConfig>) -> Result<()> {
+        admin::cancel_fee_change(ctx)
+    }
+    
+    /// Make SPL swaps create a missing treasury ATA (swapper pays rent) instead of skipping the fee
+    pub fn set_create_treasury_ata(ctx: Context<UpdatePoolConfig>, enabled: bool) -> Result<()> {
+        admin::set_create_treasury_ata(ctx, enabled)
+    }
+    
+    /// Set the mint treated as XNT for protocol fees (Pubkey::default() = SPL native mint)
+    pub fn set_native_mint(ctx: Context<UpdatePoolConfig>, native_mint: Pubkey) -> Result<()> {
+        admin::set_native_mint(ctx, native_mint)
+    }
+    
+    /// Stop or resume swaps on the pool
+    pub fn set_swaps_enabled(ctx: Context<UpdatePoolConfig>, enabled: bool) -> Result<()> {
+        admin::set_swaps_enabled(ctx, enabled)
+    }
+    
+    /// Stop or resume deposits on the pool - withdrawals are never gated
+    pub fn set_deposits_enabled(ctx: Context<UpdatePoolConfig>, enabled: bool) -> Result<()> {
+        admin::set_deposits_enabled(ctx, enabled)
+    }
+    
+    /// Make LPs wait withdrawal_delay_secs between requesting and executing a withdrawal (0 = off)
+    pub fn set_withdrawal_delay(ctx: Context<UpdatePoolConfig>, withdrawal_delay_secs: u64) -> Result<()> {
+        admin::set_withdrawal_delay(ctx, withdrawal_delay_secs)
+    }
+    
+    /// Waive the protocol fee on swaps for bootstrap_secs after pool creation (0 = off)
+    pub fn set_bootstrap_window(ctx: Context<UpdatePoolConfig>, bootstrap_secs: u64) -> Result<()> {
+        admin::set_bootstrap_window(ctx, bootstrap_secs)
+    }
+    
+    /// Leave lp_rebate_bps of the protocol fee in the pool's reserves for LPs (0 = off)
+    pub fn set_lp_rebate(ctx: Context<UpdatePoolConfig>, lp_rebate_bps: u16) -> Result<()> {
+        admin::set_lp_rebate(ctx, lp_rebate_bps)
+    }
+    
+    /// Keep the protocol fee in the pool and mint its value as LP to protocol_lp_account (default = off)
+    pub fn set_protocol_fee_compounding(ctx: Context<SetProtocolFeeCompounding>, protocol_lp_account: Pubkey) -> Result<()> {
+        admin::set_protocol_fee_compounding(ctx, protocol_lp_account)
+    }
+    
+    /// Hand the pool's admin role to another key
+    pub fn set_admin(ctx: Context<UpdatePoolConfig>, new_admin: Pubkey) -> Result<()> {
+        admin::set_admin(ctx, new_admin)
+    }
+    
+    /// Change the protocol fee recipient (Pubkey::default() = no protocol fee collected)
+    pub fn set_protocol_treasury(ctx: Context<UpdatePoolConfig>, protocol_treasury: Pubkey) -> Result<()> {
+        admin::set_protocol_treasury(ctx, protocol_treasury)
+    }
+    
+    /// Create the pool's FeeSplit table - swaps that pass it split the protocol fee by weight
+    pub fn initialize_fee_split(
+        ctx: Context<InitializeFeeSplit>,
+        recipients: Vec<Pubkey>,
+        weights_bps: Vec<u16>,
+    ) -> Result<()> {
+        fee_split::initialize_fee_split(ctx, recipients, weights_bps)
+    }
+    
+    /// Replace the recipients and weights (bps, summing to 10000) of the pool's FeeSplit table
+    pub fn set_fee_split(
+        ctx: Context<UpdateFeeSplit>,
+        recipients: Vec<Pubkey>,
+        weights_bps: Vec<u16>,
+    ) -> Result<()> {
+        fee_split::set_fee_split(ctx, recipients, weights_bps)
+    }
+    
+    /// Close the pool's FeeSplit table, sending protocol fees back to protocol_treasury
+    pub fn close_fee_split(ctx: Context<UpdateFeeSplit>) -> Result<()> {
+        fee_split::close_fee_split(ctx)
+    }
+    
+    /// Create the pool's optional PoolMetadata label (name, symbol, URI)
+    pub fn initialize_pool_metadata(
+        ctx: Context<InitializePoolMetadata>,
+        name: String,
+        symbol: String,
+        uri: String,
+    ) -> Result<()> {
+        pool_metadata::initialize_pool_metadata(ctx, name, symbol, uri)
+    }
+    
+    /// Replace the name, symbol and URI of the pool's PoolMetadata label
+    pub fn set_pool_metadata(
+        ctx: Context<UpdatePoolMetadata>,
+        name: String,
+        symbol: String,
+        uri: String,
+    ) -> Result<()> {
+        pool_metadata::set_pool_metadata(ctx, name, symbol, uri)
+    }
+    
+    /// Recover tokens stuck in an SPL pool vault while the pool has no LP supply
+    pub fn recover_stuck_tokens<'info>(
+        ctx: Context<'_, '_, '_, 'info, RecoverStuckTokens<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        admin::recover_stuck_tokens(ctx, amount)
+    }
+    
+    /// Sweep lamports above rent from a Token 2022 SPL pool vault to a recovery account
+    pub fn sweep_vault_lamports(ctx: Context<SweepVaultLamports>) -> Result<()> {
+        admin::sweep_vault_lamports(ctx)
+    }
+    
+    /// Swap the admin's funds against a protocol-owned pool to move its price to target_price_x64
+    pub fn rebalance<'info>(ctx: Context<'_, '_, '_, 'info, Rebalance<'info>>, target_price_x64: u128, max_cost: u64) -> Result<()> {
+        rebalance::rebalance(ctx, target_price_x64, max_cost)
+    }
+    
+    /// Correct total_amount_minted to the LP mint's actual supply
+    pub fn sync_lp_supply(ctx: Context<SyncLpSupply>) -> Result<()> {
+        admin::sync_lp_supply(ctx)
+    }
+    
+    /// Grow a legacy pool account to the current PoolState layout
+    pub fn migrate_pool(ctx: Context<MigratePool>) -> Result<()> {
+        migrate::migrate_pool(ctx)
+    }
+    
+    /// Overwrite native_reserve / total_amount_minted (test-helpers builds only)
+    #[cfg(feature = "test-helpers")]
+    pub fn set_reserves_for_test(
+        ctx: Context<SetReservesForTest>,
+        native_reserve: u64,
+        total_amount_minted: u64,
+    ) -> Result<()> {
+        test_helpers::set_reserves_for_test(ctx, native_reserve, total_amount_minted)
+    }
+    
+    /// Log the compute units of a swap's pricing and fee math (bench builds only)
+    #[cfg(feature = "bench")]
+    pub fn bench_swap_math(
+        ctx: Context<BenchSwapMath>,
+        amount_in: u64,
+        reserve_in: u64,
+        reserve_out: u64,
+    ) -> Result<()> {
+        bench::bench_swap_math(ctx, amount_in, reserve_in, reserve_out)
+    }
+    
+    // === EMERGENCY INSTRUCTIONS ===
+    
+    /// Create GlobalConfig, owned by the program upgrade authority
+    pub fn initialize_global_config(ctx: Context<InitializeGlobalConfig>) -> Result<()> {
+        global_config::initialize_global_config(ctx)
+    }
+    
+    /// Engage or release the global kill-switch
+    pub fn set_global_pause(ctx: Context<UpdateGlobalConfig>, paused: bool) -> Result<()> {
+        global_config::set_global_pause(ctx, paused)
+    }
+    
+    /// Drain an SPL pool's vaults to its fixed emergency_recovery wallet while paused
+    pub fn emergency_withdraw<'info>(ctx: Context<'_, '_, '_, 'info, EmergencyWithdraw<'info>>) -> Result<()> {
+        emergency::emergency_withdraw(ctx)
+    }
+    
+    /// Drain a native pool's XNT and token reserves to its fixed emergency_recovery wallet while paused
+    pub fn emergency_withdraw_native<'info>(ctx: Context<'_, '_, '_, 'info, EmergencyWithdrawNative<'info>>) -> Result<()> {
+        emergency::emergency_withdraw_native(ctx)
+    }
+    
+    /// Burn a protocol-owned pool's governance LP and pay out its reserves (GlobalConfig authority only)
+    pub fn withdraw_governance_liquidity(ctx: Context<WithdrawGovernanceLiquidity>, burn_amount: u64) -> Result<()> {
+        governance::withdraw_governance_liquidity(ctx, burn_amount)
+    }
+    
+    // === VIEW INSTRUCTIONS ===
+    
+    /// Fees earned by an LP position since the given fee growth checkpoints
+    pub fn get_claimable_fees(
+        ctx: Context<PoolView>,
+        lp_balance: u64,
+        fee_growth_checkpoint0: u128,
+        fee_growth_checkpoint1: u128,
+    ) -> Result<ClaimableFees> {
+        views::get_claimable_fees(ctx, lp_balance, fee_growth_checkpoint0, fee_growth_checkpoint1)
+    }
+    
+    /// LP mint of a pool of either kind, plus the pool kind
+    pub fn get_lp_mint(ctx: Context<PoolView>) -> Result<LpMintInfo> {
+        views::get_lp_mint(ctx)
+    }
+    
+    /// Layout version of a pool account (POOL_VERSION_V1_BASIC .. POOL_VERSION_CURRENT)
+    pub fn get_pool_version(ctx: Context<PoolView>) -> Result<u8> {
+        views::get_pool_version(ctx)
+    }
+    
+    /// Fee configuration, LP supply and cumulative protocol revenue of a pool
+    pub fn get_pool_info(ctx: Context<PoolView>) -> Result<PoolInfo> {
+        views::get_pool_info(ctx)
+    }
+    
+    /// Quote a native pool swap: output, protocol fee and price impact
+    pub fn quote_swap_native(
+        ctx: Context<QuoteSwapNative>,
+        amount_in: u64,
+        is_xnt_to_token: bool,
+        lp_balance: u64,
+    ) -> Result<NativeSwapQuote> {
+        views::quote_swap_native(ctx, amount_in, is_xnt_to_token, lp_balance)
+    }
+    
+    /// Quote native pool swap outputs for several input sizes against the same reserves
+    /// (native pools only)
+    pub fn quote_swap_batch(
+        ctx: Context<QuoteSwapNative>,
+        amounts: Vec<u64>,
+        is_xnt_to_token: bool,
+        lp_balance: u64,
+    ) -> Result<Vec<u64>> {
+        views::quote_swap_batch(ctx, amounts, is_xnt_to_token, lp_balance)
+    }
+    
+    /// Quote the input a native pool swap needs to deliver amount_out (exact-out, native pools only)
+    pub fn quote_amount_in(
+        ctx: Context<QuoteSwapNative>,
+        amount_out: u64,
+        is_xnt_to_token: bool,
+        lp_balance: u64,
+    ) -> Result<u64> {
+        views::quote_amount_in(ctx, amount_out, is_xnt_to_token, lp_balance)
+    }
+    
+    /// Quote an SPL deposit of amount0: the token1 it needs and the LP it mints
+    pub fn quote_add_liquidity(ctx: Context<QuoteAddLiquidity>, amount0: u64) -> Result<AddLiquidityQuote> {
+        views::quote_add_liquidity(ctx, amount0)
+    }
+    
+    /// Spot prices (Q64.64, token1 per token0) of up to MAX_READ_PRICES_POOLS pools at once
+    /// See views::read_prices for the remaining accounts layout
+    pub fn read_prices<'info>(ctx: Context<'_, '_, 'info, 'info, ReadPrices>) -> Result<Vec<u128>> {
+        views::read_prices(ctx)
+    }
+    
+    /// Name, symbol and URI from the pool's PoolMetadata label
+    pub fn get_pool_metadata(ctx: Context<PoolMetadataView>) -> Result<PoolMetadataInfo> {
+        views::get_pool_metadata(ctx)
+    }
+    
+    /// Current reserve0 * reserve1 of a pool, from its actual balances
+    pub fn get_invariant(ctx: Context<InvariantView>) -> Result<u128> {
+        views::get_invariant(ctx)
+    }
+    
+    /// Emit a ReserveSnapshot event of the pool's reserves and LP supply (anyone may call)
+    pub fn snapshot_reserves(ctx: Context<SnapshotReserves>) -> Result<()> {
+        views::snapshot_reserves(ctx)
+    }
 }
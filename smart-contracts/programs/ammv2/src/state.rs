@@ -1,5 +1,28 @@
 use anchor_lang::prelude::*;
 
+// Pool kinds recorded in the registry
+pub const POOL_KIND_SPL: u8 = 0;
+pub const POOL_KIND_NATIVE: u8 = 1;
+
+// How protocol_fee_bps is applied (see utils::protocol_fee_amount)
+pub const PROTOCOL_FEE_MODE_NOTIONAL: u8 = 0;
+pub const PROTOCOL_FEE_MODE_LP_FEE: u8 = 1;
+
+// PoolState layouts reported by get_pool_version (see PoolState::V*_DATA_LEN)
+// V1: fee fields only, V2: + protocol treasury/fee, V3: + native pool fields
+// (later fields read as legacy defaults), CURRENT: every field present
+pub const POOL_VERSION_V1_BASIC: u8 = 1;
+pub const POOL_VERSION_V2_PROTOCOL: u8 = 2;
+pub const POOL_VERSION_V3_NATIVE: u8 = 3;
+pub const POOL_VERSION_CURRENT: u8 = 4;
+
+// Owner of the LP tokens of protocol-owned pools, PDA [GOVERNANCE_SEED]
+pub const GOVERNANCE_SEED: &[u8] = b"governance";
+
+// LP mint decimals: chosen at pool creation, 9 when the creator passes none
+pub const DEFAULT_LP_DECIMALS: u8 = 9;
+pub const MAX_LP_DECIMALS: u8 = 9;
+
 #[account]
 #[derive(Default)] // defaults to zeros -- which we want 
 pub struct PoolState {
@@ -17,16 +40,209 @@ pub struct PoolState {
     pub is_native_pool: bool,
     // Which mint position is native: 0 = mint0 is XNT, 1 = mint1 is XNT
     // Only valid if is_native_pool = true
+    // Describes the (token0, token1) pair ordering for clients only: on-chain the
+    // XNT side is always native_reserve/pool_pda and the token side always token_vault,
+    // and swap direction comes from is_xnt_to_token. Must be 0 or 1.
     pub native_mint_index: u8,
     // Tracked native XNT balance (separate from rent reserve)
     // Only valid if is_native_pool = true
     pub native_reserve: u64,
+
+    // === POOL ADMINISTRATION ===
+    // Authority allowed to change pool parameters (Pubkey::default() = no admin)
+    pub admin: Pubkey,
+    // Max slippage (bps below the quoted output) a swap's min_amount_out may imply
+    // 10000 = unrestricted (backward compatible default)
+    pub max_allowed_slippage_bps: u16,
+
+    // === REENTRANCY GUARD ===
+    // Set while an instruction is mutating the pool, written straight to account
+    // data so a reentrant CPI sees it (see utils::begin_pool_operation)
+    pub in_progress: bool,
+
+    // === LP FEE ACCOUNTING ===
+    // Cumulative LP fees per LP unit for token0 / token1, Q64.64 fixed point
+    // Wraps on overflow - only differences between two readings are meaningful
+    pub fee_growth_global0: u128,
+    pub fee_growth_global1: u128,
+
+    // === PROTOCOL FEE COLLECTION ===
+    // If true, SPL swaps create a missing treasury XNT ATA (swapper pays rent)
+    // instead of skipping the protocol fee. Opt-in, false by default.
+    pub create_treasury_ata: bool,
+
+    // === POOL CLASSIFICATION ===
+    // POOL_KIND_SPL or POOL_KIND_NATIVE, mirrors is_native_pool
+    // At a fixed offset (POOL_KIND_OFFSET) so clients can classify a pool
+    // by fetching a single byte with a data slice
+    pub pool_kind: u8,
+
+    // PROTOCOL_FEE_MODE_NOTIONAL (0, default): protocol_fee_bps of the XNT amount swapped
+    // PROTOCOL_FEE_MODE_LP_FEE (1): protocol_fee_bps of the LP fee on that XNT amount
+    pub protocol_fee_mode: u8,
+
+    // === DEPOSIT LIMITS ===
+    // Max XNT reserve deposits may bring the pool to (0 = unlimited, default)
+    // Native pools cap native_reserve, SPL pools the wrapped XNT vault
+    // SPL pools without an XNT side are not capped
+    pub max_tvl_native: u64,
+
+    // === LP HOLDER FEE DISCOUNT ===
+    // Swappers holding at least lp_discount_threshold LP tokens of this pool
+    // pay lp_discount_bps less of fee_numerator (0 threshold = disabled, default)
+    pub lp_discount_threshold: u64,
+    pub lp_discount_bps: u16,
+
+    // === EMERGENCY RECOVERY ===
+    // Wallet emergency_withdraw drains reserves to while the global kill-switch is
+    // engaged. Fixed at pool creation, never changeable (Pubkey::default() = disabled)
+    pub emergency_recovery: Pubkey,
+
+    // === LP MINT ===
+    // Stored so clients need not know the pool kind to derive it (see lp_mint_seed)
+    // Pubkey::default() on pools created before it was recorded
+    pub lp_mint: Pubkey,
+
+    // === PROTOCOL-OWNED LIQUIDITY ===
+    // Set by initialize_protocol_owned_pool: the LP fee is routed to the treasury
+    // along with the protocol fee, and LP tokens can only go to the GOVERNANCE_SEED PDA
+    pub protocol_owned: bool,
+
+    // === OPERATION SWITCHES ===
+    // Admin can stop swaps and deposits independently (e.g. stop deposits during
+    // a migration while keeping swaps live). Withdrawals are never gated.
+    pub swaps_enabled: bool,
+    pub deposits_enabled: bool,
+
+    // === XNT MINT ===
+    // Mint SPL swaps treat as XNT when collecting the protocol fee, e.g. a Token 2022
+    // wrapped XNT. Pubkey::default() = the classic SPL native mint (see xnt_mint)
+    pub native_mint: Pubkey,
+
+    // === LP DECIMALS ===
+    // Decimals of the LP mint, chosen at creation (DEFAULT_LP_DECIMALS when omitted)
+    pub lp_decimals: u8,
+
+    // === WITHDRAWAL DELAY ===
+    // When non-zero, LPs exit through request_remove_liquidity and can only
+    // execute after this many seconds. 0 = immediate withdrawals
+    pub withdrawal_delay_secs: u64,
+
+    // === SWAP SIZE CAP ===
+    // Most of the output reserve one swap may take, in bps (10000 = unrestricted)
+    pub max_swap_out_bps_of_reserve: u16,
+
+    // === FEE TIMELOCK ===
+    // When non-zero, fee increases go through propose_fee_change and only take
+    // effect via apply_fee_change once fee_change_effective_at has passed.
+    // fee_change_effective_at = 0 means no change is pending
+    pub fee_timelock_secs: u64,
+    pub pending_fee_numerator: u64,
+    pub pending_protocol_fee_bps: u16,
+    pub fee_change_effective_at: i64,
+
+    // === BOOTSTRAP WINDOW ===
+    // Swaps pay no protocol fee until created_at + bootstrap_secs (the LP fee
+    // still applies). created_at = 0 on pools created before the field existed
+    pub created_at: i64,
+    pub bootstrap_secs: u64,
+
+    // === PROTOCOL REVENUE ===
+    // Protocol fees actually paid out by swaps over the pool's life, in XNT base
+    // units. Fees skipped for a missing or invalid treasury account are not counted
+    pub cumulative_protocol_fees_collected: u128,
+
+    // === SOULBOUND LP ===
+    // Chosen at creation: the pool authority is the LP mint's freeze authority and
+    // keeps every LP account frozen, thawing one only for the pool's own mint / burn.
+    // LP can be deposited and withdrawn but not transferred between wallets
+    pub lp_soulbound: bool,
+
+    // === LP REBATE ===
+    // Share of the protocol fee (bps of it) left in the pool's reserves for LPs
+    // instead of being paid out. Swappers pay the same total fee either way
+    pub lp_rebate_bps: u16,
+
+    // === MINT DECIMALS ===
+    // Read from the mints at init so hot paths needn't re-read the mint accounts -
+    // SPL swaps and liquidity pass them to TransferChecked. SPL pools set
+    // mint0/mint1_decimals, native pools token_decimals (the SPL side). Pools
+    // created before the fields existed get them from migrate_pool
+    pub mint0_decimals: u8,
+    pub mint1_decimals: u8,
+    pub token_decimals: u8,
+
+    // === PROTOCOL FEE COMPOUNDING ===
+    // When set, swaps keep the protocol fee (less any LP rebate) in the reserves and
+    // mint this LP token account LP worth the fee instead of paying it out.
+    // Pubkey::default() = fees are paid out as usual
+    pub protocol_lp_account: Pubkey,
+
+    // === RESERVE RECONCILIATION ===
+    // XNT reconcile_native_reserve has added to native_reserve without the admin
+    // since the last admin-approved reconcile (native pools only)
+    pub unapproved_reserve_increase: u64,
 }
 
 impl PoolState {
+    /// Serialized size of the current layout (excluding the 8-byte discriminator)
+    pub const LEN: usize = 8 + 8 + 8 // total_amount_minted + fee_numerator + fee_denominator
+        + 32 + 2 // protocol_treasury + protocol_fee_bps
+        + 1 + 1 + 8 // is_native_pool + native_mint_index + native_reserve
+        + 32 + 2 // admin + max_allowed_slippage_bps
+        + 1 // in_progress
+        + 16 + 16 // fee_growth_global0 + fee_growth_global1
+        + 1 // create_treasury_ata
+        + 1 // pool_kind
+        + 1 // protocol_fee_mode
+        + 8 // max_tvl_native
+        + 8 + 2 // lp_discount_threshold + lp_discount_bps
+        + 32 // emergency_recovery
+        + 32 // lp_mint
+        + 1 // protocol_owned
+        + 1 + 1 // swaps_enabled + deposits_enabled
+        + 32 // native_mint
+        + 1 // lp_decimals
+        + 8 // withdrawal_delay_secs
+        + 2 // max_swap_out_bps_of_reserve
+        + 8 + 8 + 2 + 8 // fee_timelock_secs + pending_fee_numerator + pending_protocol_fee_bps + fee_change_effective_at
+        + 8 + 8 // created_at + bootstrap_secs
+        + 16 // cumulative_protocol_fees_collected
+        + 1 // lp_soulbound
+        + 2 // lp_rebate_bps
+        + 1 + 1 + 1 // mint0_decimals + mint1_decimals + token_decimals
+        + 32 // protocol_lp_account
+        + 8; // unapproved_reserve_increase
+
+    // Byte offsets of manually written fields in account data (including the discriminator)
+    // Borsh lays fields out in declaration order with no padding
+    pub const TOTAL_AMOUNT_MINTED_OFFSET: usize = 8;
+    pub const NATIVE_RESERVE_OFFSET: usize = Self::TOTAL_AMOUNT_MINTED_OFFSET
+        + 8 + 8 + 8 // total_amount_minted + fee_numerator + fee_denominator
+        + 32 + 2 // protocol_treasury + protocol_fee_bps
+        + 1 + 1; // is_native_pool + native_mint_index
+    pub const IN_PROGRESS_OFFSET: usize = Self::NATIVE_RESERVE_OFFSET
+        + 8 // native_reserve
+        + 32 + 2; // admin + max_allowed_slippage_bps
+    pub const POOL_KIND_OFFSET: usize = Self::IN_PROGRESS_OFFSET
+        + 1 // in_progress
+        + 16 + 16 // fee_growth_global0 + fee_growth_global1
+        + 1; // create_treasury_ata
+
+    // Account data lengths (including the discriminator) of the legacy layouts
+    // try_deserialize reads whichever of them fits the account
+    pub const V1_DATA_LEN: usize = 8 + 8 + 8 + 8;
+    pub const V2_DATA_LEN: usize = Self::V1_DATA_LEN + 32 + 2;
+    pub const V3_DATA_LEN: usize = Self::V2_DATA_LEN + 1 + 1 + 8;
+
     /// Deserialize PoolState with backward compatibility
     /// Handles both old format (32 bytes) and new format (66 bytes)
     pub fn try_deserialize(data: &mut &[u8]) -> Result<Self> {
+        // Current layout - every field is present, let Anchor deserialize it
+        if data.len() >= 8 + Self::LEN {
+            return <Self as AccountDeserialize>::try_deserialize(data);
+        }
+
         // Minimum size: discriminator (8) + 3 u64 fields (24) = 32 bytes
         if data.len() < 32 {
             return Err(anchor_lang::error::ErrorCode::AccountDiscriminatorNotFound.into());
@@ -75,17 +291,17 @@ impl PoolState {
             cursor = &cursor[34..];
         }
         
-        // Check if native pool fields are present (v3 format: 1 + 8 + 1 = 10 bytes more)
+        // Check if native pool fields are present (v3 format: 1 + 1 + 8 = 10 bytes more)
         let (is_native_pool, native_reserve, native_mint_index) = if cursor.len() >= 10 {
-            // V3 format: read native pool fields
+            // V3 format: read native pool fields (same order as the struct)
             let is_native_pool = cursor[0] != 0;
             
+            let native_mint_index = cursor[1];
+            
             let native_reserve = u64::from_le_bytes(
-                cursor[1..9].try_into().map_err(|_| anchor_lang::error::ErrorCode::AccountDiscriminatorNotFound)?
+                cursor[2..10].try_into().map_err(|_| anchor_lang::error::ErrorCode::AccountDiscriminatorNotFound)?
             );
             
-            let native_mint_index = cursor[9];
-            
             (is_native_pool, native_reserve, native_mint_index)
         } else {
             // V1/V2 format: use defaults (backward compatible - SPL pool)
@@ -101,6 +317,590 @@ impl PoolState {
             is_native_pool,
             native_reserve,
             native_mint_index,
+            // Pre-admin layouts: no admin, no slippage cap
+            admin: Pubkey::default(),
+            max_allowed_slippage_bps: 10000,
+            in_progress: false,
+            fee_growth_global0: 0,
+            fee_growth_global1: 0,
+            create_treasury_ata: false,
+            pool_kind: if is_native_pool { POOL_KIND_NATIVE } else { POOL_KIND_SPL },
+            protocol_fee_mode: PROTOCOL_FEE_MODE_NOTIONAL,
+            max_tvl_native: 0,
+            lp_discount_threshold: 0,
+            lp_discount_bps: 0,
+            emergency_recovery: Pubkey::default(),
+            lp_mint: Pubkey::default(),
+            protocol_owned: false,
+            swaps_enabled: true,
+            deposits_enabled: true,
+            native_mint: Pubkey::default(),
+            // Every LP mint created before the field existed has 9 decimals
+            lp_decimals: DEFAULT_LP_DECIMALS,
+            withdrawal_delay_secs: 0,
+            max_swap_out_bps_of_reserve: 10000,
+            fee_timelock_secs: 0,
+            pending_fee_numerator: 0,
+            pending_protocol_fee_bps: 0,
+            fee_change_effective_at: 0,
+            created_at: 0,
+            bootstrap_secs: 0,
+            cumulative_protocol_fees_collected: 0,
+            lp_soulbound: false,
+            lp_rebate_bps: 0,
+            mint0_decimals: 0,
+            mint1_decimals: 0,
+            token_decimals: 0,
+            protocol_lp_account: Pubkey::default(),
+            unapproved_reserve_increase: 0,
         })
     }
+
+    /// Reject a native pool handed to an SPL pool instruction
+    pub fn check_spl_pool(&self) -> Result<()> {
+        require!(!self.is_native_pool, crate::error::ErrorCode::IsNativePool);
+        Ok(())
+    }
+
+    /// Reject an SPL pool handed to a native pool instruction
+    pub fn check_native_pool(&self) -> Result<()> {
+        require!(self.is_native_pool, crate::error::ErrorCode::NotNativePool);
+        Ok(())
+    }
+
+    /// native_mint_index, rejecting a corrupt stored byte (anything but 0 or 1)
+    pub fn native_mint_index_checked(&self) -> Result<u8> {
+        require!(self.native_mint_index <= 1, crate::error::ErrorCode::InvalidAccountData);
+        Ok(self.native_mint_index)
+    }
+
+    /// Seed of this pool's LP mint PDA: SPL pools use b"pool_mint", native pools b"lp_mint"
+    pub fn lp_mint_seed(&self) -> &'static [u8] {
+        if self.is_native_pool { b"lp_mint" } else { b"pool_mint" }
+    }
+
+    /// LP mint address of the pool at `pool_state_key`
+    /// Reads the stored lp_mint, deriving it for pools that predate the field
+    pub fn lp_mint_address(&self, pool_state_key: &Pubkey, program_id: &Pubkey) -> Pubkey {
+        if self.lp_mint != Pubkey::default() {
+            return self.lp_mint;
+        }
+        Pubkey::find_program_address(&[self.lp_mint_seed(), pool_state_key.as_ref()], program_id).0
+    }
+
+    /// Mint this pool treats as XNT - the stored native_mint, or the classic SPL native mint
+    pub fn xnt_mint(&self) -> Pubkey {
+        if self.native_mint != Pubkey::default() {
+            return self.native_mint;
+        }
+        anchor_spl::token::spl_token::native_mint::id()
+    }
+
+    /// fee_numerator for a swapper holding `lp_balance` of this pool's LP tokens
+    pub fn effective_fee_numerator(&self, lp_balance: u64) -> u64 {
+        if self.lp_discount_threshold == 0 || lp_balance < self.lp_discount_threshold {
+            return self.fee_numerator;
+        }
+        
+        // lp_discount_bps <= 10000, so the result never exceeds fee_numerator
+        let discount_bps = self.lp_discount_bps.min(10000) as u128;
+        (self.fee_numerator as u128 * (10000 - discount_bps) / 10000) as u64
+    }
+
+    /// Record a fee change that apply_pending_fee_change may activate fee_timelock_secs after `now`
+    /// Replaces any change already pending
+    pub fn schedule_fee_change(&mut self, fee_numerator: u64, protocol_fee_bps: u16, now: i64) -> Result<()> {
+        let delay = i64::try_from(self.fee_timelock_secs).map_err(|_| crate::error::ErrorCode::MathOverflow)?;
+        self.fee_change_effective_at = now.checked_add(delay).ok_or(crate::error::ErrorCode::MathOverflow)?;
+        self.pending_fee_numerator = fee_numerator;
+        self.pending_protocol_fee_bps = protocol_fee_bps;
+        Ok(())
+    }
+
+    /// Activate the pending fee change, once its effective time has passed at `now`
+    pub fn apply_pending_fee_change(&mut self, now: i64) -> Result<()> {
+        require!(self.fee_change_effective_at != 0, crate::error::ErrorCode::InvalidInput);
+        require!(now >= self.fee_change_effective_at, crate::error::ErrorCode::FeeChangeNotReady);
+        self.fee_numerator = self.pending_fee_numerator;
+        self.protocol_fee_bps = self.pending_protocol_fee_bps;
+        self.clear_pending_fee_change();
+        Ok(())
+    }
+
+    /// Drop the pending fee change, if any
+    pub fn clear_pending_fee_change(&mut self) {
+        self.pending_fee_numerator = 0;
+        self.pending_protocol_fee_bps = 0;
+        self.fee_change_effective_at = 0;
+    }
+
+    /// Whether swaps at `now` fall in the post-creation window that waives the protocol fee
+    pub fn in_bootstrap_window(&self, now: i64) -> bool {
+        let bootstrap_secs = i64::try_from(self.bootstrap_secs).unwrap_or(i64::MAX);
+        now < self.created_at.saturating_add(bootstrap_secs)
+    }
+
+    /// Whether a swap touching XNT sends its LP fee to the treasury instead of the vault
+    /// (protocol-owned pools - see protocol_owned)
+    pub fn routes_lp_fee(&self, xnt_involved: bool) -> bool {
+        self.protocol_owned && self.protocol_treasury != Pubkey::default() && xnt_involved
+    }
+
+    /// Whether swaps compound the protocol fee into protocol_lp_account's LP
+    pub fn compounds_protocol_fee(&self) -> bool {
+        self.protocol_lp_account != Pubkey::default()
+    }
+
+    /// Store the decimals of `mint0` / `mint1` (SPL pools) or of the token mint
+    /// (native pools, `mint1` = None)
+    pub fn record_mint_decimals(&mut self, mint0: &AccountInfo, mint1: Option<&AccountInfo>) -> Result<()> {
+        match mint1 {
+            Some(mint1) => {
+                self.mint0_decimals = crate::utils::mint_decimals(mint0)?;
+                self.mint1_decimals = crate::utils::mint_decimals(mint1)?;
+            }
+            None => self.token_decimals = crate::utils::mint_decimals(mint0)?,
+        }
+        Ok(())
+    }
+
+    /// Decimals of an SPL pool's `token_index` (0 or 1) mint, as stored at init
+    pub fn mint_decimals(&self, token_index: u8) -> u8 {
+        if token_index == 0 { self.mint0_decimals } else { self.mint1_decimals }
+    }
+
+    /// Part of `protocol_fee` that stays in the pool as an LP rebate
+    pub fn lp_rebate(&self, protocol_fee: u128) -> u128 {
+        // lp_rebate_bps <= 10000, so the rebate never exceeds protocol_fee
+        protocol_fee * self.lp_rebate_bps.min(10000) as u128 / 10000
+    }
+
+    /// Write total_amount_minted straight into account data
+    pub fn write_total_amount_minted(data: &mut [u8], total_amount_minted: u64) -> Result<()> {
+        let offset = Self::TOTAL_AMOUNT_MINTED_OFFSET;
+        require!(data.len() >= offset + 8, crate::error::ErrorCode::InvalidAccountData);
+        data[offset..offset + 8].copy_from_slice(&total_amount_minted.to_le_bytes());
+        Ok(())
+    }
+
+    /// Write native_reserve straight into account data
+    /// Fails cleanly on legacy (pre-native) layouts that are too small to hold it
+    pub fn write_native_reserve(data: &mut [u8], native_reserve: u64) -> Result<()> {
+        let offset = Self::NATIVE_RESERVE_OFFSET;
+        require!(data.len() >= offset + 8, crate::error::ErrorCode::InvalidAccountData);
+        data[offset..offset + 8].copy_from_slice(&native_reserve.to_le_bytes());
+        Ok(())
+    }
+
+    /// Write back a pool loaded via `try_deserialize`
+    /// Legacy layouts have no room for the newer fields and are left untouched
+    pub fn try_persist(&self, pool_state_info: &AccountInfo) -> Result<()> {
+        let mut data = pool_state_info.try_borrow_mut_data()?;
+        if data.len() < 8 + Self::LEN {
+            return Ok(());
+        }
+        
+        let mut writer: &mut [u8] = &mut data;
+        <Self as AccountSerialize>::try_serialize(self, &mut writer)
+    }
+
+    /// Credit an LP fee paid in token `token_index` (0 or 1) to the per-LP-unit fee growth
+    pub fn accrue_lp_fee(&mut self, token_index: u8, lp_fee: u64) {
+        if self.total_amount_minted == 0 || lp_fee == 0 {
+            return;
+        }
+        
+        // lp_fee < 2^64, so the shifted value always fits in u128
+        let growth = ((lp_fee as u128) << 64) / self.total_amount_minted as u128;
+        if token_index == 0 {
+            self.fee_growth_global0 = self.fee_growth_global0.wrapping_add(growth);
+        } else {
+            self.fee_growth_global1 = self.fee_growth_global1.wrapping_add(growth);
+        }
+    }
+
+    /// Count `protocol_fee` XNT paid out to the treasury / FeeSplit recipients
+    /// SPL swaps pass the amount received, net of any Token 2022 transfer fee
+    pub fn record_protocol_fee_collected(&mut self, protocol_fee: u64) {
+        self.cumulative_protocol_fees_collected = self.cumulative_protocol_fees_collected
+            .saturating_add(protocol_fee as u128);
+    }
+
+    /// Fees earned by `lp_balance` LP units since `fee_growth_checkpoint` was recorded
+    pub fn fees_earned(fee_growth_global: u128, fee_growth_checkpoint: u128, lp_balance: u64) -> u64 {
+        let growth = fee_growth_global.wrapping_sub(fee_growth_checkpoint);
+        
+        // (growth * lp_balance) >> 64, split so neither product overflows u128
+        let high = (growth >> 64).saturating_mul(lp_balance as u128);
+        let low = ((growth & u64::MAX as u128) * lp_balance as u128) >> 64;
+        
+        u64::try_from(high.saturating_add(low)).unwrap_or(u64::MAX)
+    }
+}
+
+/// Registry entry for a mint pair, at PDA [b"registry", mint0, mint1]
+/// Written once when the pool is initialized (see instructions::registry)
+#[account]
+pub struct PoolRegistryEntry {
+    pub pool: Pubkey,
+    // POOL_KIND_SPL or POOL_KIND_NATIVE
+    pub pool_kind: u8,
+    // NATIVE_MINT_PLACEHOLDER stands in for native XNT
+    pub mint0: Pubkey,
+    pub mint1: Pubkey,
+}
+
+impl PoolRegistryEntry {
+    pub const LEN: usize = 32 + 1 + 32 + 32;
+}
+
+/// Program-wide settings, at PDA [b"global_config"]
+#[account]
+pub struct GlobalConfig {
+    // Set to the program upgrade authority at initialization
+    pub authority: Pubkey,
+    // Global kill-switch - while engaged, pool admins may emergency_withdraw
+    pub paused: bool,
+}
+
+impl GlobalConfig {
+    pub const LEN: usize = 32 + 1;
+}
+
+/// Most recipients a FeeSplit can hold
+pub const MAX_FEE_RECIPIENTS: usize = 4;
+
+/// Protocol fee split across several wallets, at PDA [b"fee_split", pool_state]
+/// Once it exists, swaps distribute the protocol fee here instead of to protocol_treasury
+#[account]
+pub struct FeeSplit {
+    pub pool: Pubkey,
+    // Only the first `count` recipients / weights are used
+    pub count: u8,
+    pub recipients: [Pubkey; MAX_FEE_RECIPIENTS],
+    // Basis points of the fee per recipient, summing to 10000
+    pub weights_bps: [u16; MAX_FEE_RECIPIENTS],
+}
+
+impl FeeSplit {
+    pub const LEN: usize = 32 + 1 + 32 * MAX_FEE_RECIPIENTS + 2 * MAX_FEE_RECIPIENTS;
+
+    /// Each recipient's share of `fee`, in recipient order
+    /// Shares round down, the rounding remainder goes to the first recipient
+    pub fn shares(&self, fee: u64) -> Result<Vec<u64>> {
+        let count = self.count as usize;
+        let mut shares = Vec::with_capacity(count);
+        for weight_bps in &self.weights_bps[..count] {
+            let share = (fee as u128)
+                .checked_mul(*weight_bps as u128)
+                .ok_or(crate::error::ErrorCode::MathOverflow)?
+                / 10000;
+            shares.push(share as u64);
+        }
+        let distributed: u64 = shares.iter().sum();
+        if let Some(first) = shares.first_mut() {
+            *first = first
+                .checked_add(fee.checked_sub(distributed).ok_or(crate::error::ErrorCode::MathOverflow)?)
+                .ok_or(crate::error::ErrorCode::MathOverflow)?;
+        }
+        Ok(shares)
+    }
+}
+
+/// An LP's balance recorded for the LP holder fee discount, at PDA
+/// [b"lp_snapshot", pool_state, owner]
+/// Swaps count the smaller of this and the current balance, and only once the slot it
+/// was recorded in has passed, so LP deposited within the swap's transaction earns nothing
+#[account]
+pub struct LpBalanceSnapshot {
+    pub pool: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub slot: u64,
+    pub bump: u8,
+}
+
+impl LpBalanceSnapshot {
+    pub const LEN: usize = 32 + 32 + 8 + 8 + 1;
+}
+
+/// Longest fee timelock an admin can set (30 days)
+pub const MAX_FEE_TIMELOCK_SECS: u64 = 30 * 24 * 60 * 60;
+
+/// Longest withdrawal delay an admin can set (7 days), so LP funds can't be frozen
+pub const MAX_WITHDRAWAL_DELAY_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// Longest protocol-fee-free window after pool creation (7 days)
+pub const MAX_BOOTSTRAP_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// A pending delayed withdrawal, at PDA [b"withdrawal_request", pool_state, owner]
+/// The LP tokens sit in the PDA's associated token account until executed or cancelled
+#[account]
+pub struct WithdrawalRequest {
+    pub pool: Pubkey,
+    pub owner: Pubkey,
+    pub lp_amount: u64,
+    // Unix timestamp from which execute_remove_liquidity is allowed,
+    // fixed at request time so later delay changes don't affect it
+    pub unlock_at: i64,
+    pub bump: u8,
+}
+
+impl WithdrawalRequest {
+    pub const LEN: usize = 32 + 32 + 8 + 8 + 1;
+}
+
+// Longest strings a PoolMetadata can hold, in bytes
+pub const MAX_POOL_NAME_LEN: usize = 32;
+pub const MAX_POOL_SYMBOL_LEN: usize = 10;
+pub const MAX_POOL_URI_LEN: usize = 200;
+
+/// Optional human-readable pool label, at PDA [b"metadata", pool_state]
+/// Nothing on-chain reads it - pools work the same without one
+#[account]
+pub struct PoolMetadata {
+    pub pool: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+}
+
+impl PoolMetadata {
+    pub const LEN: usize = 32
+        + 4 + MAX_POOL_NAME_LEN
+        + 4 + MAX_POOL_SYMBOL_LEN
+        + 4 + MAX_POOL_URI_LEN;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+
+    fn native_pool() -> PoolState {
+        PoolState { is_native_pool: true, pool_kind: POOL_KIND_NATIVE, ..Default::default() }
+    }
+
+    #[test]
+    fn spl_instructions_reject_native_pools() {
+        assert_eq!(native_pool().check_spl_pool().unwrap_err(), ErrorCode::IsNativePool.into());
+        assert!(PoolState::default().check_spl_pool().is_ok());
+    }
+
+    #[test]
+    fn native_instructions_reject_spl_pools() {
+        assert_eq!(PoolState::default().check_native_pool().unwrap_err(), ErrorCode::NotNativePool.into());
+        assert!(native_pool().check_native_pool().is_ok());
+    }
+
+    fn fee_split(weights: &[u16]) -> FeeSplit {
+        let mut weights_bps = [0; MAX_FEE_RECIPIENTS];
+        weights_bps[..weights.len()].copy_from_slice(weights);
+        FeeSplit {
+            pool: Pubkey::new_unique(),
+            count: weights.len() as u8,
+            recipients: [Pubkey::new_unique(); MAX_FEE_RECIPIENTS],
+            weights_bps,
+        }
+    }
+
+    fn timelocked_pool() -> PoolState {
+        PoolState { fee_numerator: 3, fee_denominator: 1000, protocol_fee_bps: 10, fee_timelock_secs: 3600, ..Default::default() }
+    }
+
+    #[test]
+    fn proposed_fee_change_waits_for_timelock() {
+        let mut pool_state = timelocked_pool();
+        pool_state.schedule_fee_change(5, 20, 1_000).unwrap();
+        assert_eq!(pool_state.fee_change_effective_at, 4_600);
+        // Swaps keep the current fee meanwhile
+        assert_eq!((pool_state.fee_numerator, pool_state.protocol_fee_bps), (3, 10));
+        
+        assert_eq!(pool_state.apply_pending_fee_change(4_599).unwrap_err(), ErrorCode::FeeChangeNotReady.into());
+        pool_state.apply_pending_fee_change(4_600).unwrap();
+        assert_eq!((pool_state.fee_numerator, pool_state.protocol_fee_bps), (5, 20));
+        assert_eq!(pool_state.fee_change_effective_at, 0);
+    }
+
+    #[test]
+    fn cancelled_fee_change_cannot_be_applied() {
+        let mut pool_state = timelocked_pool();
+        pool_state.schedule_fee_change(5, 20, 1_000).unwrap();
+        pool_state.clear_pending_fee_change();
+        assert_eq!(pool_state.apply_pending_fee_change(10_000).unwrap_err(), ErrorCode::InvalidInput.into());
+        assert_eq!(pool_state.fee_numerator, 3);
+    }
+
+    #[test]
+    fn fee_split_rounding_remainder_goes_to_first_recipient() {
+        // 100 * 3333 / 10000 = 33 and 100 * 3334 / 10000 = 33 - one unit left over
+        assert_eq!(fee_split(&[3333, 3333, 3334]).shares(100).unwrap(), vec![34, 33, 33]);
+        assert_eq!(fee_split(&[3334, 3333, 3333]).shares(2).unwrap(), vec![2, 0, 0]);
+        // Shares always add up to the whole fee
+        let shares = fee_split(&[5000, 2500, 2500]).shares(u64::MAX).unwrap();
+        assert_eq!(shares.iter().map(|&share| share as u128).sum::<u128>(), u64::MAX as u128);
+    }
+
+    #[test]
+    fn stored_decimals_match_the_mints() {
+        use anchor_lang::solana_program::program_pack::Pack;
+        
+        let mint_data = |decimals: u8| {
+            let mut data = vec![0; spl_token_2022::state::Mint::LEN];
+            spl_token_2022::state::Mint { decimals, is_initialized: true, ..Default::default() }
+                .pack_into_slice(&mut data);
+            data
+        };
+        let (key0, key1) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (mut lamports0, mut lamports1) = (1_000_000, 1_000_000);
+        let (mut data0, mut data1) = (mint_data(6), mint_data(9));
+        let mint0 = AccountInfo::new(&key0, false, false, &mut lamports0, &mut data0, &spl_token_2022::ID, false, 0);
+        let mint1 = AccountInfo::new(&key1, false, false, &mut lamports1, &mut data1, &anchor_spl::token::ID, false, 0);
+        
+        let mut pool = PoolState::default();
+        pool.record_mint_decimals(&mint0, Some(&mint1)).unwrap();
+        assert_eq!((pool.mint0_decimals, pool.mint1_decimals), (6, 9));
+        assert_eq!((pool.mint_decimals(0), pool.mint_decimals(1)), (6, 9));
+        
+        // Native pools record their token mint alone
+        let mut pool = PoolState { is_native_pool: true, ..Default::default() };
+        pool.record_mint_decimals(&mint1, None).unwrap();
+        assert_eq!(pool.token_decimals, 9);
+        assert_eq!((pool.mint0_decimals, pool.mint1_decimals), (0, 0));
+    }
+
+    #[test]
+    fn lp_rebate_keeps_its_share_of_the_protocol_fee() {
+        let pool_state = |lp_rebate_bps| PoolState { lp_rebate_bps, ..Default::default() };
+        assert_eq!(pool_state(0).lp_rebate(1_000), 0);
+        assert_eq!(pool_state(2500).lp_rebate(1_000), 250);
+        assert_eq!(pool_state(10000).lp_rebate(1_000), 1_000);
+        // Rounds down, so the treasury keeps the dust
+        assert_eq!(pool_state(3333).lp_rebate(10), 3);
+        // A stored value above 10000 still never rebates more than the fee
+        assert_eq!(pool_state(u16::MAX).lp_rebate(1_000), 1_000);
+        assert_eq!(pool_state(10000).lp_rebate(u64::MAX as u128), u64::MAX as u128);
+    }
+
+    #[test]
+    fn lp_holders_above_the_threshold_get_the_fee_discount() {
+        let pool_state = PoolState {
+            fee_numerator: 30,
+            fee_denominator: 10000,
+            lp_discount_threshold: 1_000,
+            lp_discount_bps: 5000,
+            ..Default::default()
+        };
+        assert_eq!(pool_state.effective_fee_numerator(999), 30);
+        assert_eq!(pool_state.effective_fee_numerator(1_000), 15);
+        assert_eq!(pool_state.effective_fee_numerator(u64::MAX), 15);
+        
+        // Rounds down in the swapper's favour
+        let pool_state = PoolState { fee_numerator: 3, lp_discount_bps: 5000, ..pool_state };
+        assert_eq!(pool_state.effective_fee_numerator(1_000), 1);
+        // A full discount waives the LP fee, a stored value above 10000 never goes below 0
+        let pool_state = PoolState { lp_discount_bps: 10000, ..pool_state };
+        assert_eq!(pool_state.effective_fee_numerator(1_000), 0);
+        let pool_state = PoolState { lp_discount_bps: u16::MAX, ..pool_state };
+        assert_eq!(pool_state.effective_fee_numerator(1_000), 0);
+        // threshold 0 disables the discount for everyone
+        let pool_state = PoolState { lp_discount_threshold: 0, ..pool_state };
+        assert_eq!(pool_state.effective_fee_numerator(u64::MAX), 3);
+    }
+
+    #[test]
+    fn bootstrap_window_ends_bootstrap_secs_after_creation() {
+        let pool = PoolState { created_at: 1_000, bootstrap_secs: 60, ..Default::default() };
+        assert!(pool.in_bootstrap_window(1_000));
+        assert!(pool.in_bootstrap_window(1_059));
+        assert!(!pool.in_bootstrap_window(1_060));
+
+        // No window by default
+        let pool = PoolState { created_at: 1_000, ..Default::default() };
+        assert!(!pool.in_bootstrap_window(1_000));
+
+        // Windows past i64::MAX saturate rather than wrap into the past
+        let pool = PoolState { created_at: 1_000, bootstrap_secs: u64::MAX, ..Default::default() };
+        assert!(pool.in_bootstrap_window(i64::MAX - 1));
+        assert!(!pool.in_bootstrap_window(i64::MAX));
+    }
+
+    #[test]
+    fn len_is_the_serialized_current_layout() {
+        // Pools are allocated 8 + LEN bytes, which get_pool_version and migrate_pool read
+        // as the current layout
+        let mut data = Vec::new();
+        native_pool().try_serialize(&mut data).unwrap();
+        assert_eq!(data.len(), 8 + PoolState::LEN);
+        assert_eq!(PoolState::try_deserialize(&mut &data[..]).unwrap().pool_kind, POOL_KIND_NATIVE);
+    }
+
+    #[test]
+    fn lp_fees_accrue_pro_rata_from_each_checkpoint() {
+        // A power-of-two supply keeps the per-unit growth exact
+        let mut pool = PoolState { total_amount_minted: 1 << 22, ..Default::default() };
+        pool.accrue_lp_fee(0, 1_000);
+        let checkpoint0 = pool.fee_growth_global0;
+        pool.accrue_lp_fee(0, 2_000);
+        pool.accrue_lp_fee(1, 400);
+        
+        // A quarter of the supply since the start earns a quarter of every fee, on its side
+        assert_eq!(PoolState::fees_earned(pool.fee_growth_global0, 0, 1 << 20), 750);
+        assert_eq!(PoolState::fees_earned(pool.fee_growth_global1, 0, 1 << 20), 100);
+        // A position opened after the first fee only earns the later one
+        assert_eq!(PoolState::fees_earned(pool.fee_growth_global0, checkpoint0, 1 << 20), 500);
+        assert_eq!(PoolState::fees_earned(pool.fee_growth_global0, pool.fee_growth_global0, 1 << 20), 0);
+        
+        // Nobody to credit before the first deposit
+        let mut empty = PoolState::default();
+        empty.accrue_lp_fee(0, 1_000);
+        assert_eq!(empty.fee_growth_global0, 0);
+    }
+
+    #[test]
+    fn manual_writes_land_on_their_fields_and_fail_past_the_end() {
+        let mut data = Vec::new();
+        native_pool().try_serialize(&mut data).unwrap();
+        PoolState::write_total_amount_minted(&mut data, 7).unwrap();
+        PoolState::write_native_reserve(&mut data, 11).unwrap();
+        data[PoolState::IN_PROGRESS_OFFSET] = 1;
+        let pool = PoolState::try_deserialize(&mut &data[..]).unwrap();
+        assert_eq!((pool.total_amount_minted, pool.native_reserve, pool.in_progress), (7, 11, true));
+        
+        // A legacy account too short for the field is rejected, not written out of bounds
+        let mut legacy = vec![0; PoolState::V2_DATA_LEN];
+        assert_eq!(PoolState::write_native_reserve(&mut legacy, 11).unwrap_err(), ErrorCode::InvalidAccountData.into());
+        assert!(PoolState::write_total_amount_minted(&mut legacy, 7).is_ok());
+        assert_eq!(PoolState::write_total_amount_minted(&mut [0; 15], 7).unwrap_err(), ErrorCode::InvalidAccountData.into());
+    }
+
+    #[test]
+    fn pool_kind_sits_at_its_fixed_offset() {
+        // Clients filter getProgramAccounts on this byte
+        for pool in [PoolState { pool_kind: POOL_KIND_SPL, ..Default::default() }, native_pool()] {
+            let mut data = Vec::new();
+            pool.try_serialize(&mut data).unwrap();
+            assert_eq!(data[PoolState::POOL_KIND_OFFSET], pool.pool_kind);
+        }
+    }
+
+    #[test]
+    fn native_mint_index_is_zero_or_one() {
+        for index in [0, 1] {
+            let pool = PoolState { is_native_pool: true, native_mint_index: index, ..Default::default() };
+            assert_eq!(pool.native_mint_index_checked().unwrap(), index);
+        }
+        let pool = PoolState { is_native_pool: true, native_mint_index: 2, ..Default::default() };
+        assert_eq!(pool.native_mint_index_checked().unwrap_err(), ErrorCode::InvalidAccountData.into());
+    }
+
+    #[test]
+    fn stored_lp_mint_wins_over_the_derived_one() {
+        let pool_key = Pubkey::new_unique();
+        let derived = Pubkey::find_program_address(&[b"pool_mint", pool_key.as_ref()], &crate::ID).0;
+        assert_eq!(PoolState::default().lp_mint_address(&pool_key, &crate::ID), derived);
+        
+        let lp_mint = Pubkey::new_unique();
+        assert_eq!(PoolState { lp_mint, ..Default::default() }.lp_mint_address(&pool_key, &crate::ID), lp_mint);
+    }
 }
@@ -0,0 +1,215 @@
+//! Off-chain fixtures for handler tests
+//!
+//! Instruction contexts are built from TestAccounts, and the sysvars handlers read come
+//! from TestStubs. Cross-program invocations are no-ops off-chain, so a handler test
+//! covers what the handler checks and computes - token balances never actually move,
+//! and a test applies the transfers it depends on itself (see set_token_amount).
+//! Lamport transfers the handler asks for are recorded instead (see take_system_transfers),
+//! as are the events it emits (see take_events)
+
+use std::cell::{Cell, RefCell};
+use std::sync::Once;
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::entrypoint::ProgramResult;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program_option::COption;
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::solana_program::program_stubs::{set_syscall_stubs, SyscallStubs};
+use anchor_spl::token::spl_token;
+
+/// An account handed to a handler - see TestAccount::info
+pub type Info = &'static AccountInfo<'static>;
+
+pub struct TestAccount {
+    pub key: Pubkey,
+    pub owner: Pubkey,
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub is_signer: bool,
+    pub executable: bool,
+}
+
+impl TestAccount {
+    pub fn new(key: Pubkey, owner: Pubkey, data: Vec<u8>) -> Self {
+        TestAccount { key, owner, lamports: 1_000_000_000, data, is_signer: false, executable: false }
+    }
+
+    /// The account as a handler sees it - leaked, so Anchor account types built from it
+    /// live as long as the test needs them
+    pub fn info(self) -> Info {
+        let account = Box::leak(Box::new(self));
+        Box::leak(Box::new(AccountInfo::new(
+            &account.key,
+            account.is_signer,
+            true,
+            &mut account.lamports,
+            &mut account.data,
+            &account.owner,
+            account.executable,
+            0,
+        )))
+    }
+}
+
+/// `infos` as a handler's remaining accounts, which some handlers tie to their accounts' lifetime
+pub fn remaining_accounts(infos: &[Info]) -> &'static [AccountInfo<'static>] {
+    Box::leak(infos.iter().map(|info| (*info).clone()).collect())
+}
+
+/// A wallet signing the transaction
+pub fn signer() -> TestAccount {
+    TestAccount { is_signer: true, ..TestAccount::new(Pubkey::new_unique(), System::id(), Vec::new()) }
+}
+
+/// The executable account of program `id`
+pub fn program(id: Pubkey) -> TestAccount {
+    TestAccount { executable: true, ..TestAccount::new(id, Pubkey::default(), Vec::new()) }
+}
+
+/// An empty account at `key` - e.g. a PDA not created yet
+pub fn empty_account(key: Pubkey) -> TestAccount {
+    TestAccount::new(key, System::id(), Vec::new())
+}
+
+/// `account` at `key`, owned by this program
+pub fn program_account<T: AccountSerialize>(key: Pubkey, account: &T) -> TestAccount {
+    let mut data = Vec::new();
+    account.try_serialize(&mut data).unwrap();
+    TestAccount::new(key, crate::ID, data)
+}
+
+/// A standard Token account at `key`
+pub fn token_account(key: Pubkey, mint: Pubkey, owner: Pubkey, amount: u64) -> TestAccount {
+    let mut data = vec![0; spl_token::state::Account::LEN];
+    spl_token::state::Account {
+        mint,
+        owner,
+        amount,
+        state: spl_token::state::AccountState::Initialized,
+        ..Default::default()
+    }
+        .pack_into_slice(&mut data);
+    TestAccount::new(key, anchor_spl::token::ID, data)
+}
+
+/// A Token-2022 account at `key`, its balance followed by an ImmutableOwner extension
+pub fn token_2022_account(key: Pubkey, mint: Pubkey, owner: Pubkey, amount: u64) -> TestAccount {
+    use spl_token_2022::extension::{immutable_owner::ImmutableOwner, BaseStateWithExtensionsMut, ExtensionType, StateWithExtensionsMut};
+    let len = ExtensionType::try_calculate_account_len::<spl_token_2022::state::Account>(&[ExtensionType::ImmutableOwner]).unwrap();
+    let mut data = vec![0; len];
+    let mut state = StateWithExtensionsMut::<spl_token_2022::state::Account>::unpack_uninitialized(&mut data).unwrap();
+    state.base = spl_token_2022::state::Account {
+        mint,
+        owner,
+        amount,
+        state: spl_token_2022::state::AccountState::Initialized,
+        ..Default::default()
+    };
+    state.pack_base();
+    state.init_account_type().unwrap();
+    state.init_extension::<ImmutableOwner>(true).unwrap();
+    TestAccount::new(key, spl_token_2022::ID, data)
+}
+
+/// A standard Token mint at `key`
+pub fn mint_account(key: Pubkey, mint_authority: Pubkey, supply: u64, decimals: u8) -> TestAccount {
+    let mut data = vec![0; spl_token::state::Mint::LEN];
+    spl_token::state::Mint {
+        mint_authority: COption::Some(mint_authority),
+        supply,
+        decimals,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    }
+        .pack_into_slice(&mut data);
+    TestAccount::new(key, anchor_spl::token::ID, data)
+}
+
+/// Overwrite the balance of the Token account held in `account` - stands in for a transfer
+/// the off-chain CPI never made
+pub fn set_token_amount(account: &AccountInfo, amount: u64) {
+    let mut data = account.try_borrow_mut_data().unwrap();
+    let mut token_account = spl_token::state::Account::unpack(&data).unwrap();
+    token_account.amount = amount;
+    token_account.pack_into_slice(&mut data);
+}
+
+/// PDA of this program at `seeds`
+pub fn pda(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, &crate::ID).0
+}
+
+thread_local! {
+    static UNIX_TIMESTAMP: Cell<i64> = const { Cell::new(0) };
+    static SYSTEM_TRANSFERS: RefCell<Vec<(Pubkey, Pubkey, u64)>> = const { RefCell::new(Vec::new()) };
+    static LOGGED_DATA: RefCell<Vec<Vec<u8>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// (from, to, lamports) of every System Program transfer this thread's handlers invoked
+/// since the last call
+pub fn take_system_transfers() -> Vec<(Pubkey, Pubkey, u64)> {
+    SYSTEM_TRANSFERS.with(|transfers| transfers.take())
+}
+
+/// Every `T` event this thread's handlers emitted since the last call - events of other
+/// types emitted meanwhile are dropped
+pub fn take_events<T: anchor_lang::Event + AnchorDeserialize>() -> Vec<T> {
+    LOGGED_DATA
+        .with(|logged| logged.take())
+        .into_iter()
+        .filter_map(|data| data.strip_prefix(T::DISCRIMINATOR).map(|mut event| T::deserialize(&mut event).unwrap()))
+        .collect()
+}
+
+/// Set the unix_timestamp this thread's handlers read from Clock
+pub fn set_clock(unix_timestamp: i64) {
+    install_stubs();
+    UNIX_TIMESTAMP.with(|now| now.set(unix_timestamp));
+}
+
+/// Serve Clock (see set_clock) and Rent to handlers - off-chain builds have neither sysvar -
+/// and record the transfers they invoke and the events they emit
+pub fn install_stubs() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        set_syscall_stubs(Box::new(TestStubs));
+    });
+}
+
+// Stubs are process-wide, the clock and what handlers invoke and emit are per test thread
+struct TestStubs;
+
+impl SyscallStubs for TestStubs {
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        // Clock::get passes a Clock to fill in
+        let clock = Clock { unix_timestamp: UNIX_TIMESTAMP.with(Cell::get), ..Default::default() };
+        unsafe { *(var_addr as *mut Clock) = clock };
+        0
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Rent) = Rent::default() };
+        0
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        _account_infos: &[AccountInfo],
+        _signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        // SystemInstruction::Transfer: u32 variant 2, then the u64 lamports
+        if instruction.program_id == System::id() && instruction.data.len() == 12 && instruction.data[..4] == [2, 0, 0, 0] {
+            let lamports = u64::from_le_bytes(instruction.data[4..].try_into().unwrap());
+            let transfer = (instruction.accounts[0].pubkey, instruction.accounts[1].pubkey, lamports);
+            SYSTEM_TRANSFERS.with(|transfers| transfers.borrow_mut().push(transfer));
+        }
+        Ok(())
+    }
+
+    fn sol_log_data(&self, data: &[&[u8]]) {
+        // emit! logs each event as one discriminator-prefixed field
+        LOGGED_DATA.with(|logged| logged.borrow_mut().push(data.concat()));
+    }
+}
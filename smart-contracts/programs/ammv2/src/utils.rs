@@ -1,7 +1,18 @@
 use anchor_lang::prelude::*;
-use anchor_lang::solana_program::program::{invoke, invoke_signed};
+use anchor_lang::solana_program::program::invoke_signed;
 use spl_token_2022::instruction as token_2022_instruction;
-use anchor_spl::token::spl_token::instruction as token_instruction;
+use crate::error::ErrorCode;
+
+// LP units locked forever on a pool's first deposit. They are minted to an LP
+// account owned by the LOCKED_LIQUIDITY_SEED PDA, which the program never signs
+// for, so LP supply matches total_amount_minted, total_amount_minted never drops
+// below this and MINIMUM_LIQUIDITY / total_amount_minted of the reserves stays
+// in the pool permanently (guards against first-depositor share inflation)
+pub const MINIMUM_LIQUIDITY: u64 = 1000;
+// Smallest geometric mean a first deposit may have, so the depositor keeps at least
+// as much LP as is locked rather than almost nothing
+pub const MIN_INITIAL_LIQUIDITY: u64 = 2 * MINIMUM_LIQUIDITY;
+pub const LOCKED_LIQUIDITY_SEED: &[u8] = b"locked_liquidity";
 
 /// Token program IDs
 pub const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
@@ -13,26 +24,87 @@ pub fn token_2022_program_id() -> anchor_lang::solana_program::pubkey::Pubkey {
 }
 
 /// Check if a program ID is Token 2022
+/// Compares raw bytes - base58-encoding the key costs thousands of compute units
 pub fn is_token_2022(program_id: &Pubkey) -> bool {
-    program_id.to_string() == TOKEN_2022_PROGRAM_ID
+    *program_id == spl_token_2022::ID
 }
 
 /// Check if a program ID is standard Token
 pub fn is_token(program_id: &Pubkey) -> bool {
-    program_id.to_string() == TOKEN_PROGRAM_ID
+    *program_id == anchor_spl::token::ID
 }
 
-/// Get the appropriate token program account info based on program ID
-pub fn get_token_program_account<'info>(
-    token_program: &'info AccountInfo<'info>,
-    token_2022_program: &'info AccountInfo<'info>,
-    mint_program_id: &Pubkey,
-) -> &'info AccountInfo<'info> {
-    if is_token_2022(mint_program_id) {
-        token_2022_program
+/// Pick the token program for an account owned by `token_account_program`
+/// Token 2022 accounts require `token_2022_program` to be the real Token 2022 program
+pub fn token_program_for<'info>(
+    token_account_program: &Pubkey,
+    token_program: &AccountInfo<'info>,
+    token_2022_program: &AccountInfo<'info>,
+) -> Result<AccountInfo<'info>> {
+    if is_token_2022(token_account_program) {
+        require!(is_token_2022(token_2022_program.key), ErrorCode::InvalidTreasury);
+        Ok(token_2022_program.clone())
     } else {
-        token_program
+        Ok(token_program.clone())
+    }
+}
+
+/// Decimals of a Token or Token 2022 mint
+pub fn mint_decimals(mint: &AccountInfo) -> Result<u8> {
+    let mint_state = anchor_spl::token_interface::Mint::try_deserialize(&mut &mint.try_borrow_data()?[..])?;
+    Ok(mint_state.decimals)
+}
+
+/// Size of a vault for `mint`: 165 bytes for classic Token, plus whatever
+/// account extensions a Token 2022 mint requires (e.g. TransferFeeAmount)
+pub fn token_account_len(mint: &AccountInfo) -> Result<usize> {
+    use anchor_lang::solana_program::program_pack::Pack;
+    use spl_token_2022::extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions};
+    
+    if !is_token_2022(mint.owner) {
+        return Ok(spl_token_2022::state::Account::LEN);
     }
+    let data = mint.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    let account_extensions = ExtensionType::get_required_init_account_extensions(&mint_state.get_extension_types()?);
+    Ok(ExtensionType::try_calculate_account_len::<spl_token_2022::state::Account>(&account_extensions)?)
+}
+
+/// Reject Token 2022 mints the pool can't hold safely: a permanent delegate could
+/// drain the vault, paused and non-transferable mints can't be moved and frozen-by-default
+/// mints would leave the vault frozen
+/// Transfer fee and transfer hook mints need TransferChecked, so they're only accepted
+/// when `transfer_checked` is set - SPL pools move every vault transfer through
+/// transfer_tokens_checked, native pools use the plain Transfer
+pub fn check_mint_extensions_supported(mint: &AccountInfo, transfer_checked: bool) -> Result<()> {
+    use spl_token_2022::extension::{
+        default_account_state::DefaultAccountState, BaseStateWithExtensions, ExtensionType, StateWithExtensions,
+    };
+    
+    if !is_token_2022(mint.owner) {
+        return Ok(());
+    }
+    let data = mint.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    for extension_type in mint_state.get_extension_types()? {
+        match extension_type {
+            ExtensionType::TransferFeeConfig | ExtensionType::TransferHook => {
+                require!(transfer_checked, ErrorCode::UnsupportedMintExtension);
+            }
+            ExtensionType::PermanentDelegate
+            | ExtensionType::Pausable
+            | ExtensionType::NonTransferable => return err!(ErrorCode::UnsupportedMintExtension),
+            ExtensionType::DefaultAccountState => {
+                let default_state = mint_state.get_extension::<DefaultAccountState>()?;
+                require!(
+                    default_state.state != spl_token_2022::state::AccountState::Frozen as u8,
+                    ErrorCode::UnsupportedMintExtension
+                );
+            }
+            _ => {}
+        }
+    }
+    Ok(())
 }
 
 /// Transfer tokens using the correct token program (Token or Token 2022)
@@ -43,71 +115,1321 @@ pub fn transfer_tokens<'info>(
     token_program: AccountInfo<'info>,
     amount: u64,
 ) -> Result<()> {
-    // Both Token and Token 2022 support the standard transfer instruction
-    let transfer_ix = if is_token_2022(token_program.key) {
-        token_2022_instruction::transfer(
-            token_program.key,
-            from.key,
-            to.key,
-            authority.key,
-            &[],
-            amount,
-        )?
-    } else {
-        token_instruction::transfer(
-            token_program.key,
-            from.key,
-            to.key,
-            authority.key,
-            &[],
-            amount,
-        )?
-    };
+    transfer_tokens_signed(from, to, authority, token_program, amount, &[])
+}
+
+/// Transfer tokens using the correct token program with PDA signer
+pub fn transfer_tokens_signed<'info>(
+    from: AccountInfo<'info>,
+    to: AccountInfo<'info>,
+    authority: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    // The Token 2022 builder accepts either program ID and both programs
+    // share the transfer instruction layout
+    let transfer_ix = token_2022_instruction::transfer(
+        token_program.key,
+        from.key,
+        to.key,
+        authority.key,
+        &[],
+        amount,
+    )?;
     
-    invoke(
+    invoke_signed(
         &transfer_ix,
         &[from, to, authority, token_program],
+        signer_seeds,
     )?;
     
     Ok(())
 }
 
-/// Transfer tokens using the correct token program with PDA signer
-pub fn transfer_tokens_signed<'info>(
+/// Transfer tokens with TransferChecked when `mint` is given, otherwise the plain Transfer
+/// Token 2022 refuses the plain Transfer for mints with a transfer fee or transfer hook.
+/// A hook's extra accounts are looked up by address in `extra_accounts`
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_tokens_checked<'info>(
     from: AccountInfo<'info>,
     to: AccountInfo<'info>,
     authority: AccountInfo<'info>,
     token_program: AccountInfo<'info>,
+    mint: Option<AccountInfo<'info>>,
+    decimals: u8,
+    extra_accounts: &[AccountInfo<'info>],
     amount: u64,
     signer_seeds: &[&[&[u8]]],
 ) -> Result<()> {
-    // Both Token and Token 2022 support the standard transfer instruction
-    let transfer_ix = if is_token_2022(token_program.key) {
-        token_2022_instruction::transfer(
-            token_program.key,
-            from.key,
-            to.key,
-            authority.key,
-            &[],
-            amount,
-        )?
-    } else {
-        token_instruction::transfer(
-            token_program.key,
-            from.key,
-            to.key,
-            authority.key,
-            &[],
-            amount,
-        )?
+    let Some(mint) = mint else {
+        return transfer_tokens_signed(from, to, authority, token_program, amount, signer_seeds);
     };
+    spl_token_2022::onchain::invoke_transfer_checked(
+        token_program.key,
+        from,
+        mint,
+        to,
+        authority,
+        extra_accounts,
+        amount,
+        decimals,
+        signer_seeds,
+    )?;
+    Ok(())
+}
+
+/// Token 2022 transfer fee `mint` withholds from a transfer of `amount` in `epoch` -
+/// 0 for classic Token mints and mints without a TransferFeeConfig
+pub fn transfer_fee(mint: &AccountInfo, epoch: u64, amount: u64) -> Result<u64> {
+    use spl_token_2022::extension::{transfer_fee::TransferFeeConfig, BaseStateWithExtensions, StateWithExtensions};
     
-    invoke_signed(
-        &transfer_ix,
-        &[from, to, authority, token_program],
+    if !is_token_2022(mint.owner) {
+        return Ok(0);
+    }
+    let data = mint.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    let Ok(transfer_fee_config) = mint_state.get_extension::<TransferFeeConfig>() else {
+        return Ok(0);
+    };
+    Ok(transfer_fee_config.calculate_epoch_fee(epoch, amount).ok_or(ErrorCode::MathOverflow)?)
+}
+
+/// `amount` less the transfer fee `mint` (if given) withholds from it in `epoch` - what a
+/// transfer of `amount` actually delivers
+pub fn amount_after_transfer_fee(mint: Option<&AccountInfo>, epoch: u64, amount: u64) -> Result<u64> {
+    let fee = match mint {
+        Some(mint) => transfer_fee(mint, epoch, amount)?,
+        None => 0,
+    };
+    Ok(amount.checked_sub(fee).ok_or(ErrorCode::MathOverflow)?)
+}
+
+/// Create the protocol treasury's XNT associated token account if it doesn't exist yet
+/// `xnt_mint` is the pool's XNT mint (PoolState::xnt_mint), `token_program` its owner
+#[allow(clippy::too_many_arguments)]
+pub fn create_treasury_ata_if_missing<'info>(
+    payer: AccountInfo<'info>,
+    treasury_ata: AccountInfo<'info>,
+    treasury: AccountInfo<'info>,
+    native_mint: AccountInfo<'info>,
+    xnt_mint: &Pubkey,
+    system_program: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    associated_token_program: AccountInfo<'info>,
+) -> Result<()> {
+    // Protocol fees are always collected in XNT
+    require!(*native_mint.key == *xnt_mint, ErrorCode::InvalidTreasury);
+    require!(*native_mint.owner == *token_program.key, ErrorCode::InvalidTreasury);
+    
+    if !treasury_ata.data_is_empty() {
+        return Ok(());
+    }
+    
+    anchor_spl::associated_token::create_idempotent(CpiContext::new(
+        associated_token_program,
+        anchor_spl::associated_token::Create {
+            payer,
+            associated_token: treasury_ata,
+            authority: treasury,
+            mint: native_mint,
+            system_program,
+            token_program,
+        },
+    ))
+}
+
+/// Create a pool's LP mint at its PDA with `decimals` decimals, `mint_authority` as authority
+/// and an optional `freeze_authority` (soulbound pools only)
+/// A PDA pre-funded by someone else is topped up rather than rejected (as for the vaults)
+#[allow(clippy::too_many_arguments)]
+pub fn create_lp_mint<'info>(
+    payer: AccountInfo<'info>,
+    lp_mint: AccountInfo<'info>,
+    lp_mint_seeds: &[&[u8]],
+    decimals: u8,
+    mint_authority: &Pubkey,
+    freeze_authority: Option<&Pubkey>,
+    system_program: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+) -> Result<()> {
+    require!(decimals <= crate::state::MAX_LP_DECIMALS, ErrorCode::InvalidInput);
+    require!(
+        *lp_mint.owner == anchor_lang::system_program::ID,
+        ErrorCode::InvalidInput
+    );
+    
+    use anchor_lang::solana_program::program_pack::Pack;
+    let space = anchor_spl::token::spl_token::state::Mint::LEN;
+    let rent_lamports = Rent::get()?.minimum_balance(space);
+    let signer_seeds = &[lp_mint_seeds];
+    
+    // Transfer + allocate + assign works whether or not the PDA already holds lamports
+    let shortfall = rent_lamports.saturating_sub(lp_mint.lamports());
+    if shortfall > 0 {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                anchor_lang::system_program::Transfer {
+                    from: payer,
+                    to: lp_mint.clone(),
+                },
+            ),
+            shortfall,
+        )?;
+    }
+    if lp_mint.data_len() == 0 {
+        anchor_lang::system_program::allocate(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                anchor_lang::system_program::Allocate { account_to_allocate: lp_mint.clone() },
+                signer_seeds,
+            ),
+            space as u64,
+        )?;
+    }
+    anchor_lang::system_program::assign(
+        CpiContext::new_with_signer(
+            system_program,
+            anchor_lang::system_program::Assign { account_to_assign: lp_mint.clone() },
+            signer_seeds,
+        ),
+        token_program.key,
+    )?;
+    
+    anchor_spl::token::initialize_mint2(
+        CpiContext::new(
+            token_program,
+            anchor_spl::token::InitializeMint2 { mint: lp_mint },
+        ),
+        decimals,
+        mint_authority,
+        freeze_authority,
+    )
+}
+
+/// Thaw a soulbound pool's LP account ahead of a mint_to or burn into/out of it
+/// Soulbound LP accounts are kept frozen by the pool authority (the LP mint's freeze
+/// authority) so holders can't transfer them. New accounts start out thawed
+pub fn thaw_lp_account<'info>(
+    lp_account: AccountInfo<'info>,
+    lp_mint: AccountInfo<'info>,
+    pool_authority: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let is_frozen = anchor_spl::token::TokenAccount::try_deserialize(&mut &lp_account.try_borrow_data()?[..])?
+        .is_frozen();
+    if !is_frozen {
+        return Ok(());
+    }
+    
+    anchor_spl::token::thaw_account(CpiContext::new_with_signer(
+        token_program,
+        anchor_spl::token::ThawAccount {
+            account: lp_account,
+            mint: lp_mint,
+            authority: pool_authority,
+        },
+        signer_seeds,
+    ))
+}
+
+/// Freeze a soulbound pool's LP account again once the pool's mint_to / burn is done
+pub fn freeze_lp_account<'info>(
+    lp_account: AccountInfo<'info>,
+    lp_mint: AccountInfo<'info>,
+    pool_authority: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    anchor_spl::token::freeze_account(CpiContext::new_with_signer(
+        token_program,
+        anchor_spl::token::FreezeAccount {
+            account: lp_account,
+            mint: lp_mint,
+            authority: pool_authority,
+        },
         signer_seeds,
+    ))
+}
+
+/// LP worth `fee_xnt` XNT kept in a pool that held `xnt_reserve` XNT and `total_minted` LP
+/// The pool is valued at twice its XNT side, so minting total_minted * fee / (2 * xnt_reserve)
+/// leaves the value of every existing LP unit where it was
+pub fn compound_lp_amount(fee_xnt: u64, xnt_reserve: u64, total_minted: u64) -> Result<u64> {
+    require!(xnt_reserve > 0, ErrorCode::PoolReserveEmpty);
+    let lp = (total_minted as u128)
+        .checked_mul(fee_xnt as u128)
+        .ok_or(ErrorCode::MathOverflow)?
+        / (2 * xnt_reserve as u128);
+    u64::try_from(lp).map_err(|_| ErrorCode::MathOverflow.into())
+}
+
+/// Mint `amount` LP of a compounded protocol fee to the pool's protocol_lp_account
+/// (thawed around the mint for soulbound pools)
+pub fn mint_compounded_lp<'info>(
+    lp_mint: AccountInfo<'info>,
+    protocol_lp_account: AccountInfo<'info>,
+    pool_authority: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+    amount: u64,
+    lp_soulbound: bool,
+) -> Result<()> {
+    if lp_soulbound {
+        thaw_lp_account(protocol_lp_account.clone(), lp_mint.clone(), pool_authority.clone(), token_program.clone(), signer_seeds)?;
+    }
+    anchor_spl::token::mint_to(
+        CpiContext::new_with_signer(
+            token_program.clone(),
+            anchor_spl::token::MintTo {
+                mint: lp_mint.clone(),
+                to: protocol_lp_account.clone(),
+                authority: pool_authority.clone(),
+            },
+            signer_seeds,
+        ),
+        amount,
     )?;
+    if lp_soulbound {
+        freeze_lp_account(protocol_lp_account, lp_mint, pool_authority, token_program, signer_seeds)?;
+    }
+    Ok(())
+}
+
+/// Close an LP account a withdrawal emptied, returning its rent to `owner`
+/// A soulbound pool's account is thawed first - the token program won't close a frozen account
+pub fn close_empty_lp_account<'info>(
+    lp_account: AccountInfo<'info>,
+    lp_mint: AccountInfo<'info>,
+    owner: AccountInfo<'info>,
+    pool_authority: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+    lp_soulbound: bool,
+) -> Result<()> {
+    require!(token_account_amount(&lp_account)? == 0, ErrorCode::LpAccountNotEmpty);
+    
+    if lp_soulbound {
+        thaw_lp_account(lp_account.clone(), lp_mint, pool_authority, token_program.clone(), signer_seeds)?;
+    }
+    anchor_spl::token::close_account(CpiContext::new(
+        token_program,
+        anchor_spl::token::CloseAccount {
+            account: lp_account,
+            destination: owner.clone(),
+            authority: owner,
+        },
+    ))
+}
+
+/// Pair each FeeSplit recipient with its account and its share of `fee`
+/// `recipient_accounts` are in recipient order; `check_account` verifies an
+/// account belongs to the given recipient. Zero shares are left out.
+pub fn fee_split_payouts<'info>(
+    fee_split: &crate::state::FeeSplit,
+    pool: &Pubkey,
+    recipient_accounts: &[AccountInfo<'info>],
+    fee: u64,
+    check_account: impl Fn(&AccountInfo<'info>, &Pubkey) -> Result<()>,
+) -> Result<Vec<(AccountInfo<'info>, u64)>> {
+    require!(fee_split.pool == *pool, ErrorCode::InvalidTreasury);
+    let count = fee_split.count as usize;
+    require!(recipient_accounts.len() >= count, ErrorCode::InvalidTreasury);
+    
+    let mut payouts = Vec::with_capacity(count);
+    for ((account, recipient), share) in recipient_accounts
+        .iter()
+        .zip(&fee_split.recipients[..count])
+        .zip(fee_split.shares(fee)?)
+    {
+        check_account(account, recipient)?;
+        if share > 0 {
+            payouts.push((account.clone(), share));
+        }
+    }
+    Ok(payouts)
+}
+
+/// Reject a swap paying out more than `max_swap_out_bps_of_reserve` of the output reserve
+/// `output_amount` is the gross amount leaving the reserve, protocol fee included
+pub fn check_swap_size(output_amount: u128, reserve_out: u128, max_swap_out_bps_of_reserve: u16) -> Result<()> {
+    if max_swap_out_bps_of_reserve >= 10000 {
+        return Ok(()); // unrestricted
+    }
+    
+    let max_output = reserve_out
+        .checked_mul(max_swap_out_bps_of_reserve as u128)
+        .ok_or(ErrorCode::MathOverflow)?
+        / 10000;
+    
+    require!(output_amount <= max_output, ErrorCode::SwapTooLarge);
+    
+    Ok(())
+}
+
+/// Reject a min_amount_out that implies more slippage than the pool's cap
+/// `quoted_out` is the output the swap will actually deliver
+pub fn check_slippage_cap(quoted_out: u64, min_amount_out: u64, max_allowed_slippage_bps: u16) -> Result<()> {
+    if max_allowed_slippage_bps >= 10000 {
+        return Ok(()); // unrestricted
+    }
+    
+    // Lowest min_amount_out the cap allows: quoted_out * (1 - cap)
+    let slippage_floor = (quoted_out as u128)
+        .checked_mul((10000 - max_allowed_slippage_bps) as u128)
+        .ok_or(ErrorCode::MathOverflow)?
+        / 10000;
+    
+    require!(
+        min_amount_out as u128 >= slippage_floor,
+        ErrorCode::SlippageToleranceTooHigh
+    );
+    
+    Ok(())
+}
+
+/// Reject a deposit that would leave the pool's XNT reserve above `max_tvl_native`
+pub fn check_tvl_cap(xnt_reserve: u64, xnt_deposit: u64, max_tvl_native: u64) -> Result<()> {
+    if max_tvl_native == 0 {
+        return Ok(()); // unlimited
+    }
+    
+    let xnt_reserve_after = xnt_reserve
+        .checked_add(xnt_deposit)
+        .ok_or(ErrorCode::MathOverflow)?;
+    require!(xnt_reserve_after <= max_tvl_native, ErrorCode::TvlCapExceeded);
+    
+    Ok(())
+}
+
+/// LP balance that counts toward the LP holder fee discount
+/// The account must hold `lp_mint` and belong to the swapper, and the swapper's
+/// LpBalanceSnapshot bounds it (see snapshotted_lp_balance); either missing counts as 0
+pub fn lp_discount_balance(
+    user_lp_account: Option<&anchor_spl::token_interface::TokenAccount>,
+    snapshot: Option<&crate::state::LpBalanceSnapshot>,
+    lp_mint: &Pubkey,
+    swapper: &Pubkey,
+) -> Result<u64> {
+    let (Some(user_lp_account), Some(snapshot)) = (user_lp_account, snapshot) else {
+        return Ok(0);
+    };
+    
+    require!(user_lp_account.mint == *lp_mint, ErrorCode::InvalidInput);
+    require!(user_lp_account.owner == *swapper, ErrorCode::InvalidInput);
+    
+    Ok(snapshotted_lp_balance(user_lp_account.amount, snapshot.amount, snapshot.slot, Clock::get()?.slot))
+}
+
+/// The smaller of the current and snapshotted LP balance, or 0 while the snapshot
+/// is from the current slot - LP acquired since the snapshot, or flash-deposited
+/// around a fresh one, earns no discount
+pub fn snapshotted_lp_balance(current_amount: u64, snapshot_amount: u64, snapshot_slot: u64, current_slot: u64) -> u64 {
+    if snapshot_slot >= current_slot {
+        return 0;
+    }
+    current_amount.min(snapshot_amount)
+}
+
+/// Fail while the global kill-switch is engaged
+/// `global_config` is the [b"global_config"] PDA (the caller's seeds constraint checks it);
+/// it holds no data until initialize_global_config runs, and nothing is paused then
+pub fn check_not_paused(global_config: &AccountInfo) -> Result<()> {
+    if global_config.data_is_empty() {
+        return Ok(());
+    }
+    require!(global_config.owner == &crate::ID, ErrorCode::InvalidAccountData);
+    
+    let config = crate::state::GlobalConfig::try_deserialize(&mut &global_config.try_borrow_data()?[..])?;
+    require!(!config.paused, ErrorCode::ProgramPaused);
+    Ok(())
+}
+
+/// The pool's FeeSplit table, or None while it has not been created
+/// `fee_split` is the [b"fee_split", pool_state] PDA (the caller's seeds constraint checks it),
+/// so a swapper cannot route around an existing table by leaving it out
+pub fn load_fee_split(fee_split: &AccountInfo) -> Result<Option<crate::state::FeeSplit>> {
+    if fee_split.data_is_empty() {
+        return Ok(None);
+    }
+    require!(fee_split.owner == &crate::ID, ErrorCode::InvalidAccountData);
+    
+    Ok(Some(crate::state::FeeSplit::try_deserialize(&mut &fee_split.try_borrow_data()?[..])?))
+}
+
+/// Mark the pool as mid-instruction, failing if it already is (reentrant call)
+/// Written directly to account data so CPIs made by the caller observe it
+pub fn begin_pool_operation(pool_state: &AccountInfo) -> Result<()> {
+    let mut data = pool_state.try_borrow_mut_data()?;
+    
+    // Legacy layouts have no room for the flag
+    if data.len() <= crate::state::PoolState::IN_PROGRESS_OFFSET {
+        return Ok(());
+    }
+    
+    require!(
+        data[crate::state::PoolState::IN_PROGRESS_OFFSET] == 0,
+        ErrorCode::Reentrancy
+    );
+    data[crate::state::PoolState::IN_PROGRESS_OFFSET] = 1;
+    
+    Ok(())
+}
+
+/// Clear the flag set by `begin_pool_operation`
+pub fn end_pool_operation(pool_state: &AccountInfo) -> Result<()> {
+    let mut data = pool_state.try_borrow_mut_data()?;
+    
+    if data.len() > crate::state::PoolState::IN_PROGRESS_OFFSET {
+        data[crate::state::PoolState::IN_PROGRESS_OFFSET] = 0;
+    }
     
     Ok(())
 }
 
+/// Reject a fee fraction the swap math can't evaluate: a zero denominator (e.g. a
+/// corrupt or zeroed pool account) or a numerator above it, which would underflow
+pub fn check_fee_fraction(fee_numerator: u64, fee_denominator: u64) -> Result<()> {
+    require!(fee_denominator > 0 && fee_numerator <= fee_denominator, ErrorCode::InvalidInput);
+    Ok(())
+}
+
+/// Calculate swap output using constant product formula (x * y = k)
+/// Includes fee deduction
+pub fn calculate_swap_output(
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    fee_numerator: u64,
+    fee_denominator: u64,
+) -> Result<u64> {
+    require!(reserve_in > 0 && reserve_out > 0, ErrorCode::InsufficientLiquidity);
+    check_fee_fraction(fee_numerator, fee_denominator)?;
+    
+    // Deduct fee from input amount
+    let amount_in_with_fee = (amount_in as u128)
+        .checked_mul((fee_denominator - fee_numerator) as u128)
+        .ok_or(ErrorCode::MathOverflow)?
+        .checked_div(fee_denominator as u128)
+        .ok_or(ErrorCode::MathOverflow)? as u64;
+    
+    // Calculate output: (amount_in_with_fee * reserve_out) / (reserve_in + amount_in_with_fee)
+    let numerator = (amount_in_with_fee as u128)
+        .checked_mul(reserve_out as u128)
+        .ok_or(ErrorCode::MathOverflow)?;
+    
+    let denominator = (reserve_in as u128)
+        .checked_add(amount_in_with_fee as u128)
+        .ok_or(ErrorCode::MathOverflow)?;
+    
+    let amount_out = numerator
+        .checked_div(denominator)
+        .ok_or(ErrorCode::MathOverflow)? as u64;
+    
+    Ok(amount_out)
+}
+
+/// Reject a burn leaving LP supply strictly between 0 and MINIMUM_LIQUIDITY
+/// A sliver of supply would price each remaining LP token against the whole reserves
+pub fn check_burn_leaves_minimum_liquidity(total_amount_minted: u64, burn_amount: u64) -> Result<()> {
+    let remaining = total_amount_minted
+        .checked_sub(burn_amount)
+        .ok_or(ErrorCode::BurnTooMuch)?;
+    require!(
+        remaining == 0 || remaining >= MINIMUM_LIQUIDITY,
+        ErrorCode::BurnBelowMinimumLiquidity
+    );
+    Ok(())
+}
+
+/// Smallest input for which calculate_swap_output returns at least `amount_out`
+/// Inverts both floor divisions exactly, so the round trip never falls short
+pub fn calculate_swap_input(
+    amount_out: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    fee_numerator: u64,
+    fee_denominator: u64,
+) -> Result<u64> {
+    require!(reserve_in > 0 && amount_out < reserve_out, ErrorCode::InsufficientLiquidity);
+    require!(fee_numerator < fee_denominator, ErrorCode::InvalidInput);
+    
+    // Input after the fee: ceil(amount_out * reserve_in / (reserve_out - amount_out))
+    let remaining_out = (reserve_out - amount_out) as u128;
+    let amount_in_with_fee = (amount_out as u128)
+        .checked_mul(reserve_in as u128)
+        .ok_or(ErrorCode::MathOverflow)?
+        .div_ceil(remaining_out);
+    
+    // Gross input: ceil(amount_in_with_fee * fee_denominator / (fee_denominator - fee_numerator))
+    let amount_in = amount_in_with_fee
+        .checked_mul(fee_denominator as u128)
+        .ok_or(ErrorCode::MathOverflow)?
+        .div_ceil((fee_denominator - fee_numerator) as u128);
+    
+    u64::try_from(amount_in).map_err(|_| ErrorCode::MathOverflow.into())
+}
+
+/// LP to mint for depositing `amount_a` / `amount_b` into a pool holding
+/// `reserve_a` / `reserve_b` with `total_minted` LP outstanding
+/// First deposit: geometric mean of the amounts (at least MIN_INITIAL_LIQUIDITY),
+/// less the MINIMUM_LIQUIDITY the caller locks. Later deposits: the smaller pro-rata share, so the excess on
+/// either side goes to existing LPs rather than minting unbacked LP
+pub fn calculate_lp_to_mint(
+    total_minted: u64,
+    reserve_a: u64,
+    reserve_b: u64,
+    amount_a: u64,
+    amount_b: u64,
+) -> Result<u64> {
+    if total_minted == 0 {
+        let initial_liquidity = geometric_mean(amount_a, amount_b);
+        require!(initial_liquidity >= MIN_INITIAL_LIQUIDITY, ErrorCode::InsufficientInitialLiquidity);
+        return Ok(initial_liquidity - MINIMUM_LIQUIDITY);
+    }
+    
+    require!(reserve_a > 0 && reserve_b > 0, ErrorCode::InsufficientLiquidity);
+    let lp_from_a = (amount_a as u128)
+        .checked_mul(total_minted as u128)
+        .ok_or(ErrorCode::MathOverflow)?
+        / reserve_a as u128;
+    let lp_from_b = (amount_b as u128)
+        .checked_mul(total_minted as u128)
+        .ok_or(ErrorCode::MathOverflow)?
+        / reserve_b as u128;
+    
+    u64::try_from(lp_from_a.min(lp_from_b)).map_err(|_| ErrorCode::MathOverflow.into())
+}
+
+/// Floored square root of a u128
+pub fn sqrt_u128(x: u128) -> u128 {
+    x.integer_sqrt()
+}
+
+/// Floored sqrt(a * b) - the product is taken in u128, where it cannot overflow,
+/// and its root is below 2^64, so callers never cast the result down themselves
+pub fn geometric_mean(a: u64, b: u64) -> u64 {
+    // sqrt((2^64 - 1)^2) = 2^64 - 1
+    sqrt_u128(a as u128 * b as u128) as u64
+}
+
+// Integer square root helper
+trait IntegerSquareRoot {
+    fn integer_sqrt(self) -> Self;
+}
+
+impl IntegerSquareRoot for u128 {
+    fn integer_sqrt(self) -> Self {
+        if self == 0 {
+            return 0;
+        }
+        // self / 2 + 1 >= sqrt(self), and unlike (self + 1) / 2 cannot overflow at u128::MAX
+        let mut x = self / 2 + 1;
+        let mut y = (x + self / x) / 2;
+        while y < x {
+            x = y;
+            y = (x + self / x) / 2;
+        }
+        x
+    }
+}
+
+/// Protocol fee (in XNT) on a swap moving `xnt_amount` XNT
+///
+/// PROTOCOL_FEE_MODE_NOTIONAL: protocol_fee_bps of the XNT notional. Charged on top
+///   of the LP fee, so on a 0.3% pool a 100 bps protocol fee costs traders more than
+///   the LP fee and the protocol out-earns LPs.
+/// PROTOCOL_FEE_MODE_LP_FEE: protocol_fee_bps of the LP fee on that notional, so
+///   the protocol's cut scales with the pool fee and never exceeds what LPs earn.
+pub fn protocol_fee_amount(
+    xnt_amount: u128,
+    protocol_fee_bps: u16,
+    protocol_fee_mode: u8,
+    fee_numerator: u64,
+    fee_denominator: u64,
+) -> Option<u128> {
+    let fee_base = if protocol_fee_mode == crate::state::PROTOCOL_FEE_MODE_LP_FEE {
+        xnt_amount
+            .checked_mul(fee_numerator as u128)?
+            .checked_div(fee_denominator as u128)?
+    } else {
+        xnt_amount
+    };
+    
+    fee_base
+        .checked_mul(protocol_fee_bps as u128)?
+        .checked_div(10000)
+}
+
+/// Reject a protocol fee that takes a bigger cut of each swap than the LP fee
+/// Only PROTOCOL_FEE_MODE_NOTIONAL can exceed it - a share of the LP fee never does
+pub fn check_protocol_fee_within_lp_fee(
+    protocol_fee_bps: u16,
+    protocol_fee_mode: u8,
+    fee_numerator: u64,
+    fee_denominator: u64,
+) -> Result<()> {
+    if protocol_fee_mode == crate::state::PROTOCOL_FEE_MODE_LP_FEE {
+        return Ok(());
+    }
+    
+    // protocol_fee_bps / 10000 <= fee_numerator / fee_denominator, cross-multiplied
+    require!(
+        protocol_fee_bps as u128 * fee_denominator as u128 <= fee_numerator as u128 * 10000,
+        ErrorCode::ProtocolFeeExceedsLpFee
+    );
+    
+    Ok(())
+}
+
+/// Fail if a swap left the pool with a smaller x * y than it started with
+/// Used by the `invariant-checks` feature to abort on math bugs instead of leaking value
+pub fn check_invariant(
+    reserve_in_before: u64,
+    reserve_out_before: u64,
+    reserve_in_after: u64,
+    reserve_out_after: u64,
+) -> Result<()> {
+    // u64 * u64 always fits in u128
+    let k_before = reserve_in_before as u128 * reserve_out_before as u128;
+    let k_after = reserve_in_after as u128 * reserve_out_after as u128;
+    require!(k_after >= k_before, ErrorCode::InvariantViolated);
+    
+    Ok(())
+}
+
+/// Read the current balance of a token account (Token or Token2022)
+/// Standard Token accounts skip the extension-aware Token 2022 unpack
+pub fn token_account_amount(token_account: &AccountInfo) -> Result<u64> {
+    let data = token_account.try_borrow_data()?;
+    if is_token(token_account.owner) {
+        use anchor_lang::solana_program::program_pack::Pack;
+        let account = anchor_spl::token::spl_token::state::Account::unpack(&data)?;
+        return Ok(account.amount);
+    }
+    
+    let account = anchor_spl::token_interface::TokenAccount::try_deserialize(&mut &data[..])?;
+    Ok(account.amount)
+}
+
+/// Check a token account moved by exactly `expected` since it held `balance_before`
+/// A Token 2022 transfer fee is withheld from the recipient, so credits of a fee mint
+/// are expected net of it (see amount_after_transfer_fee)
+pub fn check_balance_delta(token_account: &AccountInfo, balance_before: u64, expected: u64, credit: bool) -> Result<()> {
+    let balance_after = token_account_amount(token_account)?;
+    let delta = if credit {
+        balance_after.checked_sub(balance_before)
+    } else {
+        balance_before.checked_sub(balance_after)
+    }.ok_or(ErrorCode::ExecutionMismatch)?;
+    
+    require!(delta == expected, ErrorCode::ExecutionMismatch);
+    
+    Ok(())
+}
+
+/// LP fee taken from `amount_in` by `calculate_swap_output`
+pub fn lp_fee_amount(amount_in: u64, fee_numerator: u64, fee_denominator: u64) -> Result<u64> {
+    check_fee_fraction(fee_numerator, fee_denominator)?;
+    let amount_in_with_fee = (amount_in as u128)
+        .checked_mul((fee_denominator - fee_numerator) as u128)
+        .ok_or(ErrorCode::MathOverflow)?
+        .checked_div(fee_denominator as u128)
+        .ok_or(ErrorCode::MathOverflow)? as u64;
+    
+    Ok(amount_in - amount_in_with_fee)
+}
+
+/// Price impact in basis points of swapping `amount_in`, excluding the LP fee
+/// (how far the execution price falls short of the current spot price)
+pub fn price_impact_bps(amount_in: u64, reserve_in: u64, reserve_out: u64) -> Result<u64> {
+    let amount_out = calculate_swap_output(amount_in, reserve_in, reserve_out, 0, 1)?;
+    
+    // Output if the whole trade executed at the spot price
+    let spot_out = (amount_in as u128)
+        .checked_mul(reserve_out as u128)
+        .ok_or(ErrorCode::MathOverflow)?
+        .checked_div(reserve_in as u128)
+        .ok_or(ErrorCode::MathOverflow)?;
+    
+    if spot_out == 0 {
+        return Ok(0);
+    }
+    
+    let impact = (spot_out - amount_out as u128)
+        .checked_mul(10000)
+        .ok_or(ErrorCode::MathOverflow)?
+        / spot_out;
+    
+    Ok(impact as u64)
+}
+
+/// Largest amount (up to `amount_in`) whose price impact stays within `max_price_impact_bps`
+/// Binary search - price impact grows monotonically with trade size
+pub fn max_amount_within_price_impact(
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    max_price_impact_bps: u16,
+) -> Result<u64> {
+    if price_impact_bps(amount_in, reserve_in, reserve_out)? <= max_price_impact_bps as u64 {
+        return Ok(amount_in);
+    }
+    
+    // Invariant: `low` is within the cap, `high` is not
+    let mut low = 0u64;
+    let mut high = amount_in;
+    while high - low > 1 {
+        let mid = low + (high - low) / 2;
+        if price_impact_bps(mid, reserve_in, reserve_out)? <= max_price_impact_bps as u64 {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    
+    Ok(low)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Serialized GlobalConfig account data, discriminator included
+    fn global_config_data(paused: bool) -> Vec<u8> {
+        let mut data = Vec::new();
+        crate::state::GlobalConfig { authority: Pubkey::new_unique(), paused }
+            .try_serialize(&mut data)
+            .unwrap();
+        data
+    }
+
+    fn check_paused_flag(data: &mut [u8], owner: &Pubkey) -> Result<()> {
+        let key = Pubkey::new_unique();
+        let mut lamports = 1_000_000;
+        let info = AccountInfo::new(&key, false, false, &mut lamports, data, owner, false, 0);
+        check_not_paused(&info)
+    }
+
+    #[test]
+    fn operations_run_without_global_config() {
+        assert!(check_paused_flag(&mut [], &anchor_lang::solana_program::system_program::ID).is_ok());
+    }
+
+    #[test]
+    fn operations_fail_only_while_paused() {
+        assert!(check_paused_flag(&mut global_config_data(false), &crate::ID).is_ok());
+        assert_eq!(
+            check_paused_flag(&mut global_config_data(true), &crate::ID).unwrap_err(),
+            ErrorCode::ProgramPaused.into()
+        );
+    }
+
+    #[test]
+    fn foreign_global_config_is_rejected() {
+        assert_eq!(
+            check_paused_flag(&mut global_config_data(false), &Pubkey::new_unique()).unwrap_err(),
+            ErrorCode::InvalidAccountData.into()
+        );
+    }
+
+    #[test]
+    fn protocol_fee_up_to_lp_fee_is_allowed() {
+        // 0.3% LP fee: 30 bps of notional is exactly the LP fee
+        assert!(check_protocol_fee_within_lp_fee(30, crate::state::PROTOCOL_FEE_MODE_NOTIONAL, 3, 1000).is_ok());
+        assert_eq!(
+            check_protocol_fee_within_lp_fee(31, crate::state::PROTOCOL_FEE_MODE_NOTIONAL, 3, 1000).unwrap_err(),
+            ErrorCode::ProtocolFeeExceedsLpFee.into()
+        );
+    }
+
+    #[test]
+    fn lp_fee_share_never_exceeds_lp_fee() {
+        // The same bps switched to a share of the LP fee is always within it
+        assert!(check_protocol_fee_within_lp_fee(10000, crate::state::PROTOCOL_FEE_MODE_LP_FEE, 3, 1000).is_ok());
+        assert_eq!(
+            check_protocol_fee_within_lp_fee(10000, crate::state::PROTOCOL_FEE_MODE_NOTIONAL, 3, 1000).unwrap_err(),
+            ErrorCode::ProtocolFeeExceedsLpFee.into()
+        );
+    }
+
+    #[test]
+    fn swaps_pay_treasury_until_fee_split_exists() {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let info = AccountInfo::new(&key, false, false, &mut lamports, &mut [], &anchor_lang::solana_program::system_program::ID, false, 0);
+        assert!(load_fee_split(&info).unwrap().is_none());
+    }
+
+    #[test]
+    fn existing_fee_split_is_loaded() {
+        let pool = Pubkey::new_unique();
+        let mut data = Vec::new();
+        crate::state::FeeSplit {
+            pool,
+            count: 1,
+            recipients: [Pubkey::new_unique(); crate::state::MAX_FEE_RECIPIENTS],
+            weights_bps: [10000, 0, 0, 0],
+        }
+            .try_serialize(&mut data)
+            .unwrap();
+        let key = Pubkey::new_unique();
+        let mut lamports = 1_000_000;
+        let info = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &crate::ID, false, 0);
+        assert_eq!(load_fee_split(&info).unwrap().unwrap().pool, pool);
+        
+        let foreign_owner = Pubkey::new_unique();
+        let info = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &foreign_owner, false, 0);
+        assert_eq!(load_fee_split(&info).err(), Some(ErrorCode::InvalidAccountData.into()));
+    }
+
+    #[test]
+    fn snapshotted_lp_balance_ignores_same_slot_snapshot() {
+        // Flash deposit: snapshot and swap in the same slot
+        assert_eq!(snapshotted_lp_balance(1_000_000, 1_000_000, 50, 50), 0);
+        assert_eq!(snapshotted_lp_balance(1_000_000, 1_000_000, 51, 50), 0);
+    }
+
+    #[test]
+    fn snapshotted_lp_balance_takes_smaller_balance() {
+        // LP added after the snapshot doesn't count
+        assert_eq!(snapshotted_lp_balance(5_000, 1_000, 49, 50), 1_000);
+        // LP removed after the snapshot doesn't count either
+        assert_eq!(snapshotted_lp_balance(200, 1_000, 49, 50), 200);
+    }
+
+    // Token 2022 mint account data with `extensions`, charging a 1% transfer fee when
+    // TransferFeeConfig is among them
+    fn token_2022_mint_data(extensions: &[spl_token_2022::extension::ExtensionType]) -> Vec<u8> {
+        use spl_token_2022::extension::{
+            permanent_delegate::PermanentDelegate, transfer_fee::TransferFeeConfig, transfer_hook::TransferHook,
+            BaseStateWithExtensionsMut, ExtensionType, StateWithExtensionsMut,
+        };
+        use spl_token_2022::state::Mint;
+        
+        let mut data = vec![0; ExtensionType::try_calculate_account_len::<Mint>(extensions).unwrap()];
+        let mut state = StateWithExtensionsMut::<Mint>::unpack_uninitialized(&mut data).unwrap();
+        for extension_type in extensions {
+            match extension_type {
+                ExtensionType::TransferFeeConfig => {
+                    let config = state.init_extension::<TransferFeeConfig>(true).unwrap();
+                    config.newer_transfer_fee.transfer_fee_basis_points = 100.into();
+                    config.newer_transfer_fee.maximum_fee = u64::MAX.into();
+                }
+                ExtensionType::TransferHook => {
+                    state.init_extension::<TransferHook>(true).unwrap();
+                }
+                ExtensionType::PermanentDelegate => {
+                    state.init_extension::<PermanentDelegate>(true).unwrap();
+                }
+                _ => unreachable!(),
+            }
+        }
+        state.base = Mint { decimals: 6, is_initialized: true, ..Default::default() };
+        state.pack_base();
+        state.init_account_type().unwrap();
+        data
+    }
+
+    fn check_extensions(data: &mut [u8], transfer_checked: bool) -> Result<()> {
+        let key = Pubkey::new_unique();
+        let mut lamports = 1_000_000;
+        let info = AccountInfo::new(&key, false, false, &mut lamports, data, &spl_token_2022::ID, false, 0);
+        check_mint_extensions_supported(&info, transfer_checked)
+    }
+
+    #[test]
+    fn spl_pools_accept_transfer_fee_and_hook_mints() {
+        use spl_token_2022::extension::ExtensionType;
+        
+        // Both sides of an SPL pool may be Token 2022 mints, with either extension
+        for extensions in [
+            vec![],
+            vec![ExtensionType::TransferFeeConfig],
+            vec![ExtensionType::TransferHook],
+            vec![ExtensionType::TransferFeeConfig, ExtensionType::TransferHook],
+        ] {
+            assert!(check_extensions(&mut token_2022_mint_data(&extensions), true).is_ok());
+        }
+        
+        // Native pools move the token side with the plain Transfer
+        assert!(check_extensions(&mut token_2022_mint_data(&[]), false).is_ok());
+        for extension_type in [ExtensionType::TransferFeeConfig, ExtensionType::TransferHook] {
+            assert_eq!(
+                check_extensions(&mut token_2022_mint_data(&[extension_type]), false).unwrap_err(),
+                ErrorCode::UnsupportedMintExtension.into()
+            );
+        }
+    }
+
+    #[test]
+    fn permanent_delegate_mints_are_rejected() {
+        use spl_token_2022::extension::ExtensionType;
+        
+        for transfer_checked in [true, false] {
+            assert_eq!(
+                check_extensions(&mut token_2022_mint_data(&[ExtensionType::PermanentDelegate]), transfer_checked).unwrap_err(),
+                ErrorCode::UnsupportedMintExtension.into()
+            );
+        }
+    }
+
+    #[test]
+    fn transfer_fee_comes_out_of_the_amount_delivered() {
+        use anchor_lang::solana_program::program_pack::Pack;
+        use spl_token_2022::extension::ExtensionType;
+        
+        let key = Pubkey::new_unique();
+        let mut lamports = 1_000_000;
+        let mut data = token_2022_mint_data(&[ExtensionType::TransferFeeConfig]);
+        let fee_mint = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &spl_token_2022::ID, false, 0);
+        assert_eq!(transfer_fee(&fee_mint, 0, 10_000).unwrap(), 100);
+        assert_eq!(amount_after_transfer_fee(Some(&fee_mint), 0, 10_000).unwrap(), 9_900);
+        // Vaults of a fee mint carry the TransferFeeAmount account extension
+        assert!(token_account_len(&fee_mint).unwrap() > spl_token_2022::state::Account::LEN);
+        
+        let mut lamports = 1_000_000;
+        let mut data = token_2022_mint_data(&[ExtensionType::TransferHook]);
+        let hook_mint = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &spl_token_2022::ID, false, 0);
+        assert_eq!(transfer_fee(&hook_mint, 0, 10_000).unwrap(), 0);
+        
+        // Without the mint (classic Token side) the whole amount arrives
+        assert_eq!(amount_after_transfer_fee(None, 0, 10_000).unwrap(), 10_000);
+    }
+
+    #[test]
+    fn protocol_fee_in_a_fee_mint_is_recorded_as_received() {
+        use spl_token_2022::extension::ExtensionType;
+        
+        // A Token 2022 XNT mint with a 1% transfer fee: swaps charge the full shares, the
+        // treasury and FeeSplit recipients each get their share less the transfer fee
+        let key = Pubkey::new_unique();
+        let mut lamports = 1_000_000;
+        let mut data = token_2022_mint_data(&[ExtensionType::TransferFeeConfig]);
+        let xnt_mint = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &spl_token_2022::ID, false, 0);
+        
+        let mut pool_state = crate::state::PoolState::default();
+        let shares = [7_000u64, 3_000];
+        for share in shares {
+            let received = amount_after_transfer_fee(Some(&xnt_mint), 0, share).unwrap();
+            pool_state.record_protocol_fee_collected(received);
+        }
+        assert_eq!(pool_state.cumulative_protocol_fees_collected, 6_930 + 2_970);
+        assert!(pool_state.cumulative_protocol_fees_collected < shares.iter().sum::<u64>() as u128);
+    }
+
+    #[test]
+    fn verified_swap_expects_fee_mint_credits_net_of_the_fee() {
+        use anchor_lang::solana_program::program_pack::Pack;
+        use spl_token_2022::extension::ExtensionType;
+        
+        let key = Pubkey::new_unique();
+        let mut lamports = 1_000_000;
+        let mut data = token_2022_mint_data(&[ExtensionType::TransferFeeConfig]);
+        let fee_mint = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &spl_token_2022::ID, false, 0);
+        
+        // A vault that held 1_000 and was sent 10_000 of a 1% fee mint
+        let mut vault_data = vec![0; spl_token_2022::state::Account::LEN];
+        spl_token_2022::state::Account {
+            mint: key,
+            amount: 1_000 + 9_900,
+            state: spl_token_2022::state::AccountState::Initialized,
+            ..Default::default()
+        }.pack_into_slice(&mut vault_data);
+        let vault_key = Pubkey::new_unique();
+        let mut vault_lamports = 1_000_000;
+        let vault = AccountInfo::new(&vault_key, false, false, &mut vault_lamports, &mut vault_data, &spl_token_2022::ID, false, 0);
+        
+        let expected = amount_after_transfer_fee(Some(&fee_mint), 0, 10_000).unwrap();
+        assert!(check_balance_delta(&vault, 1_000, expected, true).is_ok());
+        // The gross amount, or any other shortfall, is a mismatch
+        assert_eq!(check_balance_delta(&vault, 1_000, 10_000, true).unwrap_err(), ErrorCode::ExecutionMismatch.into());
+        assert_eq!(check_balance_delta(&vault, 1_000, 9_901, true).unwrap_err(), ErrorCode::ExecutionMismatch.into());
+        // Balance moved the wrong way
+        assert_eq!(check_balance_delta(&vault, 20_000, expected, true).unwrap_err(), ErrorCode::ExecutionMismatch.into());
+    }
+
+    #[test]
+    fn lp_account_is_only_closed_once_empty() {
+        use anchor_lang::solana_program::program_pack::Pack;
+        
+        let lp_account_data = |amount: u64| {
+            let mut data = vec![0; spl_token_2022::state::Account::LEN];
+            spl_token_2022::state::Account {
+                amount,
+                state: spl_token_2022::state::AccountState::Initialized,
+                ..Default::default()
+            }.pack_into_slice(&mut data);
+            data
+        };
+        let close = |data: &mut [u8]| {
+            let (lp_key, mint_key, owner_key, authority_key) =
+                (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+            let (mut lp_lamports, mut mint_lamports, mut owner_lamports, mut authority_lamports, mut program_lamports) =
+                (2_039_280, 0, 0, 0, 0);
+            let (mut mint_data, mut empty, mut program_data) = (Vec::new(), Vec::new(), Vec::new());
+            let token_program_id = anchor_spl::token::ID;
+            close_empty_lp_account(
+                AccountInfo::new(&lp_key, false, true, &mut lp_lamports, data, &token_program_id, false, 0),
+                AccountInfo::new(&mint_key, false, true, &mut mint_lamports, &mut mint_data, &token_program_id, false, 0),
+                AccountInfo::new(&owner_key, true, true, &mut owner_lamports, &mut empty, &owner_key, false, 0),
+                AccountInfo::new(&authority_key, false, false, &mut authority_lamports, &mut [], &crate::ID, false, 0),
+                AccountInfo::new(&token_program_id, false, false, &mut program_lamports, &mut program_data, &token_program_id, true, 0),
+                &[],
+                false,
+            )
+        };
+        
+        // LP left in the account - its rent can't be reclaimed yet
+        assert_eq!(close(&mut lp_account_data(1)).unwrap_err(), ErrorCode::LpAccountNotEmpty.into());
+        // Burning everything lets the account be closed (the CloseAccount CPI returning
+        // its rent to the owner only runs on-chain)
+        assert!(close(&mut lp_account_data(0)).is_ok());
+    }
+
+    #[test]
+    fn sqrt_is_floored_and_exact_on_squares() {
+        assert_eq!(sqrt_u128(0), 0);
+        assert_eq!(sqrt_u128(1), 1);
+        assert_eq!(sqrt_u128(3), 1);
+        assert_eq!(sqrt_u128(4), 2);
+        assert_eq!(sqrt_u128(99), 9);
+        assert_eq!(sqrt_u128(u64::MAX as u128), u32::MAX as u128);
+        assert_eq!(sqrt_u128(u128::MAX), u64::MAX as u128);
+        for root in [2u128, 1_000_003, u32::MAX as u128, u64::MAX as u128] {
+            assert_eq!(sqrt_u128(root * root), root);
+            assert_eq!(sqrt_u128(root * root - 1), root - 1);
+        }
+    }
+
+    #[test]
+    fn geometric_mean_takes_products_past_u64() {
+        // 10^10 * 10^10 overflows u64 but the first deposit must still mint 10^10
+        assert!(10_000_000_000u64.checked_mul(10_000_000_000).is_none());
+        assert_eq!(geometric_mean(10_000_000_000, 10_000_000_000), 10_000_000_000);
+        assert_eq!(geometric_mean(u64::MAX, u64::MAX), u64::MAX);
+        assert_eq!(geometric_mean(u64::MAX, 1), u32::MAX as u64);
+        assert_eq!(geometric_mean(2, 8), 4);
+        assert_eq!(geometric_mean(0, u64::MAX), 0);
+        
+        // Both first-deposit paths go through calculate_lp_to_mint
+        assert_eq!(
+            calculate_lp_to_mint(0, 0, 0, 10_000_000_000, 10_000_000_000).unwrap(),
+            10_000_000_000 - MINIMUM_LIQUIDITY
+        );
+    }
+
+    #[test]
+    fn first_deposit_must_reach_min_initial_liquidity() {
+        let just_below = MIN_INITIAL_LIQUIDITY - 1;
+        assert_eq!(
+            calculate_lp_to_mint(0, 0, 0, just_below, just_below).unwrap_err(),
+            ErrorCode::InsufficientInitialLiquidity.into()
+        );
+        // sqrt(1999 * 2001) floors to 1999
+        assert_eq!(
+            calculate_lp_to_mint(0, 0, 0, MIN_INITIAL_LIQUIDITY - 1, MIN_INITIAL_LIQUIDITY + 1).unwrap_err(),
+            ErrorCode::InsufficientInitialLiquidity.into()
+        );
+        // At the floor the depositor keeps as much LP as gets locked
+        assert_eq!(
+            calculate_lp_to_mint(0, 0, 0, MIN_INITIAL_LIQUIDITY, MIN_INITIAL_LIQUIDITY).unwrap(),
+            MIN_INITIAL_LIQUIDITY - MINIMUM_LIQUIDITY
+        );
+        assert_eq!(MIN_INITIAL_LIQUIDITY - MINIMUM_LIQUIDITY, MINIMUM_LIQUIDITY);
+    }
+
+    #[test]
+    fn tvl_cap_bounds_the_xnt_reserve_after_a_deposit() {
+        assert!(check_tvl_cap(900, 100, 1_000).is_ok());
+        assert_eq!(check_tvl_cap(900, 101, 1_000).unwrap_err(), ErrorCode::TvlCapExceeded.into());
+        // A reserve already above a lowered cap only blocks further deposits
+        assert_eq!(check_tvl_cap(2_000, 1, 1_000).unwrap_err(), ErrorCode::TvlCapExceeded.into());
+        // 0 = unlimited
+        assert!(check_tvl_cap(u64::MAX - 1, 1, 0).is_ok());
+        assert_eq!(check_tvl_cap(u64::MAX, 1, u64::MAX).unwrap_err(), ErrorCode::MathOverflow.into());
+    }
+
+    #[test]
+    fn later_deposits_mint_the_smaller_pro_rata_share() {
+        // 10% of each side mints 10% of the supply
+        assert_eq!(calculate_lp_to_mint(1_000_000, 5_000_000, 20_000_000, 500_000, 2_000_000).unwrap(), 100_000);
+        // Excess on either side is not paid for in LP
+        assert_eq!(calculate_lp_to_mint(1_000_000, 5_000_000, 20_000_000, 500_000, 4_000_000).unwrap(), 100_000);
+        assert_eq!(calculate_lp_to_mint(1_000_000, 5_000_000, 20_000_000, 1_000_000, 2_000_000).unwrap(), 100_000);
+        // Rounds down
+        assert_eq!(calculate_lp_to_mint(1_000, 3_000, 3_000, 1, 1).unwrap(), 0);
+        
+        assert_eq!(
+            calculate_lp_to_mint(1_000_000, 0, 20_000_000, 1, 1).unwrap_err(),
+            ErrorCode::InsufficientLiquidity.into()
+        );
+        assert_eq!(
+            calculate_lp_to_mint(u64::MAX, 1, 1, 2, 2).unwrap_err(),
+            ErrorCode::MathOverflow.into()
+        );
+    }
+
+    #[test]
+    fn swap_input_is_the_smallest_reaching_the_output() {
+        for (reserve_in, reserve_out, fee_numerator) in [
+            (1_000_000_000u64, 1_000_000_000u64, 30u64),
+            (7_777_777, 3_141_592_653, 25),
+            (1_000, 1_000_000_000_000, 0),
+        ] {
+            for amount_out in [1, 999, 123_456, reserve_out / 3, reserve_out - 1] {
+                let amount_in = calculate_swap_input(amount_out, reserve_in, reserve_out, fee_numerator, 10000).unwrap();
+                assert!(calculate_swap_output(amount_in, reserve_in, reserve_out, fee_numerator, 10000).unwrap() >= amount_out);
+                assert!(calculate_swap_output(amount_in - 1, reserve_in, reserve_out, fee_numerator, 10000).unwrap() < amount_out);
+            }
+        }
+        
+        // The whole output reserve is never reachable
+        assert_eq!(
+            calculate_swap_input(1_000, 1_000, 1_000, 30, 10000).unwrap_err(),
+            ErrorCode::InsufficientLiquidity.into()
+        );
+        assert_eq!(
+            calculate_swap_input(1, 0, 1_000, 30, 10000).unwrap_err(),
+            ErrorCode::InsufficientLiquidity.into()
+        );
+        assert_eq!(calculate_swap_input(1, 1_000, 1_000, 10000, 10000).unwrap_err(), ErrorCode::InvalidInput.into());
+        // An input above u64::MAX fails cleanly
+        assert_eq!(
+            calculate_swap_input(u64::MAX - 1, u64::MAX, u64::MAX, 30, 10000).unwrap_err(),
+            ErrorCode::MathOverflow.into()
+        );
+    }
+
+    #[test]
+    fn slippage_cap_rejects_a_lower_min_amount_out() {
+        // 1% cap on a 10_000 quote: min_amount_out must be at least 9_900
+        assert!(check_slippage_cap(10_000, 9_900, 100).is_ok());
+        assert!(check_slippage_cap(10_000, 10_000, 100).is_ok());
+        assert_eq!(check_slippage_cap(10_000, 9_899, 100).unwrap_err(), ErrorCode::SlippageToleranceTooHigh.into());
+        // The floor rounds down, in the swapper's favour
+        assert!(check_slippage_cap(999, 989, 100).is_ok());
+        // 0 bps demands the full quote, 10000 leaves the swapper unrestricted
+        assert_eq!(check_slippage_cap(10_000, 9_999, 0).unwrap_err(), ErrorCode::SlippageToleranceTooHigh.into());
+        assert!(check_slippage_cap(u64::MAX, 0, 10000).is_ok());
+    }
+
+    #[test]
+    fn burns_leave_nothing_or_at_least_minimum_liquidity() {
+        let total = 10 * MINIMUM_LIQUIDITY;
+        assert!(check_burn_leaves_minimum_liquidity(total, total).is_ok());
+        assert!(check_burn_leaves_minimum_liquidity(total, total - MINIMUM_LIQUIDITY).is_ok());
+        assert!(check_burn_leaves_minimum_liquidity(total, 0).is_ok());
+        assert_eq!(
+            check_burn_leaves_minimum_liquidity(total, total - MINIMUM_LIQUIDITY + 1).unwrap_err(),
+            ErrorCode::BurnBelowMinimumLiquidity.into()
+        );
+        assert_eq!(
+            check_burn_leaves_minimum_liquidity(total, total - 1).unwrap_err(),
+            ErrorCode::BurnBelowMinimumLiquidity.into()
+        );
+        assert_eq!(
+            check_burn_leaves_minimum_liquidity(total, total + 1).unwrap_err(),
+            ErrorCode::BurnTooMuch.into()
+        );
+    }
+
+    #[test]
+    fn swap_size_is_capped_at_a_share_of_the_output_reserve() {
+        // 5% of a 1_000_000 reserve
+        assert!(check_swap_size(50_000, 1_000_000, 500).is_ok());
+        assert_eq!(check_swap_size(50_001, 1_000_000, 500).unwrap_err(), ErrorCode::SwapTooLarge.into());
+        // The cap rounds down
+        assert!(check_swap_size(4, 99, 500).is_ok());
+        assert_eq!(check_swap_size(5, 99, 500).unwrap_err(), ErrorCode::SwapTooLarge.into());
+        // 10000 bps and above leave swaps unrestricted, even past the reserve
+        assert!(check_swap_size(2_000_000, 1_000_000, 10000).is_ok());
+        assert!(check_swap_size(u128::MAX, 0, u16::MAX).is_ok());
+        assert_eq!(check_swap_size(1, u128::MAX, 9999).unwrap_err(), ErrorCode::MathOverflow.into());
+    }
+
+    #[test]
+    fn compounded_fee_mints_lp_worth_half_its_xnt() {
+        // A 100 XNT fee against a 10_000 XNT reserve is worth 0.5% of the pool,
+        // half of it XNT - 0.5% of 1_000_000 LP
+        assert_eq!(compound_lp_amount(100, 10_000, 1_000_000).unwrap(), 5_000);
+        // Rounds down, to the pool's favour
+        assert_eq!(compound_lp_amount(1, 10_000, 1_000_000).unwrap(), 50);
+        assert_eq!(compound_lp_amount(1, 1_000_000, 1_000_000).unwrap(), 0);
+        assert_eq!(compound_lp_amount(0, 10_000, 1_000_000).unwrap(), 0);
+
+        assert_eq!(compound_lp_amount(100, 0, 1_000_000).unwrap_err(), ErrorCode::PoolReserveEmpty.into());
+        assert_eq!(compound_lp_amount(u64::MAX, 1, u64::MAX).unwrap_err(), ErrorCode::MathOverflow.into());
+        assert!(compound_lp_amount(u64::MAX, u64::MAX, u64::MAX).is_ok());
+    }
+
+    #[test]
+    fn pool_operation_rejects_reentry_until_it_ends() {
+        let pool_state = crate::test_fixtures::program_account(Pubkey::new_unique(), &crate::state::PoolState::default()).info();
+        begin_pool_operation(pool_state).unwrap();
+        // A CPI calling back into the pool finds it mid-instruction
+        assert_eq!(begin_pool_operation(pool_state).unwrap_err(), ErrorCode::Reentrancy.into());
+        end_pool_operation(pool_state).unwrap();
+        begin_pool_operation(pool_state).unwrap();
+        
+        // Legacy layouts have no flag to set
+        let mut data = vec![0; crate::state::PoolState::V1_DATA_LEN];
+        let key = Pubkey::new_unique();
+        let mut lamports = 1_000_000;
+        let legacy_pool = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &crate::ID, false, 0);
+        begin_pool_operation(&legacy_pool).unwrap();
+        begin_pool_operation(&legacy_pool).unwrap();
+    }
+
+    #[test]
+    fn token_2022_accounts_need_the_real_token_2022_program() {
+        use crate::test_fixtures::{program, signer};
+        let token_program = program(anchor_spl::token::ID).info();
+        let token_2022_program = program(spl_token_2022::ID).info();
+        let impostor = signer().info();
+
+        let picked = token_program_for(&anchor_spl::token::ID, token_program, token_2022_program).unwrap();
+        assert_eq!(picked.key, token_program.key);
+        let picked = token_program_for(&spl_token_2022::ID, token_program, token_2022_program).unwrap();
+        assert_eq!(picked.key, token_2022_program.key);
+        assert_eq!(
+            token_program_for(&spl_token_2022::ID, token_program, impostor).unwrap_err(),
+            ErrorCode::InvalidTreasury.into()
+        );
+    }
+
+    #[test]
+    fn invariant_holds_only_while_x_times_y_does_not_shrink() {
+        assert!(check_invariant(1_000, 1_000, 1_100, 910).is_ok());
+        assert!(check_invariant(1_000, 1_000, 1_000, 1_000).is_ok());
+        assert_eq!(check_invariant(1_000, 1_000, 1_100, 909).unwrap_err(), ErrorCode::InvariantViolated.into());
+        // u64::MAX squared still fits the u128 product
+        assert!(check_invariant(u64::MAX, u64::MAX, u64::MAX, u64::MAX).is_ok());
+    }
+
+    #[test]
+    fn token_account_amount_reads_both_token_programs() {
+        use crate::test_fixtures::{token_2022_account, token_account};
+        let (mint, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        assert_eq!(token_account_amount(token_account(Pubkey::new_unique(), mint, owner, 7).info()).unwrap(), 7);
+        assert_eq!(token_account_amount(token_2022_account(Pubkey::new_unique(), mint, owner, 9).info()).unwrap(), 9);
+        assert!(is_token(&anchor_spl::token::ID) && !is_token_2022(&anchor_spl::token::ID));
+        assert!(is_token_2022(&spl_token_2022::ID) && !is_token(&spl_token_2022::ID));
+    }
+
+    #[test]
+    fn swap_math_rejects_zero_or_inverted_fee_fractions() {
+        for (fee_numerator, fee_denominator) in [(0, 0), (30, 0), (10001, 10000)] {
+            assert_eq!(
+                calculate_swap_output(1_000, 1_000_000, 1_000_000, fee_numerator, fee_denominator).unwrap_err(),
+                ErrorCode::InvalidInput.into()
+            );
+            assert_eq!(lp_fee_amount(1_000, fee_numerator, fee_denominator).unwrap_err(), ErrorCode::InvalidInput.into());
+        }
+        // Fee-free and all-fee curves are both well-formed
+        assert!(calculate_swap_output(1_000, 1_000_000, 1_000_000, 0, 10000).unwrap() > 0);
+        assert_eq!(lp_fee_amount(1_000, 10000, 10000).unwrap(), 1_000);
+    }
+}